@@ -0,0 +1,82 @@
+/// The strength of the authentication the user performed before the token was issued,
+/// carried in the `amr` claim with the method names of RFC 8176. A stronger level satisfies
+/// every weaker level so `HardwareKey` satisfies a requirement for `Mfa`
+#[derive(Debug)]
+pub enum AuthLevel {
+    /// Authenticated with a password
+    Password,
+    /// Authenticated with more than one factor, like a password and a one-time code
+    Mfa,
+    /// Authenticated with a hardware key like a FIDO2 security key
+    HardwareKey,
+}
+
+impl core::cmp::PartialEq for AuthLevel {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank() == other.rank()
+    }
+}
+
+impl core::clone::Clone for AuthLevel {
+    fn clone(&self) -> Self {
+        match self {
+            AuthLevel::Password => AuthLevel::Password,
+            AuthLevel::Mfa => AuthLevel::Mfa,
+            AuthLevel::HardwareKey => AuthLevel::HardwareKey,
+        }
+    }
+}
+
+impl AuthLevel {
+    /// Check whether authenticating at this level meets the `required` level
+    pub fn satisfies(&self, required: &AuthLevel) -> bool {
+        self.rank() >= required.rank()
+    }
+    /// Convert the `AuthLevel` into its RFC 8176 authentication method name
+    pub fn to_string(level: &AuthLevel) -> &'static str {
+        match level {
+            AuthLevel::Password => "pwd",
+            AuthLevel::Mfa => "mfa",
+            AuthLevel::HardwareKey => "hwk",
+        }
+    }
+    /// Convert an RFC 8176 authentication method name into its `AuthLevel`
+    pub fn from_string(value: &str) -> Option<Self> {
+        match value {
+            "pwd" => Some(AuthLevel::Password),
+            "mfa" => Some(AuthLevel::Mfa),
+            "hwk" => Some(AuthLevel::HardwareKey),
+            _ => None,
+        }
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            AuthLevel::Password => 0,
+            AuthLevel::Mfa => 1,
+            AuthLevel::HardwareKey => 2,
+        }
+    }
+}
+
+#[cfg(test)]
+mod assurance_tests {
+    use super::AuthLevel;
+
+    #[test]
+    fn auth_level() {
+        assert!(AuthLevel::HardwareKey.satisfies(&AuthLevel::Mfa));
+        assert!(AuthLevel::Mfa.satisfies(&AuthLevel::Mfa));
+        assert!(!AuthLevel::Password.satisfies(&AuthLevel::Mfa));
+
+        [AuthLevel::Password, AuthLevel::Mfa, AuthLevel::HardwareKey]
+            .iter()
+            .for_each(|level| {
+                assert_eq!(
+                    AuthLevel::from_string(AuthLevel::to_string(level)).as_ref(),
+                    Some(level)
+                )
+            });
+        assert_eq!(AuthLevel::from_string("sms"), None);
+    }
+}
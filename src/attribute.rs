@@ -0,0 +1,188 @@
+use crate::{LiteSessionData, TokenOutcome};
+use std::sync::Arc;
+use tai64::TAI64N;
+
+type Predicate = Arc<dyn Fn(&LiteSessionData) -> bool + Send + Sync>;
+
+/// Named predicates over the data of a token checked by `LiteSessionToken::evaluate_attributes`.
+/// Predicates can capture the request they are registered for, like its region
+///
+/// ```
+/// use lite_session::{AttributePolicy, LiteSessionData};
+///
+/// let request_region = String::from("eu-west");
+/// let mut policy = AttributePolicy::default();
+/// policy.rule("region", move |data| {
+///     data.get_attribute("region") == Some(&request_region)
+/// });
+///
+/// let mut data = LiteSessionData::default();
+/// data.attribute("region", "eu-west");
+/// assert!(policy.evaluate(&data).is_allowed());
+/// ```
+#[derive(Default)]
+pub struct AttributePolicy {
+    rules: Vec<(String, Predicate)>,
+}
+
+impl core::fmt::Debug for AttributePolicy {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("AttributePolicy")
+            .field(
+                "rules",
+                &self.rules.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl core::clone::Clone for AttributePolicy {
+    fn clone(&self) -> Self {
+        Self {
+            rules: self.rules.clone(),
+        }
+    }
+}
+
+impl AttributePolicy {
+    /// Register a predicate named `name` that must hold for the data of a token
+    pub fn rule(
+        &mut self,
+        name: &str,
+        predicate: impl Fn(&LiteSessionData) -> bool + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.rules.push((name.into(), Arc::new(predicate)));
+
+        self
+    }
+    /// Evaluate every predicate against `data`, allowing it only if all of them hold.
+    /// A policy without predicates allows any data
+    pub fn evaluate(&self, data: &LiteSessionData) -> AccessDecision {
+        let rules: Vec<(String, bool)> = self
+            .rules
+            .iter()
+            .map(|(name, predicate)| (name.clone(), predicate(data)))
+            .collect();
+        let outcome = match rules.iter().all(|(_, holds)| *holds) {
+            true => TokenOutcome::TokenAuthorized,
+            false => TokenOutcome::TokenRejected,
+        };
+
+        AccessDecision {
+            outcome,
+            rules,
+            subject: data.get_username().clone(),
+            decided_at: TAI64N::now(),
+        }
+    }
+}
+
+/// The auditable record of evaluating an `AttributePolicy`
+///
+/// ```
+/// use lite_session::TokenOutcome;
+/// use tai64::TAI64N;
+///
+/// pub struct AccessDecision {
+///     outcome: TokenOutcome,
+///     rules: Vec<(String, bool)>,
+///     subject: String,
+///     decided_at: TAI64N,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AccessDecision {
+    outcome: TokenOutcome,
+    rules: Vec<(String, bool)>,
+    subject: String,
+    decided_at: TAI64N,
+}
+
+impl core::cmp::PartialEq for AccessDecision {
+    fn eq(&self, other: &Self) -> bool {
+        self.outcome == other.outcome
+            && self.rules == other.rules
+            && self.subject == other.subject
+            && self.decided_at == other.decided_at
+    }
+}
+
+impl core::clone::Clone for AccessDecision {
+    fn clone(&self) -> Self {
+        Self {
+            outcome: self.outcome.clone(),
+            rules: self.rules.clone(),
+            subject: self.subject.clone(),
+            decided_at: self.decided_at,
+        }
+    }
+}
+
+impl AccessDecision {
+    /// Check whether every predicate held
+    pub fn is_allowed(&self) -> bool {
+        self.outcome == TokenOutcome::TokenAuthorized
+    }
+    /// Get `TokenOutcome::TokenAuthorized` or `TokenOutcome::TokenRejected`
+    pub fn get_outcome(&self) -> &TokenOutcome {
+        &self.outcome
+    }
+    /// Get the name of every predicate and whether it held, in the order they were registered
+    pub fn get_rules(&self) -> &Vec<(String, bool)> {
+        &self.rules
+    }
+    /// Get the name of the first predicate that did not hold
+    pub fn get_failed_rule(&self) -> Option<&String> {
+        self.rules
+            .iter()
+            .find(|(_, holds)| !holds)
+            .map(|(name, _)| name)
+    }
+    /// Get the username of the token the decision was made for
+    pub fn get_subject(&self) -> &String {
+        &self.subject
+    }
+    /// Get the time the decision was made
+    pub fn get_decided_at(&self) -> &TAI64N {
+        &self.decided_at
+    }
+
+    // Deny the decision for a token that is not authentic whatever the predicates say
+    pub(crate) fn reject(&mut self) {
+        self.outcome = TokenOutcome::TokenRejected;
+    }
+}
+
+#[cfg(test)]
+mod attribute_tests {
+    use super::AttributePolicy;
+    use crate::{LiteSessionData, TokenOutcome};
+
+    #[test]
+    fn attribute_policy() {
+        let mut policy = AttributePolicy::default();
+        policy
+            .rule("region", |data| {
+                data.get_attribute("region") == Some(&"eu-west".to_owned())
+            })
+            .rule("clearance", |data| {
+                data.get_attribute("clearance").is_some()
+            });
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").attribute("region", "eu-west");
+        let decision = policy.evaluate(&data);
+        assert!(!decision.is_allowed());
+        assert_eq!(decision.get_outcome(), &TokenOutcome::TokenRejected);
+        assert_eq!(
+            decision.get_rules(),
+            &vec![("region".into(), true), ("clearance".into(), false)]
+        );
+        assert_eq!(decision.get_failed_rule(), Some(&"clearance".to_owned()));
+        assert_eq!(decision.get_subject(), "foo_user");
+
+        data.attribute("clearance", "secret");
+        assert!(policy.clone().evaluate(&data).is_allowed());
+        assert!(AttributePolicy::default().evaluate(&data).is_allowed());
+    }
+}
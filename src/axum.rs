@@ -0,0 +1,154 @@
+//! Extract the verified session of an axum request with `LiteSession<T>`, enabled by the `axum` feature
+
+use crate::{HttpRejection, LiteSessionData, LiteSessionToken, LiteSessionVerifier};
+use ::axum::{
+    extract::{FromRef, FromRequestParts},
+    http::{
+        header::{AUTHORIZATION, COOKIE, WWW_AUTHENTICATE},
+        request::Parts,
+        StatusCode,
+    },
+    response::{IntoResponse, Response},
+};
+use std::sync::Arc;
+
+/// An extractor verifying the bearer token or the session cookie of a request with the
+/// `LiteSessionVerifier` held by the state of the router, see `LiteSessionVerifier::cookie_name()`.
+/// Requests without an accepted token are rejected with the `401`, `403` or `429` status
+/// of their `HttpRejection`
+///
+/// ```
+/// use lite_session::{axum::LiteSession, LiteSessionData};
+///
+/// async fn handler(LiteSession(data): LiteSession<LiteSessionData>) -> String {
+///     data.get_username().clone()
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LiteSession<T = LiteSessionData>(pub T);
+
+/// The value `LiteSession<T>` extracts from the verified token of a request
+pub trait FromSession: Sized {
+    /// Convert the verified token, rejecting the request when it does not hold the value
+    fn from_session(token: LiteSessionToken) -> Result<Self, HttpRejection>;
+}
+
+impl FromSession for LiteSessionToken {
+    fn from_session(token: LiteSessionToken) -> Result<Self, HttpRejection> {
+        Ok(token)
+    }
+}
+
+impl FromSession for LiteSessionData {
+    fn from_session(token: LiteSessionToken) -> Result<Self, HttpRejection> {
+        Ok(token.get_hmac_data().clone())
+    }
+}
+
+impl<S, T> FromRequestParts<S> for LiteSession<T>
+where
+    Arc<LiteSessionVerifier>: FromRef<S>,
+    S: Send + Sync,
+    T: FromSession,
+{
+    type Rejection = HttpRejection;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let verifier = Arc::<LiteSessionVerifier>::from_ref(state);
+        // Plain tokens carry their `⊕` separators as UTF-8 rather than visible ASCII
+        let authorization = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| core::str::from_utf8(value.as_bytes()).ok());
+        // HTTP/2 clients may split the cookies over several headers
+        let cookie = parts
+            .headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| core::str::from_utf8(value.as_bytes()).ok())
+            .collect::<Vec<&str>>()
+            .join("; ");
+
+        let token = verifier.verify_request(
+            authorization,
+            Some(cookie.as_str()).filter(|cookie| !cookie.is_empty()),
+            verifier.get_cookie_name(),
+        )?;
+
+        T::from_session(token).map(LiteSession)
+    }
+}
+
+impl IntoResponse for HttpRejection {
+    fn into_response(self) -> Response {
+        let status =
+            StatusCode::from_u16(self.get_status()).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        match status {
+            StatusCode::UNAUTHORIZED => (status, [(WWW_AUTHENTICATE, "Bearer")]).into_response(),
+            _ => status.into_response(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod axum_tests {
+    use super::LiteSession;
+    use crate::{
+        KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken, LiteSessionVerifier,
+        ServerKey,
+    };
+    use ::axum::{
+        extract::FromRequestParts,
+        http::{header::WWW_AUTHENTICATE, Request},
+        response::IntoResponse,
+    };
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn extractor() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.cookie_name("sid");
+        let state = Arc::new(verifier);
+        let parts = |name: &str, value: &str| {
+            let (parts, _) = Request::builder()
+                .header(name, value)
+                .body(())
+                .unwrap()
+                .into_parts();
+            parts
+        };
+
+        let mut bearer = parts("authorization", &format!("Bearer {}", session_token));
+        let LiteSession(extracted) =
+            LiteSession::<LiteSessionData>::from_request_parts(&mut bearer, &state)
+                .await
+                .unwrap();
+        assert_eq!(extracted, data);
+
+        let mut cookie = parts("cookie", &format!("theme=dark; sid={}", session_token));
+        let LiteSession(extracted) =
+            LiteSession::<LiteSessionToken>::from_request_parts(&mut cookie, &state)
+                .await
+                .unwrap();
+        assert_eq!(extracted.get_identifier(), token.get_identifier());
+
+        let mut missing = parts("cookie", "theme=dark");
+        let rejection = LiteSession::<LiteSessionData>::from_request_parts(&mut missing, &state)
+            .await
+            .unwrap_err();
+        assert_eq!(rejection.get_status(), 401);
+        let response = rejection.into_response();
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers()[WWW_AUTHENTICATE], "Bearer");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use lite_session::{ServerKey, TokenInspection};
+use std::process;
+
+const USAGE: &str = "Usage:
+    lite-session inspect [--key-stdin] <token>
+    lite-session diff [--key-stdin] <token_a> <token_b>
+
+The server key is read as a 32 byte hex string from the LITE_SESSION_KEY environment
+variable, or from the first line of stdin with --key-stdin. It is never taken as an argument
+so it does not show up in the process list or the shell history";
+
+// The environment variable holding the hex server key
+const KEY_VARIABLE: &str = "LITE_SESSION_KEY";
+
+fn main() {
+    let mut args = std::env::args().skip(1).collect::<Vec<String>>();
+
+    let key_hex = match args.iter().position(|arg| arg == "--key-stdin") {
+        Some(index) => {
+            args.remove(index);
+            let mut line = String::new();
+            match std::io::stdin().read_line(&mut line) {
+                Ok(_) => Some(line),
+                Err(_) => exit_with("The server key could not be read from stdin"),
+            }
+        }
+        None => std::env::var(KEY_VARIABLE).ok(),
+    };
+    if args.iter().any(|arg| arg == "--key") {
+        exit_with(USAGE);
+    }
+
+    let server_key = match key_hex {
+        Some(key_hex) => match ServerKey::from_hex(key_hex.trim()) {
+            Ok(key) => Some(key),
+            Err(_) => exit_with("The server key must be a 32 byte hex string"),
+        },
+        None => None,
+    };
+    let server_key = server_key.as_ref();
+
+    match args.iter().map(String::as_str).collect::<Vec<&str>>()[..] {
+        ["inspect", token] => {
+            println!("{}", TokenInspection::inspect(token, server_key).to_json());
+        }
+        ["diff", token_a, token_b] => {
+            let inspection_a = TokenInspection::inspect(token_a, server_key);
+            let inspection_b = TokenInspection::inspect(token_b, server_key);
+
+            println!(
+                "outcome: {:?} -> {:?}",
+                inspection_a.get_outcome(),
+                inspection_b.get_outcome()
+            );
+            inspection_a.diff(&inspection_b).iter().for_each(|diff| {
+                println!(
+                    "{}: {} -> {}",
+                    diff.get_name(),
+                    diff.get_left().as_deref().unwrap_or("<missing>"),
+                    diff.get_right().as_deref().unwrap_or("<missing>"),
+                )
+            });
+        }
+        _ => exit_with(USAGE),
+    }
+}
+
+fn exit_with(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1)
+}
@@ -0,0 +1,250 @@
+use crate::{LiteSessionError, RevocationStore};
+use std::sync::Arc;
+
+// The filter never grows beyond 16 MiB however many revocations are expected
+const MAX_BLOOM_BITS: u64 = 128 * 1024 * 1024;
+// More hash functions than this cost more lookups than the false positives they save
+const MAX_BLOOM_HASHES: u32 = 32;
+
+/// Confirms whether an identifier reported as possibly revoked by a `RevocationBloom`
+/// has been revoked
+pub type RevocationFallback = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// A compact probabilistic set of revoked token identifiers pushed to edge verifiers
+/// instead of the full revocation list. An identifier that was revoked is always
+/// reported as possibly revoked while an identifier that was not is reported as possibly
+/// revoked at the configured false-positive rate, so a possibly revoked token can be
+/// confirmed with the authoritative store through the `fallback()` hook
+///
+/// ```
+/// use lite_session::RevocationBloom;
+///
+/// let mut bloom = RevocationBloom::new(1000, 0.01);
+/// bloom.insert("foo");
+/// assert!(bloom.might_contain("foo"));
+///
+/// let serialized = RevocationBloom::to_string(&bloom);
+/// let edge = RevocationBloom::from_string(&serialized).unwrap();
+/// assert!(edge.is_revoked("foo"));
+/// ```
+pub struct RevocationBloom {
+    bits: Vec<u8>,
+    num_bits: u64,
+    num_hashes: u32,
+    fallback: Option<RevocationFallback>,
+}
+
+impl core::fmt::Debug for RevocationBloom {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("RevocationBloom")
+            .field("num_bits", &self.num_bits)
+            .field("num_hashes", &self.num_hashes)
+            .field("fallback", &self.fallback.is_some())
+            .finish()
+    }
+}
+
+impl core::cmp::PartialEq for RevocationBloom {
+    fn eq(&self, other: &Self) -> bool {
+        self.bits == other.bits
+            && self.num_bits == other.num_bits
+            && self.num_hashes == other.num_hashes
+    }
+}
+
+impl core::clone::Clone for RevocationBloom {
+    fn clone(&self) -> Self {
+        Self {
+            bits: self.bits.clone(),
+            num_bits: self.num_bits,
+            num_hashes: self.num_hashes,
+            fallback: self.fallback.clone(),
+        }
+    }
+}
+
+impl RevocationBloom {
+    /// Create an empty filter sized for `expected_revocations` identifiers with a
+    /// `false_positive_rate` between `0` and `1`, like `0.01` for one percent
+    pub fn new(expected_revocations: usize, false_positive_rate: f64) -> Self {
+        let expected = expected_revocations.max(1) as f64;
+        let rate = if false_positive_rate > 0.0 && false_positive_rate < 1.0 {
+            false_positive_rate
+        } else {
+            0.01
+        };
+        let ln2 = core::f64::consts::LN_2;
+
+        let num_bits =
+            ((-expected * rate.ln() / (ln2 * ln2)).ceil() as u64).clamp(8, MAX_BLOOM_BITS);
+        let num_hashes =
+            ((num_bits as f64 / expected * ln2).round() as u32).clamp(1, MAX_BLOOM_HASHES);
+
+        Self {
+            bits: vec![0_u8; num_bits.div_ceil(8) as usize],
+            num_bits,
+            num_hashes,
+            fallback: None,
+        }
+    }
+    /// Create a filter holding the revocations of `store` whose tokens have not expired
+    pub fn from_store(store: &dyn RevocationStore, false_positive_rate: f64) -> Self {
+        let snapshot = store.export(0);
+        let mut bloom = RevocationBloom::new(snapshot.get_entries().len(), false_positive_rate);
+        snapshot
+            .get_entries()
+            .iter()
+            .for_each(|(identifier, _)| bloom.insert(identifier));
+
+        bloom
+    }
+    /// Confirm identifiers the filter reports as possibly revoked with `fallback`,
+    /// like a query to the authoritative `RevocationStore`
+    pub fn fallback(&mut self, fallback: RevocationFallback) -> &mut Self {
+        self.fallback = Some(fallback);
+
+        self
+    }
+    /// Add a revoked identifier
+    pub fn insert(&mut self, identifier: &str) {
+        for index in self.indexes(identifier) {
+            self.bits[(index / 8) as usize] |= 1 << (index % 8);
+        }
+    }
+    /// Check whether `identifier` may have been revoked, without consulting the fallback
+    pub fn might_contain(&self, identifier: &str) -> bool {
+        self.indexes(identifier)
+            .iter()
+            .all(|index| self.bits[(index / 8) as usize] & (1 << (index % 8)) != 0)
+    }
+    /// Check whether `identifier` has been revoked. An identifier the filter reports as
+    /// possibly revoked is confirmed with the fallback if one is set
+    pub fn is_revoked(&self, identifier: &str) -> bool {
+        if !self.might_contain(identifier) {
+            return false;
+        }
+
+        match &self.fallback {
+            Some(fallback) => fallback(identifier),
+            None => true,
+        }
+    }
+    /// Get the size of the filter in bits
+    pub fn get_num_bits(&self) -> u64 {
+        self.num_bits
+    }
+    /// Get the number of hash functions
+    pub fn get_num_hashes(&self) -> u32 {
+        self.num_hashes
+    }
+    /// Serialize the filter into its compact text form. The fallback is not serialized
+    pub fn to_string(bloom: &RevocationBloom) -> String {
+        format!(
+            "{} {} {} {}",
+            RevocationBloom::magic(),
+            bloom.num_bits,
+            bloom.num_hashes,
+            base64::encode_config(&bloom.bits, base64::URL_SAFE_NO_PAD)
+        )
+    }
+    /// Deserialize a filter from its compact text form
+    pub fn from_string(value: &str) -> Result<Self, LiteSessionError> {
+        let fields = value.split(' ').collect::<Vec<&str>>();
+        if fields.len() != 4_usize || fields[0] != RevocationBloom::magic() {
+            return Err(LiteSessionError::InvalidRevocationSnapshot);
+        }
+
+        let num_bits = match fields[1].parse::<u64>() {
+            Ok(num_bits) if num_bits > 0 && num_bits <= MAX_BLOOM_BITS => num_bits,
+            _ => return Err(LiteSessionError::InvalidRevocationSnapshot),
+        };
+        let num_hashes = match fields[2].parse::<u32>() {
+            Ok(num_hashes) if num_hashes > 0 && num_hashes <= MAX_BLOOM_HASHES => num_hashes,
+            _ => return Err(LiteSessionError::InvalidRevocationSnapshot),
+        };
+        let bits = match base64::decode_config(fields[3], base64::URL_SAFE_NO_PAD) {
+            Ok(bits) => bits,
+            Err(_) => return Err(LiteSessionError::InvalidBase64String),
+        };
+        if bits.len() as u64 != num_bits.div_ceil(8) {
+            return Err(LiteSessionError::InvalidRevocationSnapshot);
+        }
+
+        Ok(Self {
+            bits,
+            num_bits,
+            num_hashes,
+            fallback: None,
+        })
+    }
+
+    // Double hashing derives every index from the two halves of one Blake3 hash
+    fn indexes(&self, identifier: &str) -> Vec<u64> {
+        let hash = blake3::hash(identifier.as_bytes());
+        let bytes = hash.as_bytes();
+        let mut first = [0_u8; 8];
+        let mut second = [0_u8; 8];
+        first.copy_from_slice(&bytes[..8]);
+        second.copy_from_slice(&bytes[8..16]);
+        let first = u64::from_le_bytes(first);
+        let second = u64::from_le_bytes(second);
+
+        (0..u64::from(self.num_hashes))
+            .map(|round| first.wrapping_add(round.wrapping_mul(second)) % self.num_bits)
+            .collect()
+    }
+
+    fn magic() -> &'static str {
+        "lsbloom1"
+    }
+}
+
+#[cfg(test)]
+mod bloom_tests {
+    use super::RevocationBloom;
+    use crate::{LiteSessionError, MemoryRevocationStore, RevocationStore};
+    use core::time::Duration;
+    use std::sync::Arc;
+    use tai64::TAI64N;
+
+    #[test]
+    fn revocation_bloom() -> Result<(), LiteSessionError> {
+        let store = MemoryRevocationStore::new();
+        let expiry = TAI64N::now() + Duration::from_secs(3600);
+        (0..1000).for_each(|index| store.revoke(&format!("revoked{}", index), expiry));
+
+        let bloom = RevocationBloom::from_store(&store, 0.01);
+        assert!(bloom.get_num_hashes() > 1);
+        assert!((0..1000).all(|index| bloom.might_contain(&format!("revoked{}", index))));
+        let false_positives = (0..1000)
+            .filter(|index| bloom.might_contain(&format!("active{}", index)))
+            .count();
+        assert!(false_positives < 50);
+
+        let serialized = RevocationBloom::to_string(&bloom);
+        let mut edge = RevocationBloom::from_string(&serialized)?;
+        assert_eq!(edge, bloom);
+        assert!(edge.is_revoked("revoked7"));
+
+        let store = Arc::new(store);
+        let origin = store.clone();
+        edge.fallback(Arc::new(move |identifier| origin.is_revoked(identifier)));
+        assert!(edge.is_revoked("revoked7"));
+        assert!((0..1000).all(|index| !edge.is_revoked(&format!("active{}", index))));
+
+        assert_eq!(
+            RevocationBloom::from_string("lsbloom1 64 3"),
+            Err(LiteSessionError::InvalidRevocationSnapshot)
+        );
+        assert_eq!(
+            RevocationBloom::from_string("lsbloom1 64 3 AAAA"),
+            Err(LiteSessionError::InvalidRevocationSnapshot)
+        );
+        assert_eq!(
+            RevocationBloom::from_string("lsbloom1 64 0 AAAAAAAAAAA"),
+            Err(LiteSessionError::InvalidRevocationSnapshot)
+        );
+
+        Ok(())
+    }
+}
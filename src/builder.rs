@@ -0,0 +1,198 @@
+use crate::{
+    IdentifierStrategy, LiteSessionData, LiteSessionError, LiteSessionMode, LiteSessionToken,
+    MacLength, RoleTtls, SealedToken, ServerKey, TimestampRange, TokenCipher, TokenEncoding,
+    TokenFormat, TokenVersion,
+};
+use core::marker::PhantomData;
+
+/// State of a `LiteSessionTokenBuilder` whose `LiteSessionData` has not been set
+#[derive(Debug)]
+pub struct NoData;
+/// State of a `LiteSessionTokenBuilder` whose `LiteSessionData` has been set
+#[derive(Debug)]
+pub struct HasData;
+/// State of a `LiteSessionTokenBuilder` whose expiry has not been set
+#[derive(Debug)]
+pub struct NoExpiry;
+/// State of a `LiteSessionTokenBuilder` whose expiry has been set
+#[derive(Debug)]
+pub struct HasExpiry;
+
+/// Builds a `LiteSessionToken` while tracking the required fields in its type.
+/// `build_secure` can only be called once both `hmac_data` and `expiry` have been set
+/// so a token with default data or the default expiry cannot be issued by accident
+/// ```
+/// use lite_session::{LiteSessionData, LiteSessionTokenBuilder, ServerKey};
+///
+/// let mut data = LiteSessionData::default();
+/// data.username("foo_user").add_acl("Network-TCP");
+///
+/// let sealed = LiteSessionTokenBuilder::new()
+///     .hmac_data(data)
+///     .expiry(60 * 60)
+///     .build_secure(&ServerKey::new([0_u8; 32]))
+///     .unwrap();
+/// ```
+/// Calling `build_secure` before setting the data or expiry does not compile
+/// ```compile_fail
+/// use lite_session::LiteSessionTokenBuilder;
+///
+/// LiteSessionTokenBuilder::new()
+///     .expiry(60 * 60)
+///     .build_secure(&ServerKey::new([0_u8; 32]));
+/// ```
+#[derive(Debug)]
+pub struct LiteSessionTokenBuilder<D, E> {
+    token: LiteSessionToken,
+    state: PhantomData<(D, E)>,
+}
+
+impl Default for LiteSessionTokenBuilder<NoData, NoExpiry> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LiteSessionTokenBuilder<NoData, NoExpiry> {
+    /// Start building a token with neither the data nor the expiry set
+    pub fn new() -> Self {
+        Self {
+            token: LiteSessionToken::default(),
+            state: PhantomData,
+        }
+    }
+}
+
+impl<D, E> LiteSessionTokenBuilder<D, E> {
+    /// Set the data contained in the token
+    pub fn hmac_data(mut self, data: LiteSessionData) -> LiteSessionTokenBuilder<HasData, E> {
+        self.token.hmac_data(data);
+
+        LiteSessionTokenBuilder {
+            token: self.token,
+            state: PhantomData,
+        }
+    }
+    /// Set the expiry of the token in seconds from the issued time
+    pub fn expiry(mut self, expiry_in_secs: u64) -> LiteSessionTokenBuilder<D, HasExpiry> {
+        self.token.expiry(expiry_in_secs);
+
+        LiteSessionTokenBuilder {
+            token: self.token,
+            state: PhantomData,
+        }
+    }
+    /// Set a custom identifier
+    pub fn identifier(mut self, identifier: &str) -> Self {
+        self.token.identifier(identifier);
+
+        self
+    }
+    /// Generate the identifier using an `IdentifierStrategy`
+    pub fn identifier_strategy(mut self, strategy: &dyn IdentifierStrategy) -> Self {
+        self.token.identifier_strategy(strategy);
+
+        self
+    }
+    /// Choose whether the data is encrypted, see `LiteSessionToken::confidential`
+    pub fn confidential(mut self, bool_choice: bool) -> Self {
+        self.token.confidential(bool_choice);
+
+        self
+    }
+    /// Choose the `LiteSessionMode`
+    pub fn mode(mut self, mode: LiteSessionMode) -> Self {
+        self.token.mode(mode);
+
+        self
+    }
+    /// Choose the `MacLength`
+    pub fn mac_length(mut self, mac_length: MacLength) -> Self {
+        self.token.mac_length(mac_length);
+
+        self
+    }
+    /// Set the accepted range of issued times
+    pub fn timestamp_range(mut self, range: TimestampRange) -> Self {
+        self.token.timestamp_range(range);
+
+        self
+    }
+    /// Cap the lifetime of the token by role
+    pub fn role_ttls(mut self, role_ttls: RoleTtls) -> Self {
+        self.token.role_ttls(role_ttls);
+
+        self
+    }
+    /// Choose the `TokenFormat`
+    pub fn format(mut self, format: TokenFormat) -> Self {
+        self.token.format(format);
+
+        self
+    }
+    /// Choose the `TokenVersion`
+    pub fn version(mut self, version: TokenVersion) -> Self {
+        self.token.version(version);
+
+        self
+    }
+    /// Set the public footer
+    pub fn footer(mut self, footer: &str) -> Self {
+        self.token.footer(footer);
+
+        self
+    }
+    /// Choose the `TokenEncoding`
+    pub fn encoding(mut self, encoding: TokenEncoding) -> Self {
+        self.token.encoding(encoding);
+
+        self
+    }
+    /// Choose the `TokenCipher`
+    pub fn cipher(mut self, cipher: TokenCipher) -> Self {
+        self.token.cipher(cipher);
+
+        self
+    }
+}
+
+impl LiteSessionTokenBuilder<HasData, HasExpiry> {
+    /// Build the token with `High Confidentiality` into a `SealedToken`
+    pub fn build_secure(self, server_key: &ServerKey) -> Result<SealedToken, LiteSessionError> {
+        self.token.seal(server_key)
+    }
+    /// Build the token according to its `ConfidentialityMode` into a `SealedToken`
+    pub fn build(mut self, server_key: &ServerKey) -> Result<SealedToken, LiteSessionError> {
+        let session_token = self.token.build(server_key)?;
+
+        Ok(SealedToken::new(self.token, session_token))
+    }
+}
+
+#[cfg(test)]
+mod builder_tests {
+    use super::LiteSessionTokenBuilder;
+    use crate::{LiteSessionData, LiteSessionError, LiteSessionToken, ServerKey, TokenOutcome};
+
+    #[test]
+    fn typestate_builder() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let token = LiteSessionTokenBuilder::new()
+            .expiry(60)
+            .footer("kid=1")
+            .hmac_data(data.clone())
+            .build_secure(&server_key)?;
+        assert_eq!(token.get_hmac_data(), &data);
+        assert_eq!(token.get_footer(), &Some("kid=1".to_owned()));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, token.as_str())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_expiry(), token.get_expiry());
+
+        Ok(())
+    }
+}
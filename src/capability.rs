@@ -0,0 +1,167 @@
+/// A structured entry of the access control list granting an `action` on a `resource`
+/// with an optional `constraint` like a path prefix or a maximum amount.
+/// It is attached to the access control list as `resource:action[:constraint]`
+/// so the `resource` and the `action` must not contain a `:`
+///
+/// ```
+/// pub struct Capability {
+///     resource: String,
+///     action: String,
+///     constraint: Option<String>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Capability {
+    resource: String,
+    action: String,
+    constraint: Option<String>,
+}
+
+impl core::cmp::PartialEq for Capability {
+    fn eq(&self, other: &Self) -> bool {
+        self.resource == other.resource
+            && self.action == other.action
+            && self.constraint == other.constraint
+    }
+}
+
+impl core::clone::Clone for Capability {
+    fn clone(&self) -> Self {
+        Self {
+            resource: self.resource.clone(),
+            action: self.action.clone(),
+            constraint: self.constraint.clone(),
+        }
+    }
+}
+
+impl Capability {
+    /// Create a new capability granting `action` on `resource`
+    pub fn new(resource: &str, action: &str) -> Self {
+        Self {
+            resource: resource.into(),
+            action: action.into(),
+            constraint: Option::default(),
+        }
+    }
+    /// Restrict the capability with a constraint interpreted by the application
+    pub fn constraint(&mut self, constraint: &str) -> &mut Self {
+        self.constraint = Some(constraint.into());
+
+        self
+    }
+    /// Get the resource
+    pub fn get_resource(&self) -> &String {
+        &self.resource
+    }
+    /// Get the action allowed on the resource
+    pub fn get_action(&self) -> &String {
+        &self.action
+    }
+    /// Get the constraint
+    pub fn get_constraint(&self) -> &Option<String> {
+        &self.constraint
+    }
+    /// Check whether the capability grants `action` on `resource`
+    pub fn allows(&self, resource: &str, action: &str) -> bool {
+        self.resource == resource && self.action == action
+    }
+    /// Convert the capability into the entry attached to the access control list
+    pub fn to_string(capability: &Capability) -> String {
+        let mut capability_str = String::default();
+        capability_str.push_str(&capability.resource);
+        capability_str.push(':');
+        capability_str.push_str(&capability.action);
+
+        if let Some(constraint) = &capability.constraint {
+            capability_str.push(':');
+            capability_str.push_str(constraint);
+        }
+
+        capability_str
+    }
+    /// Convert an entry of the access control list into a `Capability`.
+    /// Plain entries like `Network-TCP` are not capabilities and return `None`
+    pub fn from_string(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(3, ':');
+        let resource = parts.next().filter(|resource| !resource.is_empty())?;
+        let action = parts.next().filter(|action| !action.is_empty())?;
+
+        Some(Self {
+            resource: resource.into(),
+            action: action.into(),
+            constraint: parts.next().map(|constraint| constraint.into()),
+        })
+    }
+}
+
+/// How closely an ACL entry matches a scope like `files:read`, `None` if it does not match.
+/// Scopes are `:` separated segments and a `*` as the last segment of an entry grants every
+/// scope below its prefix, so `files:*` grants `files:read` and `files:read:own` but not `files`
+/// and `*` grants every scope. An exact entry takes precedence over any wildcard and a longer
+/// wildcard prefix takes precedence over a shorter one
+pub(crate) fn scope_precedence(entry: &str, scope: &str) -> Option<usize> {
+    if entry == scope {
+        return Some(usize::MAX);
+    }
+
+    let entry_segments: Vec<&str> = entry.split(':').collect();
+    let scope_segments: Vec<&str> = scope.split(':').collect();
+    let prefix_len = entry_segments.len() - 1;
+
+    match entry_segments[prefix_len] == "*"
+        && scope_segments.len() > prefix_len
+        && entry_segments[..prefix_len] == scope_segments[..prefix_len]
+    {
+        true => Some(prefix_len),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod capability_tests {
+    use super::{scope_precedence, Capability};
+
+    #[test]
+    fn capability_tests() {
+        let capability = Capability::new("network", "tcp");
+        let capability_str = Capability::to_string(&capability);
+        assert_eq!(capability_str, "network:tcp");
+        assert_eq!(
+            Capability::from_string(&capability_str),
+            Some(capability.clone())
+        );
+        assert!(capability.allows("network", "tcp"));
+        assert!(!capability.allows("network", "udp"));
+        assert!(!capability.allows("Network", "tcp"));
+
+        let mut constrained = Capability::new("files", "read");
+        constrained.constraint("/home/foo:ro");
+        assert_eq!(
+            Capability::to_string(&constrained),
+            "files:read:/home/foo:ro"
+        );
+        assert_eq!(
+            Capability::from_string("files:read:/home/foo:ro"),
+            Some(constrained)
+        );
+
+        assert_eq!(Capability::from_string("Network-TCP"), None);
+        assert_eq!(Capability::from_string(":tcp"), None);
+        assert_eq!(Capability::from_string("network:"), None);
+
+        assert_eq!(
+            scope_precedence("files:read", "files:read"),
+            Some(usize::MAX)
+        );
+        assert_eq!(scope_precedence("files:*", "files:read"), Some(1));
+        assert_eq!(scope_precedence("files:*", "files:read:own"), Some(1));
+        assert_eq!(scope_precedence("files:read:*", "files:read:own"), Some(2));
+        assert_eq!(scope_precedence("*", "files:read"), Some(0));
+        assert_eq!(scope_precedence("files:*", "files"), None);
+        assert_eq!(scope_precedence("files:*", "filesystem:read"), None);
+        assert_eq!(scope_precedence("files:read", "files:write"), None);
+        assert_eq!(scope_precedence("files:read:own", "files:read"), None);
+        assert_eq!(scope_precedence("files:*:own", "files:read:own"), None);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{LiteSessionError, TokenFormat, TokenParser};
+
+const CAVEAT_CONTEXT: &str = "lite-session 2021-03-01 caveat chain";
+
+/// A token narrowed offline by its holder with caveats, in the spirit of macaroons.
+/// Every caveat replaces the MAC of the token with a MAC chained off the previous one,
+/// so caveats can be appended without the server key but never removed.
+/// `LiteSessionToken::from_string_attenuated()` verifies the chain and enforces the caveats:
+///
+/// - `expires<SECONDS>` accepts the token until `SECONDS` since the UNIX epoch
+/// - `path=/api/files` accepts the token for the path `/api/files` and the paths below it
+///
+/// Any other caveat is never satisfied
+///
+/// ```
+/// pub struct AttenuatedToken {
+///     token: String,
+///     caveats: Vec<String>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AttenuatedToken {
+    token: String,
+    caveats: Vec<String>,
+}
+
+impl core::cmp::PartialEq for AttenuatedToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.token == other.token && self.caveats == other.caveats
+    }
+}
+
+impl core::clone::Clone for AttenuatedToken {
+    fn clone(&self) -> Self {
+        Self {
+            token: self.token.clone(),
+            caveats: self.caveats.clone(),
+        }
+    }
+}
+
+impl AttenuatedToken {
+    /// Append `caveat` and chain the MAC of the token off the previous one.
+    /// Only tokens in the plain `TokenEncoding` can be attenuated
+    pub fn attenuate(&mut self, caveat: &str) -> Result<&mut Self, LiteSessionError> {
+        let format = TokenFormat::detect(&self.token).unwrap_or_default();
+        let parsed = match TokenParser::default().format(format).parse(&self.token) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(error.into_error()),
+        };
+        let hmac = parsed.get_hmac();
+        let signature = match hex::decode(hmac) {
+            Ok(signature) => signature,
+            Err(_) => return Err(LiteSessionError::InvalidHexString),
+        };
+
+        // The MAC is a slice of the token so its offset locates the field to replace
+        let start = hmac.as_ptr() as usize - self.token.as_ptr() as usize;
+        let chained = hex::encode(chain(&signature, caveat));
+        self.token
+            .replace_range(start..start + hmac.len(), &chained);
+        self.caveats.push(caveat.into());
+
+        Ok(self)
+    }
+    /// Get the token holding the chained MAC
+    pub fn get_token(&self) -> &String {
+        &self.token
+    }
+    /// Get the caveats in the order they were appended
+    pub fn get_caveats(&self) -> &Vec<String> {
+        &self.caveats
+    }
+    /// Serialize the token with its caveats
+    pub fn to_string(attenuated: &AttenuatedToken) -> String {
+        let mut value = format!(
+            "{}.{}",
+            AttenuatedToken::magic(),
+            base64::encode_config(&attenuated.token, base64::URL_SAFE_NO_PAD)
+        );
+        attenuated.caveats.iter().for_each(|caveat| {
+            value.push('.');
+            value.push_str(&base64::encode_config(caveat, base64::URL_SAFE_NO_PAD));
+        });
+
+        value
+    }
+    /// Deserialize a token with its caveats. A token that was never attenuated is read
+    /// as a token without caveats
+    pub fn from_string(value: &str) -> Result<Self, LiteSessionError> {
+        let mut fields = value.split('.');
+        if fields.next() != Some(AttenuatedToken::magic()) {
+            return Ok(Self {
+                token: value.into(),
+                caveats: Vec::default(),
+            });
+        }
+
+        let mut decoded = Vec::new();
+        for field in fields {
+            match base64::decode_config(field, base64::URL_SAFE_NO_PAD)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+            {
+                Some(field) => decoded.push(field),
+                None => return Err(LiteSessionError::InvalidAttenuation),
+            }
+        }
+        if decoded.is_empty() {
+            return Err(LiteSessionError::InvalidAttenuation);
+        }
+        let token = decoded.remove(0);
+
+        Ok(Self {
+            token,
+            caveats: decoded,
+        })
+    }
+
+    fn magic() -> &'static str {
+        "lsat1"
+    }
+}
+
+// The MAC after appending `caveat` to a token whose MAC is `signature`, as long as `signature`
+pub(crate) fn chain(signature: &[u8], caveat: &str) -> Vec<u8> {
+    let mut key_material: Vec<u8> = Vec::new();
+    for field in [signature, caveat.as_bytes()].iter() {
+        key_material.extend_from_slice(&(field.len() as u64).to_be_bytes());
+        key_material.extend_from_slice(field);
+    }
+
+    let mut chained = [0_u8; 32];
+    blake3::derive_key(CAVEAT_CONTEXT, &key_material, &mut chained);
+
+    chained[..signature.len().min(32)].to_vec()
+}
+
+// Whether `caveat` holds for a request to `path` at `now` seconds since the UNIX epoch
+pub(crate) fn is_satisfied(caveat: &str, now: u64, path: &str) -> bool {
+    if let Some(expires) = caveat.strip_prefix("expires<") {
+        return match expires.parse::<u64>() {
+            Ok(expires) => now < expires,
+            Err(_) => false,
+        };
+    }
+    if let Some(allowed) = caveat.strip_prefix("path=") {
+        let allowed = allowed.trim_end_matches('/');
+        return match path.strip_prefix(allowed) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        };
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod caveat_tests {
+    use super::{chain, is_satisfied, AttenuatedToken};
+    use crate::LiteSessionError;
+
+    #[test]
+    fn caveats() {
+        assert!(is_satisfied("expires<100", 99, "/"));
+        assert!(!is_satisfied("expires<100", 100, "/"));
+        assert!(!is_satisfied("expires<soon", 0, "/"));
+        assert!(is_satisfied("path=/api/files", 0, "/api/files"));
+        assert!(is_satisfied("path=/api/files/", 0, "/api/files/foo.txt"));
+        assert!(!is_satisfied("path=/api/files", 0, "/api/filesystem"));
+        assert!(!is_satisfied("path=/api/files", 0, "/api"));
+        assert!(!is_satisfied("role=admin", 0, "/"));
+
+        assert_eq!(chain(&[7_u8; 16], "path=/").len(), 16);
+        assert_ne!(chain(&[7_u8; 32], "path=/"), chain(&[7_u8; 32], "path=/a"));
+    }
+
+    #[test]
+    fn attenuated_token() -> Result<(), LiteSessionError> {
+        let plain = AttenuatedToken::from_string("foo")?;
+        assert_eq!(plain.get_token(), "foo");
+        assert!(plain.get_caveats().is_empty());
+
+        let attenuated = AttenuatedToken {
+            token: "foo".into(),
+            caveats: vec!["path=/api".into(), "expires<100".into()],
+        };
+        let serialized = AttenuatedToken::to_string(&attenuated);
+        assert_eq!(AttenuatedToken::from_string(&serialized)?, attenuated);
+        assert_eq!(
+            AttenuatedToken::from_string("lsat1.%%"),
+            Err(LiteSessionError::InvalidAttenuation)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use crate::LiteSessionError;
+
+/// A built token split into chunks that each fit in a cookie.
+/// Every chunk starts with its ordering metadata `index/count:` where `index` starts at `1`
+/// so the chunks can be reassembled even if they are received out of order
+/// ```
+/// use lite_session::TokenChunks;
+///
+/// let chunks = TokenChunks::split_with("foo⊕bar⊕baz", 12).unwrap();
+/// assert_eq!(chunks.get_chunks()[0], "1/3:foo⊕");
+///
+/// let mut received = chunks.get_chunks().iter().map(|chunk| chunk.as_str()).collect::<Vec<&str>>();
+/// received.reverse();
+/// assert_eq!(TokenChunks::reassemble(&received).unwrap(), "foo⊕bar⊕baz");
+/// ```
+#[derive(Debug)]
+pub struct TokenChunks {
+    chunks: Vec<String>,
+}
+
+impl core::cmp::PartialEq for TokenChunks {
+    fn eq(&self, other: &Self) -> bool {
+        self.chunks == other.chunks
+    }
+}
+
+impl core::clone::Clone for TokenChunks {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+        }
+    }
+}
+
+impl TokenChunks {
+    /// The default maximum length of a chunk in bytes, leaving room for the cookie name and
+    /// attributes within the `4096` bytes browsers allow for a cookie
+    pub const DEFAULT_CHUNK_LEN: usize = 3072;
+
+    /// Split a token into chunks of at most `TokenChunks::DEFAULT_CHUNK_LEN` bytes
+    pub fn split(token: &str) -> Result<Self, LiteSessionError> {
+        TokenChunks::split_with(token, TokenChunks::DEFAULT_CHUNK_LEN)
+    }
+    /// Split a token into chunks of at most `max_chunk_len` bytes including the ordering metadata.
+    /// Chunks are only split at character boundaries
+    pub fn split_with(token: &str, max_chunk_len: usize) -> Result<Self, LiteSessionError> {
+        if token.is_empty() {
+            return Err(LiteSessionError::InvalidTokenChunk);
+        }
+
+        // There are never more chunks than bytes so this bounds the length of the metadata
+        let max_metadata_len = 2 * token.len().to_string().len() + 2;
+        let max_payload_len = match max_chunk_len.checked_sub(max_metadata_len) {
+            Some(max_payload_len) if max_payload_len >= 4 => max_payload_len,
+            _ => return Err(LiteSessionError::InvalidTokenChunk),
+        };
+
+        let mut payloads: Vec<&str> = Vec::new();
+        let mut remaining = token;
+        while !remaining.is_empty() {
+            let mut end = max_payload_len.min(remaining.len());
+            while !remaining.is_char_boundary(end) {
+                end -= 1;
+            }
+            let (payload, rest) = remaining.split_at(end);
+            payloads.push(payload);
+            remaining = rest;
+        }
+
+        let count = payloads.len();
+        let chunks = payloads
+            .iter()
+            .enumerate()
+            .map(|(index, payload)| format!("{}/{}:{}", index + 1, count, payload))
+            .collect();
+
+        Ok(Self { chunks })
+    }
+    /// Get the chunks in order
+    pub fn get_chunks(&self) -> &Vec<String> {
+        &self.chunks
+    }
+    /// Get the number of chunks
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+    /// Check whether there are no chunks
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+    /// Reassemble a token from its chunks in any order.
+    /// Fails with `LiteSessionError::InvalidTokenChunk` if a chunk is missing, duplicated
+    /// or its ordering metadata does not agree with the other chunks
+    pub fn reassemble(chunks: &[&str]) -> Result<String, LiteSessionError> {
+        let mut ordered: Vec<Option<&str>> = vec![None; chunks.len()];
+
+        for chunk in chunks {
+            let (metadata, payload) = match chunk.split_once(':') {
+                Some(parts) => parts,
+                None => return Err(LiteSessionError::InvalidTokenChunk),
+            };
+            let (index, count) = match metadata.split_once('/') {
+                Some((index, count)) => (index.parse::<usize>(), count.parse::<usize>()),
+                None => return Err(LiteSessionError::InvalidTokenChunk),
+            };
+            let index = match (index, count) {
+                (Ok(index), Ok(count)) if count == chunks.len() && index >= 1 && index <= count => {
+                    index
+                }
+                _ => return Err(LiteSessionError::InvalidTokenChunk),
+            };
+
+            match ordered[index - 1] {
+                Some(_) => return Err(LiteSessionError::InvalidTokenChunk),
+                None => ordered[index - 1] = Some(payload),
+            }
+        }
+
+        // Every slot is filled since there are as many distinct indexes as chunks
+        let token: String = ordered.into_iter().flatten().collect();
+        match token.is_empty() {
+            true => Err(LiteSessionError::InvalidTokenChunk),
+            false => Ok(token),
+        }
+    }
+}
+
+#[cfg(test)]
+mod chunks_tests {
+    use super::TokenChunks;
+    use crate::LiteSessionError;
+
+    #[test]
+    fn token_chunks() -> Result<(), LiteSessionError> {
+        let token = "foo⊕".repeat(2000);
+        let chunks = TokenChunks::split(&token)?;
+        assert_eq!(chunks.len(), 4);
+        assert!(chunks
+            .get_chunks()
+            .iter()
+            .all(|chunk| chunk.len() <= TokenChunks::DEFAULT_CHUNK_LEN));
+
+        let mut received: Vec<&str> = chunks.get_chunks().iter().map(|c| c.as_str()).collect();
+        received.swap(0, 3);
+        assert_eq!(TokenChunks::reassemble(&received)?, token);
+
+        // Missing, duplicated and malformed chunks are rejected
+        assert_eq!(
+            TokenChunks::reassemble(&received[..3]),
+            Err(LiteSessionError::InvalidTokenChunk)
+        );
+        received[1] = received[0];
+        assert_eq!(
+            TokenChunks::reassemble(&received),
+            Err(LiteSessionError::InvalidTokenChunk)
+        );
+        assert_eq!(
+            TokenChunks::reassemble(&["foo"]),
+            Err(LiteSessionError::InvalidTokenChunk)
+        );
+        assert_eq!(
+            TokenChunks::reassemble(&[]),
+            Err(LiteSessionError::InvalidTokenChunk)
+        );
+
+        assert_eq!(
+            TokenChunks::split_with(&token, 8),
+            Err(LiteSessionError::InvalidTokenChunk)
+        );
+
+        Ok(())
+    }
+}
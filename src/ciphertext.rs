@@ -1,10 +1,165 @@
-use crate::{LiteSessionData, LiteSessionError, SessionTokenRng};
+use crate::fips;
+use crate::{EntropySource, LiteSessionData, LiteSessionError, OsEntropy, SessionTokenRng};
 use chacha20::{
-    cipher::{NewStreamCipher, StreamCipher, SyncStreamCipher, SyncStreamCipherSeek},
-    ChaCha8, Key, Nonce,
+    cipher::{NewStreamCipher, StreamCipher, SyncStreamCipherSeek},
+    ChaCha12, ChaCha20, ChaCha8, Key, Nonce, XChaCha20, XNonce,
 };
 use core::fmt::Debug;
 
+/// The number of ChaCha rounds. `Rounds::R8` keeps constrained IoT devices fast while
+/// security-conservative deployments can pick the full `Rounds::R20`
+#[derive(Debug, Default)]
+pub enum Rounds {
+    /// `ChaCha8`
+    #[default]
+    R8,
+    /// `ChaCha12`
+    R12,
+    /// `ChaCha20`
+    R20,
+}
+
+impl core::cmp::PartialEq for Rounds {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Rounds::R8, Rounds::R8) | (Rounds::R12, Rounds::R12) | (Rounds::R20, Rounds::R20)
+        )
+    }
+}
+
+impl core::clone::Clone for Rounds {
+    fn clone(&self) -> Self {
+        match self {
+            Rounds::R8 => Rounds::R8,
+            Rounds::R12 => Rounds::R12,
+            Rounds::R20 => Rounds::R20,
+        }
+    }
+}
+
+impl Rounds {
+    /// Get the parameter recorded in the version header, like `r20` in `ls2-r20`.
+    /// `Rounds::R8` is the default and is not recorded
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            Rounds::R8 => None,
+            Rounds::R12 => Some("r12"),
+            Rounds::R20 => Some("r20"),
+        }
+    }
+    /// Get the rounds of a version header parameter. `None` if the parameter is not supported
+    pub fn from_header(parameter: &str) -> Option<Self> {
+        match parameter {
+            "r12" => Some(Rounds::R12),
+            "r20" => Some(Rounds::R20),
+            _ => None,
+        }
+    }
+}
+
+/// The stream cipher used to encrypt the data section of a token.
+/// The cipher is identified by the length of the authenticated nonce field
+/// and the rounds recorded in the version header
+#[derive(Debug)]
+pub enum TokenCipher {
+    /// ChaCha with the given `Rounds` and a `12` random byte nonce carried hex encoded.
+    /// Nonces of `12` alphanumeric characters issued by older versions are still accepted
+    ChaCha(Rounds),
+    /// `XChaCha20` with a `24` random byte nonce carried hex encoded.
+    /// The larger nonce space removes collision concerns for servers issuing
+    /// billions of tokens under one key
+    XChaCha20,
+    /// `AES-256-GCM` with a `12` random byte nonce carried hex encoded, recorded as `aes` in the
+    /// version header. Keys are derived and tokens authenticated with `HMAC-SHA-256` instead of
+    /// `Blake3` so that only FIPS-approved algorithms are used. Requires the `fips` feature
+    Aes256Gcm,
+}
+
+impl Default for TokenCipher {
+    fn default() -> Self {
+        TokenCipher::ChaCha(Rounds::default())
+    }
+}
+
+impl core::cmp::PartialEq for TokenCipher {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (TokenCipher::ChaCha(rounds), TokenCipher::ChaCha(rounds2)) => rounds == rounds2,
+            (TokenCipher::XChaCha20, TokenCipher::XChaCha20)
+            | (TokenCipher::Aes256Gcm, TokenCipher::Aes256Gcm) => true,
+            _ => false,
+        }
+    }
+}
+
+impl core::clone::Clone for TokenCipher {
+    fn clone(&self) -> Self {
+        match self {
+            TokenCipher::ChaCha(rounds) => TokenCipher::ChaCha(rounds.clone()),
+            TokenCipher::XChaCha20 => TokenCipher::XChaCha20,
+            TokenCipher::Aes256Gcm => TokenCipher::Aes256Gcm,
+        }
+    }
+}
+
+impl TokenCipher {
+    /// Get the cipher of a token from its nonce field.
+    /// The nonce does not carry the rounds so `TokenCipher::ChaCha` has the default `Rounds::R8`
+    pub fn from_nonce(nonce: &str) -> Option<TokenCipher> {
+        let is_hex = || nonce.bytes().all(|byte| byte.is_ascii_hexdigit());
+
+        match nonce.len() {
+            12 => Some(TokenCipher::default()),
+            24 if is_hex() => Some(TokenCipher::default()),
+            48 if is_hex() => Some(TokenCipher::XChaCha20),
+            _ => None,
+        }
+    }
+    /// Get the parameter of the cipher recorded in the version header
+    pub fn header(&self) -> Option<&'static str> {
+        match self {
+            TokenCipher::ChaCha(rounds) => rounds.header(),
+            TokenCipher::XChaCha20 => None,
+            TokenCipher::Aes256Gcm => Some("aes"),
+        }
+    }
+    /// Get the cipher of a version header parameter. `None` if the parameter is not supported
+    pub fn from_header(parameter: &str) -> Option<Self> {
+        match parameter {
+            "aes" => Some(TokenCipher::Aes256Gcm),
+            _ => Rounds::from_header(parameter).map(TokenCipher::ChaCha),
+        }
+    }
+    /// Check whether the cipher can be used by this build.
+    /// `TokenCipher::Aes256Gcm` requires the `fips` feature
+    pub fn is_available(&self) -> bool {
+        self != &TokenCipher::Aes256Gcm || cfg!(feature = "fips")
+    }
+    /// Generate a nonce field for the cipher
+    pub fn nonce(&self) -> String {
+        self.nonce_from(&OsEntropy)
+    }
+    /// Generate a nonce field for the cipher from the bytes of `entropy`
+    pub fn nonce_from(&self, entropy: &dyn EntropySource) -> String {
+        match self {
+            TokenCipher::ChaCha(_) | TokenCipher::Aes256Gcm => SessionTokenRng::nonce_from(entropy),
+            TokenCipher::XChaCha20 => hex::encode(SessionTokenRng::bytes_from(entropy, 24)),
+        }
+    }
+    /// Get the raw nonce of a nonce field. Legacy `12` character nonces are used as-is
+    pub(crate) fn nonce_bytes(nonce: &str) -> Result<Vec<u8>, LiteSessionError> {
+        match (TokenCipher::from_nonce(nonce), nonce.len()) {
+            (Some(_), 12) => Ok(nonce.as_bytes().to_vec()),
+            (Some(_), _) => match hex::decode(nonce) {
+                Ok(bytes) => Ok(bytes),
+                Err(_) => Err(LiteSessionError::NonceLengthError),
+            },
+            (None, _) => Err(LiteSessionError::NonceLengthError),
+        }
+    }
+}
+
 /// Holds the generated `ChaCha8` cipher text and r`andom generated nonce`
 #[derive(Debug)]
 pub struct CipherText {
@@ -29,19 +184,34 @@ impl CipherText {
         &mut self,
         ls_data: &LiteSessionData,
         key: &[u8],
+    ) -> Result<&Self, LiteSessionError> {
+        self.encrypt_bytes(
+            ls_data.build().as_bytes(),
+            key,
+            &TokenCipher::default(),
+            &OsEntropy,
+        )
+    }
+
+    pub(crate) fn encrypt_bytes(
+        &mut self,
+        plaintext: &[u8],
+        key: &[u8],
+        token_cipher: &TokenCipher,
+        entropy: &dyn EntropySource,
     ) -> Result<&Self, LiteSessionError> {
         if key.len() != 32 {
             return Err(LiteSessionError::ServerKeyLengthError);
         }
 
-        let nonce_string = SessionTokenRng::nonce();
+        let nonce_string = token_cipher.nonce_from(entropy);
+        let nonce = TokenCipher::nonce_bytes(&nonce_string)?;
 
-        let key = Key::from_slice(key);
-        let nonce = Nonce::from_slice(&nonce_string.as_bytes());
-
-        let mut cipher = ChaCha8::new(&key, &nonce);
-        let mut cipher_text = ls_data.build().into_bytes();
-        cipher.apply_keystream(&mut cipher_text);
+        let mut cipher_text = plaintext.to_vec();
+        match token_cipher {
+            TokenCipher::Aes256Gcm => fips::seal(key, &nonce, &mut cipher_text)?,
+            _ => CipherText::apply_keystream(key, &nonce, token_cipher, &mut cipher_text)?,
+        }
 
         let cipher_hex = hex::encode(cipher_text);
 
@@ -51,40 +221,91 @@ impl CipherText {
         Ok(self)
     }
 
-    /// Decrypts the user data
+    /// Decrypts the user data. The nonce selects the cipher, `12` bytes for `ChaCha8`
+    /// and `24` bytes for `XChaCha20`
     pub fn decrypt(
         &self,
         key: &[u8],
-        mut ciphertext: &mut [u8],
+        ciphertext: &mut [u8],
         nonce: &[u8],
     ) -> Result<LiteSessionData, LiteSessionError> {
+        let token_cipher = match nonce.len() {
+            24 => TokenCipher::XChaCha20,
+            _ => TokenCipher::default(),
+        };
+        self.decrypt_bytes(key, ciphertext, nonce, &token_cipher)?;
+
+        let raw_data = match String::from_utf8(ciphertext.to_vec()) {
+            Ok(data) => data,
+            Err(_) => return Err(LiteSessionError::FromUtf8TokenError),
+        };
+
+        LiteSessionData::default().destructure(&raw_data)
+    }
+
+    // Returns the length of the plaintext at the start of `ciphertext`,
+    // `TokenCipher::Aes256Gcm` leaves its authentication tag at the end
+    pub(crate) fn decrypt_bytes(
+        &self,
+        key: &[u8],
+        ciphertext: &mut [u8],
+        nonce: &[u8],
+        token_cipher: &TokenCipher,
+    ) -> Result<usize, LiteSessionError> {
         if key.len() != 32 {
             return Err(LiteSessionError::ServerKeyLengthError);
         }
 
-        if nonce.len() != 12 {
-            return Err(LiteSessionError::NonceLengthError);
+        match token_cipher {
+            TokenCipher::Aes256Gcm => fips::open(key, nonce, ciphertext),
+            _ => {
+                CipherText::apply_keystream(key, nonce, token_cipher, ciphertext)?;
+                Ok(ciphertext.len())
+            }
         }
+    }
 
+    pub(crate) fn apply_keystream(
+        key: &[u8],
+        nonce: &[u8],
+        token_cipher: &TokenCipher,
+        data: &mut [u8],
+    ) -> Result<(), LiteSessionError> {
         let key = Key::from_slice(key);
-        let nonce = Nonce::from_slice(nonce);
-        let mut cipher = ChaCha8::new(&key, &nonce);
-        cipher.seek(0);
-        cipher.decrypt(&mut ciphertext);
 
-        let raw_data = match String::from_utf8(ciphertext.to_vec()) {
-            Ok(data) => data,
-            Err(_) => return Err(LiteSessionError::FromUtf8TokenError),
-        };
+        match (token_cipher, nonce.len()) {
+            (TokenCipher::ChaCha(Rounds::R8), 12) => {
+                let mut cipher = ChaCha8::new(key, Nonce::from_slice(nonce));
+                cipher.seek(0);
+                cipher.decrypt(data);
+            }
+            (TokenCipher::ChaCha(Rounds::R12), 12) => {
+                let mut cipher = ChaCha12::new(key, Nonce::from_slice(nonce));
+                cipher.seek(0);
+                cipher.decrypt(data);
+            }
+            (TokenCipher::ChaCha(Rounds::R20), 12) => {
+                let mut cipher = ChaCha20::new(key, Nonce::from_slice(nonce));
+                cipher.seek(0);
+                cipher.decrypt(data);
+            }
+            (TokenCipher::XChaCha20, 24) => {
+                let mut cipher = XChaCha20::new(key, XNonce::from_slice(nonce));
+                cipher.seek(0);
+                cipher.decrypt(data);
+            }
+            (TokenCipher::Aes256Gcm, 12) => fips::apply_keystream(key, nonce, data)?,
+            _ => return Err(LiteSessionError::NonceLengthError),
+        }
 
-        LiteSessionData::default().destructure(&raw_data)
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod ciphertext_tests {
-    use super::CipherText;
-    use crate::{LiteSessionData, LiteSessionError, Role};
+    use super::{CipherText, Rounds, TokenCipher};
+    use crate::{LiteSessionData, LiteSessionError, OsEntropy, Role};
 
     #[test]
     fn cipher() -> Result<(), LiteSessionError> {
@@ -92,7 +313,7 @@ mod ciphertext_tests {
 
         data.username("foo_user");
         data.role(Role::SuperUser);
-        data.tag("Foo-Tag");
+        data.tag("env", "prod");
         data.add_acl("Network-TCP");
         data.add_acl("Network-UDP");
 
@@ -108,16 +329,11 @@ mod ciphertext_tests {
             Err(_) => return Err(LiteSessionError::InvalidHexString),
         };
 
-        let decryption = decrypt_ops.decrypt(
-            &bad_key,
-            &mut ciphertext_bytes,
-            &ciphertext.nonce.as_bytes(),
-        )?;
-        let bad_decryption = decrypt_ops.decrypt(
-            &bad_key2,
-            &mut ciphertext_bytes,
-            &ciphertext.nonce.as_bytes(),
-        );
+        let nonce = TokenCipher::nonce_bytes(&ciphertext.nonce)?;
+        assert_eq!(nonce.len(), 12);
+
+        let decryption = decrypt_ops.decrypt(&bad_key, &mut ciphertext_bytes, &nonce)?;
+        let bad_decryption = decrypt_ops.decrypt(&bad_key2, &mut ciphertext_bytes, &nonce);
 
         assert_eq!(data, decryption);
 
@@ -125,4 +341,72 @@ mod ciphertext_tests {
 
         Ok(())
     }
+
+    #[test]
+    fn token_cipher() -> Result<(), LiteSessionError> {
+        assert_eq!(
+            TokenCipher::from_nonce(&TokenCipher::ChaCha(Rounds::R20).nonce()),
+            Some(TokenCipher::ChaCha(Rounds::R8))
+        );
+        assert_eq!(
+            TokenCipher::from_nonce(&TokenCipher::XChaCha20.nonce()),
+            Some(TokenCipher::XChaCha20)
+        );
+        assert_eq!(TokenCipher::from_nonce("foo"), None);
+        assert_eq!(TokenCipher::from_nonce(&"z".repeat(24)), None);
+        // Nonces of older versions are alphanumeric characters used as-is
+        assert_eq!(
+            TokenCipher::from_nonce("abcdefghijkl"),
+            Some(TokenCipher::default())
+        );
+        assert_eq!(
+            TokenCipher::nonce_bytes("abcdefghijkl")?,
+            b"abcdefghijkl".to_vec()
+        );
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let key = [0_u8; 32];
+        let mut ciphertext = CipherText::default();
+        ciphertext.encrypt_bytes(
+            data.build().as_bytes(),
+            &key,
+            &TokenCipher::XChaCha20,
+            &OsEntropy,
+        )?;
+        let mut ciphertext_bytes = hex::decode(&ciphertext.cipher).unwrap();
+        let nonce = TokenCipher::nonce_bytes(&ciphertext.nonce)?;
+        assert_eq!(nonce.len(), 24);
+
+        let decryption = CipherText::default().decrypt(&key, &mut ciphertext_bytes, &nonce)?;
+        assert_eq!(decryption, data);
+
+        // Each round count produces a different keystream
+        let nonce = TokenCipher::nonce_bytes(&TokenCipher::default().nonce())?;
+        let mut ciphertexts: Vec<Vec<u8>> = Vec::new();
+        for rounds in [Rounds::R8, Rounds::R12, Rounds::R20].iter() {
+            let token_cipher = TokenCipher::ChaCha(rounds.clone());
+            let mut bytes = data.build().into_bytes();
+            CipherText::apply_keystream(&key, &nonce, &token_cipher, &mut bytes)?;
+            assert!(!ciphertexts.contains(&bytes));
+            ciphertexts.push(bytes.clone());
+
+            CipherText::default().decrypt_bytes(&key, &mut bytes, &nonce, &token_cipher)?;
+            assert_eq!(bytes, data.build().into_bytes());
+        }
+        assert_eq!(
+            CipherText::apply_keystream(&key, &[0_u8; 24], &TokenCipher::default(), &mut []),
+            Err(LiteSessionError::NonceLengthError)
+        );
+
+        assert_eq!(Rounds::R8.header(), None);
+        assert_eq!(
+            Rounds::R20.header().and_then(Rounds::from_header),
+            Some(Rounds::R20)
+        );
+        assert_eq!(Rounds::from_header("r8"), None);
+
+        Ok(())
+    }
 }
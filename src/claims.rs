@@ -0,0 +1,124 @@
+use crate::{ConfidentialityMode, ParsedToken, TokenVersion};
+use tai64::TAI64N;
+
+/// The public fields of a token read by `LiteSessionToken::peek()` without the server key.
+/// None of the fields have been authenticated so they may only be used to route a token
+/// to the service holding its key
+///
+/// ```
+/// use lite_session::{ConfidentialityMode, TokenVersion};
+/// use tai64::TAI64N;
+///
+/// pub struct TokenClaims {
+///     version: TokenVersion,
+///     identifier: String,
+///     issued: TAI64N,
+///     expiry: TAI64N,
+///     confidentiality: ConfidentialityMode,
+///     key_id: Option<String>,
+///     tenant_id: Option<String>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TokenClaims {
+    version: TokenVersion,
+    identifier: String,
+    issued: TAI64N,
+    expiry: TAI64N,
+    confidentiality: ConfidentialityMode,
+    key_id: Option<String>,
+    tenant_id: Option<String>,
+}
+
+impl core::cmp::PartialEq for TokenClaims {
+    fn eq(&self, other: &Self) -> bool {
+        self.version == other.version
+            && self.identifier == other.identifier
+            && self.issued == other.issued
+            && self.expiry == other.expiry
+            && self.confidentiality == other.confidentiality
+            && self.key_id == other.key_id
+            && self.tenant_id == other.tenant_id
+    }
+}
+
+impl core::clone::Clone for TokenClaims {
+    fn clone(&self) -> Self {
+        Self {
+            version: self.version.clone(),
+            identifier: self.identifier.clone(),
+            issued: self.issued,
+            expiry: self.expiry,
+            confidentiality: self.confidentiality.clone(),
+            key_id: self.key_id.clone(),
+            tenant_id: self.tenant_id.clone(),
+        }
+    }
+}
+
+impl TokenClaims {
+    /// Get the version of the token
+    pub fn get_version(&self) -> &TokenVersion {
+        &self.version
+    }
+    /// Get the identifier
+    pub fn get_identifier(&self) -> &String {
+        &self.identifier
+    }
+    /// Get the time the token claims to be issued
+    pub fn get_issued(&self) -> &TAI64N {
+        &self.issued
+    }
+    /// Get the time the token claims to expire
+    pub fn get_expiry(&self) -> &TAI64N {
+        &self.expiry
+    }
+    /// Get the `ConfidentialityMode`
+    pub fn get_confidentiality(&self) -> &ConfidentialityMode {
+        &self.confidentiality
+    }
+    /// Get the ID of the key the token claims to be built with
+    pub fn get_key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+    /// Get the `TenantId` the token claims to be issued for
+    pub fn get_tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    pub(crate) fn from_parsed(parsed: &ParsedToken) -> Self {
+        Self {
+            version: parsed.get_version().clone(),
+            identifier: parsed.get_identifier().into(),
+            issued: *parsed.get_issued(),
+            expiry: *parsed.get_expiry(),
+            confidentiality: ConfidentialityMode::from_string(parsed.get_confidentiality()),
+            key_id: parsed.get_key_id().map(Into::into),
+            tenant_id: parsed.get_tenant_id().map(Into::into),
+        }
+    }
+}
+
+#[cfg(test)]
+mod claims_tests {
+    use super::TokenClaims;
+    use crate::{ConfidentialityMode, TokenParser, TokenVersion};
+
+    #[test]
+    fn token_claims() {
+        let token = "ls2-kfoo⊕abc⊕400000005f0d3f0400000000⊕400000005f0e907400000000⊕00⊕000000000000000000000000⊕ConfidentialityMode::High⊕0000000000000000000000000000000000000000000000000000000000000000";
+        let parsed = match TokenParser::default().parse(token) {
+            Ok(parsed) => parsed,
+            Err(error) => panic!("{:?}", error),
+        };
+        let claims = TokenClaims::from_parsed(&parsed);
+
+        assert_eq!(claims.get_version(), &TokenVersion::V2);
+        assert_eq!(claims.get_identifier(), "abc");
+        assert_eq!(claims.get_issued(), parsed.get_issued());
+        assert_eq!(claims.get_confidentiality(), &ConfidentialityMode::High);
+        assert_eq!(claims.get_key_id(), Some("foo"));
+        assert_eq!(claims.get_tenant_id(), None);
+        assert_eq!(claims.clone(), claims);
+    }
+}
@@ -0,0 +1,60 @@
+use tai64::TAI64N;
+
+/// A source of the current time for issuing and verifying tokens.
+/// `SystemClock` is used unless another clock is set, inject a `FixedClock`
+/// with `LiteSessionToken::clock()` to test expiry or to run without a wall clock
+pub trait Clock: core::fmt::Debug + Send + Sync {
+    /// Get the current time
+    fn now(&self) -> TAI64N;
+}
+
+/// The wall clock of the operating system
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> TAI64N {
+        TAI64N::now()
+    }
+}
+
+/// A clock that always returns the same time
+///
+/// ```
+/// use lite_session::{Clock, FixedClock};
+/// use tai64::TAI64N;
+///
+/// let time = TAI64N::now();
+/// assert_eq!(FixedClock::new(time).now(), time);
+/// ```
+#[derive(Debug)]
+pub struct FixedClock(TAI64N);
+
+impl FixedClock {
+    /// Create a clock stopped at `time`
+    pub fn new(time: TAI64N) -> Self {
+        Self(time)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> TAI64N {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod clock_tests {
+    use super::{Clock, FixedClock, SystemClock};
+    use tai64::TAI64N;
+
+    #[test]
+    fn clocks() {
+        let before = TAI64N::now();
+        assert!(SystemClock.now() >= before);
+
+        let fixed = FixedClock::new(before);
+        assert_eq!(fixed.now(), before);
+        assert_eq!(fixed.now(), fixed.now());
+    }
+}
@@ -0,0 +1,47 @@
+use crate::LiteSessionError;
+
+/// Prefixes the hex encoded data section of a token whose data was deflate-compressed
+/// before encryption. The marker is covered by the MAC like the rest of the data section
+pub(crate) const COMPRESSED_MARKER: char = 'z';
+
+// A small token must not inflate into a huge allocation
+#[cfg(feature = "compression")]
+const MAX_INFLATED_LEN: usize = 1024 * 1024;
+
+#[cfg(feature = "compression")]
+pub(crate) fn deflate(data: &[u8]) -> Vec<u8> {
+    miniz_oxide::deflate::compress_to_vec(data, 6)
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn inflate(data: &[u8]) -> Result<Vec<u8>, LiteSessionError> {
+    match miniz_oxide::inflate::decompress_to_vec_with_limit(data, MAX_INFLATED_LEN) {
+        Ok(inflated) => Ok(inflated),
+        Err(_) => Err(LiteSessionError::CompressionError),
+    }
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn inflate(_data: &[u8]) -> Result<Vec<u8>, LiteSessionError> {
+    Err(LiteSessionError::CompressionError)
+}
+
+#[cfg(all(test, feature = "compression"))]
+mod compression_tests {
+    use super::{deflate, inflate};
+    use crate::LiteSessionError;
+
+    #[test]
+    fn deflate_inflate() -> Result<(), LiteSessionError> {
+        let data = "Network-TCP⊕".repeat(100);
+        let compressed = deflate(data.as_bytes());
+        assert!(compressed.len() < data.len());
+        assert_eq!(inflate(&compressed)?, data.as_bytes());
+
+        // A deflate bomb is cut off at the limit
+        let bomb = deflate(&vec![0_u8; 2 * 1024 * 1024]);
+        assert_eq!(inflate(&bomb), Err(LiteSessionError::CompressionError));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,536 @@
+use crate::{
+    AlphanumericIdentifier, BytesIdentifier, IdentifierStrategy, LiteSessionData, LiteSessionToken,
+    MacLength, RefreshDelivery, RefreshPolicy, Role, RoleTtls, UlidIdentifier,
+    UsernameNormalization, UuidIdentifier,
+};
+use std::collections::BTreeMap;
+use timelite::LiteDuration;
+
+#[cfg(any(feature = "json", feature = "toml"))]
+use crate::LiteSessionError;
+
+/// The built-in identifier strategies that can be named in an `IssuerConfig`
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[derive(Debug, Default)]
+pub enum IdentifierKind {
+    /// `AlphanumericIdentifier`
+    #[default]
+    Alphanumeric,
+    /// `UlidIdentifier`
+    Ulid,
+    /// `UuidIdentifier`
+    Uuid,
+    /// `BytesIdentifier` with its default of `32` random bytes
+    Bytes,
+}
+
+impl core::cmp::PartialEq for IdentifierKind {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (IdentifierKind::Alphanumeric, IdentifierKind::Alphanumeric)
+                | (IdentifierKind::Ulid, IdentifierKind::Ulid)
+                | (IdentifierKind::Uuid, IdentifierKind::Uuid)
+                | (IdentifierKind::Bytes, IdentifierKind::Bytes)
+        )
+    }
+}
+
+impl core::clone::Clone for IdentifierKind {
+    fn clone(&self) -> Self {
+        match self {
+            IdentifierKind::Alphanumeric => IdentifierKind::Alphanumeric,
+            IdentifierKind::Ulid => IdentifierKind::Ulid,
+            IdentifierKind::Uuid => IdentifierKind::Uuid,
+            IdentifierKind::Bytes => IdentifierKind::Bytes,
+        }
+    }
+}
+
+impl IdentifierKind {
+    /// Get the `IdentifierStrategy` named by this kind
+    pub fn strategy(&self) -> &'static dyn IdentifierStrategy {
+        match self {
+            IdentifierKind::Alphanumeric => &AlphanumericIdentifier,
+            IdentifierKind::Ulid => &UlidIdentifier,
+            IdentifierKind::Uuid => &UuidIdentifier,
+            IdentifierKind::Bytes => &BytesIdentifier(32),
+        }
+    }
+}
+
+/// The attributes of the cookie carrying the token
+///
+/// ```
+/// pub struct CookieProfile {
+///     name: String,
+///     path: String,
+///     domain: Option<String>,
+///     secure: bool,
+///     http_only: bool,
+///     same_site: String,
+/// }
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Debug)]
+pub struct CookieProfile {
+    name: String,
+    path: String,
+    domain: Option<String>,
+    secure: bool,
+    http_only: bool,
+    same_site: String,
+}
+
+impl Default for CookieProfile {
+    fn default() -> Self {
+        Self {
+            name: "lite_session".into(),
+            path: "/".into(),
+            domain: None,
+            secure: true,
+            http_only: true,
+            same_site: "Strict".into(),
+        }
+    }
+}
+
+impl core::cmp::PartialEq for CookieProfile {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.path == other.path
+            && self.domain == other.domain
+            && self.secure == other.secure
+            && self.http_only == other.http_only
+            && self.same_site == other.same_site
+    }
+}
+
+impl core::clone::Clone for CookieProfile {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            path: self.path.clone(),
+            domain: self.domain.clone(),
+            secure: self.secure,
+            http_only: self.http_only,
+            same_site: self.same_site.clone(),
+        }
+    }
+}
+
+impl CookieProfile {
+    /// Set the name of the cookie
+    pub fn name(&mut self, name: &str) -> &mut Self {
+        self.name = name.into();
+
+        self
+    }
+    /// Set the path of the cookie
+    pub fn path(&mut self, path: &str) -> &mut Self {
+        self.path = path.into();
+
+        self
+    }
+    /// Set the domain of the cookie
+    pub fn domain(&mut self, domain: &str) -> &mut Self {
+        self.domain = Some(domain.into());
+
+        self
+    }
+    /// Set whether the cookie is only sent over HTTPS
+    pub fn secure(&mut self, secure: bool) -> &mut Self {
+        self.secure = secure;
+
+        self
+    }
+    /// Set whether the cookie is hidden from scripts
+    pub fn http_only(&mut self, http_only: bool) -> &mut Self {
+        self.http_only = http_only;
+
+        self
+    }
+    /// Set the `SameSite` attribute of the cookie, one of `Strict`, `Lax` or `None`
+    pub fn same_site(&mut self, same_site: &str) -> &mut Self {
+        self.same_site = same_site.into();
+
+        self
+    }
+    /// Get the name of the cookie
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+    /// Get the path of the cookie
+    pub fn get_path(&self) -> &String {
+        &self.path
+    }
+    /// Get the domain of the cookie
+    pub fn get_domain(&self) -> &Option<String> {
+        &self.domain
+    }
+    /// Check whether the cookie is only sent over HTTPS
+    pub fn is_secure(&self) -> bool {
+        self.secure
+    }
+    /// Check whether the cookie is hidden from scripts
+    pub fn is_http_only(&self) -> bool {
+        self.http_only
+    }
+    /// Get the `SameSite` attribute of the cookie
+    pub fn get_same_site(&self) -> &String {
+        &self.same_site
+    }
+}
+
+/// The token policy of an issuer that can be shared by a fleet of services as one
+/// reviewed configuration file. It never holds server keys
+///
+/// ```
+/// use lite_session::{CookieProfile, IdentifierKind, UsernameNormalization};
+/// use std::collections::BTreeMap;
+///
+/// pub struct IssuerConfig {
+///     default_ttl: u64,
+///     identifier: IdentifierKind,
+///     confidential: bool,
+///     truncated_mac: bool,
+///     refresh_threshold: u8,
+///     username_normalization: UsernameNormalization,
+///     role_ttls: BTreeMap<String, u64>,
+///     cookie: CookieProfile,
+/// }
+/// ```
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+#[derive(Debug)]
+pub struct IssuerConfig {
+    default_ttl: u64,
+    identifier: IdentifierKind,
+    confidential: bool,
+    truncated_mac: bool,
+    refresh_threshold: u8,
+    // TOML requires the plain values to come before the `role_ttls` and `cookie` tables
+    username_normalization: UsernameNormalization,
+    role_ttls: BTreeMap<String, u64>,
+    cookie: CookieProfile,
+}
+
+impl Default for IssuerConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl: LiteDuration::hours(24),
+            identifier: IdentifierKind::default(),
+            confidential: true,
+            truncated_mac: false,
+            refresh_threshold: 20,
+            role_ttls: BTreeMap::default(),
+            cookie: CookieProfile::default(),
+            username_normalization: UsernameNormalization::default(),
+        }
+    }
+}
+
+impl core::cmp::PartialEq for IssuerConfig {
+    fn eq(&self, other: &Self) -> bool {
+        self.default_ttl == other.default_ttl
+            && self.role_ttls == other.role_ttls
+            && self.identifier == other.identifier
+            && self.confidential == other.confidential
+            && self.truncated_mac == other.truncated_mac
+            && self.refresh_threshold == other.refresh_threshold
+            && self.cookie == other.cookie
+            && self.username_normalization == other.username_normalization
+    }
+}
+
+impl core::clone::Clone for IssuerConfig {
+    fn clone(&self) -> Self {
+        Self {
+            default_ttl: self.default_ttl,
+            role_ttls: self.role_ttls.clone(),
+            identifier: self.identifier.clone(),
+            confidential: self.confidential,
+            truncated_mac: self.truncated_mac,
+            refresh_threshold: self.refresh_threshold,
+            cookie: self.cookie.clone(),
+            username_normalization: self.username_normalization.clone(),
+        }
+    }
+}
+
+impl IssuerConfig {
+    /// Set the lifetime in seconds of tokens issued without an explicit expiry
+    pub fn default_ttl(&mut self, ttl_in_secs: u64) -> &mut Self {
+        self.default_ttl = ttl_in_secs;
+
+        self
+    }
+    /// Set the maximum lifetime in seconds of tokens issued to `role`
+    pub fn role_ttl(&mut self, role: Role, max_ttl_in_secs: u64) -> &mut Self {
+        self.role_ttls
+            .insert(Role::to_string(&role), max_ttl_in_secs);
+
+        self
+    }
+    /// Set the strategy used to generate identifiers
+    pub fn identifier(&mut self, identifier: IdentifierKind) -> &mut Self {
+        self.identifier = identifier;
+
+        self
+    }
+    /// Set whether the data section of tokens is encrypted
+    pub fn confidential(&mut self, confidential: bool) -> &mut Self {
+        self.confidential = confidential;
+
+        self
+    }
+    /// Set whether tokens carry `MacLength::Truncated` MACs instead of `MacLength::Full`
+    pub fn truncated_mac(&mut self, truncated_mac: bool) -> &mut Self {
+        self.truncated_mac = truncated_mac;
+
+        self
+    }
+    /// Set the percentage of the lifetime below which tokens are re-issued
+    pub fn refresh_threshold(&mut self, percent: u8) -> &mut Self {
+        self.refresh_threshold = percent;
+
+        self
+    }
+    /// Set the attributes of the cookie carrying the token
+    pub fn cookie(&mut self, cookie: CookieProfile) -> &mut Self {
+        self.cookie = cookie;
+
+        self
+    }
+    /// Set how the usernames of the data created by `data()` are normalized
+    pub fn username_normalization(&mut self, normalization: UsernameNormalization) -> &mut Self {
+        self.username_normalization = normalization;
+
+        self
+    }
+    /// Get the lifetime in seconds of tokens issued without an explicit expiry
+    pub fn get_default_ttl(&self) -> u64 {
+        self.default_ttl
+    }
+    /// Get the maximum lifetimes of tokens per role
+    pub fn get_role_ttls(&self) -> RoleTtls {
+        let mut role_ttls = RoleTtls::default();
+        self.role_ttls.iter().for_each(|(role, ttl)| {
+            role_ttls.insert(Role::from_str(role), *ttl);
+        });
+
+        role_ttls
+    }
+    /// Get the strategy used to generate identifiers
+    pub fn get_identifier(&self) -> &IdentifierKind {
+        &self.identifier
+    }
+    /// Check whether the data section of tokens is encrypted
+    pub fn is_confidential(&self) -> bool {
+        self.confidential
+    }
+    /// Get the length of the MACs of tokens
+    pub fn get_mac_length(&self) -> MacLength {
+        match self.truncated_mac {
+            true => MacLength::Truncated,
+            false => MacLength::Full,
+        }
+    }
+    /// Get the attributes of the cookie carrying the token
+    pub fn get_cookie(&self) -> &CookieProfile {
+        &self.cookie
+    }
+    /// Get how the usernames of the data created by `data()` are normalized
+    pub fn get_username_normalization(&self) -> &UsernameNormalization {
+        &self.username_normalization
+    }
+    /// Get the `RefreshPolicy` that replaces the session cookie of near-expiry tokens
+    pub fn refresh_policy(&self) -> RefreshPolicy {
+        let mut policy = RefreshPolicy::default();
+        policy
+            .threshold(self.refresh_threshold)
+            .delivery(RefreshDelivery::Cookie(self.cookie.name.clone()));
+
+        policy
+    }
+    /// Configure a token to be issued according to this configuration
+    pub fn apply<'a>(&self, token: &'a mut LiteSessionToken) -> &'a mut LiteSessionToken {
+        token
+            .identifier_strategy(self.identifier.strategy())
+            .expiry(self.default_ttl)
+            .role_ttls(self.get_role_ttls())
+            .confidential(self.confidential)
+            .mac_length(self.get_mac_length())
+    }
+    /// Create a token to be issued according to this configuration
+    pub fn issuer(&self) -> LiteSessionToken {
+        let mut token = LiteSessionToken::default();
+        self.apply(&mut token);
+
+        token
+    }
+    /// Create the data of a token to be issued according to this configuration
+    pub fn data(&self) -> LiteSessionData {
+        let mut data = LiteSessionData::default();
+        data.username_normalization(self.username_normalization.clone());
+
+        data
+    }
+    /// Deserialize the configuration from JSON
+    #[cfg(feature = "json")]
+    pub fn from_json(value: &str) -> Result<Self, LiteSessionError> {
+        match serde_json::from_str(value) {
+            Ok(config) => Ok(config),
+            Err(_) => Err(LiteSessionError::InvalidIssuerConfig),
+        }
+    }
+    /// Serialize the configuration to JSON
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, LiteSessionError> {
+        match serde_json::to_string_pretty(self) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(LiteSessionError::InvalidIssuerConfig),
+        }
+    }
+    /// Deserialize the configuration from TOML
+    #[cfg(feature = "toml")]
+    pub fn from_toml(value: &str) -> Result<Self, LiteSessionError> {
+        match toml::from_str(value) {
+            Ok(config) => Ok(config),
+            Err(_) => Err(LiteSessionError::InvalidIssuerConfig),
+        }
+    }
+    /// Serialize the configuration to TOML
+    #[cfg(feature = "toml")]
+    pub fn to_toml(&self) -> Result<String, LiteSessionError> {
+        match toml::to_string_pretty(self) {
+            Ok(value) => Ok(value),
+            Err(_) => Err(LiteSessionError::InvalidIssuerConfig),
+        }
+    }
+    /// Load the configuration from a `.json` or `.toml` file
+    #[cfg(any(feature = "json", feature = "toml"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, LiteSessionError> {
+        let path = path.as_ref();
+        let value = match std::fs::read_to_string(path) {
+            Ok(value) => value,
+            Err(_) => return Err(LiteSessionError::ConfigIoError),
+        };
+
+        match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "json")]
+            Some("json") => IssuerConfig::from_json(&value),
+            #[cfg(feature = "toml")]
+            Some("toml") => IssuerConfig::from_toml(&value),
+            _ => Err(LiteSessionError::InvalidIssuerConfig),
+        }
+    }
+    /// Save the configuration to a `.json` or `.toml` file
+    #[cfg(any(feature = "json", feature = "toml"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), LiteSessionError> {
+        let path = path.as_ref();
+        let value = match path.extension().and_then(|extension| extension.to_str()) {
+            #[cfg(feature = "json")]
+            Some("json") => self.to_json()?,
+            #[cfg(feature = "toml")]
+            Some("toml") => self.to_toml()?,
+            _ => return Err(LiteSessionError::InvalidIssuerConfig),
+        };
+
+        match std::fs::write(path, value) {
+            Ok(_) => Ok(()),
+            Err(_) => Err(LiteSessionError::ConfigIoError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod config_tests {
+    use super::{CookieProfile, IdentifierKind, IssuerConfig};
+    use crate::{
+        LiteSessionData, LiteSessionError, MacLength, Role, ServerKey, UsernameNormalization,
+    };
+
+    fn config() -> IssuerConfig {
+        let mut cookie = CookieProfile::default();
+        cookie.name("sid").domain("example.com").same_site("Lax");
+
+        let mut config = IssuerConfig::default();
+        config
+            .default_ttl(7200)
+            .role_ttl(Role::SuperUser, 3600)
+            .identifier(IdentifierKind::Uuid)
+            .truncated_mac(true)
+            .cookie(cookie)
+            .username_normalization(UsernameNormalization::NfcCaseFold);
+
+        config
+    }
+
+    #[test]
+    fn issuer_config() -> Result<(), LiteSessionError> {
+        let config = config();
+        assert_eq!(config.get_role_ttls().get(&Role::SuperUser), Some(3600));
+        assert_eq!(config.get_mac_length(), MacLength::Truncated);
+
+        let mut data = config.data();
+        data.username("JOSE\u{301}");
+        data.role(Role::SuperUser);
+        data.add_acl("Network-TCP");
+        assert_eq!(data.get_username(), "jos\u{e9}");
+        assert_eq!(
+            LiteSessionData::default()
+                .username("JOSE\u{301}")
+                .get_username(),
+            "JOS\u{c9}"
+        );
+
+        let mut token = config.issuer();
+        token.hmac_data(data);
+        assert_eq!(token.get_identifier().len(), 36_usize);
+        token.build_secure(&ServerKey::new([0_u8; 32]))?;
+        assert_eq!(
+            token
+                .get_expiry()
+                .duration_since(token.get_issued())
+                .map(|lifetime| lifetime.as_secs()),
+            Ok(3600)
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_config() -> Result<(), LiteSessionError> {
+        let config = config();
+        let json = config.to_json()?;
+        assert!(json.contains("\"identifier\": \"uuid\""));
+        assert!(json.contains("\"username_normalization\": \"nfccasefold\""));
+        assert_eq!(IssuerConfig::from_json(&json)?, config);
+        assert_eq!(IssuerConfig::from_json("{}")?, IssuerConfig::default());
+
+        let path = std::env::temp_dir().join("lite_session_issuer_config.json");
+        config.save(&path)?;
+        assert_eq!(IssuerConfig::load(&path)?, config);
+        let _ = std::fs::remove_file(&path);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_config() -> Result<(), LiteSessionError> {
+        let config = config();
+        let toml = config.to_toml()?;
+        assert_eq!(IssuerConfig::from_toml(&toml)?, config);
+        assert_eq!(
+            IssuerConfig::from_toml("default_ttl = \"foo\""),
+            Err(LiteSessionError::InvalidIssuerConfig)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{CookieProfile, LiteSessionError, LiteSessionToken, ServerKey, TokenOutcome};
+use ::cookie::{time::Duration, Cookie, SameSite};
+
+impl LiteSessionToken {
+    /// Build the token with `High Confidentiality` and issue it as a cookie named `name`.
+    /// The cookie is `HttpOnly`, `Secure` and `SameSite=Strict` with its `Max-Age`
+    /// set to the time left until the token expires
+    pub fn to_cookie(
+        &mut self,
+        server_key: &ServerKey,
+        name: &str,
+    ) -> Result<Cookie<'static>, LiteSessionError> {
+        let mut profile = CookieProfile::default();
+        profile.name(name);
+
+        self.to_cookie_with_profile(server_key, &profile)
+    }
+    /// Build the token with `High Confidentiality` and issue it as a cookie with the name,
+    /// path, domain and attributes of a `CookieProfile`. The `Max-Age` is set to the time
+    /// left until the token expires
+    pub fn to_cookie_with_profile(
+        &mut self,
+        server_key: &ServerKey,
+        profile: &CookieProfile,
+    ) -> Result<Cookie<'static>, LiteSessionError> {
+        let same_site = match profile.get_same_site().to_ascii_lowercase().as_str() {
+            "strict" => SameSite::Strict,
+            "lax" => SameSite::Lax,
+            "none" => SameSite::None,
+            _ => return Err(LiteSessionError::InvalidIssuerConfig),
+        };
+
+        let session_token = self.build_secure(server_key)?;
+        let max_age = match self.get_expiry().duration_since(&self.get_clock().now()) {
+            Ok(remaining) => remaining.as_secs(),
+            Err(_) => 0,
+        };
+
+        let mut cookie = Cookie::build((profile.get_name().clone(), session_token))
+            .path(profile.get_path().clone())
+            .secure(profile.is_secure())
+            .http_only(profile.is_http_only())
+            .same_site(same_site)
+            .max_age(Duration::seconds(max_age as i64))
+            .build();
+        if let Some(domain) = profile.get_domain() {
+            cookie.set_domain(domain.clone());
+        }
+
+        Ok(cookie)
+    }
+    /// Destructure and authenticate the token carried by a cookie
+    pub fn from_cookie(
+        &mut self,
+        server_key: &ServerKey,
+        cookie: &Cookie<'_>,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.from_string(server_key, cookie.value())
+    }
+}
+
+#[cfg(test)]
+mod cookie_tests {
+    use crate::{
+        CookieProfile, LiteSessionData, LiteSessionError, LiteSessionToken, ServerKey, TokenOutcome,
+    };
+    use ::cookie::{Cookie, SameSite};
+
+    #[test]
+    fn token_cookie() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).expiry(60 * 60);
+        let cookie = token.to_cookie(&server_key, "sid")?;
+
+        assert_eq!(cookie.name(), "sid");
+        assert_eq!(cookie.http_only(), Some(true));
+        assert_eq!(cookie.secure(), Some(true));
+        assert_eq!(cookie.same_site(), Some(SameSite::Strict));
+        let max_age = cookie.max_age().unwrap().whole_seconds();
+        assert!(max_age > 60 * 59 && max_age <= 60 * 60);
+
+        // The cookie survives a round trip through its header value
+        let parsed = Cookie::parse(cookie.to_string()).unwrap();
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_cookie(&server_key, &parsed)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        let mut profile = CookieProfile::default();
+        profile.name("sid").domain("example.com").same_site("Lax");
+        let cookie = token.to_cookie_with_profile(&server_key, &profile)?;
+        assert_eq!(cookie.domain(), Some("example.com"));
+        assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+
+        profile.same_site("Foo");
+        assert_eq!(
+            token.to_cookie_with_profile(&server_key, &profile),
+            Err(LiteSessionError::InvalidIssuerConfig)
+        );
+
+        Ok(())
+    }
+}
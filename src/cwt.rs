@@ -0,0 +1,341 @@
+use crate::{LiteSessionData, LiteSessionError, LiteSessionToken, Quota, Role, TokenOutcome};
+use ciborium::value::Value;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::convert::TryFrom;
+use tai64::{TAI64, TAI64N};
+
+type HmacSha256 = Hmac<Sha256>;
+
+// Registered CWT claim keys from RFC 8392
+const CLAIM_ISS: i64 = 1;
+const CLAIM_SUB: i64 = 2;
+const CLAIM_AUD: i64 = 3;
+const CLAIM_EXP: i64 = 4;
+const CLAIM_IAT: i64 = 6;
+const CLAIM_CTI: i64 = 7;
+
+// COSE header and algorithm values from RFC 8152
+const COSE_MAC0_TAG: u64 = 17;
+const HEADER_ALG: i64 = 1;
+const ALG_HMAC_256_256: i64 = 5;
+
+impl LiteSessionToken {
+    /// Export the token as a CBOR Web Token in a `COSE_Mac0` structure authenticated using
+    /// `HMAC 256/256` and `mac_key`, for CoAP and MQTT devices that cannot carry the string token.
+    /// The `cti`, `sub`, `iat`, `exp`, `aud` and `iss` claims hold the identifier, username, issued and
+    /// expiry times, audience and issuer while the `role`, `tags`, `acl`, `acl_deny`, `acl_exp` and `quota` text keys hold the rest of the data.
+    /// The claims are authenticated but not encrypted
+    pub fn to_cwt(&self, mac_key: &[u8]) -> Result<Vec<u8>, LiteSessionError> {
+        let data = self.get_hmac_data();
+
+        let mut claims = vec![
+            (
+                Value::from(CLAIM_CTI),
+                Value::Bytes(self.get_identifier().as_bytes().to_vec()),
+            ),
+            (
+                Value::from(CLAIM_SUB),
+                Value::Text(data.get_username().clone()),
+            ),
+            (
+                Value::from(CLAIM_IAT),
+                Value::from(self.get_issued().0.to_unix()),
+            ),
+            (
+                Value::from(CLAIM_EXP),
+                Value::from(self.get_expiry().0.to_unix()),
+            ),
+            (
+                Value::Text("role".into()),
+                Value::Text(Role::to_string(data.get_role())),
+            ),
+        ];
+        if !data.get_tags().is_empty() {
+            let tags = data
+                .get_tags()
+                .iter()
+                .map(|(key, value)| (Value::Text(key.clone()), Value::Text(value.clone())))
+                .collect();
+            claims.push((Value::Text("tags".into()), Value::Map(tags)));
+        }
+        claims.push((
+            Value::Text("acl".into()),
+            Value::Array(data.get_acl().iter().cloned().map(Value::Text).collect()),
+        ));
+        if !data.get_acl_deny().is_empty() {
+            claims.push((
+                Value::Text("acl_deny".into()),
+                Value::Array(
+                    data.get_acl_deny()
+                        .iter()
+                        .cloned()
+                        .map(Value::Text)
+                        .collect(),
+                ),
+            ));
+        }
+        let acl_exp: Vec<(Value, Value)> = data
+            .get_acl()
+            .iter()
+            .filter_map(|acl| {
+                data.get_acl_expiry(acl)
+                    .map(|expiry| (Value::Text(acl.clone()), Value::from(expiry.0.to_unix())))
+            })
+            .collect();
+        if !acl_exp.is_empty() {
+            claims.push((Value::Text("acl_exp".into()), Value::Map(acl_exp)));
+        }
+        if let Some(quota) = data.get_quota() {
+            claims.push((
+                Value::Text("quota".into()),
+                Value::Text(Quota::to_string(quota)),
+            ));
+        }
+        if let Some(audience) = data.get_audience() {
+            claims.push((Value::from(CLAIM_AUD), Value::Text(audience.clone())));
+        }
+        if let Some(issuer) = data.get_issuer() {
+            claims.push((Value::from(CLAIM_ISS), Value::Text(issuer.clone())));
+        }
+
+        let protected = cbor_encode(&Value::Map(vec![(
+            Value::from(HEADER_ALG),
+            Value::from(ALG_HMAC_256_256),
+        )]))?;
+        let payload = cbor_encode(&Value::Map(claims))?;
+        let tag = cwt_mac(mac_key, &protected, &payload)?
+            .finalize()
+            .into_bytes()
+            .to_vec();
+
+        cbor_encode(&Value::Tag(
+            COSE_MAC0_TAG,
+            Box::new(Value::Array(vec![
+                Value::Bytes(protected),
+                Value::Map(Vec::new()),
+                Value::Bytes(payload),
+                Value::Bytes(tag),
+            ])),
+        ))
+    }
+}
+
+impl LiteSessionData {
+    /// Import the claims of a CBOR Web Token in a `COSE_Mac0` structure authenticated using
+    /// `HMAC 256/256` and `mac_key`. Returns `TokenOutcome::TokenRejected` if the tag does
+    /// not match and `TokenOutcome::SessionExpired` if the `exp` claim has passed
+    pub fn from_cwt(
+        cwt: &[u8],
+        mac_key: &[u8],
+    ) -> Result<(TokenOutcome, LiteSessionData), LiteSessionError> {
+        let cose = match ciborium::de::from_reader::<Value, _>(cwt) {
+            Ok(Value::Tag(COSE_MAC0_TAG, cose)) => *cose,
+            Ok(cose @ Value::Array(_)) => cose,
+            _ => return Err(LiteSessionError::InvalidCwt),
+        };
+        let (protected, payload, tag) = match cose {
+            Value::Array(items) => match &items[..] {
+                [Value::Bytes(protected), Value::Map(_), Value::Bytes(payload), Value::Bytes(tag)] => {
+                    (protected.clone(), payload.clone(), tag.clone())
+                }
+                _ => return Err(LiteSessionError::InvalidCwt),
+            },
+            _ => return Err(LiteSessionError::InvalidCwt),
+        };
+
+        let algorithm = cbor_map(&protected)?
+            .into_iter()
+            .find(|(key, _)| cbor_int(key) == Some(HEADER_ALG))
+            .and_then(|(_, value)| cbor_int(&value));
+        if algorithm != Some(ALG_HMAC_256_256) {
+            return Err(LiteSessionError::InvalidCwt);
+        }
+
+        if cwt_mac(mac_key, &protected, &payload)?
+            .verify_slice(&tag)
+            .is_err()
+        {
+            return Ok((TokenOutcome::TokenRejected, LiteSessionData::default()));
+        }
+
+        let claims = cbor_map(&payload)?;
+        let claim = |name: &str, key: Option<i64>| {
+            claims.iter().find_map(|(claim_key, value)| {
+                let matches = match (claim_key, key) {
+                    (Value::Text(text), _) => text == name,
+                    (claim_key, Some(key)) => cbor_int(claim_key) == Some(key),
+                    _ => false,
+                };
+                match matches {
+                    true => Some(value),
+                    false => None,
+                }
+            })
+        };
+
+        let expiry = match claim("exp", Some(CLAIM_EXP)).and_then(cbor_int) {
+            Some(expiry) => expiry,
+            None => return Err(LiteSessionError::InvalidCwt),
+        };
+        if expiry <= TAI64N::now().0.to_unix() {
+            return Ok((TokenOutcome::SessionExpired, LiteSessionData::default()));
+        }
+
+        let mut data = LiteSessionData::default();
+        if let Some(Value::Text(username)) = claim("sub", Some(CLAIM_SUB)) {
+            data.username(username);
+        }
+        if let Some(Value::Text(role)) = claim("role", None) {
+            data.role(Role::from_str(role));
+        }
+        if let Some(Value::Map(tags)) = claim("tags", None) {
+            tags.iter().for_each(|tag| {
+                if let (Value::Text(key), Value::Text(value)) = tag {
+                    data.tag(key, value);
+                }
+            });
+        }
+        if let Some(Value::Array(acl)) = claim("acl", None) {
+            let acl_exp = match claim("acl_exp", None) {
+                Some(Value::Map(acl_exp)) => acl_exp.as_slice(),
+                _ => &[],
+            };
+            acl.iter().for_each(|acl| {
+                if let Value::Text(acl) = acl {
+                    let expiry = acl_exp
+                        .iter()
+                        .find_map(|(timed_acl, expiry)| match timed_acl {
+                            Value::Text(timed_acl) if timed_acl == acl => cbor_int(expiry),
+                            _ => None,
+                        });
+                    match expiry {
+                        Some(expiry) => {
+                            data.add_acl_until(acl, TAI64N(TAI64::from_unix(expiry), 0))
+                        }
+                        None => data.add_acl(acl),
+                    };
+                }
+            });
+            data.remove_expired_acl(&TAI64N::now());
+        }
+        if let Some(Value::Array(acl_deny)) = claim("acl_deny", None) {
+            acl_deny.iter().for_each(|deny| {
+                if let Value::Text(deny) = deny {
+                    data.add_acl_deny(deny);
+                }
+            });
+        }
+        if let Some(Value::Text(quota)) = claim("quota", None) {
+            match Quota::from_string(quota) {
+                Some(quota) => data.quota(quota),
+                None => return Err(LiteSessionError::InvalidClaim),
+            };
+        }
+        if let Some(Value::Text(audience)) = claim("aud", Some(CLAIM_AUD)) {
+            data.audience(audience);
+        }
+        if let Some(Value::Text(issuer)) = claim("iss", Some(CLAIM_ISS)) {
+            data.issuer(issuer);
+        }
+
+        Ok((TokenOutcome::TokenAuthentic, data))
+    }
+}
+
+fn cbor_encode(value: &Value) -> Result<Vec<u8>, LiteSessionError> {
+    let mut bytes: Vec<u8> = Vec::new();
+
+    match ciborium::ser::into_writer(value, &mut bytes) {
+        Ok(_) => Ok(bytes),
+        Err(_) => Err(LiteSessionError::InvalidCwt),
+    }
+}
+
+fn cbor_map(bytes: &[u8]) -> Result<Vec<(Value, Value)>, LiteSessionError> {
+    match ciborium::de::from_reader(bytes) {
+        Ok(Value::Map(map)) => Ok(map),
+        _ => Err(LiteSessionError::InvalidCwt),
+    }
+}
+
+fn cbor_int(value: &Value) -> Option<i64> {
+    match value {
+        Value::Integer(integer) => i64::try_from(*integer).ok(),
+        _ => None,
+    }
+}
+
+// MAC_structure = ["MAC0", protected, external_aad, payload]
+fn cwt_mac(
+    mac_key: &[u8],
+    protected: &[u8],
+    payload: &[u8],
+) -> Result<HmacSha256, LiteSessionError> {
+    if mac_key.is_empty() {
+        return Err(LiteSessionError::ServerKeyLengthError);
+    }
+
+    let mac_structure = cbor_encode(&Value::Array(vec![
+        Value::Text("MAC0".into()),
+        Value::Bytes(protected.to_vec()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(payload.to_vec()),
+    ]))?;
+
+    let mut mac = match HmacSha256::new_from_slice(mac_key) {
+        Ok(mac) => mac,
+        Err(_) => return Err(LiteSessionError::ServerKeyLengthError),
+    };
+    mac.update(&mac_structure);
+
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod cwt_tests {
+    use crate::{
+        LiteSessionData, LiteSessionError, LiteSessionToken, Quota, Role, ServerKey, TokenOutcome,
+    };
+    use tai64::{TAI64, TAI64N};
+
+    #[test]
+    fn cwt_profile() -> Result<(), LiteSessionError> {
+        let mac_key = [7_u8; 32];
+        let expiry = TAI64N::now().0.to_unix() + 900;
+        let mut data = LiteSessionData::default();
+        data.username("foo_device")
+            .role(Role::SlaveNode)
+            .tag("firmware", "2.1")
+            .add_acl("Mqtt-*")
+            .add_acl_deny("Mqtt-Subscribe")
+            .add_acl_until("Mqtt-Admin", TAI64N(TAI64::from_unix(expiry), 0));
+        data.quota(Quota::new(10, 60))
+            .audience("broker.example.com");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        token.build_secure(&ServerKey::new([0_u8; 32]))?;
+
+        let cwt = token.to_cwt(&mac_key)?;
+        assert_eq!(&cwt[..1], &[0xd1]);
+
+        let (outcome, imported) = LiteSessionData::from_cwt(&cwt, &mac_key)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(imported, data);
+
+        let (outcome, _) = LiteSessionData::from_cwt(&cwt, &[8_u8; 32])?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        token.expiry(0);
+        let (outcome, _) = LiteSessionData::from_cwt(&token.to_cwt(&mac_key)?, &mac_key)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        assert_eq!(
+            LiteSessionData::from_cwt(&[0xd1, 0x80], &mac_key),
+            Err(LiteSessionError::InvalidCwt)
+        );
+
+        Ok(())
+    }
+}
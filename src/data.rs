@@ -1,42 +1,118 @@
-use crate::{LiteSessionError, Role};
+use crate::{
+    capability::scope_precedence,
+    escape::{escape, escape_keyword, unescape},
+    AuthLevel, Capability, LiteSessionError, ProofOfPossession, Quota, Role, RoleHierarchy,
+    SessionFamily, TokenVersion, UsernameNormalization,
+};
+use tai64::TAI64N;
+
+const REDACTED: &str = "[REDACTED]";
 
 /// The data part of the token which contains additional client identifying data
 ///
 /// ```
-/// use lite_session::Role;
+/// use lite_session::{AuthLevel, Quota, Role, SessionFamily, UsernameNormalization};
+/// use tai64::TAI64N;
 ///
 /// pub struct LiteSessionData {
 ///     username: String,
+///     username_normalization: UsernameNormalization,
+///     subject_id: Option<String>,
 ///     role: Role,
-///     tag: Option<String>,
+///     tags: Vec<(String, String)>,
 ///     acl: Vec<String>,
+///     acl_deny: Vec<String>,
+///     acl_expiry: Vec<(String, TAI64N)>,
+///     quota: Option<Quota>,
+///     family: Option<SessionFamily>,
+///     audience: Option<String>,
+///     issuer: Option<String>,
+///     payload: Option<String>,
+///     attachment: Option<Vec<u8>>,
+///     attributes: Vec<(String, String)>,
+///     single_use: bool,
+///     epoch: u64,
+///     certificate_thumbprint: Option<Vec<u8>>,
+///     proof_key: Option<Vec<u8>>,
+///     auth_level: Option<AuthLevel>,
+///     auth_time: Option<TAI64N>,
 /// }
 /// ```
-#[derive(Debug)]
 pub struct LiteSessionData {
     username: String,
+    username_normalization: UsernameNormalization,
+    subject_id: Option<String>,
     role: Role,
-    tag: Option<String>,
+    tags: Vec<(String, String)>,
     acl: Vec<String>,
+    acl_deny: Vec<String>,
+    acl_expiry: Vec<(String, TAI64N)>,
+    quota: Option<Quota>,
+    family: Option<SessionFamily>,
+    audience: Option<String>,
+    issuer: Option<String>,
+    payload: Option<String>,
+    attachment: Option<Vec<u8>>,
+    attributes: Vec<(String, String)>,
+    single_use: bool,
+    epoch: u64,
+    certificate_thumbprint: Option<Vec<u8>>,
+    proof_key: Option<Vec<u8>>,
+    auth_level: Option<AuthLevel>,
+    auth_time: Option<TAI64N>,
 }
 
 impl Default for LiteSessionData {
     fn default() -> Self {
         Self {
             username: String::default(),
+            username_normalization: UsernameNormalization::default(),
+            subject_id: Option::default(),
             role: Role::default(),
-            tag: Option::default(),
+            tags: Vec::default(),
             acl: Vec::default(),
+            acl_deny: Vec::default(),
+            acl_expiry: Vec::default(),
+            quota: Option::default(),
+            family: Option::default(),
+            audience: Option::default(),
+            issuer: Option::default(),
+            payload: Option::default(),
+            attachment: Option::default(),
+            attributes: Vec::default(),
+            single_use: bool::default(),
+            epoch: u64::default(),
+            certificate_thumbprint: Option::default(),
+            proof_key: Option::default(),
+            auth_level: Option::default(),
+            auth_time: Option::default(),
         }
     }
 }
 
 impl core::cmp::PartialEq for LiteSessionData {
+    // The normalization only applies while setting the username so it is not compared
     fn eq(&self, other: &Self) -> bool {
         if self.username == other.username
+            && self.subject_id == other.subject_id
             && self.role == other.role
-            && self.tag == other.tag
+            && self.tags == other.tags
             && self.acl == other.acl
+            && self.acl_deny == other.acl_deny
+            && self.acl_expiry == other.acl_expiry
+            && self.quota == other.quota
+            && self.family == other.family
+            && self.audience == other.audience
+            && self.issuer == other.issuer
+            && self.payload == other.payload
+            && self.attachment == other.attachment
+            && self.attributes == other.attributes
+            && self.single_use == other.single_use
+            && self.epoch == other.epoch
+            && self.certificate_thumbprint == other.certificate_thumbprint
+            && self.proof_key == other.proof_key
+            && self.auth_level == other.auth_level
+            && self.auth_time == other.auth_time
         {
             true
         } else {
@@ -45,21 +121,84 @@ impl core::cmp::PartialEq for LiteSessionData {
     }
 }
 
+// The username and claim values identify a person so they are masked to keep them out of logs
+impl core::fmt::Debug for LiteSessionData {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let redacted = self.redacted();
+
+        f.debug_struct("LiteSessionData")
+            .field("username", &redacted.username)
+            .field("username_normalization", &redacted.username_normalization)
+            .field("subject_id", &redacted.subject_id)
+            .field("role", &redacted.role)
+            .field("tags", &redacted.tags)
+            .field("acl", &redacted.acl)
+            .field("acl_deny", &redacted.acl_deny)
+            .field("acl_expiry", &redacted.acl_expiry)
+            .field("quota", &redacted.quota)
+            .field("family", &redacted.family)
+            .field("audience", &redacted.audience)
+            .field("issuer", &redacted.issuer)
+            .field("payload", &redacted.payload)
+            .field("attachment", &redacted.attachment.map(|_| REDACTED))
+            .field("attributes", &redacted.attributes)
+            .field("single_use", &redacted.single_use)
+            .field("epoch", &redacted.epoch)
+            .field("certificate_thumbprint", &redacted.certificate_thumbprint)
+            .field("proof_key", &redacted.proof_key)
+            .field("auth_level", &redacted.auth_level)
+            .field("auth_time", &redacted.auth_time)
+            .finish()
+    }
+}
+
 impl core::clone::Clone for LiteSessionData {
     fn clone(&self) -> Self {
         Self {
             username: self.username.clone(),
+            username_normalization: self.username_normalization.clone(),
+            subject_id: self.subject_id.clone(),
             role: self.role.clone(),
-            tag: self.tag.clone(),
+            tags: self.tags.clone(),
             acl: self.acl.clone(),
+            acl_deny: self.acl_deny.clone(),
+            acl_expiry: self.acl_expiry.clone(),
+            quota: self.quota.clone(),
+            family: self.family.clone(),
+            audience: self.audience.clone(),
+            issuer: self.issuer.clone(),
+            payload: self.payload.clone(),
+            attachment: self.attachment.clone(),
+            attributes: self.attributes.clone(),
+            single_use: self.single_use,
+            epoch: self.epoch,
+            certificate_thumbprint: self.certificate_thumbprint.clone(),
+            proof_key: self.proof_key.clone(),
+            auth_level: self.auth_level.clone(),
+            auth_time: self.auth_time,
         }
     }
 }
 
 impl LiteSessionData {
-    /// Add a custom username
+    /// Add a custom username normalized according to the `UsernameNormalization`
     pub fn username(&mut self, value: &str) -> &mut Self {
-        self.username = value.into();
+        self.username = self.username_normalization.normalize(value);
+
+        self
+    }
+    /// Set how usernames are normalized, `UsernameNormalization::Nfc` by default.
+    /// The current username is normalized again
+    pub fn username_normalization(&mut self, normalization: UsernameNormalization) -> &mut Self {
+        self.username = normalization.normalize(&self.username);
+        self.username_normalization = normalization;
+
+        self
+    }
+    /// Add the stable identifier of the subject, like a database UUID.
+    /// Usernames can change so authorization decisions should use the subject ID
+    pub fn subject_id(&mut self, subject_id: &str) -> &mut Self {
+        self.subject_id = Some(subject_id.into());
 
         self
     }
@@ -69,12 +208,22 @@ impl LiteSessionData {
 
         self
     }
-    /// Add a custom tag to identify this token or current client/server/node
-    pub fn tag(&mut self, tag: &str) -> &mut Self {
-        self.tag = Some(tag.into());
+    /// Add a tag like `env=prod` to identify this token or current client/server/node.
+    /// Setting an existing tag replaces its value and keeps its position
+    pub fn tag(&mut self, key: &str, value: &str) -> &mut Self {
+        match self.tags.iter_mut().find(|(existing, _)| existing == key) {
+            Some(tag) => tag.1 = value.into(),
+            None => self.tags.push((key.into(), value.into())),
+        }
 
         self
     }
+    /// Remove a tag returning its value
+    pub fn remove_tag(&mut self, key: &str) -> Option<String> {
+        let index = self.tags.iter().position(|(existing, _)| existing == key)?;
+
+        Some(self.tags.remove(index).1)
+    }
     /// Add a capability to the access control list
     pub fn add_acl(&mut self, capability: &str) -> &mut Self {
         self.acl.push(capability.into());
@@ -82,79 +231,732 @@ impl LiteSessionData {
 
         self
     }
+    /// Add a capability that expires at `expiry` like a temporary elevation
+    /// inside a longer session. Expired capabilities are removed when the token is verified
+    pub fn add_acl_until(&mut self, capability: &str, expiry: TAI64N) -> &mut Self {
+        self.acl_expiry
+            .retain(|(timed_capability, _)| timed_capability != capability);
+        self.acl_expiry.push((capability.into(), expiry));
+
+        self.add_acl(capability)
+    }
     /// Remove a capability from the access control list
     pub fn remove_acl(&mut self, capability: &str) -> Option<String> {
         match self.acl.binary_search(&capability.to_owned()) {
-            Ok(index) => Some(self.acl.remove(index)),
+            Ok(index) => {
+                self.acl_expiry
+                    .retain(|(timed_capability, _)| timed_capability != capability);
+                Some(self.acl.remove(index))
+            }
+            Err(_) => None,
+        }
+    }
+    /// Remove the capabilities that expired at `now` returning them
+    pub fn remove_expired_acl(&mut self, now: &TAI64N) -> Vec<String> {
+        let expired: Vec<String> = self
+            .acl_expiry
+            .iter()
+            .filter(|(_, expiry)| expiry <= now)
+            .map(|(capability, _)| capability.clone())
+            .collect();
+
+        self.acl.retain(|capability| !expired.contains(capability));
+        self.acl_expiry.retain(|(_, expiry)| expiry > now);
+
+        expired
+    }
+    /// Add a deny entry to the access control list.
+    /// Deny entries override grants when evaluated by `has_capability()`
+    /// and may use wildcards like `billing:*`
+    pub fn add_acl_deny(&mut self, capability: &str) -> &mut Self {
+        self.acl_deny.push(capability.into());
+        self.acl_deny.sort();
+
+        self
+    }
+    /// Remove a deny entry from the access control list
+    pub fn remove_acl_deny(&mut self, capability: &str) -> Option<String> {
+        match self.acl_deny.binary_search(&capability.to_owned()) {
+            Ok(index) => Some(self.acl_deny.remove(index)),
             Err(_) => None,
         }
     }
+    /// Add a structured capability to the access control list
+    pub fn add_capability(&mut self, capability: &Capability) -> &mut Self {
+        self.add_acl(&Capability::to_string(capability))
+    }
+    /// Add a quota claim limiting the number of requests the token can make within a window.
+    /// The quota is enforced by the verifier using a `QuotaStore`
+    pub fn quota(&mut self, quota: Quota) -> &mut Self {
+        self.quota = Some(quota);
+
+        self
+    }
+    /// Add the session family this token belongs to.
+    /// Families bind the access and refresh tokens minted by the same chain of refreshes
+    pub fn family(&mut self, family: SessionFamily) -> &mut Self {
+        self.family = Some(family);
+
+        self
+    }
+    /// Add the service the token is intended for.
+    /// Verifiers using `LiteSessionToken::require_audience()` reject tokens for other services
+    pub fn audience(&mut self, audience: &str) -> &mut Self {
+        self.audience = Some(audience.into());
+
+        self
+    }
+    /// Add the service that issued the token.
+    /// Verifiers using `LiteSessionToken::allowed_issuers()` reject tokens from other services
+    pub fn issuer(&mut self, issuer: &str) -> &mut Self {
+        self.issuer = Some(issuer.into());
+
+        self
+    }
+    /// Mark the token as usable once, like a password reset or email confirmation link.
+    /// `LiteSessionVerifier` rejects replays of the token when a `ReplayGuard` is set
+    pub fn single_use(&mut self, single_use: bool) -> &mut Self {
+        self.single_use = single_use;
+
+        self
+    }
+    /// Set the epoch of the user when the token is issued. `LiteSessionVerifier` rejects the
+    /// tokens of an earlier epoch than the one its `UserEpochProvider` holds for the user,
+    /// so bumping the epoch of a user logs them out everywhere
+    pub fn epoch(&mut self, epoch: u64) -> &mut Self {
+        self.epoch = epoch;
+
+        self
+    }
+    /// Confirm the client certificate the token is issued to with `thumbprint`, a hash of the
+    /// certificate like the SHA-256 digest of its DER encoding as in the `x5t#S256` confirmation
+    /// method of RFC 8705. `LiteSessionToken::from_string_with_certificate()` rejects the token
+    /// when presented over a mutual TLS connection with another certificate
+    pub fn certificate_thumbprint(&mut self, thumbprint: &[u8]) -> &mut Self {
+        self.certificate_thumbprint = Some(thumbprint.to_vec());
+
+        self
+    }
+    /// Confirm the public key of the client the token is issued to by its thumbprint.
+    /// `LiteSessionToken::from_string_with_proof()` only accepts the token together with a
+    /// `ProofOfPossession` signed by the matching private key
+    pub fn proof_key(&mut self, public_key: &[u8]) -> &mut Self {
+        self.proof_key = Some(ProofOfPossession::thumbprint(public_key).to_vec());
+
+        self
+    }
+    /// Record how strongly the user authenticated before the token was issued.
+    /// `LiteSessionVerifier::require_auth_level()` asks for step-up authentication when
+    /// the level is weaker than required or not set
+    pub fn auth_level(&mut self, auth_level: AuthLevel) -> &mut Self {
+        self.auth_level = Some(auth_level);
+
+        self
+    }
+    /// Record when the user authenticated, the time the first token of the session was issued.
+    /// Sliding sessions set it on the tokens they re-issue so that `LiteSessionToken::max_age()`
+    /// is measured from the start of the session rather than from the last refresh
+    pub fn auth_time(&mut self, auth_time: TAI64N) -> &mut Self {
+        self.auth_time = Some(auth_time);
+
+        self
+    }
+    /// Attach opaque bytes like a serialized protobuf profile.
+    /// The bytes are carried base64 encoded in the optional claims of the data section
+    pub fn attachment(&mut self, attachment: &[u8]) -> &mut Self {
+        self.attachment = Some(attachment.to_vec());
+
+        self
+    }
+    /// Set an attribute like the `region` of the user for `AttributePolicy` predicates
+    pub fn attribute(&mut self, name: &str, value: &str) -> &mut Self {
+        match self
+            .attributes
+            .iter_mut()
+            .find(|(existing, _)| existing == name)
+        {
+            Some(attribute) => attribute.1 = value.into(),
+            None => self.attributes.push((name.into(), value.into())),
+        }
+
+        self
+    }
+    /// Embed application claims serialized as JSON using serde.
+    /// The claims are carried base64 encoded in the optional claims of the data section
+    #[cfg(feature = "json")]
+    pub fn claims<T: serde::Serialize>(
+        &mut self,
+        claims: &T,
+    ) -> Result<&mut Self, LiteSessionError> {
+        match serde_json::to_string(claims) {
+            Ok(payload) => self.payload = Some(payload),
+            Err(_) => return Err(LiteSessionError::InvalidClaim),
+        }
+
+        Ok(self)
+    }
     /// Get the username
     pub fn get_username(&self) -> &String {
         &self.username
     }
+    /// Get the subject ID
+    pub fn get_subject_id(&self) -> &Option<String> {
+        &self.subject_id
+    }
+    /// Get how usernames are normalized
+    pub fn get_username_normalization(&self) -> &UsernameNormalization {
+        &self.username_normalization
+    }
     /// Get the role
     pub fn get_role(&self) -> &Role {
         &self.role
     }
-    /// Get the tag
-    pub fn get_tag(&self) -> &Option<String> {
-        &self.tag
+    /// Get the value of a tag
+    pub fn get_tag(&self, key: &str) -> Option<&String> {
+        self.tags
+            .iter()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, value)| value)
+    }
+    /// Get all the tags in the order they were added
+    pub fn get_tags(&self) -> &Vec<(String, String)> {
+        &self.tags
     }
     /// Get the access control list of capabilities
     pub fn get_acl(&self) -> &Vec<String> {
         &self.acl
     }
+    /// Get the expiry of a capability added by `add_acl_until()`
+    pub fn get_acl_expiry(&self, capability: &str) -> Option<&TAI64N> {
+        self.acl_expiry
+            .iter()
+            .find(|(timed_capability, _)| timed_capability == capability)
+            .map(|(_, expiry)| expiry)
+    }
+    /// Get the deny entries of the access control list
+    pub fn get_acl_deny(&self) -> &Vec<String> {
+        &self.acl_deny
+    }
+    /// Get the structured capabilities in the access control list skipping plain entries
+    pub fn get_capabilities(&self) -> Vec<Capability> {
+        self.acl
+            .iter()
+            .filter_map(|acl| Capability::from_string(acl))
+            .collect()
+    }
+    /// Check whether a capability in the access control list grants `action` on `resource`
+    pub fn allows(&self, resource: &str, action: &str) -> bool {
+        self.acl
+            .iter()
+            .filter_map(|acl| Capability::from_string(acl))
+            .any(|capability| capability.allows(resource, action))
+    }
+    /// Check whether the role is `role` or implies it in the default `RoleHierarchy`
+    pub fn role_at_least(&self, role: Role) -> bool {
+        RoleHierarchy::default().implies(&self.role, &role)
+    }
+    /// Check whether the role is `role` or implies it in `hierarchy`
+    pub fn role_at_least_in(&self, hierarchy: &RoleHierarchy, role: Role) -> bool {
+        hierarchy.implies(&self.role, &role)
+    }
+    /// Check whether the access control list grants a scope like `files:read`
+    /// either exactly or through a wildcard entry like `files:*` or `*`.
+    /// Deny entries are not consulted, use `has_capability()` for that
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.get_scope_grant(scope).is_some()
+    }
+    /// Check whether the access control list grants `capability` and no deny entry
+    /// matches it exactly or through a wildcard, so `*` with a `billing:*` deny entry
+    /// grants everything except billing
+    pub fn has_capability(&self, capability: &str) -> bool {
+        !self.is_denied(capability) && self.has_scope(capability)
+    }
+    // Whether a deny entry matches `capability` exactly or through a wildcard
+    pub(crate) fn is_denied(&self, capability: &str) -> bool {
+        self.acl_deny
+            .iter()
+            .any(|deny| scope_precedence(deny, capability).is_some())
+    }
+    /// Get the entry of the access control list granting `scope`.
+    /// An exact entry is preferred over the wildcard with the longest prefix
+    pub fn get_scope_grant(&self, scope: &str) -> Option<&String> {
+        self.acl
+            .iter()
+            .filter_map(|acl| scope_precedence(acl, scope).map(|precedence| (precedence, acl)))
+            .max_by_key(|(precedence, _)| *precedence)
+            .map(|(_, acl)| acl)
+    }
+    /// Get the quota claim
+    pub fn get_quota(&self) -> &Option<Quota> {
+        &self.quota
+    }
+    /// Get the session family
+    pub fn get_family(&self) -> &Option<SessionFamily> {
+        &self.family
+    }
+    /// Get the audience
+    pub fn get_audience(&self) -> &Option<String> {
+        &self.audience
+    }
+    /// Get the issuer
+    pub fn get_issuer(&self) -> &Option<String> {
+        &self.issuer
+    }
+    /// Check whether the token is usable once
+    pub fn is_single_use(&self) -> bool {
+        self.single_use
+    }
+    /// Get the hash of the client certificate the token is issued to
+    pub fn get_certificate_thumbprint(&self) -> Option<&[u8]> {
+        self.certificate_thumbprint.as_deref()
+    }
+    /// Get the thumbprint of the public key of the client the token is issued to
+    pub fn get_proof_key(&self) -> Option<&[u8]> {
+        self.proof_key.as_deref()
+    }
+    /// Get how strongly the user authenticated before the token was issued
+    pub fn get_auth_level(&self) -> Option<&AuthLevel> {
+        self.auth_level.as_ref()
+    }
+    /// Get when the user authenticated
+    pub fn get_auth_time(&self) -> Option<&TAI64N> {
+        self.auth_time.as_ref()
+    }
+    /// Get the epoch of the user when the token was issued, `0` if it was not set
+    pub fn get_epoch(&self) -> u64 {
+        self.epoch
+    }
+    /// Get the value of an attribute
+    pub fn get_attribute(&self, name: &str) -> Option<&String> {
+        self.attributes
+            .iter()
+            .find(|(existing, _)| existing == name)
+            .map(|(_, value)| value)
+    }
+    /// Get all the attributes
+    pub fn get_attributes(&self) -> &Vec<(String, String)> {
+        &self.attributes
+    }
+    /// Get the attached bytes
+    pub fn get_attachment(&self) -> &Option<Vec<u8>> {
+        &self.attachment
+    }
+    /// Get the application claims as JSON
+    pub fn get_payload(&self) -> &Option<String> {
+        &self.payload
+    }
+    /// Deserialize the application claims embedded with `claims()`.
+    /// Returns `None` if the data holds no application claims
+    #[cfg(feature = "json")]
+    pub fn get_claims<T: serde::de::DeserializeOwned>(
+        &self,
+    ) -> Result<Option<T>, LiteSessionError> {
+        match &self.payload {
+            Some(payload) => match serde_json::from_str(payload) {
+                Ok(claims) => Ok(Some(claims)),
+                Err(_) => Err(LiteSessionError::InvalidClaim),
+            },
+            None => Ok(None),
+        }
+    }
+    /// Get a copy of the data safe for logging with the username, the subject ID and
+    /// the values of the tags, attributes, application claims and attachment masked.
+    /// The role, the ACL and the other claims are kept
+    pub fn redacted(&self) -> Self {
+        let mut redacted = self.clone();
+
+        if !redacted.username.is_empty() {
+            redacted.username = REDACTED.into();
+        }
+        redacted.subject_id = redacted.subject_id.map(|_| REDACTED.into());
+        redacted
+            .tags
+            .iter_mut()
+            .for_each(|(_, value)| *value = REDACTED.into());
+        redacted
+            .attributes
+            .iter_mut()
+            .for_each(|(_, value)| *value = REDACTED.into());
+        redacted.payload = redacted.payload.map(|_| REDACTED.into());
+        redacted.attachment = redacted.attachment.map(|_| REDACTED.as_bytes().to_vec());
+
+        redacted
+    }
     /// Build the data to a string that can be attached to a token
     pub fn build(&self) -> String {
         let mut acl_token = String::default();
         let mut acl_list = String::default();
 
-        acl_token.push_str(&self.username);
+        acl_token.push_str(&escape(&self.username));
         acl_token.push(self.ls_separator());
-        acl_token.push_str(&Role::to_string(&self.role));
+        match &self.role {
+            // A custom role spelling a predefined role must not be read back as that role
+            Role::Custom(role) => match Role::from_str(role) {
+                Role::Custom(_) => acl_token.push_str(&escape(role)),
+                _ => acl_token.push_str(&escape_keyword(role)),
+            },
+            role => acl_token.push_str(&Role::to_string(role)),
+        }
         acl_token.push(self.ls_separator());
 
-        match &self.tag {
-            None => acl_token.push_str("None"),
-            Some(tag) => acl_token.push_str(&tag),
+        match self.tags.is_empty() {
+            true => acl_token.push_str("None"),
+            false => {
+                let tags: Vec<String> = self
+                    .tags
+                    .iter()
+                    .map(|(key, value)| {
+                        format!("{}={}", escape(key).replace('=', "%3D"), escape(value))
+                    })
+                    .collect();
+                acl_token.push_str(&tags.join(&self.acl_separator().to_string()))
+            }
         }
 
-        let initial = &self.acl[0];
-        acl_list.push_str(&initial);
-        self.acl.iter().skip(1).for_each(|item| {
-            acl_list.push(self.acl_separator());
-            acl_list.push_str(&item)
+        let mut acl_entries: Vec<String> =
+            self.acl.iter().map(|item| self.escape_acl(item)).collect();
+        self.acl_deny.iter().for_each(|item| {
+            let mut deny = String::default();
+            deny.push(self.deny_marker());
+            deny.push_str(&escape(item));
+            acl_entries.push(deny)
         });
+        // An ACL without grants or deny entries is encoded like the empty tags
+        match acl_entries.is_empty() {
+            true => acl_list.push_str("None"),
+            false => acl_list.push_str(&acl_entries.join(&self.acl_separator().to_string())),
+        }
         acl_token.push(self.ls_separator());
         acl_token.push_str(&acl_list);
 
+        let claims = self.build_claims();
+        if !claims.is_empty() {
+            acl_token.push(self.ls_separator());
+            acl_token.push_str(&claims);
+        }
+
         acl_token
     }
 
     /// Destructure the current cipher text into its components and check if they are valid
     pub fn destructure(mut self, data: &str) -> Result<Self, LiteSessionError> {
-        let first_split: Vec<&str> = data.split(self.ls_separator()).collect();
-        if first_split.len() != 4_usize {
+        // Read at most five fields and stop as soon as a sixth one shows up
+        let mut field_count = 0_usize;
+
+        for field in data.split(self.ls_separator()) {
+            match field_count {
+                0 => self.username = unescape(field)?,
+                1 => {
+                    self.role = match field.starts_with('%') {
+                        true => Role::Custom(unescape(field)?),
+                        false => Role::from_str(&unescape(field)?),
+                    }
+                }
+                2 => {
+                    self.tags = Vec::default();
+                    if field != "None" {
+                        for tag in field.split(self.acl_separator()) {
+                            self.destructure_tag(tag)?
+                        }
+                    }
+                }
+                3 => {
+                    self.acl = Vec::default();
+                    self.acl_deny = Vec::default();
+                    self.acl_expiry = Vec::default();
+                    if field != "None" {
+                        for acl in field.split(self.acl_separator()) {
+                            match acl.strip_prefix(self.deny_marker()) {
+                                Some(deny) => self.acl_deny.push(unescape(deny)?),
+                                None => self.destructure_acl(acl)?,
+                            }
+                        }
+                    }
+                }
+                4 => self.destructure_claims(field)?,
+                _ => return Err(LiteSessionError::DataFieldsLengthError),
+            }
+            field_count += 1;
+        }
+
+        match field_count {
+            4 | 5 => Ok(self),
+            _ => Err(LiteSessionError::DataFieldsLengthError),
+        }
+    }
+
+    // Unversioned tokens with the original four field layout were built before fields
+    // were escaped and before deny entries and timed capabilities, so their fields are read
+    // as they are. Every other token uses the escaped syntax
+    pub(crate) fn destructure_with_version(
+        self,
+        data: &str,
+        version: &TokenVersion,
+    ) -> Result<Self, LiteSessionError> {
+        match version == &TokenVersion::V0 && data.split(self.ls_separator()).count() == 4 {
+            true => self.destructure_legacy(data),
+            false => self.destructure(data),
+        }
+    }
+
+    // Whether an unversioned token carrying the data reads it back unchanged
+    pub(crate) fn is_legacy_compatible(&self) -> bool {
+        let data = self.build();
+
+        data.split(self.ls_separator()).count() != 4
+            || LiteSessionData::default()
+                .destructure_legacy(&data)
+                .is_ok_and(|legacy| &legacy == self)
+    }
+
+    fn destructure_legacy(mut self, data: &str) -> Result<Self, LiteSessionError> {
+        let fields: Vec<&str> = data.split(self.ls_separator()).collect();
+        if fields.len() != 4 {
             return Err(LiteSessionError::DataFieldsLengthError);
         }
 
-        self.username = first_split[0].into();
-        self.role = Role::from_str(first_split[1]);
-        self.tag = match first_split[2] {
-            "None" => None,
-            _ => Some(first_split[2].into()),
+        self.username = fields[0].into();
+        self.role = Role::from_str(fields[1]);
+        self.tags = match fields[2] {
+            "None" => Vec::default(),
+            tag => match tag.split_once('=') {
+                Some((key, value)) => vec![(key.into(), value.into())],
+                None => vec![("tag".into(), tag.into())],
+            },
         };
-
-        let mut acl_list: Vec<String> = Vec::new();
-        first_split[3]
-            .split(self.acl_separator())
-            .for_each(|acl| acl_list.push(acl.into()));
-        self.acl = acl_list;
+        self.acl = match fields[3] {
+            "None" => Vec::default(),
+            acl => acl.split(self.acl_separator()).map(Into::into).collect(),
+        };
+        self.acl_deny = Vec::default();
+        self.acl_expiry = Vec::default();
 
         Ok(self)
     }
 
+    // Optional claims are appended as a fifth field of `name=value` entries
+    // so that tokens without them keep the original four field layout
+    fn build_claims(&self) -> String {
+        let mut claims: Vec<String> = Vec::new();
+
+        if let Some(subject_id) = &self.subject_id {
+            claims.push(format!("subject={}", escape(subject_id)));
+        }
+        if let Some(quota) = &self.quota {
+            claims.push(format!("quota={}", Quota::to_string(quota)));
+        }
+        if let Some(family) = &self.family {
+            claims.push(format!("family={}", SessionFamily::to_string(family)));
+        }
+        if let Some(audience) = &self.audience {
+            claims.push(format!("aud={}", escape(audience)));
+        }
+        if let Some(issuer) = &self.issuer {
+            claims.push(format!("iss={}", escape(issuer)));
+        }
+        if self.single_use {
+            claims.push("once=1".into());
+        }
+        if self.epoch != 0 {
+            claims.push(format!("epoch={}", self.epoch));
+        }
+        if let Some(thumbprint) = &self.certificate_thumbprint {
+            claims.push(format!(
+                "cnf={}",
+                base64::encode_config(thumbprint, base64::URL_SAFE_NO_PAD)
+            ));
+        }
+        if let Some(auth_level) = &self.auth_level {
+            claims.push(format!("amr={}", AuthLevel::to_string(auth_level)));
+        }
+        if let Some(auth_time) = &self.auth_time {
+            claims.push(format!("auth_time={}", hex::encode(auth_time.to_bytes())));
+        }
+        if let Some(proof_key) = &self.proof_key {
+            claims.push(format!(
+                "jkt={}",
+                base64::encode_config(proof_key, base64::URL_SAFE_NO_PAD)
+            ));
+        }
+        // The JSON may contain the separators so it is base64 encoded
+        if let Some(payload) = &self.payload {
+            claims.push(format!(
+                "payload={}",
+                base64::encode_config(payload, base64::URL_SAFE_NO_PAD)
+            ));
+        }
+        if let Some(attachment) = &self.attachment {
+            claims.push(format!(
+                "attachment={}",
+                base64::encode_config(attachment, base64::URL_SAFE_NO_PAD)
+            ));
+        }
+
+        self.attributes.iter().for_each(|(name, value)| {
+            claims.push(format!(
+                "attr.{}={}",
+                escape(name).replace('=', "%3D"),
+                escape(value)
+            ))
+        });
+
+        claims.join(&self.acl_separator().to_string())
+    }
+
+    fn destructure_claims(&mut self, claims: &str) -> Result<(), LiteSessionError> {
+        for claim in claims.split(self.acl_separator()) {
+            let mut parts = claim.splitn(2, '=');
+            let name = parts.next().unwrap_or_default();
+            let value = match parts.next() {
+                Some(value) => value,
+                None => return Err(LiteSessionError::InvalidClaim),
+            };
+
+            match name {
+                "quota" => {
+                    self.quota = match Quota::from_string(value) {
+                        Some(quota) => Some(quota),
+                        None => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "family" => {
+                    self.family = match SessionFamily::from_string(value) {
+                        Some(family) => Some(family),
+                        None => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "subject" => self.subject_id = Some(unescape(value)?),
+                "aud" => self.audience = Some(unescape(value)?),
+                "iss" => self.issuer = Some(unescape(value)?),
+                "epoch" => {
+                    self.epoch = match value.parse::<u64>() {
+                        Ok(epoch) => epoch,
+                        Err(_) => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "cnf" => {
+                    self.certificate_thumbprint =
+                        match base64::decode_config(value, base64::URL_SAFE_NO_PAD) {
+                            Ok(thumbprint) => Some(thumbprint),
+                            Err(_) => return Err(LiteSessionError::InvalidClaim),
+                        }
+                }
+                "amr" => {
+                    self.auth_level = match AuthLevel::from_string(value) {
+                        Some(auth_level) => Some(auth_level),
+                        None => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "auth_time" => {
+                    self.auth_time = match hex::decode(value)
+                        .ok()
+                        .and_then(|bytes| TAI64N::from_slice(&bytes).ok())
+                    {
+                        Some(auth_time) => Some(auth_time),
+                        None => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "jkt" => {
+                    self.proof_key = match base64::decode_config(value, base64::URL_SAFE_NO_PAD) {
+                        Ok(proof_key) => Some(proof_key),
+                        Err(_) => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "once" => {
+                    self.single_use = match value {
+                        "1" => true,
+                        _ => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "payload" => {
+                    self.payload = match base64::decode_config(value, base64::URL_SAFE_NO_PAD)
+                        .ok()
+                        .and_then(|payload| String::from_utf8(payload).ok())
+                    {
+                        Some(payload) => Some(payload),
+                        None => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                "attachment" => {
+                    self.attachment = match base64::decode_config(value, base64::URL_SAFE_NO_PAD) {
+                        Ok(attachment) => Some(attachment),
+                        Err(_) => return Err(LiteSessionError::InvalidClaim),
+                    }
+                }
+                _ => {
+                    if let Some(name) = name.strip_prefix("attr.") {
+                        let name = unescape(name)?;
+                        let value = unescape(value)?;
+                        self.attribute(&name, &value);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Deny entries share the ACL field so a grant starting with the marker is escaped
+    // as is a grant spelling the `None` of an empty ACL.
+    // The expiry of a timed capability follows it as `@<TAI64N hex>` so that
+    // verifiers unaware of it never mistake the entry for a permanent capability
+    fn escape_acl(&self, acl: &str) -> String {
+        let mut escaped = match acl.starts_with(self.deny_marker()) || acl == "None" {
+            true => escape_keyword(acl),
+            false => escape(acl),
+        }
+        .replace(self.expiry_marker(), "%40");
+
+        if let Some(expiry) = self.get_acl_expiry(acl) {
+            escaped.push(self.expiry_marker());
+            escaped.push_str(&hex::encode(expiry.to_bytes()));
+        }
+
+        escaped
+    }
+
+    // Tokens issued before tags were key-value pairs carry a single free-form tag
+    fn destructure_tag(&mut self, tag: &str) -> Result<(), LiteSessionError> {
+        match tag.split_once('=') {
+            Some((key, value)) => {
+                let key = unescape(key)?;
+                let value = unescape(value)?;
+                self.tag(&key, &value)
+            }
+            None => self.tag("tag", &unescape(tag)?),
+        };
+
+        Ok(())
+    }
+
+    fn destructure_acl(&mut self, acl: &str) -> Result<(), LiteSessionError> {
+        match acl.split_once(self.expiry_marker()) {
+            None => self.acl.push(unescape(acl)?),
+            Some((acl, expiry)) => {
+                let expiry = match hex::decode(expiry)
+                    .ok()
+                    .and_then(|bytes| TAI64N::from_slice(&bytes).ok())
+                {
+                    Some(expiry) => expiry,
+                    None => return Err(LiteSessionError::InvalidTai64NTime),
+                };
+                let acl = unescape(acl)?;
+                self.acl_expiry.push((acl.clone(), expiry));
+                self.acl.push(acl);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn expiry_marker(&self) -> char {
+        '@'
+    }
+
+    fn deny_marker(&self) -> char {
+        '!'
+    }
+
     fn ls_separator(&self) -> char {
         '⥂'
     }
@@ -166,7 +968,10 @@ impl LiteSessionData {
 
 #[cfg(test)]
 mod data_tests {
-    use super::{LiteSessionData, Role};
+    use super::{
+        AuthLevel, Capability, LiteSessionData, ProofOfPossession, Role, RoleHierarchy,
+        UsernameNormalization, TAI64N,
+    };
 
     #[test]
     fn data_tests() -> Result<(), crate::LiteSessionError> {
@@ -178,8 +983,8 @@ mod data_tests {
         data.role(Role::SuperUser);
         assert_eq!(data.role, Role::SuperUser);
 
-        data.tag("Foo-Tag");
-        assert_eq!(data.tag, Some("Foo-Tag".into()));
+        data.tag("env", "prod");
+        assert_eq!(data.tags, vec![("env".into(), "prod".into())]);
 
         data.add_acl("Network-TCP");
         assert_eq!(data.acl, vec!["Network-TCP"]);
@@ -203,7 +1008,7 @@ mod data_tests {
         let prepared_data = data.build();
         assert_eq!(
             prepared_data,
-            "foo_user⥂SuperUser⥂Foo-Tag⥂Network-TCP⇅Network-UDP".to_owned()
+            "foo_user⥂SuperUser⥂env=prod⥂Network-TCP⇅Network-UDP".to_owned()
         );
 
         let destructured = LiteSessionData::default();
@@ -211,8 +1016,447 @@ mod data_tests {
 
         assert_eq!(token_data.username, data.username);
         assert_eq!(token_data.role, data.role);
-        assert_eq!(token_data.tag, data.tag);
+        assert_eq!(token_data.tags, data.tags);
         assert_eq!(token_data.acl, data.acl);
+        assert_eq!(token_data.quota, None);
+
+        data.quota(crate::Quota::new(100, 60));
+        let prepared_data = data.build();
+        assert_eq!(
+            prepared_data,
+            "foo_user⥂SuperUser⥂env=prod⥂Network-TCP⇅Network-UDP⥂quota=100/60".to_owned()
+        );
+        let token_data = LiteSessionData::default().destructure(&prepared_data)?;
+        assert_eq!(token_data, data);
+
+        data.audience("api.example.com").issuer("auth.example.com");
+        let prepared_data = data.build();
+        assert!(prepared_data.ends_with("⇅aud=api.example.com⇅iss=auth.example.com"));
+
+        let token_data = LiteSessionData::default().destructure(&prepared_data)?;
+        assert_eq!(token_data.get_audience(), &Some("api.example.com".into()));
+        assert_eq!(token_data.get_issuer(), &Some("auth.example.com".into()));
+        assert_eq!(token_data, data);
+
+        // The subject ID stays the same when the username changes
+        data.subject_id("5f0c6a2e-8d3b-4b7e-9a51-2c1d7e3f4a60");
+        let mut renamed = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(renamed, data);
+        renamed.username("bar_user");
+        let renamed = LiteSessionData::default().destructure(&renamed.build())?;
+        assert_eq!(renamed.get_username(), "bar_user");
+        assert_eq!(renamed.get_subject_id(), data.get_subject_id());
+
+        // Attached bytes are carried as-is, including the bytes of the separators
+        let attachment = [0_u8, 255, 226, 165, 130, 226, 135, 133, 61];
+        data.attachment(&attachment);
+        let token_data = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(token_data.get_attachment(), &Some(attachment.to_vec()));
+        assert_eq!(token_data, data);
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂Network-TCP⥂attachment=$"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂Network-TCP⥂quota=100"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂Network-TCP⥂payload=$"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn escaped_fields() -> Result<(), crate::LiteSessionError> {
+        // Separators inside the values cannot shift the fields around them
+        let mut data = LiteSessionData::default();
+        data.username("foo⥂SuperUser⥂None⥂*")
+            .role(Role::Custom("Admin".into()))
+            .tag("None", "None")
+            .tag("env=dev", "prod⇅eu")
+            .add_acl("Network-TCP⇅Network-UDP")
+            .add_acl("100%")
+            .subject_id("a⇅quota=1/1")
+            .audience("api⊕example");
+
+        let prepared_data = data.build();
+        assert_eq!(prepared_data.matches('⥂').count(), 4);
+        assert_eq!(prepared_data.matches('⇅').count(), 3);
+        assert!(!prepared_data.contains('⊕'));
+
+        let token_data = LiteSessionData::default().destructure(&prepared_data)?;
+        assert_eq!(token_data, data);
+        assert_eq!(token_data.get_role(), &Role::Custom("Admin".into()));
+        assert_eq!(token_data.get_tag("None"), Some(&"None".to_owned()));
+        assert_eq!(token_data.get_tag("env=dev"), Some(&"prod⇅eu".to_owned()));
+        assert_eq!(token_data.get_acl().len(), 2);
+        assert_eq!(token_data.get_quota(), &None);
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo%⥂User⥂None⥂Network-TCP"),
+            Err(crate::LiteSessionError::InvalidFieldEscape)
+        );
+
+        // A free-form tag of an older token is read as the `tag` tag
+        let token_data =
+            LiteSessionData::default().destructure("foo_user⥂User⥂Foo-Tag⥂Network-TCP")?;
+        assert_eq!(token_data.get_tag("tag"), Some(&"Foo-Tag".to_owned()));
+        let mut data = token_data.clone();
+        assert_eq!(data.remove_tag("tag"), Some("Foo-Tag".into()));
+        assert!(data.get_tags().is_empty());
+        assert!(data.build().starts_with("foo_user⥂User⥂None⥂"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_acl() -> Result<(), crate::LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂None");
+        assert_eq!(LiteSessionData::default().destructure(&data.build())?, data);
+
+        data.add_acl_deny("Sudo");
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂!Sudo");
+        assert_eq!(LiteSessionData::default().destructure(&data.build())?, data);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("None");
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂%4Eone");
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(destructured.get_acl(), &vec!["None"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn single_use() -> Result<(), crate::LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Password-Reset");
+        assert!(!data.is_single_use());
+
+        data.single_use(true);
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂Password-Reset⥂once=1");
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert!(destructured.is_single_use());
+        assert_eq!(destructured, data);
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂None⥂once=0"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn epoch() -> Result<(), crate::LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        assert_eq!(data.get_epoch(), 0);
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂Network-TCP");
+
+        data.epoch(3);
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂Network-TCP⥂epoch=3");
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(destructured.get_epoch(), 3);
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂None⥂epoch=-1"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn username_normalization() {
+        let mut data = LiteSessionData::default();
+        data.username("Jose\u{301}");
+        assert_eq!(data.get_username(), "Jos\u{e9}");
+        assert_eq!(
+            data.clone().username("Jos\u{e9}").get_username(),
+            data.get_username()
+        );
+
+        data.username_normalization(UsernameNormalization::NfcCaseFold);
+        assert_eq!(data.get_username(), "jos\u{e9}");
+        data.username_normalization(UsernameNormalization::None)
+            .username("Jose\u{301}");
+        assert_eq!(data.get_username(), "Jose\u{301}");
+    }
+
+    #[test]
+    fn role_at_least() {
+        let mut data = LiteSessionData::default();
+        data.role(Role::Admin);
+        assert!(data.role_at_least(Role::Admin));
+        assert!(data.role_at_least(Role::User));
+        assert!(!data.role_at_least(Role::SuperUser));
+
+        let mut hierarchy = RoleHierarchy::empty();
+        hierarchy.imply(Role::Admin, Role::Custom("Auditor".into()));
+        assert!(data.role_at_least_in(&hierarchy, Role::Custom("Auditor".into())));
+        assert!(!data.role_at_least_in(&hierarchy, Role::User));
+    }
+
+    #[test]
+    fn certificate_thumbprint() -> Result<(), crate::LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_service").add_acl("Network-TCP");
+        assert_eq!(data.get_certificate_thumbprint(), None);
+
+        data.certificate_thumbprint(&[7_u8; 32]);
+        assert!(data.build().ends_with(&format!(
+            "cnf={}",
+            base64::encode_config([7_u8; 32], base64::URL_SAFE_NO_PAD)
+        )));
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(
+            destructured.get_certificate_thumbprint(),
+            Some(&[7_u8; 32][..])
+        );
+        assert_eq!(destructured, data);
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_service⥂User⥂None⥂None⥂cnf=%%"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn proof_key() -> Result<(), crate::LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_service").add_acl("Network-TCP");
+        assert_eq!(data.get_proof_key(), None);
+
+        data.proof_key(b"foo-public-key");
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(
+            destructured.get_proof_key(),
+            Some(&ProofOfPossession::thumbprint(b"foo-public-key")[..])
+        );
+        assert_eq!(destructured, data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn auth_level() -> Result<(), crate::LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        assert_eq!(data.get_auth_level(), None);
+
+        data.auth_level(AuthLevel::Mfa);
+        assert_eq!(data.build(), "foo_user⥂User⥂None⥂Network-TCP⥂amr=mfa");
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(destructured.get_auth_level(), Some(&AuthLevel::Mfa));
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂None⥂amr=sms"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn auth_time() -> Result<(), crate::LiteSessionError> {
+        let auth_time = TAI64N::now();
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        assert_eq!(data.get_auth_time(), None);
+
+        data.auth_time(auth_time);
+        assert_eq!(
+            data.build(),
+            format!(
+                "foo_user⥂User⥂None⥂Network-TCP⥂auth_time={}",
+                hex::encode(auth_time.to_bytes())
+            )
+        );
+        let destructured = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(destructured.get_auth_time(), Some(&auth_time));
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂None⥂auth_time=00"),
+            Err(crate::LiteSessionError::InvalidClaim)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn redacted() {
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .subject_id("user-1234")
+            .tag("email", "foo@example.com")
+            .attribute("region", "eu-west")
+            .attachment(b"passport")
+            .add_acl("Network-TCP");
+
+        let redacted = data.redacted();
+        assert_eq!(redacted.get_username(), "[REDACTED]");
+        assert_eq!(redacted.get_subject_id(), &Some("[REDACTED]".into()));
+        assert_eq!(redacted.get_tag("email"), Some(&"[REDACTED]".to_owned()));
+        assert_eq!(
+            redacted.get_attribute("region"),
+            Some(&"[REDACTED]".to_owned())
+        );
+        assert_eq!(redacted.get_acl(), data.get_acl());
+        assert_eq!(redacted.get_role(), data.get_role());
+
+        let debug = format!("{:?}", data);
+        assert!(debug.contains("Network-TCP"));
+        for secret in ["foo_user", "user-1234", "foo@example.com", "eu-west", "112"].iter() {
+            assert!(!debug.contains(secret));
+        }
+    }
+
+    #[test]
+    fn capabilities() -> Result<(), crate::LiteSessionError> {
+        let mut files = Capability::new("files", "read");
+        files.constraint("/home/foo");
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .add_capability(&Capability::new("network", "tcp"))
+            .add_capability(&files);
+
+        let token_data = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(token_data.get_acl().len(), 3);
+        assert_eq!(
+            token_data.get_capabilities(),
+            vec![files, Capability::new("network", "tcp")]
+        );
+        assert!(token_data.allows("network", "tcp"));
+        assert!(token_data.allows("files", "read"));
+        assert!(!token_data.allows("network", "udp"));
+        assert!(!token_data.allows("Network-TCP", ""));
+
+        data.add_acl("files:*");
+        assert!(data.has_scope("files:read"));
+        assert!(data.has_scope("files:write:own"));
+        assert!(data.has_scope("network:tcp"));
+        assert!(!data.has_scope("network:udp"));
+        assert_eq!(
+            data.get_scope_grant("files:read"),
+            Some(&"files:*".to_owned())
+        );
+        data.add_acl("files:read").add_acl("*");
+        assert_eq!(
+            data.get_scope_grant("files:read"),
+            Some(&"files:read".to_owned())
+        );
+        assert_eq!(
+            data.get_scope_grant("files:write"),
+            Some(&"files:*".to_owned())
+        );
+        assert_eq!(data.get_scope_grant("network:udp"), Some(&"*".to_owned()));
+
+        // Deny entries override any grant and survive the round trip
+        data.add_acl("!legacy")
+            .add_acl_deny("billing:*")
+            .add_acl_deny("Network-UDP");
+        assert!(data.has_scope("billing:read"));
+        assert!(!data.has_capability("billing:read"));
+        assert!(!data.has_capability("Network-UDP"));
+        assert!(data.has_capability("Network-TCP"));
+        assert!(data.has_capability("files:write"));
+
+        let token_data = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(token_data, data);
+        assert_eq!(token_data.get_acl_deny(), &vec!["Network-UDP", "billing:*"]);
+        assert!(token_data.get_acl().contains(&"!legacy".to_owned()));
+        assert!(!token_data.has_capability("billing:read"));
+        assert_eq!(data.remove_acl_deny("billing:*"), Some("billing:*".into()));
+        assert!(data.has_capability("billing:read"));
+
+        // Timed capabilities carry their expiry and unrelated `@` survive escaping
+        let now = tai64::TAI64N::now();
+        data.add_acl("user@example.com")
+            .add_acl_until("Admin", now + core::time::Duration::from_secs(900));
+        let token_data = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(token_data, data);
+        assert!(token_data
+            .get_acl()
+            .contains(&"user@example.com".to_owned()));
+        assert_eq!(
+            token_data.get_acl_expiry("Admin"),
+            Some(&(now + core::time::Duration::from_secs(900)))
+        );
+        assert_eq!(data.remove_expired_acl(&now), Vec::<String>::new());
+        assert_eq!(
+            data.remove_expired_acl(&(now + core::time::Duration::from_secs(900))),
+            vec!["Admin"]
+        );
+        assert!(!data.get_acl().contains(&"Admin".to_owned()));
+        assert_eq!(data.get_acl_expiry("Admin"), None);
+        // Attributes are escaped claims that keep their order
+        data.attribute("region", "eu⇅west")
+            .attribute("a=b", "1")
+            .attribute("region", "eu-west");
+        let token_data = LiteSessionData::default().destructure(&data.build())?;
+        assert_eq!(
+            token_data.get_attribute("region"),
+            Some(&"eu-west".to_owned())
+        );
+        assert_eq!(token_data.get_attribute("a=b"), Some(&"1".to_owned()));
+        assert_eq!(token_data.get_attributes(), data.get_attributes());
+
+        assert_eq!(
+            LiteSessionData::default().destructure("foo_user⥂User⥂None⥂Admin@zz"),
+            Err(crate::LiteSessionError::InvalidTai64NTime)
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "json"))]
+mod typed_data_tests {
+    use super::LiteSessionData;
+    use crate::LiteSessionError;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct AppClaims {
+        tenant: String,
+        seats: u32,
+        features: Vec<String>,
+    }
+
+    #[test]
+    fn typed_claims() -> Result<(), LiteSessionError> {
+        let claims = AppClaims {
+            tenant: "acme⥂corp".into(),
+            seats: 5,
+            features: vec!["reports⇅export".into(), "sso=on".into()],
+        };
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        assert_eq!(data.get_claims::<AppClaims>()?, None);
+        data.claims(&claims)?;
+
+        // Separators inside the claims do not leak into the data section
+        let prepared_data = data.build();
+        assert_eq!(prepared_data.matches('⥂').count(), 4);
+        let token_data = LiteSessionData::default().destructure(&prepared_data)?;
+        assert_eq!(token_data, data);
+        assert_eq!(token_data.get_claims::<AppClaims>()?, Some(claims));
+        assert_eq!(
+            token_data.get_claims::<u32>(),
+            Err(LiteSessionError::InvalidClaim)
+        );
 
         Ok(())
     }
@@ -0,0 +1,154 @@
+use crate::LiteSessionError;
+
+const BASE32_ALPHABET: base32::Alphabet = base32::Alphabet::RFC4648 { padding: false };
+
+/// The encoding of the whole token.
+/// `TokenEncoding::Base32` only uses uppercase letters and digits so it survives
+/// case-insensitive channels and the alphanumeric mode of QR codes
+#[derive(Debug, Default)]
+pub enum TokenEncoding {
+    /// The fields joined by the separator of the `TokenFormat`
+    #[default]
+    Plain,
+    /// The fixed-width hex layout of `LiteSessionToken::build_hex()`
+    Hex,
+    /// The plain token encoded as URL-safe Base64 without padding
+    Base64Url,
+    /// The plain token encoded as RFC 4648 Base32 without padding
+    Base32,
+    /// The plain token encoded as Base58 using the Bitcoin alphabet
+    Base58,
+}
+
+impl core::cmp::PartialEq for TokenEncoding {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (TokenEncoding::Plain, TokenEncoding::Plain)
+                | (TokenEncoding::Hex, TokenEncoding::Hex)
+                | (TokenEncoding::Base64Url, TokenEncoding::Base64Url)
+                | (TokenEncoding::Base32, TokenEncoding::Base32)
+                | (TokenEncoding::Base58, TokenEncoding::Base58)
+        )
+    }
+}
+
+impl core::clone::Clone for TokenEncoding {
+    fn clone(&self) -> Self {
+        match self {
+            TokenEncoding::Plain => TokenEncoding::Plain,
+            TokenEncoding::Hex => TokenEncoding::Hex,
+            TokenEncoding::Base64Url => TokenEncoding::Base64Url,
+            TokenEncoding::Base32 => TokenEncoding::Base32,
+            TokenEncoding::Base58 => TokenEncoding::Base58,
+        }
+    }
+}
+
+impl TokenEncoding {
+    /// Detect the encoding of a token from its alphabet, trying the narrowest alphabet first.
+    /// Base32, Base58 and Base64 candidates are only accepted if they decode to a plain token.
+    /// Returns `None` if the token does not fit any of the encodings
+    pub fn detect(token: &str) -> Option<TokenEncoding> {
+        if token.contains("ConfidentialityMode::") {
+            return Some(TokenEncoding::Plain);
+        }
+        if token.is_empty() {
+            return None;
+        }
+        if token
+            .bytes()
+            .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'))
+        {
+            return Some(TokenEncoding::Hex);
+        }
+
+        let candidates = [
+            TokenEncoding::Base32,
+            TokenEncoding::Base58,
+            TokenEncoding::Base64Url,
+        ];
+        candidates
+            .iter()
+            .find(|encoding| match encoding.decode(token) {
+                Ok(decoded) => decoded.contains("ConfidentialityMode::"),
+                Err(_) => false,
+            })
+            .cloned()
+    }
+
+    // The hex layout is not an encoding of the plain token, see `LiteSessionToken::build_hex()`
+    pub(crate) fn encode(&self, token: &str) -> String {
+        match self {
+            TokenEncoding::Plain | TokenEncoding::Hex => token.to_owned(),
+            TokenEncoding::Base64Url => base64::encode_config(token, base64::URL_SAFE_NO_PAD),
+            TokenEncoding::Base32 => base32::encode(BASE32_ALPHABET, token.as_bytes()),
+            TokenEncoding::Base58 => bs58::encode(token).into_string(),
+        }
+    }
+
+    pub(crate) fn decode(&self, token: &str) -> Result<String, LiteSessionError> {
+        let bytes = match self {
+            TokenEncoding::Plain | TokenEncoding::Hex => return Ok(token.to_owned()),
+            TokenEncoding::Base64Url => {
+                match base64::decode_config(token, base64::URL_SAFE_NO_PAD) {
+                    Ok(bytes) => bytes,
+                    Err(_) => return Err(LiteSessionError::InvalidBase64String),
+                }
+            }
+            TokenEncoding::Base32 => {
+                match base32::decode(BASE32_ALPHABET, &token.to_ascii_uppercase()) {
+                    Some(bytes) => bytes,
+                    None => return Err(LiteSessionError::InvalidTokenEncoding),
+                }
+            }
+            TokenEncoding::Base58 => match bs58::decode(token).into_vec() {
+                Ok(bytes) => bytes,
+                Err(_) => return Err(LiteSessionError::InvalidTokenEncoding),
+            },
+        };
+
+        match String::from_utf8(bytes) {
+            Ok(token) => Ok(token),
+            Err(_) => Err(LiteSessionError::FromUtf8TokenError),
+        }
+    }
+}
+
+#[cfg(test)]
+mod encoding_tests {
+    use super::TokenEncoding;
+    use crate::LiteSessionError;
+
+    #[test]
+    fn token_encoding() -> Result<(), LiteSessionError> {
+        let token = "foo⊕ConfidentialityMode::High⊕bar";
+        assert_eq!(TokenEncoding::detect(token), Some(TokenEncoding::Plain));
+
+        for encoding in [
+            TokenEncoding::Base64Url,
+            TokenEncoding::Base32,
+            TokenEncoding::Base58,
+        ]
+        .iter()
+        {
+            let encoded = encoding.encode(token);
+            assert_eq!(TokenEncoding::detect(&encoded).as_ref(), Some(encoding));
+            assert_eq!(encoding.decode(&encoded)?, token);
+        }
+
+        // Base32 survives case-insensitive channels
+        let encoded = TokenEncoding::Base32.encode(token).to_ascii_lowercase();
+        assert_eq!(TokenEncoding::Base32.decode(&encoded)?, token);
+
+        assert_eq!(TokenEncoding::detect("00ff"), Some(TokenEncoding::Hex));
+        assert_eq!(TokenEncoding::detect("Zm9vYmFy"), None);
+        assert_eq!(TokenEncoding::detect("foo bar"), None);
+        assert_eq!(
+            TokenEncoding::Base58.decode("0OIl"),
+            Err(LiteSessionError::InvalidTokenEncoding)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,69 @@
+/// A source of random bytes for identifiers, nonces and keys.
+/// `OsEntropy` is used unless another source is set, inject a deterministic source
+/// with `LiteSessionToken::entropy_source()` to make tokens reproducible in tests
+pub trait EntropySource: core::fmt::Debug + Send + Sync {
+    /// Fill `dest` with random bytes
+    fn fill(&self, dest: &mut [u8]);
+}
+
+/// Random bytes from the operating system using the `getrandom` crate.
+/// Falls back to `NanorandEntropy` if the operating system fails and the `nanorand` feature is enabled
+#[derive(Debug, Default)]
+pub struct OsEntropy;
+
+impl EntropySource for OsEntropy {
+    fn fill(&self, dest: &mut [u8]) {
+        if getrandom::getrandom(dest).is_err() {
+            OsEntropy::fallback(dest);
+        }
+    }
+}
+
+impl OsEntropy {
+    #[cfg(feature = "nanorand")]
+    fn fallback(dest: &mut [u8]) {
+        NanorandEntropy.fill(dest);
+    }
+
+    // Tokens must never be issued with predictable nonces or identifiers
+    #[cfg(not(feature = "nanorand"))]
+    fn fallback(_dest: &mut [u8]) {
+        panic!("The operating system entropy source failed and the `nanorand` feature is disabled");
+    }
+}
+
+/// Random bytes from the `ChaCha` generator of the `nanorand` crate
+/// for targets without an operating system entropy source
+#[cfg(feature = "nanorand")]
+#[derive(Debug, Default)]
+pub struct NanorandEntropy;
+
+#[cfg(feature = "nanorand")]
+impl EntropySource for NanorandEntropy {
+    fn fill(&self, dest: &mut [u8]) {
+        use nanorand::{ChaCha, RNG};
+
+        ChaCha::new(8).fill(dest);
+    }
+}
+
+#[cfg(test)]
+mod entropy_tests {
+    use super::{EntropySource, OsEntropy};
+
+    #[test]
+    fn os_entropy() {
+        let mut first = [0_u8; 32];
+        let mut second = [0_u8; 32];
+        OsEntropy.fill(&mut first);
+        OsEntropy.fill(&mut second);
+        assert_ne!(first, [0_u8; 32]);
+        assert_ne!(first, second);
+
+        #[cfg(feature = "nanorand")]
+        {
+            super::NanorandEntropy.fill(&mut first);
+            assert_ne!(first, second);
+        }
+    }
+}
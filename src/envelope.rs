@@ -0,0 +1,91 @@
+use crate::{CipherText, LiteSessionError, TokenCipher};
+
+/// Prefixes the data section of a token whose data was encrypted under a random data key.
+/// The data key wrapped under the token key follows the marker as 64 hex characters
+pub(crate) const ENVELOPE_MARKER: char = 'w';
+/// The length of a wrapped data key in bytes
+pub(crate) const WRAPPED_KEY_LEN: usize = 32;
+
+// The data key is wrapped with the keystream of the token key and the nonce of the token.
+// The data itself is encrypted under the data key so the key and nonce pair is used once
+pub(crate) fn wrap(
+    data_key: &[u8; 32],
+    token_key: &[u8; 32],
+    nonce: &[u8],
+    cipher: &TokenCipher,
+) -> Result<String, LiteSessionError> {
+    let mut wrapped_key = *data_key;
+    CipherText::apply_keystream(token_key, nonce, cipher, &mut wrapped_key)?;
+
+    let mut envelope = String::with_capacity(1 + WRAPPED_KEY_LEN * 2);
+    envelope.push(ENVELOPE_MARKER);
+    envelope.push_str(&hex::encode(wrapped_key));
+
+    Ok(envelope)
+}
+
+pub(crate) fn unwrap(
+    wrapped_key: &str,
+    token_key: &[u8; 32],
+    nonce: &[u8],
+    cipher: &TokenCipher,
+) -> Result<[u8; 32], LiteSessionError> {
+    let mut data_key = [0_u8; 32];
+    if hex::decode_to_slice(wrapped_key, &mut data_key).is_err() {
+        return Err(LiteSessionError::MissingWrappedKey);
+    }
+    CipherText::apply_keystream(token_key, nonce, cipher, &mut data_key)?;
+
+    Ok(data_key)
+}
+
+/// Split the data section into the hex encoded wrapped data key and the rest of the data section.
+/// Returns `None` if the data section is not envelope encrypted
+pub(crate) fn split(field: &str) -> Option<Result<(&str, &str), LiteSessionError>> {
+    let field = field.strip_prefix(ENVELOPE_MARKER)?;
+
+    match (
+        field.get(..WRAPPED_KEY_LEN * 2),
+        field.get(WRAPPED_KEY_LEN * 2..),
+    ) {
+        (Some(wrapped_key), Some(data)) => Some(Ok((wrapped_key, data))),
+        _ => Some(Err(LiteSessionError::MissingWrappedKey)),
+    }
+}
+
+#[cfg(test)]
+mod envelope_tests {
+    use super::{split, unwrap, wrap, ENVELOPE_MARKER};
+    use crate::{LiteSessionError, Rounds, TokenCipher};
+
+    #[test]
+    fn wrap_unwrap() -> Result<(), LiteSessionError> {
+        let data_key = [7_u8; 32];
+        let token_key = [1_u8; 32];
+        let nonce = [0_u8; 12];
+        let cipher = TokenCipher::ChaCha(Rounds::R8);
+
+        let envelope = wrap(&data_key, &token_key, &nonce, &cipher)?;
+        assert_eq!(envelope.len(), 65);
+        assert!(envelope.starts_with(ENVELOPE_MARKER));
+        assert!(!envelope.contains(&hex::encode(data_key)));
+
+        let field = format!("{}z00ff", envelope);
+        let (wrapped_key, data) = split(&field).unwrap()?;
+        assert_eq!(data, "z00ff");
+        assert_eq!(unwrap(wrapped_key, &token_key, &nonce, &cipher)?, data_key);
+        assert_ne!(unwrap(wrapped_key, &[2_u8; 32], &nonce, &cipher)?, data_key);
+
+        assert!(split("00ff").is_none());
+        assert_eq!(
+            split("w00ff"),
+            Some(Err(LiteSessionError::MissingWrappedKey))
+        );
+        assert_eq!(
+            unwrap(&"zz".repeat(32), &token_key, &nonce, &cipher),
+            Err(LiteSessionError::MissingWrappedKey)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,85 @@
+use crate::LiteSessionData;
+use std::{collections::HashMap, sync::Mutex};
+
+/// A source of the current epoch of every user, consulted by `LiteSessionVerifier` to reject
+/// tokens issued before the user logged out everywhere without tracking individual tokens.
+/// Users are identified by their subject ID, or by their username for tokens without one
+pub trait UserEpochProvider: core::fmt::Debug + Send + Sync {
+    /// Get the current epoch of `user`, `0` for a user who never logged out everywhere
+    fn current_epoch(&self, user: &str) -> u64;
+}
+
+/// An in-memory `UserEpochProvider` suitable for a single server
+///
+/// ```
+/// use lite_session::{MemoryUserEpochs, UserEpochProvider};
+///
+/// let epochs = MemoryUserEpochs::new();
+/// assert_eq!(epochs.current_epoch("foo_user"), 0);
+/// assert_eq!(epochs.bump("foo_user"), 1);
+/// assert_eq!(epochs.current_epoch("foo_user"), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryUserEpochs {
+    epochs: Mutex<HashMap<String, u64>>,
+}
+
+impl MemoryUserEpochs {
+    /// Create a new store where every user is at epoch `0`
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Advance the epoch of `user`, invalidating every token issued before, and return the
+    /// new epoch to set on the tokens issued from now on
+    pub fn bump(&self, user: &str) -> u64 {
+        let mut epochs = match self.epochs.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let epoch = epochs.entry(user.to_owned()).or_insert(0);
+        *epoch = epoch.saturating_add(1);
+
+        *epoch
+    }
+}
+
+impl UserEpochProvider for MemoryUserEpochs {
+    fn current_epoch(&self, user: &str) -> u64 {
+        let epochs = match self.epochs.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        epochs.get(user).copied().unwrap_or_default()
+    }
+}
+
+// The user whose epoch applies to the token
+pub(crate) fn epoch_user(data: &LiteSessionData) -> &str {
+    match data.get_subject_id() {
+        Some(subject_id) => subject_id,
+        None => data.get_username(),
+    }
+}
+
+#[cfg(test)]
+mod epoch_tests {
+    use super::{epoch_user, MemoryUserEpochs, UserEpochProvider};
+    use crate::LiteSessionData;
+
+    #[test]
+    fn memory_user_epochs() {
+        let epochs = MemoryUserEpochs::new();
+        assert_eq!(epochs.current_epoch("foo_user"), 0);
+        assert_eq!(epochs.bump("foo_user"), 1);
+        assert_eq!(epochs.bump("foo_user"), 2);
+        assert_eq!(epochs.current_epoch("foo_user"), 2);
+        assert_eq!(epochs.current_epoch("bar_user"), 0);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        assert_eq!(epoch_user(&data), "foo_user");
+        data.subject_id("8f2c");
+        assert_eq!(epoch_user(&data), "8f2c");
+    }
+}
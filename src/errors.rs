@@ -19,6 +19,62 @@ pub enum LiteSessionError {
     DataFieldsLengthError,
     /// The string provided was not of type `hex` even though a hex type is needed
     InvalidHexString,
+    /// The token is not a valid URL-safe Base64 string
+    InvalidBase64String,
+    /// The `ConfidentialityMode` of the token is not a known mode
+    InvalidConfidentialityMode,
+    /// The field separator of the token is not allowed or does not match the configured `TokenFormat`
+    InvalidTokenFormat,
+    /// The version prefix of the token is not a supported `TokenVersion`
+    UnsupportedTokenVersion,
+    /// The JWT is malformed, uses an unsupported algorithm or lacks an `exp` claim
+    InvalidJwt,
+    /// The CWT is not a `COSE_Mac0` structure, uses an unsupported algorithm or lacks an `exp` claim
+    InvalidCwt,
+    /// The chunks of a token are missing, duplicated or carry inconsistent ordering metadata
+    InvalidTokenChunk,
+    /// The token cannot be decoded using its `TokenEncoding`
+    InvalidTokenEncoding,
+    /// The data section cannot be compressed or inflated, or the `compression` feature is disabled
+    CompressionError,
+    /// A key ID is empty, longer than 32 bytes or not ASCII alphanumeric
+    InvalidKeyId,
+    /// The key ID is not held by the `KeyRing`
+    UnknownKeyId,
+    /// A tenant ID is empty, longer than 32 bytes or not ASCII alphanumeric, or no tenant ID was set
+    InvalidTenantId,
+    /// The data section holds no data key wrapped by `envelope()`, or it is truncated
+    MissingWrappedKey,
+    /// The token uses `TokenCipher::Aes256Gcm` and the `fips` feature is disabled
+    UnavailableCipher,
+    /// A field of the data section contains an invalid percent escape
+    InvalidFieldEscape,
+    /// The encoded data section is larger than the maximum data size of the token
+    DataSizeTooLarge,
+    /// Only a token built or verified as authentic can be renewed
+    TokenNotAuthentic,
+    /// The revocation file cannot be read or written
+    RevocationIoError,
+    /// The session store file holds a record that cannot be decoded
+    InvalidSessionRecord,
+    /// The session store file cannot be read or written
+    SessionStoreIoError,
+    /// The IP address or CIDR range of a `NetworkBinding` is not valid
+    InvalidNetworkBinding,
+    /// The proof of possession cannot be decoded
+    InvalidProof,
+    /// The caveats of an `AttenuatedToken` cannot be decoded
+    InvalidAttenuation,
+    /// A command sent to Redis by a Redis backed store failed
+    RedisCommandFailed,
+    /// A statement run by a SQL backed store failed
+    SqlStatementFailed,
+    /// A delta revocation snapshot starts after the last revocation the store imported,
+    /// so the revocations in between were missed and a full snapshot must be imported
+    RevocationSnapshotGap,
+    /// The `MemoryReplayGuard` already remembers as many unexpired single-use tokens as its
+    /// capacity allows, so another one cannot be recorded
+    ReplayGuardFull,
     /// The destructured time in `hex` cannot be converted to a valid `TAI64N` scientific time value
     InvalidTai64NTime,
     /// The bytes provided cannot be converted to a valid `blake3::Hash`
@@ -27,6 +83,16 @@ pub enum LiteSessionError {
     /// This usually happens when the `key` or `nonce` used or both are invalid
     /// resulting in a bad deserialization
     FromUtf8TokenError,
+    /// The provided token contains an optional claim in the data section that cannot be decoded
+    InvalidClaim,
+    /// The provided revocation snapshot is malformed and cannot be imported
+    InvalidRevocationSnapshot,
+    /// The `issued` or `expiry` time of the token falls outside the plausible `TimestampRange`
+    TimestampOutOfRange,
+    /// The issuer configuration cannot be serialized or deserialized
+    InvalidIssuerConfig,
+    /// The issuer configuration file cannot be read or written
+    ConfigIoError,
 }
 
 impl core::cmp::PartialEq for LiteSessionError {
@@ -41,10 +107,170 @@ impl core::cmp::PartialEq for LiteSessionError {
             )
             | (LiteSessionError::DataFieldsLengthError, LiteSessionError::DataFieldsLengthError)
             | (LiteSessionError::InvalidHexString, LiteSessionError::InvalidHexString)
+            | (LiteSessionError::InvalidBase64String, LiteSessionError::InvalidBase64String)
+            | (
+                LiteSessionError::InvalidConfidentialityMode,
+                LiteSessionError::InvalidConfidentialityMode,
+            )
+            | (LiteSessionError::InvalidTokenFormat, LiteSessionError::InvalidTokenFormat)
+            | (
+                LiteSessionError::UnsupportedTokenVersion,
+                LiteSessionError::UnsupportedTokenVersion,
+            )
+            | (LiteSessionError::InvalidJwt, LiteSessionError::InvalidJwt)
+            | (LiteSessionError::InvalidCwt, LiteSessionError::InvalidCwt)
+            | (LiteSessionError::InvalidTokenChunk, LiteSessionError::InvalidTokenChunk)
+            | (LiteSessionError::InvalidTokenEncoding, LiteSessionError::InvalidTokenEncoding)
+            | (LiteSessionError::CompressionError, LiteSessionError::CompressionError)
+            | (LiteSessionError::InvalidKeyId, LiteSessionError::InvalidKeyId)
+            | (LiteSessionError::UnknownKeyId, LiteSessionError::UnknownKeyId)
+            | (LiteSessionError::InvalidTenantId, LiteSessionError::InvalidTenantId)
+            | (LiteSessionError::MissingWrappedKey, LiteSessionError::MissingWrappedKey)
+            | (LiteSessionError::UnavailableCipher, LiteSessionError::UnavailableCipher)
+            | (LiteSessionError::InvalidFieldEscape, LiteSessionError::InvalidFieldEscape)
+            | (LiteSessionError::DataSizeTooLarge, LiteSessionError::DataSizeTooLarge)
+            | (LiteSessionError::TokenNotAuthentic, LiteSessionError::TokenNotAuthentic)
+            | (LiteSessionError::RevocationIoError, LiteSessionError::RevocationIoError)
+            | (LiteSessionError::InvalidSessionRecord, LiteSessionError::InvalidSessionRecord)
+            | (LiteSessionError::SessionStoreIoError, LiteSessionError::SessionStoreIoError)
+            | (LiteSessionError::InvalidNetworkBinding, LiteSessionError::InvalidNetworkBinding)
+            | (LiteSessionError::InvalidProof, LiteSessionError::InvalidProof)
+            | (LiteSessionError::InvalidAttenuation, LiteSessionError::InvalidAttenuation)
+            | (LiteSessionError::RedisCommandFailed, LiteSessionError::RedisCommandFailed)
+            | (LiteSessionError::SqlStatementFailed, LiteSessionError::SqlStatementFailed)
+            | (LiteSessionError::RevocationSnapshotGap, LiteSessionError::RevocationSnapshotGap)
+            | (LiteSessionError::ReplayGuardFull, LiteSessionError::ReplayGuardFull)
             | (LiteSessionError::InvalidTai64NTime, LiteSessionError::InvalidTai64NTime)
             | (LiteSessionError::InvalidBytesForBlake3, LiteSessionError::InvalidBytesForBlake3)
-            | (LiteSessionError::FromUtf8TokenError, LiteSessionError::FromUtf8TokenError) => true,
+            | (LiteSessionError::FromUtf8TokenError, LiteSessionError::FromUtf8TokenError)
+            | (LiteSessionError::InvalidClaim, LiteSessionError::InvalidClaim)
+            | (
+                LiteSessionError::InvalidRevocationSnapshot,
+                LiteSessionError::InvalidRevocationSnapshot,
+            )
+            | (LiteSessionError::TimestampOutOfRange, LiteSessionError::TimestampOutOfRange)
+            | (LiteSessionError::InvalidIssuerConfig, LiteSessionError::InvalidIssuerConfig)
+            | (LiteSessionError::ConfigIoError, LiteSessionError::ConfigIoError) => true,
             _ => false,
         }
     }
 }
+
+impl LiteSessionError {
+    /// Get the stable numeric code of the error for FFI consumers, embedded devices and
+    /// wire protocols. Error codes start at `100` and a code is never reused or renumbered
+    pub fn code(&self) -> u16 {
+        match self {
+            LiteSessionError::NonceLengthError => 100,
+            LiteSessionError::ServerKeyLengthError => 101,
+            LiteSessionError::TokenSizeTooLarge => 102,
+            LiteSessionError::TokenFieldsLengthError => 103,
+            LiteSessionError::DataFieldsLengthError => 104,
+            LiteSessionError::InvalidHexString => 105,
+            LiteSessionError::InvalidTai64NTime => 106,
+            LiteSessionError::InvalidBytesForBlake3 => 107,
+            LiteSessionError::FromUtf8TokenError => 108,
+            LiteSessionError::InvalidClaim => 109,
+            LiteSessionError::InvalidRevocationSnapshot => 110,
+            LiteSessionError::TimestampOutOfRange => 111,
+            LiteSessionError::InvalidIssuerConfig => 112,
+            LiteSessionError::ConfigIoError => 113,
+            LiteSessionError::InvalidBase64String => 114,
+            LiteSessionError::InvalidConfidentialityMode => 115,
+            LiteSessionError::InvalidTokenFormat => 116,
+            LiteSessionError::UnsupportedTokenVersion => 117,
+            LiteSessionError::InvalidJwt => 118,
+            LiteSessionError::InvalidCwt => 119,
+            LiteSessionError::InvalidTokenChunk => 120,
+            LiteSessionError::InvalidTokenEncoding => 121,
+            LiteSessionError::CompressionError => 122,
+            LiteSessionError::InvalidKeyId => 123,
+            LiteSessionError::UnknownKeyId => 124,
+            LiteSessionError::InvalidTenantId => 125,
+            LiteSessionError::MissingWrappedKey => 126,
+            LiteSessionError::UnavailableCipher => 127,
+            LiteSessionError::InvalidFieldEscape => 128,
+            LiteSessionError::DataSizeTooLarge => 129,
+            LiteSessionError::TokenNotAuthentic => 130,
+            LiteSessionError::RevocationIoError => 131,
+            LiteSessionError::InvalidSessionRecord => 132,
+            LiteSessionError::SessionStoreIoError => 133,
+            LiteSessionError::InvalidNetworkBinding => 134,
+            LiteSessionError::InvalidProof => 135,
+            LiteSessionError::InvalidAttenuation => 136,
+            LiteSessionError::RedisCommandFailed => 137,
+            LiteSessionError::SqlStatementFailed => 138,
+            LiteSessionError::RevocationSnapshotGap => 139,
+            LiteSessionError::ReplayGuardFull => 140,
+        }
+    }
+    /// Get the error matching a numeric code returned by `LiteSessionError::code()`
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            100 => Some(LiteSessionError::NonceLengthError),
+            101 => Some(LiteSessionError::ServerKeyLengthError),
+            102 => Some(LiteSessionError::TokenSizeTooLarge),
+            103 => Some(LiteSessionError::TokenFieldsLengthError),
+            104 => Some(LiteSessionError::DataFieldsLengthError),
+            105 => Some(LiteSessionError::InvalidHexString),
+            106 => Some(LiteSessionError::InvalidTai64NTime),
+            107 => Some(LiteSessionError::InvalidBytesForBlake3),
+            108 => Some(LiteSessionError::FromUtf8TokenError),
+            109 => Some(LiteSessionError::InvalidClaim),
+            110 => Some(LiteSessionError::InvalidRevocationSnapshot),
+            111 => Some(LiteSessionError::TimestampOutOfRange),
+            112 => Some(LiteSessionError::InvalidIssuerConfig),
+            113 => Some(LiteSessionError::ConfigIoError),
+            114 => Some(LiteSessionError::InvalidBase64String),
+            115 => Some(LiteSessionError::InvalidConfidentialityMode),
+            116 => Some(LiteSessionError::InvalidTokenFormat),
+            117 => Some(LiteSessionError::UnsupportedTokenVersion),
+            118 => Some(LiteSessionError::InvalidJwt),
+            119 => Some(LiteSessionError::InvalidCwt),
+            120 => Some(LiteSessionError::InvalidTokenChunk),
+            121 => Some(LiteSessionError::InvalidTokenEncoding),
+            122 => Some(LiteSessionError::CompressionError),
+            123 => Some(LiteSessionError::InvalidKeyId),
+            124 => Some(LiteSessionError::UnknownKeyId),
+            125 => Some(LiteSessionError::InvalidTenantId),
+            126 => Some(LiteSessionError::MissingWrappedKey),
+            127 => Some(LiteSessionError::UnavailableCipher),
+            128 => Some(LiteSessionError::InvalidFieldEscape),
+            129 => Some(LiteSessionError::DataSizeTooLarge),
+            130 => Some(LiteSessionError::TokenNotAuthentic),
+            131 => Some(LiteSessionError::RevocationIoError),
+            132 => Some(LiteSessionError::InvalidSessionRecord),
+            133 => Some(LiteSessionError::SessionStoreIoError),
+            134 => Some(LiteSessionError::InvalidNetworkBinding),
+            135 => Some(LiteSessionError::InvalidProof),
+            136 => Some(LiteSessionError::InvalidAttenuation),
+            137 => Some(LiteSessionError::RedisCommandFailed),
+            138 => Some(LiteSessionError::SqlStatementFailed),
+            139 => Some(LiteSessionError::RevocationSnapshotGap),
+            140 => Some(LiteSessionError::ReplayGuardFull),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod errors_tests {
+    use super::LiteSessionError;
+
+    #[test]
+    fn error_codes() {
+        let codes = (0..=u16::MAX)
+            .filter_map(LiteSessionError::from_code)
+            .map(|error| error.code())
+            .collect::<Vec<u16>>();
+        assert_eq!(codes, (100..=140).collect::<Vec<u16>>());
+
+        assert_eq!(LiteSessionError::NonceLengthError.code(), 100);
+        assert_eq!(LiteSessionError::InvalidTokenFormat.code(), 116);
+        assert_eq!(
+            LiteSessionError::from_code(103),
+            Some(LiteSessionError::TokenFieldsLengthError)
+        );
+        assert_eq!(LiteSessionError::from_code(0), None);
+    }
+}
@@ -0,0 +1,111 @@
+use crate::LiteSessionError;
+
+/// Characters that delimit the fields of the data section and of the token
+/// and the escape character itself
+const RESERVED: [char; 4] = ['%', '⥂', '⇅', '⊕'];
+
+// Percent-escape the reserved characters so that a value can never shift the fields around it
+pub(crate) fn escape(value: &str) -> String {
+    escape_reserved(value, &RESERVED)
+}
+
+// Percent-escape `reserved`, which must include `%`, for formats with other delimiters
+pub(crate) fn escape_reserved(value: &str, reserved: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    value
+        .chars()
+        .for_each(|character| match reserved.contains(&character) {
+            true => push_escaped(&mut escaped, character),
+            false => escaped.push(character),
+        });
+
+    escaped
+}
+
+// Like `escape()` but also escapes the first character so that a value
+// spelling a keyword like the `None` tag is not read back as the keyword
+pub(crate) fn escape_keyword(value: &str) -> String {
+    let mut characters = value.chars();
+
+    match characters.next() {
+        None => String::default(),
+        Some(first) => {
+            let mut escaped = String::with_capacity(value.len() + 2);
+            push_escaped(&mut escaped, first);
+            escaped.push_str(&escape(characters.as_str()));
+
+            escaped
+        }
+    }
+}
+
+pub(crate) fn unescape(value: &str) -> Result<String, LiteSessionError> {
+    if !value.contains('%') {
+        return Ok(value.into());
+    }
+
+    let bytes = value.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut index = 0_usize;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            b'%' => {
+                let escaped = match bytes.get(index + 1..index + 3) {
+                    Some(escaped) => escaped,
+                    None => return Err(LiteSessionError::InvalidFieldEscape),
+                };
+                let mut byte = [0_u8; 1];
+                match hex::decode_to_slice(escaped, &mut byte) {
+                    Ok(_) => unescaped.push(byte[0]),
+                    Err(_) => return Err(LiteSessionError::InvalidFieldEscape),
+                }
+                index += 3;
+            }
+            byte => {
+                unescaped.push(byte);
+                index += 1;
+            }
+        }
+    }
+
+    match String::from_utf8(unescaped) {
+        Ok(unescaped) => Ok(unescaped),
+        Err(_) => Err(LiteSessionError::InvalidFieldEscape),
+    }
+}
+
+fn push_escaped(escaped: &mut String, character: char) {
+    let mut buffer = [0_u8; 4];
+    character
+        .encode_utf8(&mut buffer)
+        .bytes()
+        .for_each(|byte| escaped.push_str(&format!("%{:02X}", byte)));
+}
+
+#[cfg(test)]
+mod escape_tests {
+    use super::{escape, escape_keyword, unescape};
+    use crate::LiteSessionError;
+
+    #[test]
+    fn escape_unescape() -> Result<(), LiteSessionError> {
+        assert_eq!(escape("foo_user"), "foo_user");
+        assert_eq!(escape("100%"), "100%25");
+        assert_eq!(escape("foo⥂Admin"), "foo%E2%A5%82Admin");
+        assert_eq!(escape_keyword("None"), "%4Eone");
+        assert_eq!(escape_keyword(""), "");
+
+        for value in &["foo_user", "100%", "a⥂b⇅c⊕d", "%E2", "None", "ユーザー"] {
+            assert_eq!(&unescape(&escape(value))?, value);
+            assert_eq!(&unescape(&escape_keyword(value))?, value);
+        }
+
+        assert_eq!(unescape("100%"), Err(LiteSessionError::InvalidFieldEscape));
+        assert_eq!(unescape("%zz"), Err(LiteSessionError::InvalidFieldEscape));
+        assert_eq!(unescape("%FF"), Err(LiteSessionError::InvalidFieldEscape));
+
+        Ok(())
+    }
+}
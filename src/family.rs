@@ -0,0 +1,476 @@
+use crate::{
+    Clock, LiteSessionData, LiteSessionError, LiteSessionToken, RejectionReason, RevocationStore,
+    ServerKey, SessionTokenRng, TokenOutcome,
+};
+use core::time::Duration;
+use std::sync::Arc;
+use tai64::TAI64N;
+use timelite::LiteDuration;
+
+/// The family claim shared by all access and refresh tokens minted from the same
+/// initial login through a chain of refreshes. The generation counts the refreshes
+/// so that presenting a refresh token of an older generation reveals its reuse
+///
+/// ```
+/// pub struct SessionFamily {
+///     id: String,
+///     refresh: bool,
+///     generation: u64,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SessionFamily {
+    id: String,
+    refresh: bool,
+    generation: u64,
+}
+
+impl core::cmp::PartialEq for SessionFamily {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id && self.refresh == other.refresh && self.generation == other.generation
+    }
+}
+
+impl core::clone::Clone for SessionFamily {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id.clone(),
+            refresh: self.refresh,
+            generation: self.generation,
+        }
+    }
+}
+
+impl SessionFamily {
+    /// Create the family claim of an access token
+    pub fn access(id: &str) -> Self {
+        Self {
+            id: id.into(),
+            refresh: false,
+            generation: 0,
+        }
+    }
+    /// Create the family claim of a refresh token
+    pub fn refresh(id: &str) -> Self {
+        Self {
+            id: id.into(),
+            refresh: true,
+            generation: 0,
+        }
+    }
+    /// Set the number of refreshes since the initial login
+    pub fn generation(&mut self, generation: u64) -> &mut Self {
+        self.generation = generation;
+
+        self
+    }
+    /// Get the family identifier
+    pub fn get_id(&self) -> &String {
+        &self.id
+    }
+    /// Check whether the token is a refresh token
+    pub fn is_refresh(&self) -> bool {
+        self.refresh
+    }
+    /// Get the number of refreshes since the initial login
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+    /// Convert the family into the string attached to the data section of a token.
+    /// The generation follows the kind of token as `.<generation>` unless it is `0`
+    pub fn to_string(family: &SessionFamily) -> String {
+        let mut family_str = family.id.clone();
+        match family.refresh {
+            true => family_str.push_str("/refresh"),
+            false => family_str.push_str("/access"),
+        }
+        if family.generation != 0 {
+            family_str.push_str(&format!(".{}", family.generation));
+        }
+
+        family_str
+    }
+    /// Convert a family string from the data section of a token into a `SessionFamily`
+    pub fn from_string(value: &str) -> Option<Self> {
+        let mut parts = value.rsplitn(2, '/');
+        let mut kind = parts.next()?.splitn(2, '.');
+        let refresh = match kind.next()? {
+            "refresh" => true,
+            "access" => false,
+            _ => return None,
+        };
+        let generation = match kind.next() {
+            Some(generation) => generation.parse::<u64>().ok()?,
+            None => 0,
+        };
+
+        Some(Self {
+            id: parts.next()?.into(),
+            refresh,
+            generation,
+        })
+    }
+}
+
+/// An access token and the refresh token used to obtain the next pair
+/// bound together by the ID of their `SessionFamily`
+///
+/// ```
+/// pub struct TokenPair {
+///     access: String,
+///     refresh: String,
+///     family_id: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TokenPair {
+    access: String,
+    refresh: String,
+    family_id: String,
+}
+
+impl core::cmp::PartialEq for TokenPair {
+    fn eq(&self, other: &Self) -> bool {
+        self.access == other.access
+            && self.refresh == other.refresh
+            && self.family_id == other.family_id
+    }
+}
+
+impl core::clone::Clone for TokenPair {
+    fn clone(&self) -> Self {
+        Self {
+            access: self.access.clone(),
+            refresh: self.refresh.clone(),
+            family_id: self.family_id.clone(),
+        }
+    }
+}
+
+impl TokenPair {
+    /// Get the short lived access token
+    pub fn get_access(&self) -> &String {
+        &self.access
+    }
+    /// Get the long lived refresh token
+    pub fn get_refresh(&self) -> &String {
+        &self.refresh
+    }
+    /// Get the ID of the family shared by every pair rotated from the same login
+    pub fn get_family_id(&self) -> &String {
+        &self.family_id
+    }
+}
+
+/// Issues token pairs and rotates them on every refresh, advancing the generation of the family.
+/// Every generation of refresh token can only be used once. Presenting a refresh token of a
+/// generation that has already been rotated indicates that it was stolen, so the whole family
+/// is revoked in the `RevocationStore`, logging out both the thief and the legitimate client
+#[derive(Debug)]
+pub struct RefreshRotation<'a, R: RevocationStore> {
+    revocations: &'a R,
+    access_ttl: u64,
+    refresh_ttl: u64,
+    clock: Option<Arc<dyn Clock>>,
+}
+
+impl<'a, R: RevocationStore> RefreshRotation<'a, R> {
+    /// Create a rotation using `revocations` to track rotated refresh tokens and revoked families.
+    /// The default lifetime of access tokens is 15 minutes and of refresh tokens 30 days
+    pub fn new(revocations: &'a R) -> Self {
+        Self {
+            revocations,
+            access_ttl: LiteDuration::minutes(15),
+            refresh_ttl: LiteDuration::days(30),
+            clock: None,
+        }
+    }
+    /// Set the lifetime of access tokens in seconds
+    pub fn access_ttl(&mut self, ttl_in_secs: u64) -> &mut Self {
+        self.access_ttl = ttl_in_secs;
+
+        self
+    }
+    /// Set the lifetime of refresh tokens in seconds
+    pub fn refresh_ttl(&mut self, ttl_in_secs: u64) -> &mut Self {
+        self.refresh_ttl = ttl_in_secs;
+
+        self
+    }
+    /// Read the current time from `clock` instead of `SystemClock` when minting and verifying
+    /// tokens and when revoking a family
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = Some(clock);
+
+        self
+    }
+    /// Issue the first token pair of a new family
+    pub fn issue(
+        &self,
+        server_key: &ServerKey,
+        data: LiteSessionData,
+    ) -> Result<TokenPair, LiteSessionError> {
+        self.mint(server_key, data, &SessionTokenRng::alphanumeric(), 0)
+    }
+    /// Rotate a refresh token into a new token pair of the same family.
+    /// Returns `TokenOutcome::TokenRevoked` if the family has been revoked or if the refresh
+    /// token has already been rotated, in which case the whole family is revoked
+    pub fn refresh(
+        &self,
+        server_key: &ServerKey,
+        refresh_token: &str,
+    ) -> Result<(TokenOutcome, Option<TokenPair>), LiteSessionError> {
+        let mut token = self.token();
+        let (outcome, _) = token.from_string(server_key, refresh_token)?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, None));
+        }
+
+        let family = match token.get_hmac_data().get_family() {
+            Some(family) if family.is_refresh() => family.clone(),
+            _ => return Ok((TokenOutcome::TokenRejected, None)),
+        };
+
+        if self.revocations.is_revoked(family.get_id()) {
+            return Ok((TokenOutcome::TokenRevoked, None));
+        }
+
+        // Only the refresh that newly marks the token as rotated gets a new pair,
+        // a concurrent or later refresh of the same token is a reuse
+        let rotated = SessionFamily::to_string(&family);
+        if !self.revocations.try_revoke(&rotated, *token.get_expiry()) {
+            self.revoke_family(family.get_id());
+            return Ok((TokenOutcome::TokenRevoked, None));
+        }
+
+        let pair = self.mint(
+            server_key,
+            token.get_hmac_data().clone(),
+            family.get_id(),
+            family.get_generation().saturating_add(1),
+        )?;
+
+        Ok((TokenOutcome::TokenAuthentic, Some(pair)))
+    }
+    /// Verify an access or refresh token and check that neither it nor its family has been revoked
+    pub fn verify(
+        &self,
+        server_key: &ServerKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, LiteSessionToken), LiteSessionError> {
+        let mut verified = self.token();
+        let (outcome, _) = verified.from_string(server_key, token)?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, verified));
+        }
+
+        let family_revoked = match verified.get_hmac_data().get_family() {
+            Some(family) => self.revocations.is_revoked(family.get_id()),
+            None => false,
+        };
+        if family_revoked || self.revocations.is_revoked(verified.get_identifier()) {
+            verified.reject(RejectionReason::Revoked);
+            return Ok((TokenOutcome::TokenRevoked, verified));
+        }
+
+        Ok((TokenOutcome::TokenAuthentic, verified))
+    }
+    /// Revoke every token of the family `family_id`
+    pub fn revoke_family(&self, family_id: &str) {
+        let now = match &self.clock {
+            Some(clock) => clock.now(),
+            None => TAI64N::now(),
+        };
+        let horizon = now + Duration::from_secs(self.refresh_ttl);
+        self.revocations.revoke(family_id, horizon);
+    }
+
+    fn mint(
+        &self,
+        server_key: &ServerKey,
+        mut data: LiteSessionData,
+        family_id: &str,
+        generation: u64,
+    ) -> Result<TokenPair, LiteSessionError> {
+        let mut access = self.token();
+        access.expiry(self.access_ttl);
+        let mut family = SessionFamily::access(family_id);
+        family.generation(generation);
+        data.family(family);
+        access.hmac_data(data.clone());
+
+        let mut refresh = self.token();
+        refresh.expiry(self.refresh_ttl);
+        let mut family = SessionFamily::refresh(family_id);
+        family.generation(generation);
+        data.family(family);
+        refresh.hmac_data(data);
+
+        Ok(TokenPair {
+            access: access.build_secure(server_key)?,
+            refresh: refresh.build_secure(server_key)?,
+            family_id: family_id.into(),
+        })
+    }
+
+    fn token(&self) -> LiteSessionToken {
+        let mut token = LiteSessionToken::default();
+        if let Some(clock) = &self.clock {
+            token.clock(clock.clone());
+        }
+
+        token
+    }
+}
+
+#[cfg(test)]
+mod family_tests {
+    use super::{RefreshRotation, SessionFamily};
+    use crate::{
+        FixedClock, LiteSessionData, LiteSessionError, MemoryRevocationStore, RejectionReason,
+        RevocationStore, ServerKey, TokenOutcome,
+    };
+    use core::time::Duration;
+    use std::sync::Arc;
+    use tai64::TAI64N;
+
+    #[test]
+    fn family_claim() {
+        let family = SessionFamily::refresh("foo/bar");
+        assert_eq!(SessionFamily::to_string(&family), "foo/bar/refresh");
+        assert_eq!(
+            SessionFamily::from_string("foo/bar/refresh"),
+            Some(family.clone())
+        );
+        assert_eq!(
+            SessionFamily::from_string("foo/access"),
+            Some(SessionFamily::access("foo"))
+        );
+        assert_eq!(SessionFamily::from_string("foo/bar"), None);
+        assert_eq!(SessionFamily::from_string("foo"), None);
+
+        let mut family = SessionFamily::refresh("foo");
+        family.generation(3);
+        assert_eq!(SessionFamily::to_string(&family), "foo/refresh.3");
+        assert_eq!(SessionFamily::from_string("foo/refresh.3"), Some(family));
+        assert_eq!(SessionFamily::from_string("foo/refresh.x"), None);
+    }
+
+    #[test]
+    fn refresh_rotation() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let revocations = MemoryRevocationStore::new();
+        let rotation = RefreshRotation::new(&revocations);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        data.add_acl("Network-TCP");
+
+        let first = rotation.issue(&server_key, data)?;
+        let (outcome, access) = rotation.verify(&server_key, first.get_access())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let family = access.get_hmac_data().get_family().clone();
+        assert_eq!(
+            family.as_ref().map(|family| family.is_refresh()),
+            Some(false)
+        );
+        assert_eq!(
+            family.as_ref().map(|family| family.get_id()),
+            Some(first.get_family_id())
+        );
+
+        // Access tokens cannot be used to refresh
+        let (outcome, pair) = rotation.refresh(&server_key, first.get_access())?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert!(pair.is_none());
+
+        let (outcome, second) = rotation.refresh(&server_key, first.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let second = second.unwrap();
+        assert_eq!(second.get_family_id(), first.get_family_id());
+        assert_ne!(second, first);
+        let (_, access) = rotation.verify(&server_key, second.get_access())?;
+        assert_eq!(
+            access
+                .get_hmac_data()
+                .get_family()
+                .as_ref()
+                .map(|family| family.get_generation()),
+            Some(1)
+        );
+        assert_eq!(
+            access
+                .get_hmac_data()
+                .get_family()
+                .as_ref()
+                .map(|family| family.get_id()),
+            family.as_ref().map(|family| family.get_id())
+        );
+        assert_eq!(access.get_hmac_data().get_username(), "foo_user");
+
+        // Reusing the rotated refresh token revokes the whole family
+        let (outcome, _) = rotation.refresh(&server_key, first.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        let (outcome, _) = rotation.refresh(&server_key, second.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        let (outcome, access) = rotation.verify(&server_key, second.get_access())?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        assert_eq!(access.get_rejection(), &Some(RejectionReason::Revoked));
+
+        Ok(())
+    }
+
+    #[test]
+    fn rotation_clock() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let revocations = MemoryRevocationStore::new();
+        let now = TAI64N::now() + Duration::from_secs(3600 * 24 * 40);
+        let mut rotation = RefreshRotation::new(&revocations);
+        rotation
+            .refresh_ttl(3600)
+            .clock(Arc::new(FixedClock::new(now)));
+
+        // Tokens minted at the time of the clock are only valid at that time
+        let first = rotation.issue(&server_key, LiteSessionData::default())?;
+        let (outcome, _) = rotation.verify(&server_key, first.get_access())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = rotation.refresh(&server_key, first.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        // The family stays revoked for the refresh lifetime from the time of the clock
+        let (outcome, _) = rotation.refresh(&server_key, first.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        let family = revocations
+            .export(0)
+            .get_entries()
+            .iter()
+            .find(|(identifier, _)| identifier == first.get_family_id())
+            .map(|(_, expiry)| *expiry);
+        assert_eq!(family, Some(now + Duration::from_secs(3600)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_refresh() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let revocations = MemoryRevocationStore::new();
+        let rotation = RefreshRotation::new(&revocations);
+        let pair = rotation.issue(&server_key, LiteSessionData::default())?;
+
+        // Only one of the refreshes racing on the same refresh token gets a new pair
+        let refreshed = std::thread::scope(|scope| {
+            let refreshes = (0..8)
+                .map(|_| scope.spawn(|| rotation.refresh(&server_key, pair.get_refresh())))
+                .collect::<Vec<_>>();
+            refreshes
+                .into_iter()
+                .map(|refresh| refresh.join().unwrap())
+                .filter(|refresh| matches!(refresh, Ok((_, Some(_)))))
+                .count()
+        });
+        assert_eq!(refreshed, 1);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use crate::LiteSessionError;
+#[cfg(feature = "fips")]
+use aes_gcm::{
+    aead::{AeadInPlace, KeyInit},
+    Aes256Gcm, Nonce, Tag,
+};
+#[cfg(feature = "fips")]
+use hmac::{Hmac, Mac};
+#[cfg(feature = "fips")]
+use sha2::Sha256;
+
+/// The length of the `AES-256-GCM` authentication tag appended to the data section
+#[cfg(feature = "fips")]
+pub(crate) const TAG_LEN: usize = 16;
+
+#[cfg(feature = "fips")]
+type HmacSha256 = Hmac<Sha256>;
+#[cfg(feature = "fips")]
+type Aes256Ctr = ctr::Ctr32BE<aes::Aes256>;
+
+#[cfg(feature = "fips")]
+pub(crate) fn seal(key: &[u8], nonce: &[u8], data: &mut Vec<u8>) -> Result<(), LiteSessionError> {
+    let cipher = match Aes256Gcm::new_from_slice(key) {
+        Ok(cipher) => cipher,
+        Err(_) => return Err(LiteSessionError::ServerKeyLengthError),
+    };
+    if nonce.len() != 12 {
+        return Err(LiteSessionError::NonceLengthError);
+    }
+
+    match cipher.encrypt_in_place_detached(Nonce::from_slice(nonce), b"", data) {
+        Ok(tag) => {
+            data.extend_from_slice(&tag);
+            Ok(())
+        }
+        Err(_) => Err(LiteSessionError::TokenSizeTooLarge),
+    }
+}
+
+// Returns the length of the plaintext at the start of `data`
+#[cfg(feature = "fips")]
+pub(crate) fn open(key: &[u8], nonce: &[u8], data: &mut [u8]) -> Result<usize, LiteSessionError> {
+    let cipher = match Aes256Gcm::new_from_slice(key) {
+        Ok(cipher) => cipher,
+        Err(_) => return Err(LiteSessionError::ServerKeyLengthError),
+    };
+    if nonce.len() != 12 {
+        return Err(LiteSessionError::NonceLengthError);
+    }
+    if data.len() < TAG_LEN {
+        return Err(LiteSessionError::FromUtf8TokenError);
+    }
+
+    let (data, tag) = data.split_at_mut(data.len() - TAG_LEN);
+    match cipher.decrypt_in_place_detached(
+        Nonce::from_slice(nonce),
+        b"",
+        data,
+        Tag::from_slice(tag),
+    ) {
+        Ok(_) => Ok(data.len()),
+        Err(_) => Err(LiteSessionError::FromUtf8TokenError),
+    }
+}
+
+// `AES-256-CTR` for the data keys wrapped by `envelope()`
+#[cfg(feature = "fips")]
+pub(crate) fn apply_keystream(
+    key: &[u8],
+    nonce: &[u8],
+    data: &mut [u8],
+) -> Result<(), LiteSessionError> {
+    use aes::cipher::{KeyIvInit, StreamCipher};
+
+    if nonce.len() != 12 {
+        return Err(LiteSessionError::NonceLengthError);
+    }
+    let mut iv = [0_u8; 16];
+    iv[..12].copy_from_slice(nonce);
+
+    match Aes256Ctr::new_from_slices(key, &iv) {
+        Ok(mut cipher) => {
+            cipher.apply_keystream(data);
+            Ok(())
+        }
+        Err(_) => Err(LiteSessionError::ServerKeyLengthError),
+    }
+}
+
+#[cfg(feature = "fips")]
+pub(crate) fn hmac_sha256(key: &[u8], message: &[u8]) -> [u8; 32] {
+    let mut mac =
+        <HmacSha256 as Mac>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+
+    mac.finalize().into_bytes().into()
+}
+
+#[cfg(not(feature = "fips"))]
+pub(crate) fn seal(
+    _key: &[u8],
+    _nonce: &[u8],
+    _data: &mut Vec<u8>,
+) -> Result<(), LiteSessionError> {
+    Err(LiteSessionError::UnavailableCipher)
+}
+
+#[cfg(not(feature = "fips"))]
+pub(crate) fn open(
+    _key: &[u8],
+    _nonce: &[u8],
+    _data: &mut [u8],
+) -> Result<usize, LiteSessionError> {
+    Err(LiteSessionError::UnavailableCipher)
+}
+
+#[cfg(not(feature = "fips"))]
+pub(crate) fn apply_keystream(
+    _key: &[u8],
+    _nonce: &[u8],
+    _data: &mut [u8],
+) -> Result<(), LiteSessionError> {
+    Err(LiteSessionError::UnavailableCipher)
+}
+
+#[cfg(all(test, feature = "fips"))]
+mod fips_tests {
+    use super::{apply_keystream, hmac_sha256, open, seal, TAG_LEN};
+    use crate::LiteSessionError;
+
+    #[test]
+    fn aes_gcm() -> Result<(), LiteSessionError> {
+        let key = [0_u8; 32];
+        let nonce = [1_u8; 12];
+        let mut data = b"Network-TCP".to_vec();
+        seal(&key, &nonce, &mut data)?;
+        assert_eq!(data.len(), 11 + TAG_LEN);
+
+        let mut sealed = data.clone();
+        assert_eq!(open(&key, &nonce, &mut sealed)?, 11);
+        assert_eq!(&sealed[..11], b"Network-TCP");
+
+        let mut sealed = data.clone();
+        assert_eq!(
+            open(&[1_u8; 32], &nonce, &mut sealed),
+            Err(LiteSessionError::FromUtf8TokenError)
+        );
+        data[0] ^= 1;
+        assert_eq!(
+            open(&key, &nonce, &mut data),
+            Err(LiteSessionError::FromUtf8TokenError)
+        );
+
+        let mut keystream = [0_u8; 32];
+        apply_keystream(&key, &nonce, &mut keystream)?;
+        assert_ne!(keystream, [0_u8; 32]);
+        apply_keystream(&key, &nonce, &mut keystream)?;
+        assert_eq!(keystream, [0_u8; 32]);
+
+        assert_eq!(hmac_sha256(&key, b"foo"), hmac_sha256(&key, b"foo"));
+        assert_ne!(hmac_sha256(&key, b"foo"), hmac_sha256(&[1_u8; 32], b"foo"));
+
+        Ok(())
+    }
+}
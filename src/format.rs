@@ -0,0 +1,89 @@
+/// The separator placed between the fields of a token.
+/// `TokenFormat::Legacy` keeps the original `⊕` separator while `TokenFormat::Ascii`
+/// produces tokens that are valid cookie values and HTTP header values
+#[derive(Debug, Default)]
+pub enum TokenFormat {
+    /// Separate fields using the multi-byte `⊕` character
+    #[default]
+    Legacy,
+    /// Separate fields using an ASCII punctuation character like `.` or `|`
+    Ascii(char),
+}
+
+impl core::cmp::PartialEq for TokenFormat {
+    fn eq(&self, other: &Self) -> bool {
+        self.separator() == other.separator()
+    }
+}
+
+impl core::clone::Clone for TokenFormat {
+    fn clone(&self) -> Self {
+        match self {
+            TokenFormat::Legacy => TokenFormat::Legacy,
+            TokenFormat::Ascii(separator) => TokenFormat::Ascii(*separator),
+        }
+    }
+}
+
+impl TokenFormat {
+    /// The separator of `TokenFormat::Legacy`
+    pub const LEGACY_SEPARATOR: char = '⊕';
+
+    /// Get the field separator
+    pub fn separator(&self) -> char {
+        match self {
+            TokenFormat::Legacy => TokenFormat::LEGACY_SEPARATOR,
+            TokenFormat::Ascii(separator) => *separator,
+        }
+    }
+    /// Check that the separator cannot appear inside the fields of a token and that it is
+    /// allowed in cookie values. Alphanumerics, `-` used by identifiers, `:` used by the
+    /// confidentiality field and the characters reserved in cookies are rejected
+    pub fn is_valid(&self) -> bool {
+        match self {
+            TokenFormat::Legacy => true,
+            TokenFormat::Ascii(separator) => {
+                separator.is_ascii_punctuation()
+                    && !matches!(separator, '-' | ':' | '"' | ',' | ';' | '\\')
+            }
+        }
+    }
+    /// Detect the format of a token from the separator in front of its confidentiality field
+    pub fn detect(token: &str) -> Option<Self> {
+        let position = token.rfind("ConfidentialityMode::")?;
+        let separator = token[..position].chars().next_back()?;
+
+        match separator {
+            TokenFormat::LEGACY_SEPARATOR => Some(TokenFormat::Legacy),
+            _ => Some(TokenFormat::Ascii(separator)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod format_tests {
+    use super::TokenFormat;
+
+    #[test]
+    fn token_format() {
+        assert_eq!(TokenFormat::default().separator(), '⊕');
+        assert_eq!(TokenFormat::Ascii('⊕'), TokenFormat::Legacy);
+        assert!(TokenFormat::Ascii('.').is_valid());
+        assert!(TokenFormat::Ascii('|').is_valid());
+        assert!(!TokenFormat::Ascii('a').is_valid());
+        assert!(!TokenFormat::Ascii(':').is_valid());
+        assert!(!TokenFormat::Ascii(';').is_valid());
+        assert!(!TokenFormat::Ascii('⥂').is_valid());
+
+        assert_eq!(
+            TokenFormat::detect("id.00.00.00.nonce.ConfidentialityMode::High.00"),
+            Some(TokenFormat::Ascii('.'))
+        );
+        assert_eq!(
+            TokenFormat::detect("id⊕00⊕00⊕00⊕nonce⊕ConfidentialityMode::Low⊕00"),
+            Some(TokenFormat::Legacy)
+        );
+        assert_eq!(TokenFormat::detect("ConfidentialityMode::High"), None);
+        assert_eq!(TokenFormat::detect("foo"), None);
+    }
+}
@@ -1,43 +1,53 @@
+use crate::{EntropySource, OsEntropy};
 use core::fmt::{self, Debug, Display};
-use nanorand::{ChaCha, RNG};
 
-/// A CSPRNG random string generator using the `nanorand` crate using its `ChaCha` mode
+/// A CSPRNG random string generator drawing from an `EntropySource`, `OsEntropy` by default
 #[derive(Debug)]
 pub struct SessionTokenRng;
 
 impl SessionTokenRng {
-    /// Generate a CSPRNG string. This is used to generate the random user identifiers for the token
+    /// Generate a CSPRNG string of 32 lowercase alphanumeric characters, each drawn independently
+    /// so that characters can repeat. This is used to generate the random user identifiers for the token
     pub fn alphanumeric() -> String {
-        let mut rng = ChaCha::new(8);
-        let mut alphabet = [
-            "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q",
-            "r", "s", "t", "u", "v", "w", "x", "y", "z", "0", "1", "2", "3", "4", "5", "6", "7",
-            "8", "9",
-        ];
-        rng.shuffle(&mut alphabet);
-        let mut random = String::default();
-        alphabet
-            .iter()
-            .take(32)
-            .for_each(|character| random.push_str(character));
+        SessionTokenRng::alphanumeric_from(&OsEntropy)
+    }
+
+    /// Generate a string like `alphanumeric()` from the bytes of `entropy`
+    pub fn alphanumeric_from(entropy: &dyn EntropySource) -> String {
+        let alphabet = b"abcdefghijklmnopqrstuvwxyz0123456789";
+        let mut random = String::with_capacity(32);
+
+        while random.len() < 32 {
+            // 252 is the largest multiple of 36 below 256, rejecting larger bytes avoids modulo bias
+            SessionTokenRng::bytes_from(entropy, 32)
+                .iter()
+                .filter(|byte| **byte < 252)
+                .take(32 - random.len())
+                .for_each(|byte| random.push(alphabet[(*byte % 36) as usize] as char));
+        }
 
         random
     }
 
-    /// Generate a secure nonce string using `nanorand` crate and its `ChaCha` random number generator
+    /// Generate a hex encoded `12byte/96bit` nonce. Every bit of the nonce is random
     pub fn nonce() -> String {
-        let mut rng = ChaCha::new(8);
-        let mut alphabet = [
-            "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "o", "p", "q",
-            "r", "s", "t", "u", "v", "w", "x", "y", "z", "0", "1", "2", "3", "4", "5", "6", "7",
-            "8", "9",
-        ];
-        rng.shuffle(&mut alphabet);
-        let mut random = String::default();
-        alphabet
-            .iter()
-            .take(12)
-            .for_each(|character| random.push_str(character));
+        SessionTokenRng::nonce_from(&OsEntropy)
+    }
+
+    /// Generate a nonce like `nonce()` from the bytes of `entropy`
+    pub fn nonce_from(entropy: &dyn EntropySource) -> String {
+        hex::encode(SessionTokenRng::bytes_from(entropy, 12))
+    }
+
+    /// Generate `len` CSPRNG bytes using the operating system entropy source
+    pub fn bytes(len: usize) -> Vec<u8> {
+        SessionTokenRng::bytes_from(&OsEntropy, len)
+    }
+
+    /// Generate `len` bytes from `entropy`
+    pub fn bytes_from(entropy: &dyn EntropySource, len: usize) -> Vec<u8> {
+        let mut random = vec![0_u8; len];
+        entropy.fill(&mut random);
 
         random
     }
@@ -174,18 +184,15 @@ impl Default for ConfidentialityMode {
 impl Debug for ConfidentialityMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Low => write!(f, "{:?}", self),
-            Self::High => write!(f, "{}", "ConfidentialityMode::Low"),
+            Self::Low => write!(f, "Low"),
+            Self::High => write!(f, "High"),
         }
     }
 }
 
 impl Display for ConfidentialityMode {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::Low => write!(f, "{:?}", self),
-            Self::High => write!(f, "{}", "ConfidentialityMode::High"),
-        }
+        write!(f, "{}", ConfidentialityMode::to_string(self))
     }
 }
 
@@ -240,6 +247,13 @@ pub enum TokenOutcome {
     BadToken,
     /// The session held by the provided token has expired
     SessionExpired,
+    /// The token has exceeded the requests allowed by its `Quota` claim in the current window
+    QuotaExceeded,
+    /// The token is authentic and expired less than the grace period ago
+    ExpiredWithinGrace,
+    /// The token is authentic but the user authenticated with a weaker `AuthLevel` than
+    /// required, re-authenticate the user at the required level before proceeding
+    StepUpRequired,
 }
 
 impl core::cmp::PartialEq for TokenOutcome {
@@ -250,22 +264,91 @@ impl core::cmp::PartialEq for TokenOutcome {
             | (TokenOutcome::TokenRejected, TokenOutcome::TokenRejected)
             | (TokenOutcome::TokenRevoked, TokenOutcome::TokenRevoked)
             | (TokenOutcome::BadToken, TokenOutcome::BadToken)
-            | (TokenOutcome::SessionExpired, TokenOutcome::SessionExpired) => true,
+            | (TokenOutcome::SessionExpired, TokenOutcome::SessionExpired)
+            | (TokenOutcome::QuotaExceeded, TokenOutcome::QuotaExceeded)
+            | (TokenOutcome::ExpiredWithinGrace, TokenOutcome::ExpiredWithinGrace)
+            | (TokenOutcome::StepUpRequired, TokenOutcome::StepUpRequired) => true,
             _ => false,
         }
     }
 }
 
+impl core::clone::Clone for TokenOutcome {
+    fn clone(&self) -> Self {
+        match self {
+            TokenOutcome::TokenAuthentic => TokenOutcome::TokenAuthentic,
+            TokenOutcome::TokenAuthorized => TokenOutcome::TokenAuthorized,
+            TokenOutcome::TokenRejected => TokenOutcome::TokenRejected,
+            TokenOutcome::TokenRevoked => TokenOutcome::TokenRevoked,
+            TokenOutcome::BadToken => TokenOutcome::BadToken,
+            TokenOutcome::SessionExpired => TokenOutcome::SessionExpired,
+            TokenOutcome::QuotaExceeded => TokenOutcome::QuotaExceeded,
+            TokenOutcome::ExpiredWithinGrace => TokenOutcome::ExpiredWithinGrace,
+            TokenOutcome::StepUpRequired => TokenOutcome::StepUpRequired,
+        }
+    }
+}
+
+impl TokenOutcome {
+    /// Get the stable numeric code of the outcome for FFI consumers, embedded devices and
+    /// wire protocols. Outcome codes are below `100` so they never collide with
+    /// `LiteSessionError::code()` and a code is never reused or renumbered
+    pub fn code(&self) -> u16 {
+        match self {
+            TokenOutcome::TokenAuthentic => 0,
+            TokenOutcome::TokenAuthorized => 1,
+            TokenOutcome::TokenRejected => 2,
+            TokenOutcome::TokenRevoked => 3,
+            TokenOutcome::BadToken => 4,
+            TokenOutcome::SessionExpired => 5,
+            TokenOutcome::QuotaExceeded => 6,
+            TokenOutcome::ExpiredWithinGrace => 7,
+            TokenOutcome::StepUpRequired => 8,
+        }
+    }
+    /// Get the outcome matching a numeric code returned by `TokenOutcome::code()`
+    pub fn from_code(code: u16) -> Option<Self> {
+        match code {
+            0 => Some(TokenOutcome::TokenAuthentic),
+            1 => Some(TokenOutcome::TokenAuthorized),
+            2 => Some(TokenOutcome::TokenRejected),
+            3 => Some(TokenOutcome::TokenRevoked),
+            4 => Some(TokenOutcome::BadToken),
+            5 => Some(TokenOutcome::SessionExpired),
+            6 => Some(TokenOutcome::QuotaExceeded),
+            7 => Some(TokenOutcome::ExpiredWithinGrace),
+            8 => Some(TokenOutcome::StepUpRequired),
+            _ => None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod global_tests {
-    use super::{ConfidentialityMode, Role, SessionTokenRng};
+    use super::{ConfidentialityMode, Role, SessionTokenRng, TokenOutcome};
+
+    #[test]
+    fn outcome_codes() {
+        (0..9).for_each(|code| {
+            assert_eq!(
+                TokenOutcome::from_code(code).map(|outcome| outcome.code()),
+                Some(code)
+            )
+        });
+        assert_eq!(TokenOutcome::TokenAuthentic.code(), 0);
+        assert_eq!(TokenOutcome::from_code(100), None);
+    }
 
     #[test]
     fn sessiontoken_rng_tests() {
         let alphanumeric = SessionTokenRng::alphanumeric();
         let nonce = SessionTokenRng::nonce();
         assert_eq!(alphanumeric.len(), 32_usize);
-        assert_eq!(nonce.len(), 12_usize);
+        assert_eq!(nonce.len(), 24_usize);
+        assert!(nonce.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert_ne!(nonce, SessionTokenRng::nonce());
+        assert_eq!(SessionTokenRng::bytes(16).len(), 16_usize);
+        assert_ne!(SessionTokenRng::bytes(16), SessionTokenRng::bytes(16));
     }
 
     #[test]
@@ -0,0 +1,185 @@
+use crate::{HttpRejection, LiteSessionToken, LiteSessionVerifier, RejectionReason};
+
+/// The gRPC status a request is rejected with, numbered like the status codes of gRPC
+#[derive(Debug)]
+pub enum GrpcCode {
+    /// `16`, the token is missing, expired, revoked or not authentic
+    Unauthenticated,
+    /// `7`, the token is authentic but not allowed by a policy of the verifier
+    PermissionDenied,
+    /// `8`, the quota of the token is exceeded
+    ResourceExhausted,
+    /// `13`, the verifier is misconfigured
+    Internal,
+}
+
+impl core::cmp::PartialEq for GrpcCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.code() == other.code()
+    }
+}
+
+impl core::clone::Clone for GrpcCode {
+    fn clone(&self) -> Self {
+        match self {
+            GrpcCode::Unauthenticated => GrpcCode::Unauthenticated,
+            GrpcCode::PermissionDenied => GrpcCode::PermissionDenied,
+            GrpcCode::ResourceExhausted => GrpcCode::ResourceExhausted,
+            GrpcCode::Internal => GrpcCode::Internal,
+        }
+    }
+}
+
+impl GrpcCode {
+    /// Get the numeric gRPC status code
+    pub fn code(&self) -> i32 {
+        match self {
+            GrpcCode::Unauthenticated => 16,
+            GrpcCode::PermissionDenied => 7,
+            GrpcCode::ResourceExhausted => 8,
+            GrpcCode::Internal => 13,
+        }
+    }
+}
+
+/// Why a gRPC request was not let through by `LiteSessionVerifier::verify_metadata()`.
+/// The message tells an expired token apart from a rejected one so clients know whether
+/// refreshing the token can help
+///
+/// ```
+/// use lite_session::{GrpcCode, RejectionReason};
+///
+/// pub struct GrpcRejection {
+///     code: GrpcCode,
+///     reason: Option<RejectionReason>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct GrpcRejection {
+    code: GrpcCode,
+    reason: Option<RejectionReason>,
+}
+
+impl core::cmp::PartialEq for GrpcRejection {
+    fn eq(&self, other: &Self) -> bool {
+        self.code == other.code && self.reason == other.reason
+    }
+}
+
+impl core::clone::Clone for GrpcRejection {
+    fn clone(&self) -> Self {
+        Self {
+            code: self.code.clone(),
+            reason: self.reason.clone(),
+        }
+    }
+}
+
+impl GrpcRejection {
+    /// Get the gRPC status code to respond with
+    pub fn get_code(&self) -> &GrpcCode {
+        &self.code
+    }
+    /// Get the reason the token was not accepted, `None` when the request carried no token
+    pub fn get_reason(&self) -> Option<&RejectionReason> {
+        self.reason.as_ref()
+    }
+    /// Get the status message to respond with
+    pub fn message(&self) -> &'static str {
+        match (&self.code, &self.reason) {
+            (GrpcCode::Internal, _) => "session verification failed",
+            (_, None) => "missing session token",
+            (_, Some(RejectionReason::Expired)) => "session token expired",
+            (_, Some(RejectionReason::Revoked)) => "session token revoked",
+            (GrpcCode::ResourceExhausted, _) => "session quota exceeded",
+            (GrpcCode::PermissionDenied, _) => "session not permitted",
+            _ => "session token rejected",
+        }
+    }
+}
+
+impl From<HttpRejection> for GrpcRejection {
+    fn from(rejection: HttpRejection) -> Self {
+        let code = match rejection.get_status() {
+            401 => GrpcCode::Unauthenticated,
+            403 => GrpcCode::PermissionDenied,
+            429 => GrpcCode::ResourceExhausted,
+            _ => GrpcCode::Internal,
+        };
+
+        Self {
+            code,
+            reason: rejection.get_reason().cloned(),
+        }
+    }
+}
+
+impl LiteSessionVerifier {
+    /// Verify the token of a gRPC request taken from the value of its `authorization`
+    /// metadata using the `Bearer` scheme, the check run by the `tonic::SessionInterceptor`
+    /// of the `tonic` feature
+    pub fn verify_metadata(
+        &self,
+        authorization: Option<&str>,
+    ) -> Result<LiteSessionToken, GrpcRejection> {
+        match self.verify_request(authorization, None, "") {
+            Ok(token) => Ok(token),
+            Err(rejection) => Err(rejection.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod grpc_tests {
+    use super::{GrpcCode, GrpcRejection};
+    use crate::{
+        KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken, LiteSessionVerifier,
+        RejectionReason, ServerKey,
+    };
+
+    #[test]
+    fn verify_metadata() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let bearer = format!("Bearer {}", token.build_secure_with_provider(&ring)?);
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        let verified = verifier.verify_metadata(Some(&bearer));
+        assert_eq!(
+            verified.map(|verified| verified.get_hmac_data().get_username().clone()),
+            Ok("foo_user".to_owned())
+        );
+
+        let missing = verifier.verify_metadata(None).map(|_| ()).unwrap_err();
+        assert_eq!(missing.get_code().code(), 16);
+        assert_eq!(missing.message(), "missing session token");
+
+        verifier.require_acl("Network-UDP");
+        let denied = verifier
+            .verify_metadata(Some(&bearer))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(denied.get_code(), &GrpcCode::PermissionDenied);
+        assert_eq!(denied.message(), "session not permitted");
+
+        Ok(())
+    }
+
+    #[test]
+    fn rejection_messages() {
+        let expired = GrpcRejection {
+            code: GrpcCode::Unauthenticated,
+            reason: Some(RejectionReason::Expired),
+        };
+        let forged = GrpcRejection {
+            code: GrpcCode::Unauthenticated,
+            reason: Some(RejectionReason::MacMismatch),
+        };
+        assert_eq!(expired.message(), "session token expired");
+        assert_eq!(forged.message(), "session token rejected");
+    }
+}
@@ -0,0 +1,120 @@
+use crate::Role;
+
+/// Roles implying other roles so that services compare roles consistently.
+/// The default hierarchy has `SuperUser` implying `Admin` implying `User`
+/// and `SuperNode` implying `MasterNode` implying `SlaveNode`
+///
+/// ```
+/// use lite_session::{Role, RoleHierarchy};
+///
+/// let mut hierarchy = RoleHierarchy::default();
+/// hierarchy.imply(Role::Custom("Auditor".into()), Role::User);
+///
+/// assert!(hierarchy.implies(&Role::SuperUser, &Role::User));
+/// assert!(hierarchy.implies(&Role::Custom("Auditor".into()), &Role::User));
+/// assert!(!hierarchy.implies(&Role::User, &Role::Admin));
+/// ```
+#[derive(Debug)]
+pub struct RoleHierarchy {
+    implications: Vec<(Role, Role)>,
+}
+
+impl Default for RoleHierarchy {
+    fn default() -> Self {
+        let mut hierarchy = Self::empty();
+        hierarchy
+            .imply(Role::SuperUser, Role::Admin)
+            .imply(Role::Admin, Role::User)
+            .imply(Role::SuperNode, Role::MasterNode)
+            .imply(Role::MasterNode, Role::SlaveNode);
+
+        hierarchy
+    }
+}
+
+impl core::cmp::PartialEq for RoleHierarchy {
+    fn eq(&self, other: &Self) -> bool {
+        self.implications == other.implications
+    }
+}
+
+impl core::clone::Clone for RoleHierarchy {
+    fn clone(&self) -> Self {
+        Self {
+            implications: self.implications.clone(),
+        }
+    }
+}
+
+impl RoleHierarchy {
+    /// Create a hierarchy where every role only implies itself
+    pub fn empty() -> Self {
+        Self {
+            implications: Vec::default(),
+        }
+    }
+    /// Make `role` imply `implied` and every role `implied` implies
+    pub fn imply(&mut self, role: Role, implied: Role) -> &mut Self {
+        if !self
+            .implications
+            .iter()
+            .any(|(existing, existing_implied)| *existing == role && *existing_implied == implied)
+        {
+            self.implications.push((role, implied));
+        }
+
+        self
+    }
+    /// Check whether `role` is `required` or implies it directly or through other roles
+    pub fn implies(&self, role: &Role, required: &Role) -> bool {
+        let mut reached = vec![role];
+        let mut index = 0_usize;
+
+        while index < reached.len() {
+            let current = reached[index];
+            if current == required {
+                return true;
+            }
+            for (existing, implied) in self.implications.iter() {
+                if existing == current && !reached.contains(&implied) {
+                    reached.push(implied)
+                }
+            }
+            index += 1;
+        }
+
+        false
+    }
+    /// Get the direct implications of the hierarchy
+    pub fn get_all(&self) -> &Vec<(Role, Role)> {
+        &self.implications
+    }
+}
+
+#[cfg(test)]
+mod hierarchy_tests {
+    use super::RoleHierarchy;
+    use crate::Role;
+
+    #[test]
+    fn role_hierarchy() {
+        let hierarchy = RoleHierarchy::default();
+        assert!(hierarchy.implies(&Role::SuperUser, &Role::SuperUser));
+        assert!(hierarchy.implies(&Role::SuperUser, &Role::Admin));
+        assert!(hierarchy.implies(&Role::SuperUser, &Role::User));
+        assert!(hierarchy.implies(&Role::SuperNode, &Role::SlaveNode));
+        assert!(!hierarchy.implies(&Role::Admin, &Role::SuperUser));
+        assert!(!hierarchy.implies(&Role::SuperUser, &Role::SlaveNode));
+
+        let mut hierarchy = RoleHierarchy::empty();
+        hierarchy
+            .imply(Role::Custom("Owner".into()), Role::Custom("Editor".into()))
+            .imply(Role::Custom("Editor".into()), Role::Custom("Owner".into()))
+            .imply(Role::Custom("Editor".into()), Role::User)
+            .imply(Role::Custom("Editor".into()), Role::User);
+        assert_eq!(hierarchy.get_all().len(), 3);
+        assert!(hierarchy.implies(&Role::Custom("Owner".into()), &Role::User));
+        assert!(!hierarchy.implies(&Role::User, &Role::Custom("Owner".into())));
+        assert!(!hierarchy.implies(&Role::SuperUser, &Role::Admin));
+    }
+}
@@ -0,0 +1,389 @@
+use crate::{
+    LiteSessionData, LiteSessionToken, LiteSessionVerifier, RejectionReason, Role, TokenOutcome,
+};
+use std::sync::Arc;
+
+/// Why an HTTP request was not let through by `LiteSessionVerifier::verify_request()`,
+/// carrying the status code to respond with. Web framework extractors turn it into their
+/// response type, like the `axum::LiteSession<T>` extractor of the `axum` feature
+///
+/// ```
+/// use lite_session::RejectionReason;
+///
+/// pub struct HttpRejection {
+///     status: u16,
+///     reason: Option<RejectionReason>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct HttpRejection {
+    status: u16,
+    reason: Option<RejectionReason>,
+}
+
+impl core::cmp::PartialEq for HttpRejection {
+    fn eq(&self, other: &Self) -> bool {
+        self.status == other.status && self.reason == other.reason
+    }
+}
+
+impl core::clone::Clone for HttpRejection {
+    fn clone(&self) -> Self {
+        Self {
+            status: self.status,
+            reason: self.reason.clone(),
+        }
+    }
+}
+
+impl HttpRejection {
+    /// Get the HTTP status code, `401` when the client must authenticate again,
+    /// `403` when an authentic token is not allowed and `429` when its quota is exceeded
+    pub fn get_status(&self) -> u16 {
+        self.status
+    }
+    /// Get the reason the token was not accepted, `None` when the request carried no token
+    pub fn get_reason(&self) -> Option<&RejectionReason> {
+        self.reason.as_ref()
+    }
+
+    #[cfg(feature = "rocket")]
+    pub(crate) fn new(status: u16, reason: Option<RejectionReason>) -> Self {
+        Self { status, reason }
+    }
+}
+
+impl RejectionReason {
+    /// Get the HTTP status code to respond with when a token is not accepted for this reason
+    pub fn http_status(&self) -> u16 {
+        match self {
+            RejectionReason::PolicyFailed { which } if which == "quota" => 429,
+            // The user can still re-authenticate at a stronger level to proceed
+            RejectionReason::PolicyFailed { which } if which == "auth_level" => 401,
+            RejectionReason::PolicyFailed { .. } => 403,
+            RejectionReason::MacMismatch
+            | RejectionReason::Expired
+            | RejectionReason::Revoked
+            | RejectionReason::Malformed { .. } => 401,
+        }
+    }
+}
+
+impl LiteSessionVerifier {
+    /// Verify the token of an HTTP request taken from the `Authorization: Bearer` header,
+    /// or from the cookie named `cookie_name` of the `Cookie` header when there is no bearer
+    /// token. Requests without an authentic token are rejected with the status code to respond
+    /// with, errors of the verifier configuration are `500`
+    pub fn verify_request(
+        &self,
+        authorization: Option<&str>,
+        cookie: Option<&str>,
+        cookie_name: &str,
+    ) -> Result<LiteSessionToken, HttpRejection> {
+        let token = match authorization
+            .and_then(bearer_token)
+            .or_else(|| cookie.and_then(|cookie| cookie_token(cookie, cookie_name)))
+        {
+            Some(token) => token,
+            None => {
+                return Err(HttpRejection {
+                    status: 401,
+                    reason: None,
+                })
+            }
+        };
+
+        match self.verify(token) {
+            Ok((TokenOutcome::TokenAuthentic, verified)) => Ok(verified),
+            Ok((outcome, verified)) => {
+                let reason = match verified.get_rejection() {
+                    Some(reason) => reason.clone(),
+                    None => RejectionReason::from_outcome(&outcome)
+                        .unwrap_or(RejectionReason::MacMismatch),
+                };
+                Err(HttpRejection {
+                    status: reason.http_status(),
+                    reason: Some(reason),
+                })
+            }
+            Err(error) => Err(match RejectionReason::from_error(&error) {
+                Some(reason) => HttpRejection {
+                    status: reason.http_status(),
+                    reason: Some(reason),
+                },
+                None => HttpRejection {
+                    status: 500,
+                    reason: None,
+                },
+            }),
+        }
+    }
+}
+
+/// The session of an HTTP request whose token was verified, the value a web framework
+/// request guard hands to a handler. `require_role()` and `require_acl()` narrow the guard
+/// to sessions holding a role or a capability. With the `rocket` feature it is a Rocket
+/// `FromRequest` guard, see `require_role!` and `require_acl!`
+///
+/// ```
+/// use lite_session::LiteSessionToken;
+///
+/// pub struct AuthenticatedSession {
+///     token: LiteSessionToken,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct AuthenticatedSession {
+    token: LiteSessionToken,
+}
+
+impl core::clone::Clone for AuthenticatedSession {
+    fn clone(&self) -> Self {
+        Self {
+            token: self.token.clone(),
+        }
+    }
+}
+
+impl AuthenticatedSession {
+    /// Authenticate the session of a request like `LiteSessionVerifier::verify_request()`
+    pub fn from_request(
+        verifier: &LiteSessionVerifier,
+        authorization: Option<&str>,
+        cookie: Option<&str>,
+        cookie_name: &str,
+    ) -> Result<Self, HttpRejection> {
+        let token = verifier.verify_request(authorization, cookie, cookie_name)?;
+
+        Ok(Self { token })
+    }
+    /// Reject the session with `403` unless its role is `role` or implies it in the
+    /// default `RoleHierarchy`
+    pub fn require_role(&self, role: Role) -> Result<&Self, HttpRejection> {
+        match self.get_data().role_at_least(role) {
+            true => Ok(self),
+            false => Err(AuthenticatedSession::forbidden("role")),
+        }
+    }
+    /// Reject the session with `403` unless its access control list grants `capability`
+    pub fn require_acl(&self, capability: &str) -> Result<&Self, HttpRejection> {
+        match self.get_data().has_capability(capability) {
+            true => Ok(self),
+            false => Err(AuthenticatedSession::forbidden("acl")),
+        }
+    }
+    /// Get the data of the session
+    pub fn get_data(&self) -> &LiteSessionData {
+        self.token.get_hmac_data()
+    }
+    /// Get the verified token
+    pub fn get_token(&self) -> &LiteSessionToken {
+        &self.token
+    }
+
+    fn forbidden(which: &str) -> HttpRejection {
+        let reason = RejectionReason::PolicyFailed {
+            which: which.into(),
+        };
+
+        HttpRejection {
+            status: reason.http_status(),
+            reason: Some(reason),
+        }
+    }
+}
+
+/// Create a closure turning the optional `Authorization` and `Cookie` headers of a request into
+/// the data of its verified session or an `HttpRejection`, shaped for filter based frameworks
+/// like warp where both headers are extracted as optional owned strings.
+/// The `warp` feature wraps it into the `warp::with_session()` filter
+pub fn with_session(
+    verifier: Arc<LiteSessionVerifier>,
+    cookie_name: &str,
+) -> impl Fn(Option<String>, Option<String>) -> Result<LiteSessionData, HttpRejection>
+       + Clone
+       + Send
+       + Sync {
+    let cookie_name = cookie_name.to_owned();
+
+    move |authorization, cookie| {
+        let token =
+            verifier.verify_request(authorization.as_deref(), cookie.as_deref(), &cookie_name)?;
+
+        Ok(token.get_hmac_data().clone())
+    }
+}
+
+/// Get the token of an `Authorization` header using the `Bearer` scheme
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+    let mut parts = authorization.trim().splitn(2, ' ');
+    match (parts.next(), parts.next()) {
+        (Some(scheme), Some(token)) if scheme.eq_ignore_ascii_case("bearer") => {
+            match token.trim() {
+                "" => None,
+                token => Some(token),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Get the value of the cookie named `name` from a `Cookie` header
+pub fn cookie_token<'a>(cookie: &'a str, name: &str) -> Option<&'a str> {
+    cookie.split(';').find_map(|pair| {
+        let mut parts = pair.trim().splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some(cookie_name), Some(value)) if cookie_name == name && !value.is_empty() => {
+                Some(value)
+            }
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod http_tests {
+    use super::{bearer_token, cookie_token, with_session, AuthenticatedSession, HttpRejection};
+    use crate::{
+        KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken, LiteSessionVerifier,
+        MemoryQuotaStore, Quota, RejectionReason, Role, ServerKey,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn request_tokens() {
+        assert_eq!(bearer_token("Bearer foo"), Some("foo"));
+        assert_eq!(bearer_token("bearer  foo "), Some("foo"));
+        assert_eq!(bearer_token("Basic Zm9vOmJhcg=="), None);
+        assert_eq!(bearer_token("Bearer "), None);
+        assert_eq!(cookie_token("theme=dark; sid=foo", "sid"), Some("foo"));
+        assert_eq!(cookie_token("theme=dark; sid=", "sid"), None);
+        assert_eq!(cookie_token("theme=dark", "sid"), None);
+    }
+
+    #[test]
+    fn verify_request() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        let bearer = format!("Bearer {}", session_token);
+        let verified = verifier.verify_request(Some(&bearer), None, "sid");
+        assert_eq!(
+            verified.map(|verified| verified.get_hmac_data().get_username().clone()),
+            Ok("foo_user".to_owned())
+        );
+        let cookie = format!("theme=dark; sid={}", session_token);
+        assert!(verifier.verify_request(None, Some(&cookie), "sid").is_ok());
+
+        assert_eq!(
+            verifier.verify_request(None, None, "sid").map(|_| ()),
+            Err(HttpRejection {
+                status: 401,
+                reason: None
+            })
+        );
+
+        verifier.require_acl("Network-UDP");
+        let rejection = verifier
+            .verify_request(Some(&bearer), None, "sid")
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(rejection.get_status(), 403);
+        assert_eq!(
+            rejection.get_reason(),
+            Some(&RejectionReason::PolicyFailed {
+                which: "acl".into()
+            })
+        );
+
+        let mut other_ring = KeyRing::default();
+        other_ring.add_key("2021a", ServerKey::new([1_u8; 32]))?;
+        let rejection = LiteSessionVerifier::new(other_ring)
+            .verify_request(Some(&bearer), None, "sid")
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(rejection.get_reason(), Some(&RejectionReason::MacMismatch));
+        assert_eq!(rejection.get_status(), 401);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").quota(Quota::new(1, 0));
+        token.hmac_data(data);
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let bearer = format!("Bearer {}", token.build_secure_with_provider(&ring)?);
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.quota(Arc::new(MemoryQuotaStore::new()));
+        assert!(verifier.verify_request(Some(&bearer), None, "sid").is_ok());
+        let rejection = verifier
+            .verify_request(Some(&bearer), None, "sid")
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(rejection.get_status(), 429);
+
+        Ok(())
+    }
+
+    #[test]
+    fn authenticated_session() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_admin")
+            .role(Role::Admin)
+            .add_acl("files:read");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let cookie = format!("sid={}", token.build_secure_with_provider(&ring)?);
+
+        let verifier = LiteSessionVerifier::new(ring);
+        let session =
+            AuthenticatedSession::from_request(&verifier, None, Some(&cookie), "sid").unwrap();
+        assert_eq!(session.get_data().get_username(), "foo_admin");
+        assert!(session.require_role(Role::User).is_ok());
+        assert!(session.require_acl("files:read").is_ok());
+
+        let rejection = session
+            .require_role(Role::SuperUser)
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(rejection.get_status(), 403);
+        assert_eq!(
+            rejection.get_reason(),
+            Some(&RejectionReason::PolicyFailed {
+                which: "role".into()
+            })
+        );
+        let rejection = session.require_acl("files:write").map(|_| ()).unwrap_err();
+        assert_eq!(rejection.get_status(), 403);
+
+        Ok(())
+    }
+
+    #[test]
+    fn session_filter() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let bearer = format!("Bearer {}", token.build_secure_with_provider(&ring)?);
+
+        let filter = with_session(Arc::new(LiteSessionVerifier::new(ring)), "sid");
+        assert_eq!(filter(Some(bearer), None), Ok(data));
+        assert_eq!(
+            filter(None, Some("sid=garbage".into()))
+                .map(|_| ())
+                .map_err(|rejection| rejection.get_status()),
+            Err(401)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{Clock, SessionTokenRng, SystemClock};
+use std::time::UNIX_EPOCH;
+
+/// Generates the identifiers of tokens.
+/// Implement it to embed information like region codes or shard hints in identifiers.
+/// Any `Fn() -> String` closure is also an `IdentifierStrategy`
+pub trait IdentifierStrategy {
+    /// Generate a new identifier
+    fn generate(&self) -> String;
+    /// Generate a new identifier reading the current time from `clock`.
+    /// `LiteSessionToken::identifier_strategy()` passes the `Clock` of the token.
+    /// Strategies that do not embed a time generate it like `generate()`
+    fn generate_with_clock(&self, clock: &dyn Clock) -> String {
+        let _ = clock;
+
+        self.generate()
+    }
+}
+
+impl<F: Fn() -> String> IdentifierStrategy for F {
+    fn generate(&self) -> String {
+        self()
+    }
+}
+
+/// Generates the default 32 character alphanumeric identifiers using `SessionTokenRng::alphanumeric()`
+#[derive(Debug, Default)]
+pub struct AlphanumericIdentifier;
+
+impl IdentifierStrategy for AlphanumericIdentifier {
+    fn generate(&self) -> String {
+        SessionTokenRng::alphanumeric()
+    }
+}
+
+/// Generates lexicographically sortable identifiers in the 26 character
+/// Crockford Base32 `ULID` format made up of a 48 bit millisecond Unix timestamp and 80 random bits.
+/// The timestamp is read from the `Clock` of the token when generated by `identifier_strategy()`
+#[derive(Debug, Default)]
+pub struct UlidIdentifier;
+
+impl IdentifierStrategy for UlidIdentifier {
+    fn generate(&self) -> String {
+        self.generate_with_clock(&SystemClock)
+    }
+
+    fn generate_with_clock(&self, clock: &dyn Clock) -> String {
+        // TAI64N is ahead of UTC so the time is converted to the Unix epoch through `SystemTime`
+        let millis = clock
+            .now()
+            .to_system_time()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let mut random = [0_u8; 16];
+        random.copy_from_slice(&SessionTokenRng::bytes(16));
+        let random = u128::from_be_bytes(random) >> 48;
+
+        let value = ((millis & 0xFFFF_FFFF_FFFF) << 80) | random;
+        let alphabet = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+        (0..26)
+            .rev()
+            .map(|position| alphabet[((value >> (position * 5)) & 0x1F) as usize] as char)
+            .collect()
+    }
+}
+
+/// Generates identifiers from the given number of random bytes, hex encoded.
+/// `BytesIdentifier(16)` gives `128` bits of entropy in 32 characters.
+/// The `TokenParser` accepts identifiers of up to `256` characters by default
+#[derive(Debug)]
+pub struct BytesIdentifier(pub usize);
+
+impl Default for BytesIdentifier {
+    fn default() -> Self {
+        BytesIdentifier(32)
+    }
+}
+
+impl IdentifierStrategy for BytesIdentifier {
+    fn generate(&self) -> String {
+        hex::encode(SessionTokenRng::bytes(self.0))
+    }
+}
+
+/// Generates random version 4 `UUID` identifiers in their hyphenated form
+#[derive(Debug, Default)]
+pub struct UuidIdentifier;
+
+impl IdentifierStrategy for UuidIdentifier {
+    fn generate(&self) -> String {
+        let mut bytes = SessionTokenRng::bytes(16);
+        bytes[6] = (bytes[6] & 0x0F) | 0x40;
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+
+        let hex = hex::encode(bytes);
+        format!(
+            "{}-{}-{}-{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[12..16],
+            &hex[16..20],
+            &hex[20..32]
+        )
+    }
+}
+
+#[cfg(test)]
+mod identifier_tests {
+    use super::{
+        AlphanumericIdentifier, BytesIdentifier, IdentifierStrategy, UlidIdentifier, UuidIdentifier,
+    };
+    use crate::FixedClock;
+    use core::time::Duration;
+
+    #[test]
+    fn identifier_strategies() {
+        assert_eq!(AlphanumericIdentifier.generate().len(), 32_usize);
+        // Characters are drawn independently so some identifiers repeat characters
+        assert!((0..100).any(|_| {
+            let alphanumeric = AlphanumericIdentifier.generate();
+            alphanumeric
+                .chars()
+                .any(|character| alphanumeric.matches(character).count() > 1)
+        }));
+
+        let bytes = BytesIdentifier(16).generate();
+        assert_eq!(bytes.len(), 32_usize);
+        assert!(bytes.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        assert_eq!(BytesIdentifier::default().generate().len(), 64_usize);
+        assert_ne!(bytes, BytesIdentifier(16).generate());
+
+        let ulid = UlidIdentifier.generate();
+        assert_eq!(ulid.len(), 26_usize);
+        assert!(ulid
+            .chars()
+            .all(|character| "0123456789ABCDEFGHJKMNPQRSTVWXYZ".contains(character)));
+        // The timestamp is the Unix time of the clock, not its TAI64N time
+        let clock =
+            FixedClock::new(tai64::UNIX_EPOCH_TAI64N + Duration::from_millis(1_600_000_000_123));
+        assert_eq!(
+            &UlidIdentifier.generate_with_clock(&clock)[..10],
+            "01EJ3PX03V"
+        );
+
+        let uuid = UuidIdentifier.generate();
+        assert_eq!(uuid.len(), 36_usize);
+        assert_eq!(&uuid[14..15], "4");
+        assert!("89ab".contains(&uuid[19..20]));
+        assert_ne!(uuid, UuidIdentifier.generate());
+
+        let region = || format!("eu-west-{}", AlphanumericIdentifier.generate());
+        assert!(region.generate().starts_with("eu-west-"));
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{LiteSessionToken, Quota, Role, ServerKey, TokenFormat, TokenOutcome, TokenVersion};
+use tai64::TAI64N;
+
+/// A single field of an inspected token
+///
+/// ```
+/// pub struct InspectedField {
+///     name: &'static str,
+///     value: String,
+///     verified: bool,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InspectedField {
+    name: &'static str,
+    value: String,
+    verified: bool,
+}
+
+impl core::cmp::PartialEq for InspectedField {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value && self.verified == other.verified
+    }
+}
+
+impl core::clone::Clone for InspectedField {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            value: self.value.clone(),
+            verified: self.verified,
+        }
+    }
+}
+
+impl InspectedField {
+    /// Get the name of the field
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+    /// Get the value of the field
+    pub fn get_value(&self) -> &String {
+        &self.value
+    }
+    /// Check whether the value was authenticated using the server key.
+    /// Unverified values are read from the token as-is and must not be trusted
+    pub fn is_verified(&self) -> bool {
+        self.verified
+    }
+}
+
+/// A difference in one field between two inspected tokens
+///
+/// ```
+/// pub struct FieldDiff {
+///     name: &'static str,
+///     left: Option<String>,
+///     right: Option<String>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct FieldDiff {
+    name: &'static str,
+    left: Option<String>,
+    right: Option<String>,
+}
+
+impl core::cmp::PartialEq for FieldDiff {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.left == other.left && self.right == other.right
+    }
+}
+
+impl FieldDiff {
+    /// Get the name of the field
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+    /// Get the value of the field in the first token if present
+    pub fn get_left(&self) -> &Option<String> {
+        &self.left
+    }
+    /// Get the value of the field in the second token if present
+    pub fn get_right(&self) -> &Option<String> {
+        &self.right
+    }
+}
+
+/// The fields of a token as seen by a support engineer, marking which fields
+/// were verified using the server key and which were only read from the token
+#[derive(Debug)]
+pub struct TokenInspection {
+    outcome: Option<TokenOutcome>,
+    fields: Vec<InspectedField>,
+}
+
+impl TokenInspection {
+    /// Inspect a token. The structure of the token is always read without verification.
+    /// If a `server_key` is provided the token is also verified and, when authentic,
+    /// its fields are marked as verified and the decrypted data fields are added
+    pub fn inspect(token: &str, server_key: Option<&ServerKey>) -> Self {
+        let mut inspection = TokenInspection {
+            outcome: None,
+            fields: Vec::new(),
+        };
+
+        let names = [
+            "identifier",
+            "issued",
+            "expiry",
+            "ciphertext",
+            "nonce",
+            "confidentiality",
+            "hmac",
+        ];
+        let separator = TokenFormat::detect(token).unwrap_or_default().separator();
+        let mut fields = token.split(separator).collect::<Vec<&str>>();
+        if fields.len() > names.len() && TokenVersion::is_prefix(fields[0]) {
+            inspection.push("version", fields.remove(0).to_owned(), false);
+        }
+        let footer = match fields.len() == names.len() + 1 {
+            true => fields.pop(),
+            false => None,
+        };
+        if fields.len() != names.len() {
+            inspection.outcome = Some(TokenOutcome::BadToken);
+            return inspection;
+        }
+
+        names.iter().zip(fields.iter()).for_each(|(name, value)| {
+            let value = match *name {
+                "issued" | "expiry" => TokenInspection::unix_time(value),
+                _ => (*value).to_owned(),
+            };
+            inspection.push(name, value, false);
+        });
+        if let Some(footer) = footer {
+            let footer = base64::decode_config(footer, base64::URL_SAFE_NO_PAD)
+                .ok()
+                .and_then(|bytes| String::from_utf8(bytes).ok())
+                .unwrap_or_else(|| footer.to_owned());
+            inspection.push("footer", footer, false);
+        }
+
+        let server_key = match server_key {
+            Some(server_key) => server_key,
+            None => return inspection,
+        };
+
+        let mut verifier = LiteSessionToken::default();
+        let outcome = match verifier.from_string(server_key, token) {
+            Ok((outcome, _)) => outcome,
+            Err(_) => TokenOutcome::TokenRejected,
+        };
+
+        if outcome == TokenOutcome::TokenAuthentic {
+            inspection
+                .fields
+                .iter_mut()
+                .for_each(|field| field.verified = true);
+
+            let data = verifier.get_hmac_data();
+            inspection.push("username", data.get_username().clone(), true);
+            inspection.push("role", Role::to_string(data.get_role()), true);
+            inspection.push(
+                "tags",
+                match data.get_tags().is_empty() {
+                    true => "None".into(),
+                    false => data
+                        .get_tags()
+                        .iter()
+                        .map(|(key, value)| format!("{}={}", key, value))
+                        .collect::<Vec<String>>()
+                        .join(","),
+                },
+                true,
+            );
+            inspection.push("acl", data.get_acl().join(","), true);
+            if !data.get_acl_deny().is_empty() {
+                inspection.push("acl_deny", data.get_acl_deny().join(","), true);
+            }
+            if let Some(quota) = data.get_quota() {
+                inspection.push("quota", Quota::to_string(quota), true);
+            }
+        }
+        inspection.outcome = Some(outcome);
+
+        inspection
+    }
+    /// Get the outcome of verifying the token. `None` if no server key was provided
+    pub fn get_outcome(&self) -> &Option<TokenOutcome> {
+        &self.outcome
+    }
+    /// Get the inspected fields
+    pub fn get_fields(&self) -> &Vec<InspectedField> {
+        &self.fields
+    }
+    /// Get a field by its name
+    pub fn get_field(&self, name: &str) -> Option<&InspectedField> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+    /// Compare the fields of two inspected tokens returning the fields whose values differ
+    pub fn diff(&self, other: &TokenInspection) -> Vec<FieldDiff> {
+        let mut diffs: Vec<FieldDiff> = Vec::new();
+
+        self.fields.iter().for_each(|field| {
+            let right = other.get_field(field.name).map(|other| other.value.clone());
+            if right.as_ref() != Some(&field.value) {
+                diffs.push(FieldDiff {
+                    name: field.name,
+                    left: Some(field.value.clone()),
+                    right,
+                });
+            }
+        });
+        other
+            .fields
+            .iter()
+            .filter(|field| self.get_field(field.name).is_none())
+            .for_each(|field| {
+                diffs.push(FieldDiff {
+                    name: field.name,
+                    left: None,
+                    right: Some(field.value.clone()),
+                })
+            });
+
+        diffs
+    }
+    /// Render the inspection as a JSON object
+    pub fn to_json(&self) -> String {
+        let mut json = String::default();
+        json.push_str("{\"outcome\":");
+        match &self.outcome {
+            Some(outcome) => {
+                json.push_str(&TokenInspection::json_string(&format!("{:?}", outcome)))
+            }
+            None => json.push_str("null"),
+        }
+        json.push_str(",\"fields\":{");
+        let fields = self
+            .fields
+            .iter()
+            .map(|field| {
+                format!(
+                    "{}:{{\"value\":{},\"verified\":{}}}",
+                    TokenInspection::json_string(field.name),
+                    TokenInspection::json_string(&field.value),
+                    field.verified
+                )
+            })
+            .collect::<Vec<String>>();
+        json.push_str(&fields.join(","));
+        json.push_str("}}");
+
+        json
+    }
+
+    fn push(&mut self, name: &'static str, value: String, verified: bool) {
+        self.fields.push(InspectedField {
+            name,
+            value,
+            verified,
+        });
+    }
+
+    fn unix_time(hex_str: &str) -> String {
+        let tai_time = hex::decode(hex_str)
+            .ok()
+            .and_then(|bytes| TAI64N::from_slice(&bytes).ok());
+
+        match tai_time {
+            Some(tai_time) => tai_time.0.to_unix().to_string(),
+            None => hex_str.to_owned(),
+        }
+    }
+
+    fn json_string(value: &str) -> String {
+        let mut escaped = String::with_capacity(value.len() + 2);
+        escaped.push('"');
+        value.chars().for_each(|character| match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            control if (control as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", control as u32))
+            }
+            _ => escaped.push(character),
+        });
+        escaped.push('"');
+
+        escaped
+    }
+}
+
+#[cfg(test)]
+mod inspect_tests {
+    use super::TokenInspection;
+    use crate::{LiteSessionData, LiteSessionError, LiteSessionToken, ServerKey, TokenOutcome};
+
+    #[test]
+    fn inspect_and_diff() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let token_a = token.build_secure(&server_key)?;
+
+        data.username("bar_user");
+        token.hmac_data(data).footer("tenant=foo");
+        let token_b = token.build_secure(&server_key)?;
+
+        let untrusted = TokenInspection::inspect(&token_a, None);
+        assert_eq!(untrusted.get_outcome(), &None);
+        assert_eq!(untrusted.get_fields().len(), 8);
+        assert_eq!(
+            untrusted
+                .get_field("version")
+                .map(|field| field.get_value()),
+            Some(&"ls2".to_owned())
+        );
+        assert!(untrusted
+            .get_fields()
+            .iter()
+            .all(|field| !field.is_verified()));
+        assert!(untrusted.to_json().contains("\"verified\":false"));
+
+        let verified_a = TokenInspection::inspect(&token_a, Some(&server_key));
+        let verified_b = TokenInspection::inspect(&token_b, Some(&server_key));
+        assert_eq!(
+            verified_a.get_outcome(),
+            &Some(TokenOutcome::TokenAuthentic)
+        );
+        assert!(verified_a
+            .get_fields()
+            .iter()
+            .all(|field| field.is_verified()));
+        assert_eq!(
+            verified_a
+                .get_field("username")
+                .map(|field| field.get_value()),
+            Some(&"foo_user".to_owned())
+        );
+        assert!(verified_a
+            .to_json()
+            .contains("\"username\":{\"value\":\"foo_user\",\"verified\":true}"));
+
+        let diffs = verified_a.diff(&verified_b);
+        let names = diffs
+            .iter()
+            .map(|diff| diff.get_name())
+            .collect::<Vec<&str>>();
+        assert!(names.contains(&"username"));
+        assert!(names.contains(&"hmac"));
+        assert_eq!(
+            verified_b
+                .get_field("footer")
+                .map(|field| field.get_value()),
+            Some(&"tenant=foo".to_owned())
+        );
+        assert!(names.contains(&"footer"));
+        assert!(!names.contains(&"identifier"));
+        assert!(!names.contains(&"role"));
+
+        let rejected = TokenInspection::inspect(&token_a, Some(&ServerKey::new([1_u8; 32])));
+        assert_eq!(rejected.get_outcome(), &Some(TokenOutcome::TokenRejected));
+        assert_eq!(
+            TokenInspection::inspect("foo", None).get_outcome(),
+            &Some(TokenOutcome::BadToken)
+        );
+        assert_eq!(TokenInspection::json_string("a\"b"), "\"a\\\"b\"");
+
+        Ok(())
+    }
+}
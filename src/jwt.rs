@@ -0,0 +1,246 @@
+use crate::{LiteSessionData, LiteSessionError, LiteSessionToken, Quota, Role, TokenOutcome};
+use hmac::{Hmac, Mac};
+use serde_json::{json, Map, Value};
+use sha2::Sha256;
+use tai64::{TAI64, TAI64N};
+
+type HmacSha256 = Hmac<Sha256>;
+
+impl LiteSessionToken {
+    /// Export the token as a `HS256` JWT signed with `signing_key` for services that only
+    /// accept JWTs. Only export tokens that were built or verified as authentic by `from_string`.
+    /// The `jti`, `sub`, `iat`, `exp`, `aud` and `iss` claims hold the identifier, username, issued and
+    /// expiry times, audience and issuer while `role`, `tags`, `acl`, `acl_deny`, `acl_exp` and `quota` hold the rest of the data
+    pub fn to_jwt(&self, signing_key: &[u8]) -> Result<String, LiteSessionError> {
+        let data = self.get_hmac_data();
+
+        let mut claims = Map::new();
+        claims.insert("jti".into(), json!(self.get_identifier()));
+        claims.insert("sub".into(), json!(data.get_username()));
+        claims.insert("iat".into(), json!(self.get_issued().0.to_unix()));
+        claims.insert("exp".into(), json!(self.get_expiry().0.to_unix()));
+        claims.insert("role".into(), json!(Role::to_string(data.get_role())));
+        if !data.get_tags().is_empty() {
+            let tags: Map<String, Value> = data
+                .get_tags()
+                .iter()
+                .map(|(key, value)| (key.clone(), json!(value)))
+                .collect();
+            claims.insert("tags".into(), Value::Object(tags));
+        }
+        claims.insert("acl".into(), json!(data.get_acl()));
+        if !data.get_acl_deny().is_empty() {
+            claims.insert("acl_deny".into(), json!(data.get_acl_deny()));
+        }
+        let acl_exp: Map<String, Value> = data
+            .get_acl()
+            .iter()
+            .filter_map(|acl| {
+                data.get_acl_expiry(acl)
+                    .map(|expiry| (acl.clone(), json!(expiry.0.to_unix())))
+            })
+            .collect();
+        if !acl_exp.is_empty() {
+            claims.insert("acl_exp".into(), Value::Object(acl_exp));
+        }
+        if let Some(quota) = data.get_quota() {
+            claims.insert("quota".into(), json!(Quota::to_string(quota)));
+        }
+        if let Some(audience) = data.get_audience() {
+            claims.insert("aud".into(), json!(audience));
+        }
+        if let Some(issuer) = data.get_issuer() {
+            claims.insert("iss".into(), json!(issuer));
+        }
+
+        let header = json!({ "alg": "HS256", "typ": "JWT" });
+        let mut jwt = String::default();
+        jwt.push_str(&jwt_encode(header.to_string().as_bytes()));
+        jwt.push('.');
+        jwt.push_str(&jwt_encode(Value::Object(claims).to_string().as_bytes()));
+
+        let signature = jwt_signature(signing_key, &jwt)?.finalize().into_bytes();
+        jwt.push('.');
+        jwt.push_str(&jwt_encode(&signature));
+
+        Ok(jwt)
+    }
+}
+
+impl LiteSessionData {
+    /// Import the claims of a `HS256` JWT signed with `signing_key`.
+    /// Returns `TokenOutcome::TokenRejected` if the signature does not match and
+    /// `TokenOutcome::SessionExpired` if the `exp` claim has passed
+    pub fn from_jwt(
+        jwt: &str,
+        signing_key: &[u8],
+    ) -> Result<(TokenOutcome, LiteSessionData), LiteSessionError> {
+        let mut parts = jwt.rsplitn(2, '.');
+        let signature = parts.next().unwrap_or_default();
+        let signed = match parts.next() {
+            Some(signed) => signed,
+            None => return Err(LiteSessionError::InvalidJwt),
+        };
+        let (header, claims) = match signed.split_once('.') {
+            Some(parts) => parts,
+            None => return Err(LiteSessionError::InvalidJwt),
+        };
+
+        let header = jwt_decode(header)?;
+        if header.get("alg").and_then(Value::as_str) != Some("HS256") {
+            return Err(LiteSessionError::InvalidJwt);
+        }
+
+        let signature = match base64::decode_config(signature, base64::URL_SAFE_NO_PAD) {
+            Ok(signature) => signature,
+            Err(_) => return Err(LiteSessionError::InvalidJwt),
+        };
+        if jwt_signature(signing_key, signed)?
+            .verify_slice(&signature)
+            .is_err()
+        {
+            return Ok((TokenOutcome::TokenRejected, LiteSessionData::default()));
+        }
+
+        let claims = jwt_decode(claims)?;
+        let expiry = match claims.get("exp").and_then(Value::as_i64) {
+            Some(expiry) => expiry,
+            None => return Err(LiteSessionError::InvalidJwt),
+        };
+        if expiry <= TAI64N::now().0.to_unix() {
+            return Ok((TokenOutcome::SessionExpired, LiteSessionData::default()));
+        }
+
+        let mut data = LiteSessionData::default();
+        if let Some(username) = claims.get("sub").and_then(Value::as_str) {
+            data.username(username);
+        }
+        if let Some(role) = claims.get("role").and_then(Value::as_str) {
+            data.role(Role::from_str(role));
+        }
+        if let Some(tags) = claims.get("tags").and_then(Value::as_object) {
+            tags.iter().for_each(|(key, value)| {
+                if let Some(value) = value.as_str() {
+                    data.tag(key, value);
+                }
+            });
+        }
+        if let Some(acl) = claims.get("acl").and_then(Value::as_array) {
+            let acl_exp = claims.get("acl_exp").and_then(Value::as_object);
+            acl.iter().filter_map(Value::as_str).for_each(|acl| {
+                match acl_exp
+                    .and_then(|acl_exp| acl_exp.get(acl))
+                    .and_then(Value::as_i64)
+                {
+                    Some(expiry) => data.add_acl_until(acl, TAI64N(TAI64::from_unix(expiry), 0)),
+                    None => data.add_acl(acl),
+                };
+            });
+            data.remove_expired_acl(&TAI64N::now());
+        }
+        if let Some(acl_deny) = claims.get("acl_deny").and_then(Value::as_array) {
+            acl_deny.iter().filter_map(Value::as_str).for_each(|deny| {
+                data.add_acl_deny(deny);
+            });
+        }
+        if let Some(quota) = claims.get("quota").and_then(Value::as_str) {
+            match Quota::from_string(quota) {
+                Some(quota) => data.quota(quota),
+                None => return Err(LiteSessionError::InvalidClaim),
+            };
+        }
+        if let Some(audience) = claims.get("aud").and_then(Value::as_str) {
+            data.audience(audience);
+        }
+        if let Some(issuer) = claims.get("iss").and_then(Value::as_str) {
+            data.issuer(issuer);
+        }
+
+        Ok((TokenOutcome::TokenAuthentic, data))
+    }
+}
+
+fn jwt_encode(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+fn jwt_decode(part: &str) -> Result<Map<String, Value>, LiteSessionError> {
+    let bytes = match base64::decode_config(part, base64::URL_SAFE_NO_PAD) {
+        Ok(bytes) => bytes,
+        Err(_) => return Err(LiteSessionError::InvalidJwt),
+    };
+
+    match serde_json::from_slice(&bytes) {
+        Ok(Value::Object(object)) => Ok(object),
+        _ => Err(LiteSessionError::InvalidJwt),
+    }
+}
+
+fn jwt_signature(signing_key: &[u8], signed: &str) -> Result<HmacSha256, LiteSessionError> {
+    if signing_key.is_empty() {
+        return Err(LiteSessionError::ServerKeyLengthError);
+    }
+
+    let mut mac = match HmacSha256::new_from_slice(signing_key) {
+        Ok(mac) => mac,
+        Err(_) => return Err(LiteSessionError::ServerKeyLengthError),
+    };
+    mac.update(signed.as_bytes());
+
+    Ok(mac)
+}
+
+#[cfg(test)]
+mod jwt_tests {
+    use crate::{
+        LiteSessionData, LiteSessionError, LiteSessionToken, Quota, Role, ServerKey, TokenOutcome,
+    };
+    use tai64::{TAI64, TAI64N};
+
+    #[test]
+    fn jwt_bridge() -> Result<(), LiteSessionError> {
+        let signing_key = b"jwt-signing-key";
+        let expiry = TAI64N::now().0.to_unix() + 900;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .role(Role::Admin)
+            .tag("env", "prod")
+            .tag("region", "eu-west")
+            .add_acl("Network-TCP")
+            .add_acl("Network-UDP")
+            .add_acl_deny("Network-UDP")
+            .add_acl_until("Admin", TAI64N(TAI64::from_unix(expiry), 0));
+        data.quota(Quota::new(100, 60))
+            .audience("api.example.com")
+            .issuer("auth.example.com");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        token.build_secure(&ServerKey::new([0_u8; 32]))?;
+
+        let jwt = token.to_jwt(signing_key)?;
+        assert_eq!(jwt.split('.').count(), 3);
+
+        let (outcome, imported) = LiteSessionData::from_jwt(&jwt, signing_key)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(imported, data);
+
+        let (outcome, _) = LiteSessionData::from_jwt(&jwt, b"other-key")?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        token.expiry(0);
+        let (outcome, _) = LiteSessionData::from_jwt(&token.to_jwt(signing_key)?, signing_key)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        assert_eq!(
+            LiteSessionData::from_jwt("foo", signing_key),
+            Err(LiteSessionError::InvalidJwt)
+        );
+        assert_eq!(
+            LiteSessionData::from_jwt("e30.e30.", signing_key),
+            Err(LiteSessionError::InvalidJwt)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use crate::{EntropySource, LiteSessionError, OsEntropy, SessionTokenRng};
+use secrecy::{zeroize::Zeroize, ExposeSecret, Secret};
+use std::convert::TryInto;
+
+const VERIFICATION_KEY_CONTEXT: &str = "lite-session 2021-03-01 scoped verification key";
+
+/// A `32byte/256bit` server key held in a `secrecy::Secret`.
+/// The key is zeroized when dropped, redacted from `Debug` output and cannot be cloned
+/// ```
+/// use lite_session::ServerKey;
+///
+/// let server_key = ServerKey::from_slice(&[0_u8; 32]).unwrap();
+/// assert_eq!(format!("{:?}", server_key), "ServerKey([REDACTED])");
+/// ```
+pub struct ServerKey(Secret<[u8; 32]>);
+
+impl core::fmt::Debug for ServerKey {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str("ServerKey([REDACTED])")
+    }
+}
+
+impl From<[u8; 32]> for ServerKey {
+    fn from(key: [u8; 32]) -> Self {
+        ServerKey::new(key)
+    }
+}
+
+impl ExposeSecret<[u8; 32]> for ServerKey {
+    fn expose_secret(&self) -> &[u8; 32] {
+        self.0.expose_secret()
+    }
+}
+
+impl ServerKey {
+    /// Wrap a `32byte/256bit` key
+    pub fn new(key: [u8; 32]) -> Self {
+        Self(Secret::new(key))
+    }
+    /// Copy a key from a slice. Fails with `LiteSessionError::ServerKeyLengthError`
+    /// if the slice is not `32` bytes long
+    pub fn from_slice(key: &[u8]) -> Result<Self, LiteSessionError> {
+        match key.try_into() {
+            Ok(key) => Ok(ServerKey::new(key)),
+            Err(_) => Err(LiteSessionError::ServerKeyLengthError),
+        }
+    }
+    /// Decode a hex encoded key
+    pub fn from_hex(key_hex: &str) -> Result<Self, LiteSessionError> {
+        let mut key = match hex::decode(key_hex) {
+            Ok(key) => key,
+            Err(_) => return Err(LiteSessionError::InvalidHexString),
+        };
+        let server_key = ServerKey::from_slice(&key);
+        key.zeroize();
+
+        server_key
+    }
+    /// Derive the subkey of `scope`, like a region or a service, for edge nodes that only verify
+    /// the tokens of their scope. Build the tokens of the scope with the subkey so that a
+    /// compromised edge node exposes the tokens of its scope only and never the server key.
+    /// A subkey can still build tokens for its own scope
+    pub fn derive_verification_key(&self, scope: &str) -> ServerKey {
+        // The server key has a fixed length so the scope needs no length prefix
+        let mut key_material = self.expose_secret().to_vec();
+        key_material.extend_from_slice(scope.as_bytes());
+
+        let mut scoped_key = [0_u8; 32];
+        blake3::derive_key(VERIFICATION_KEY_CONTEXT, &key_material, &mut scoped_key);
+        let server_key = ServerKey::new(scoped_key);
+        key_material.zeroize();
+        scoped_key.zeroize();
+
+        server_key
+    }
+    /// Generate a random key using the operating system entropy source
+    pub fn generate() -> Self {
+        ServerKey::generate_from(&OsEntropy)
+    }
+    /// Generate a key from the bytes of `entropy`
+    pub fn generate_from(entropy: &dyn EntropySource) -> Self {
+        let mut random = SessionTokenRng::bytes_from(entropy, 32);
+        let mut key = [0_u8; 32];
+        key.copy_from_slice(&random);
+        let server_key = ServerKey::new(key);
+        random.zeroize();
+        key.zeroize();
+
+        server_key
+    }
+}
+
+#[cfg(test)]
+mod key_tests {
+    use super::ServerKey;
+    use crate::{LiteSessionData, LiteSessionError, LiteSessionToken, TokenOutcome};
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn server_key() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::from_hex(&"ab".repeat(32))?;
+        assert_eq!(server_key.expose_secret(), &[0xab_u8; 32]);
+        assert!(!format!("{:?}", server_key).contains("171"));
+
+        assert_eq!(
+            ServerKey::from_slice(&[0_u8; 16]).map(|_| ()),
+            Err(LiteSessionError::ServerKeyLengthError)
+        );
+        assert_eq!(
+            ServerKey::from_hex("zz").map(|_| ()),
+            Err(LiteSessionError::InvalidHexString)
+        );
+        assert_ne!(
+            ServerKey::generate().expose_secret(),
+            ServerKey::generate().expose_secret()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn verification_keys() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let eu_key = server_key.derive_verification_key("eu-west");
+        assert_eq!(
+            eu_key.expose_secret(),
+            server_key
+                .derive_verification_key("eu-west")
+                .expose_secret()
+        );
+        assert_ne!(eu_key.expose_secret(), server_key.expose_secret());
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&eu_key)?;
+
+        let mut edge = LiteSessionToken::default();
+        let (outcome, _) = edge.from_string(&eu_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let us_key = server_key.derive_verification_key("us-east");
+        let (outcome, _) = edge.from_string(&us_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        let (outcome, _) = edge.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{LiteSessionError, ServerKey};
+use secrecy::ExposeSecret;
+use std::collections::HashMap;
+
+/// A source of server keys identified by key IDs, like a KMS, an HSM or a secrets vault.
+/// Tokens built with a `KeyProvider` carry the ID of the current key in their version header
+/// and are verified with the key the provider returns for that ID.
+/// `KeyRing` is the in-memory implementation
+pub trait KeyProvider {
+    /// Get the ID and the key used to build new tokens
+    fn current_key(&self) -> Result<(String, ServerKey), LiteSessionError>;
+    /// Get the key with `key_id`. Returns `LiteSessionError::UnknownKeyId` if the provider
+    /// does not hold the key so that the token is rejected as malformed
+    fn key_by_id(&self, key_id: &str) -> Result<ServerKey, LiteSessionError>;
+}
+
+/// Server keys identified by key IDs so that the server key can be rotated without
+/// invalidating every outstanding session. It is the in-memory `KeyProvider`,
+/// tokens built with a `KeyRing` carry the ID of
+/// the active key in their version header, like `ls2-kfoo`, and are verified with the key
+/// of that ID. Retire a key with `remove_key()` once the tokens it issued have expired
+///
+/// ```
+/// use lite_session::{KeyRing, ServerKey};
+///
+/// let mut ring = KeyRing::default();
+/// ring.add_key("2021a", ServerKey::generate()).unwrap();
+/// ring.add_key("2021b", ServerKey::generate()).unwrap();
+/// assert_eq!(ring.get_active_id(), Some("2021a"));
+///
+/// ring.activate("2021b").unwrap();
+/// assert_eq!(ring.get_active_id(), Some("2021b"));
+/// ```
+#[derive(Debug, Default)]
+pub struct KeyRing {
+    keys: HashMap<String, ServerKey>,
+    active: Option<String>,
+}
+
+impl KeyRing {
+    /// The maximum length of a key ID in bytes
+    pub const MAX_KEY_ID_LEN: usize = 32;
+
+    /// Add a server key under `key_id`, replacing any key with the same ID.
+    /// The first key added becomes the active key.
+    /// Key IDs are ASCII alphanumeric and at most `KeyRing::MAX_KEY_ID_LEN` bytes long
+    pub fn add_key(
+        &mut self,
+        key_id: &str,
+        server_key: ServerKey,
+    ) -> Result<&mut Self, LiteSessionError> {
+        if !KeyRing::is_key_id(key_id) {
+            return Err(LiteSessionError::InvalidKeyId);
+        }
+
+        self.keys.insert(key_id.into(), server_key);
+        if self.active.is_none() {
+            self.active = Some(key_id.into());
+        }
+
+        Ok(self)
+    }
+    /// Use the key with `key_id` to build new tokens
+    pub fn activate(&mut self, key_id: &str) -> Result<&mut Self, LiteSessionError> {
+        if !self.keys.contains_key(key_id) {
+            return Err(LiteSessionError::UnknownKeyId);
+        }
+        self.active = Some(key_id.into());
+
+        Ok(self)
+    }
+    /// Remove the key with `key_id`, tokens issued with it are no longer authentic.
+    /// The active key cannot be removed, activate another key first
+    pub fn remove_key(&mut self, key_id: &str) -> Result<&mut Self, LiteSessionError> {
+        if self.active.as_deref() == Some(key_id) {
+            return Err(LiteSessionError::InvalidKeyId);
+        }
+        match self.keys.remove(key_id) {
+            Some(_) => Ok(self),
+            None => Err(LiteSessionError::UnknownKeyId),
+        }
+    }
+    /// Get the ID of the key used to build new tokens
+    pub fn get_active_id(&self) -> Option<&str> {
+        self.active.as_deref()
+    }
+    /// Check whether the ring holds a key with `key_id`
+    pub fn contains(&self, key_id: &str) -> bool {
+        self.keys.contains_key(key_id)
+    }
+    /// Get the number of keys
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+    /// Check whether the ring holds no keys
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+    /// Check whether `key_id` can be used as a key ID
+    pub fn is_key_id(key_id: &str) -> bool {
+        !key_id.is_empty()
+            && key_id.len() <= KeyRing::MAX_KEY_ID_LEN
+            && key_id.bytes().all(|byte| byte.is_ascii_alphanumeric())
+    }
+}
+
+impl KeyProvider for KeyRing {
+    fn current_key(&self) -> Result<(String, ServerKey), LiteSessionError> {
+        match &self.active {
+            Some(key_id) => Ok((key_id.clone(), self.key_by_id(key_id)?)),
+            None => Err(LiteSessionError::UnknownKeyId),
+        }
+    }
+
+    fn key_by_id(&self, key_id: &str) -> Result<ServerKey, LiteSessionError> {
+        match self.keys.get(key_id) {
+            Some(key) => Ok(ServerKey::new(*key.expose_secret())),
+            None => Err(LiteSessionError::UnknownKeyId),
+        }
+    }
+}
+
+#[cfg(test)]
+mod keyring_tests {
+    use super::{KeyProvider, KeyRing};
+    use crate::{LiteSessionError, ServerKey};
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn key_ring() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        assert_eq!(
+            ring.current_key().map(|(key_id, _)| key_id),
+            Err(LiteSessionError::UnknownKeyId)
+        );
+
+        ring.add_key("old", ServerKey::new([0_u8; 32]))?
+            .add_key("new", ServerKey::new([1_u8; 32]))?;
+        let (key_id, server_key) = ring.current_key()?;
+        assert_eq!(
+            (key_id.as_str(), server_key.expose_secret()),
+            ("old", &[0_u8; 32])
+        );
+        assert_eq!(ring.len(), 2);
+
+        ring.activate("new")?;
+        let (key_id, server_key) = ring.current_key()?;
+        assert_eq!(
+            (key_id.as_str(), server_key.expose_secret()),
+            ("new", &[1_u8; 32])
+        );
+        assert_eq!(
+            ring.remove_key("new").map(|_| ()),
+            Err(LiteSessionError::InvalidKeyId)
+        );
+        ring.remove_key("old")?;
+        assert!(!ring.contains("old"));
+        assert_eq!(
+            ring.key_by_id("old").map(|_| ()),
+            Err(LiteSessionError::UnknownKeyId)
+        );
+
+        assert_eq!(
+            ring.add_key("foo-bar", ServerKey::generate()).map(|_| ()),
+            Err(LiteSessionError::InvalidKeyId)
+        );
+        assert_eq!(
+            ring.add_key(&"a".repeat(33), ServerKey::generate())
+                .map(|_| ()),
+            Err(LiteSessionError::InvalidKeyId)
+        );
+        assert_eq!(
+            ring.activate("foo").map(|_| ()),
+            Err(LiteSessionError::UnknownKeyId)
+        );
+
+        Ok(())
+    }
+}
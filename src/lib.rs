@@ -39,7 +39,7 @@
 //! 1. [**TAI64N**](https://crates.io/crates/tai64) - handles issued time down to the nanosecond without the need to handle leap seconds and timezones.
 //! 2. [**ChaCha8**](https://crates.io/crates/chacha20) - handles symetric encryption of the data to prevent it from being read by a party other than the server that issued the token.
 //! 3. [**Blake3**](https://crates.io/crates/blake3) - a crazy fast non-cryptographic hashing algorithm used in keyed-mode to act as the  **Keyed-Hash Message Authentication Code**
-//! 4. [**Nanorand**](https://crates.io/crates/nanorand) - used as a **cryptographically secure random number generator (*CSPRNG*)** with `ChaCha` mode enabled
+//! 4. [**Getrandom**](https://crates.io/crates/getrandom) - reads the **operating system entropy source** for identifiers, nonces and keys through the `EntropySource` trait. [**Nanorand**](https://crates.io/crates/nanorand) with `ChaCha` mode enabled is an optional fallback behind the `nanorand` feature
 //! 5. [**Secrecy**](https://crates.io/crates/secrecy) - used to hold the keys or token in memory to prevent them from being logged by logging tools, cloning and being moved around.
 //!
 //! ##### The steps to generate the token:
@@ -75,7 +75,7 @@
 //!    - Append `ConfidentialityMode` to `token`
 //!    - Append `Blake3Hmac` to `token`
 //!    - Return the token as a string or hex
-//!    - The token generated is in the format `identifier⊕issued⊕expiry⊕ciphertext⊕nonce⊕confidentiality⊕hmac`
+//!    - The token generated is in the format `version⊕identifier⊕issued⊕expiry⊕ciphertext⊕nonce⊕confidentiality⊕hmac` where `version` is `ls1`
 //!
 //!    
 //!
@@ -97,9 +97,87 @@
 //!    ##### NOTES:
 //!
 //!    The `Blake3` algorithm is used in `keyed` mode where the key is a `32byte/256bit` in length
-//!    The `ChaCha8` algorithm takes a `32byte/256bit` key and `12byte/96bit nonce`
+//!    Server keys are passed as a `ServerKey` which is zeroized when dropped and redacted from `Debug` output
+//!    The `ChaCha8` algorithm takes a `32byte/256bit` key and `12byte/96bit nonce` of random bytes carried hex encoded
+//!    `TokenCipher::XChaCha20` can be chosen instead, taking a `24byte/192bit` random nonce
+//!    The `fips` feature adds `TokenCipher::Aes256Gcm` which encrypts with `AES-256-GCM` and derives keys and authenticates `ls2-aes` tokens with `HMAC-SHA-256` in place of `Blake3`
+//!    `TokenCipher::ChaCha(Rounds::R12)` and `TokenCipher::ChaCha(Rounds::R20)` select `ChaCha12` and `ChaCha20`,
+//!    the rounds are recorded in the version header like `ls2-r20` so verification uses the matching variant
+//!    A `KeyRing` holds several server keys by key ID, `build_secure_with_provider()` embeds the ID of the active key
+//!    in the version header like `ls2-k2021a` so the server key can be rotated without invalidating outstanding sessions. Implement `KeyProvider` to fetch the keys from a KMS or an HSM instead
+//!    A `TenantKeyring` derives a subkey per `TenantId` from a master key, the tenant ID is recorded in the version header like `ls2-tacme` and a leaked subkey cannot forge the tokens of another tenant
+//!    `key_commitment(true)` adds a Blake3 commitment to the derived key in the version header like `ls2-c<hex>`, tokens committing to another key are rejected before decryption
+//!    `envelope(true)` encrypts the data section under a random data key wrapped by the derived key, `rewrap()` moves such tokens to a new server key and key ID without re-encrypting the data
 //!    `International Atomic Time(TAI)` is used for nanosecond accuracy and not having to deal with leap seconds and timezones
 //!    Using the `session key` prevents `volume` and `Denning-Sacco` attacks
+//!    `LiteSessionData::add_capability()` attaches a `Capability` as a `resource:action[:constraint]` ACL entry, check it with `allows("network", "tcp")`
+//!    `LiteSessionData::has_scope("files:read")` also matches wildcard entries like `files:*` and `*`, an exact entry takes precedence over the wildcard with the longest prefix
+//!    `LiteSessionData::add_acl_deny()` adds deny entries that override grants in `has_capability()`, so `*` with a `billing:*` deny grants everything except billing
+//!    `LiteSessionData::add_acl_until()` grants a capability until a `TAI64N` time shorter than the session, expired capabilities are removed during verification
+//!    `LiteSessionData::role_at_least(Role::Admin)` compares roles through a `RoleHierarchy` where `SuperUser` implies `Admin` implies `User`
+//!    A `Policy` maps roles to capabilities, `token.authorize(&policy, "files:read")` returns `TokenOutcome::TokenAuthorized` or rejects with `RejectionReason::PolicyFailed`
+//!    `LiteSessionData::attribute()` sets attributes like a `region` that `AttributePolicy` predicates check, `evaluate_attributes()` returns an auditable `AccessDecision`
+//!    Tags are ordered key-value pairs set with `tag("env", "prod")` and read with `get_tag("env")`, the free-form tag of older tokens is read as the `tag` tag
+//!    Usernames are normalized to Unicode NFC when set, `UsernameNormalization::NfcCaseFold` in the `IssuerConfig` also lowercases them so visually identical usernames produce identical tokens
+//!    The encoded data is limited to `4 KiB` by default so that tokens fit in a cookie, `max_data_size()` changes the limit and building or verifying larger data fails with `LiteSessionError::DataSizeTooLarge`
+//!    Tokens without capabilities are supported, an empty ACL is encoded as `None` like empty tags
+//!    The `Debug` output of `LiteSessionData` masks the username and claim values, `redacted()` returns a masked copy for logging and `LiteSessionToken::fingerprint()` hashes a token into an identifier for log correlation
+//!    `leeway(Duration)` tolerates clocks drifting between servers, a token stays valid until its expiry is the leeway behind the current time and a token issued further ahead than the leeway or after its expiry is malformed
+//!    Tokens read the current time from a `Clock`, `SystemClock` by default, set a `FixedClock` or a custom clock with `clock()` to test expiry or to run without a wall clock
+//!    `max_age(Duration)` treats tokens issued longer ago than the maximum age as expired even if the issuer set a longer expiry, measured from the `auth_time` claim of tokens re-issued by a sliding session
+//!    `renew(&server_key, expiry_in_secs)` re-issues a verified token with the same identifier and data, `renew_rotating()` also draws a new identifier
+//!    Sliding sessions set with `sliding(RefreshPolicy)` re-issue tokens verified by `from_string()` once less than the threshold of their lifetime remains, `get_refreshed()` returns the replacement token and `should_refresh()` checks the threshold, the replacement keeps the confidentiality mode and records the start of the session in the `auth_time` claim so that `max_age` still ends it
+//!    `expired_grace(Duration)` verifies authentic tokens that expired less than the grace period ago as `TokenOutcome::ExpiredWithinGrace` instead of `TokenOutcome::SessionExpired`
+//!    `from_string()` checks the MAC before decrypting the data section, a forged token is rejected with `RejectionReason::MacMismatch` without being decrypted or read into the token
+//!    `LiteSessionToken::parse(&server_key, &token)` verifies a token into a new `LiteSessionToken` instead of mutating an existing one
+//!    `LiteSessionToken::peek(&token)` reads the unauthenticated identifier, times, confidentiality, key ID and tenant of a token into `TokenClaims` without the server key so gateways can route it
+//!    `get_report()` returns the `VerificationReport` of the last verification with every check performed, whether it passed and how long it took, `get_failed_check()` names the check that rejected the token
+//!    `parse_policy(ParsePolicy::Lenient)` returns malformed tokens as `TokenOutcome::BadToken` instead of the `LiteSessionError` returned by the default `ParsePolicy::Strict`
+//!    `LiteSessionVerifier::new(key_ring)` holds the leeway, maximum data size, required audience and required ACL entries configured once, `verifier.verify(&token)` returns the outcome and a new `LiteSessionToken`
+//!    `data.single_use(true)` marks a token like a password reset link as usable once, `verifier.replay_guard(Arc::new(MemoryReplayGuard::new(capacity)))` rejects its replays
+//!    `verifier.revocations(store)` returns `TokenOutcome::TokenRevoked` for revoked tokens and families, `FileRevocationStore::open(path)` persists the revocations of a single server across restarts
+//!    `RevocationBloom::from_store(&store, 0.01)` serializes the revocations into a compact filter for edge verifiers, `verifier.revocation_bloom(bloom)` consults it and `bloom.fallback(hook)` confirms possible revocations with the origin
+//!    `data.epoch(epochs.bump(user))` logs a user out everywhere, `verifier.user_epochs(epochs)` revokes the tokens of a `UserEpochProvider` epoch older than the current one
+//!    The `SessionFamily` claim counts refreshes in its generation, presenting a rotated generation of a refresh token to `RefreshRotation` or to a `LiteSessionVerifier` with a `ReplayGuard` revokes the whole family
+//!    `token.build_secure_with_store(&key, &store, session_id)` anchors a `LiteSessionMode::SessionID` session in a `SessionStore` like `MemorySessionStore` or `FileSessionStore`, `from_string_with_store()` revokes tokens whose session record was deleted
+//!    `SessionManager::new(&store).max_sessions(3)` limits the concurrent sessions of a subject, `manager.issue()` ends the oldest sessions beyond the limit and returns their IDs
+//!    `LiteSessionMode::tls_exporter(&keying_material)` binds a token to a TLS connection using the RFC 5705 keying material exported with `LiteSessionMode::TLS_EXPORTER_LABEL`, the token is rejected over any other connection
+//!    `LiteSessionMode::device_bound(device_hash)` folds a device fingerprint into the MAC and the encryption key derivation of `ls2` tokens, verify them with `from_string_with_device()` so a token replayed from another device is rejected
+//!    `token.network_binding(NetworkBinding::from_string("10.0.0.0/24")?)` mixes a client IP address or CIDR range into the MAC of `ls2` tokens without storing it, verify them with `from_string_with_client_ip()`. Clients behind proxies or NAT may change address so the binding is opt-in
+//!    `data.certificate_thumbprint(&cert_hash)` adds a confirmation claim binding the token to a client certificate in the spirit of RFC 8705, `from_string_with_certificate()` rejects it when presented over mutual TLS with another certificate and every verification that cannot see the certificate, like `from_string()` or `LiteSessionVerifier::verify()`, rejects it
+//!    `data.proof_key(&public_key)` confirms the public key of a client so that `from_string_with_proof()` only accepts the token with a fresh `ProofOfPossession` signed over the HTTP method and URI, checked by a `ProofSignatureVerifier` implementing the signature scheme of the clients, while verifications checking no proof reject the token
+//!    `token.derive_csrf_token()` derives a per-session CSRF secret from an authentic token for double-submit protection without extra state, `verify_csrf(candidate)` checks it in constant time
+//!    `data.auth_level(AuthLevel::Mfa)` records how the user authenticated in the `amr` claim, `verifier.require_auth_level(AuthLevel::Mfa)` verifies tokens of a weaker level as `TokenOutcome::StepUpRequired` so the app can trigger step-up authentication
+//!    `AttenuatedToken::from_string(&token)?.attenuate("path=/api/files")` lets a holder narrow a token offline with caveats chained into its MAC, `from_string_attenuated()` verifies the chain and enforces the `expires<SECONDS>` and `path=` caveats
+//!    `server_key.derive_verification_key(scope)` derives a scoped subkey for edge nodes, tokens built with it are only verified by nodes holding the subkey of the same scope
+//!    `verifier.verify_request(authorization, cookie, cookie_name)` verifies the bearer token or session cookie of an HTTP request and rejects it with an `HttpRejection` holding the `401`, `403` or `429` status to respond with, the building block of web framework extractors. With the `axum` feature the `axum::LiteSession<T>` extractor verifies requests against the `Arc<LiteSessionVerifier>` in the router state, reading the cookie named by `cookie_name()`
+//!    `AuthenticatedSession::from_request(&verifier, authorization, cookie, cookie_name)` holds the verified session of a request for web framework request guards, `require_role()` and `require_acl()` reject sessions without the role or capability with `403`. With the `rocket` feature `AuthenticatedSession` is a Rocket request guard verifying requests with the managed `Arc<LiteSessionVerifier>`, and `require_role!(AdminSession, Role::Admin)` and `require_acl!(FileWriter, "files:write")` declare guards narrowed to a role or capability
+//!    `with_session(Arc::new(verifier), cookie_name)` creates a closure from the optional `Authorization` and `Cookie` headers to the verified `LiteSessionData` or an `HttpRejection` for filter based frameworks. With the `warp` feature `warp::with_session(Arc::new(verifier))` is a warp `Filter` extracting the `LiteSessionData` of a request and `warp::handle_rejection` recovers its rejections into `401`, `403` or `429` responses
+//!    `LiteSessionVerifier::verify_metadata()` verifies the `authorization` metadata of a gRPC request for an interceptor, rejecting it with a typed `GrpcCode` and a message telling expired tokens apart from rejected ones. With the `tonic` feature `tonic::SessionInterceptor` verifies requests in a tonic `Interceptor`, inserting the verified `LiteSessionData` into the request extensions or failing with the matching `Status`
+//!    `WebSocketSession::from_upgrade()` authenticates a WebSocket upgrade with the token of the `Sec-WebSocket-Protocol` header or the `access_token` query parameter and `poll()` re-validates its expiry periodically, yielding `WebSocketEvent::ReauthRequired` once it expired
+//!    With the `redis` feature `RedisRevocationStore` and `RedisSessionStore` share revocations and sessions across servers, with keys expiring together with their tokens, over any client implementing `RedisConnection`. Writes that belong together, like replacing every revocation on a full import, run in a single `MULTI`/`EXEC`. `RedisConnection` is implemented for the `redis::Client` of the redis crate, and a revocation claimed with `try_revoke()` enters the export in the same Lua script
+//!    With the `sql` feature `SqlRevocationStore` and `SqlSessionStore` share revocations and sessions across servers through PostgreSQL, MySQL or SQLite over any client implementing `SqlConnection`, with `migrate()` creating their tables. Writes run in transactions and use upserts so a revocation or full import is never seen half done. The feature pulls in no SQL client, the `sqlx` feature adds `SqlxConnection` running the stores on a sqlx `AnyPool`
+//!    The `⥂`, `⇅`, `⊕` and `%` characters in usernames, roles, tags, ACL entries and claims are percent-escaped so they cannot shift the data fields. Unversioned tokens with four data fields were built before escaping and are read unescaped, so such tokens cannot carry data that needs escaping, deny entries or timed capabilities
+//!    `LiteSessionData::subject_id()` carries a stable subject like a database UUID next to the display `username`
+//!    `LiteSessionData::attachment()` carries opaque bytes like a serialized protobuf profile base64 encoded inside the encrypted data section
+//!    `LiteSessionData::audience()` and `issuer()` name the intended service and the issuing service, verifiers reject
+//!    tokens not meant for them with `require_audience()` and `allowed_issuers()`
+//!    `LiteSessionMode::SessionID` mixes the transport session ID into the MAC of `ls2` tokens, verify them with `from_string_with_session()`
+//!    `ls2` tokens derive the `session key` with Blake3 `derive_key` and compute the MAC over length-prefixed fields, `ls1` and unversioned tokens keep the original concatenation
+//!    On `wasm32-wasi` targets the time is read from the WASI `clock_time_get` call and
+//!    randomness from the WASI `random_get` call so tokens can be issued and verified inside wasm hosts
+//!    The `jwt` feature exports tokens as `HS256` JWTs with `to_jwt()` and imports JWT claims
+//!    with `LiteSessionData::from_jwt()` for services that only accept JWTs
+//!    The `json` feature also embeds an application claim struct in the data section with `LiteSessionData::claims()`,
+//!    serialized with serde and read back with `get_claims()`
+//!    The `cwt` feature exports tokens as CBOR Web Tokens in a `COSE_Mac0` structure with `to_cwt()`
+//!    and imports CWT claims with `LiteSessionData::from_cwt()` for CoAP and MQTT devices
+//!    The `cookie` feature issues tokens as cookies with `to_cookie()` setting `HttpOnly`, `Secure`,
+//!    `SameSite` and a `Max-Age` derived from the expiry, and verifies them with `from_cookie()`
+//!    The `compression` feature deflate-compresses data sections larger than the threshold set with
+//!    `compression()` before encryption. Compressed data sections are prefixed with an authenticated `z`
+//!    The `json` and `toml` features allow an `IssuerConfig` holding the token policies, TTLs,
+//!    identifier strategy and cookie profile to be loaded from and saved to a file. Server keys are never part of it
 //!
 //!
 //! ### Usage
@@ -108,7 +186,7 @@
 //! #### Creating a token
 //!
 //! ```rust
-//! use lite_session::{LiteSessionToken, LiteSessionError, ConfidentialityMode, LiteSessionData, Role, LiteSessionMode};
+//! use lite_session::{LiteSessionToken, LiteSessionError, ConfidentialityMode, LiteSessionData, Role, LiteSessionMode, ServerKey};
 //! use core::time::Duration;
 //!
 //! fn main() -> Result<(), LiteSessionError> {
@@ -120,14 +198,14 @@
 //!     let mut data = LiteSessionData::default();
 //!     data.username("foo_user");
 //!     data.role(Role::SuperUser);
-//!     data.tag("Foo-Tag");
+//!     data.tag("env", "prod");
 //!     data.add_acl("Network-TCP");
 //!     data.add_acl("Network-UDP");
 //!     token.hmac_data(data);
 //!     token.confidential(true);
-//!     token.mode(LiteSessionMode::SessionID("foobarbaz".into()));
+//!     token.mode(LiteSessionMode::session_id("foobarbaz"));
 //!
-//!     let server_key = [0_u8; 32];
+//!     let server_key = ServerKey::new([0_u8; 32]);
 //!     let session_token = token.build_secure(&server_key)?;
 //!
 //!     Ok(())
@@ -138,9 +216,9 @@
 //! #### Verifying a token
 //!
 //! ```rust
-//! use lite_session::{LiteSessionToken, LiteSessionError, ConfidentialityMode, LiteSessionData, LiteSessionMode};
+//! use lite_session::{LiteSessionToken, LiteSessionError, ConfidentialityMode, LiteSessionData, LiteSessionMode, ServerKey};
 //! fn main() -> Result<(), LiteSessionError> {
-//!     let server_key = [0_u8; 32];
+//!     let server_key = ServerKey::new([0_u8; 32]);
 //!
 //!     let mut destructured = LiteSessionToken::default();
 //!     let session_token = "5tl726krvgmhoe1pyc4jadqs3fw09bi8⊕40000000602e51ab3a8e2d17⊕40000000603013ab3a8e2d17⊕3cf157bed212d5b34122a713ea860ec373800e5004bff1a195d603305bd5b7921d1017e70ef599bc1f7ed949bd3c66c696d74a16487f95a3f6fd⊕jrzapflsi618⊕ConfidentialityMode::High⊕4faab373d7247dfb2d50e213e5cb66e415afc22066f71c2b966fdeabb11cac64";
@@ -151,15 +229,140 @@
 //! ````
 //!
 
+mod assurance;
+pub use assurance::*;
+mod attribute;
+pub use attribute::*;
+#[cfg(feature = "axum")]
+pub mod axum;
+mod bloom;
+pub use bloom::*;
+mod builder;
+pub use builder::*;
+mod caveat;
+pub use caveat::*;
+mod capability;
+pub use capability::*;
+mod chunks;
+pub use chunks::*;
+mod claims;
+pub use claims::*;
 mod ciphertext;
 pub use ciphertext::*;
+mod clock;
+pub use clock::*;
+mod compression;
+mod config;
+pub use config::*;
+#[cfg(feature = "cookie")]
+mod cookie;
+#[cfg(feature = "cwt")]
+mod cwt;
 mod data;
 pub use data::*;
+mod encoding;
+pub use encoding::*;
+mod envelope;
+mod epoch;
+pub use epoch::*;
 mod errors;
 pub use errors::*;
+mod escape;
+mod family;
+pub use family::*;
+mod fips;
+mod format;
+pub use format::*;
 mod global;
 pub use global::*;
+mod grpc;
+pub use grpc::*;
+
+mod entropy;
+pub use entropy::*;
+mod hierarchy;
+pub use hierarchy::*;
+mod http;
+pub use http::*;
+mod identifier;
+pub use identifier::*;
+mod inspect;
+pub use inspect::*;
+#[cfg(feature = "jwt")]
+mod jwt;
+mod key;
+pub use key::*;
+mod keyring;
+pub use keyring::*;
+
+mod tenant;
+pub use tenant::*;
+mod mac;
+pub use mac::*;
+mod manager;
+pub use manager::*;
 mod mode;
 pub use mode::*;
+mod network;
+pub use network::*;
+mod normalize;
+pub use normalize::*;
+mod parser;
+pub use parser::*;
+mod persist;
+mod policy;
+pub use policy::*;
+mod proof;
+pub use proof::*;
+mod quota;
+pub use quota::*;
+#[cfg(feature = "redis")]
+mod redis;
+#[cfg(feature = "redis")]
+pub use self::redis::*;
+mod refresh;
+pub use refresh::*;
+mod rejection;
+pub use rejection::*;
+mod replay;
+pub use replay::*;
+mod report;
+pub use report::*;
+mod revocation;
+pub use revocation::*;
+#[cfg(feature = "rocket")]
+mod rocket;
+#[cfg(feature = "rocket")]
+pub use self::rocket::*;
+mod sealed;
+pub use sealed::*;
+mod store;
+pub use store::*;
+#[cfg(feature = "sql")]
+mod sql;
+#[cfg(feature = "sql")]
+pub use sql::*;
+#[cfg(feature = "sqlx")]
+mod sqlx;
+#[cfg(feature = "sqlx")]
+pub use self::sqlx::*;
+mod strictness;
+pub use strictness::*;
+mod timestamp;
+pub use timestamp::*;
 mod token;
 pub use token::*;
+#[cfg(feature = "tonic")]
+pub mod tonic;
+mod ttl;
+pub use ttl::*;
+mod validate;
+pub use validate::*;
+mod verifier;
+pub use verifier::*;
+#[cfg(feature = "warp")]
+pub mod warp;
+mod websocket;
+pub use websocket::*;
+mod version;
+pub use version::*;
@@ -0,0 +1,62 @@
+/// The number of bytes of the `Blake3` keyed hash emitted in the token.
+/// `MacLength::Truncated` produces more compact tokens while `MacLength::Full` keeps
+/// the whole `32byte/256bit` output of `Blake3`
+#[derive(Debug, Default)]
+pub enum MacLength {
+    /// The first `16bytes/128bits` of the `Blake3` keyed hash
+    Truncated,
+    /// The full `32bytes/256bits` `Blake3` keyed hash
+    #[default]
+    Full,
+}
+
+impl core::cmp::PartialEq for MacLength {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (MacLength::Truncated, MacLength::Truncated) | (MacLength::Full, MacLength::Full)
+        )
+    }
+}
+
+impl core::clone::Clone for MacLength {
+    fn clone(&self) -> Self {
+        match self {
+            MacLength::Truncated => MacLength::Truncated,
+            MacLength::Full => MacLength::Full,
+        }
+    }
+}
+
+impl MacLength {
+    /// The length of the MAC in bytes
+    pub fn byte_len(&self) -> usize {
+        match self {
+            MacLength::Truncated => 16,
+            MacLength::Full => blake3::OUT_LEN,
+        }
+    }
+    /// Get the `MacLength` matching a MAC of `len` bytes
+    pub fn from_len(len: usize) -> Option<Self> {
+        match len {
+            16 => Some(MacLength::Truncated),
+            blake3::OUT_LEN => Some(MacLength::Full),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod mac_tests {
+    use super::MacLength;
+
+    #[test]
+    fn mac_length_tests() {
+        assert_eq!(MacLength::default(), MacLength::Full);
+        assert_eq!(MacLength::Full.byte_len(), 32_usize);
+        assert_eq!(MacLength::Truncated.byte_len(), 16_usize);
+        assert_eq!(MacLength::from_len(16), Some(MacLength::Truncated));
+        assert_eq!(MacLength::from_len(32), Some(MacLength::Full));
+        assert_eq!(MacLength::from_len(20), None);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::{
+    epoch::epoch_user, Clock, LiteSessionError, LiteSessionToken, ServerKey, SessionRecord,
+    SessionStore,
+};
+use std::sync::{Arc, Mutex};
+use tai64::TAI64N;
+
+/// Issues `LiteSessionMode::SessionID` tokens anchored in a `SessionStore` and tracks the
+/// active sessions of every subject, so that a policy like "max 3 devices per account" can be
+/// enforced at issuance time. The oldest sessions of a subject are ended once a new session
+/// takes them over the limit. The manager issues one session at a time so that concurrent
+/// logins cannot take a subject over the limit, sessions issued through other managers
+/// sharing the store are not counted until they are stored
+///
+/// ```
+/// use lite_session::{
+///     LiteSessionData, LiteSessionToken, MemorySessionStore, ServerKey, SessionManager,
+/// };
+///
+/// let store = MemorySessionStore::new();
+/// let mut manager = SessionManager::new(&store);
+/// manager.max_sessions(1);
+///
+/// let mut data = LiteSessionData::default();
+/// data.username("foo_user");
+/// let mut token = LiteSessionToken::default();
+/// token.hmac_data(data.clone());
+/// let server_key = ServerKey::new([0_u8; 32]);
+/// manager.issue(&server_key, &mut token, "laptop").unwrap();
+///
+/// let mut token = LiteSessionToken::default();
+/// token.hmac_data(data);
+/// let (_, evicted) = manager.issue(&server_key, &mut token, "phone").unwrap();
+/// assert_eq!(evicted, vec!["laptop".to_owned()]);
+/// ```
+#[derive(Debug)]
+pub struct SessionManager<'a, S: SessionStore> {
+    store: &'a S,
+    max_sessions: Option<usize>,
+    clock: Option<Arc<dyn Clock>>,
+    issuing: Mutex<()>,
+}
+
+impl<'a, S: SessionStore> SessionManager<'a, S> {
+    /// Create a manager keeping the sessions in `store` without a limit on concurrent sessions
+    pub fn new(store: &'a S) -> Self {
+        Self {
+            store,
+            max_sessions: None,
+            clock: None,
+            issuing: Mutex::new(()),
+        }
+    }
+    /// Limit the number of sessions a subject can hold at once
+    pub fn max_sessions(&mut self, max_sessions: usize) -> &mut Self {
+        self.max_sessions = Some(max_sessions);
+
+        self
+    }
+    /// Read the current time from `clock` instead of `SystemClock` when telling
+    /// the active sessions from the expired ones
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = Some(clock);
+
+        self
+    }
+    /// Get the maximum number of concurrent sessions of a subject
+    pub fn get_max_sessions(&self) -> Option<usize> {
+        self.max_sessions
+    }
+    /// Build `token` for the session `session_id` like
+    /// `LiteSessionToken::build_secure_with_store()` and end the oldest sessions of its
+    /// subject beyond the limit. Returns the token and the IDs of the ended sessions
+    pub fn issue(
+        &self,
+        server_key: &ServerKey,
+        token: &mut LiteSessionToken,
+        session_id: &str,
+    ) -> Result<(String, Vec<String>), LiteSessionError> {
+        // Storing, counting and ending sessions is a single step for concurrent logins
+        let _issuing = match self.issuing.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let built = token.build_secure_with_store(server_key, self.store, session_id)?;
+        let subject = epoch_user(token.get_hmac_data());
+
+        let mut evicted = Vec::new();
+        if let Some(max_sessions) = self.max_sessions {
+            let active = self.active_sessions(subject);
+            let excess = active.len().saturating_sub(max_sessions);
+            for (evicted_id, _) in active
+                .into_iter()
+                .filter(|(active_id, _)| active_id != session_id)
+                .take(excess)
+            {
+                self.store.delete(&evicted_id);
+                evicted.push(evicted_id);
+            }
+        }
+
+        Ok((built, evicted))
+    }
+    /// Get the sessions of `subject` that have not expired, oldest first
+    pub fn active_sessions(&self, subject: &str) -> Vec<(String, SessionRecord)> {
+        let now = match &self.clock {
+            Some(clock) => clock.now(),
+            None => TAI64N::now(),
+        };
+        let mut active = self
+            .store
+            .sessions_of(subject)
+            .into_iter()
+            .filter(|(_, record)| !record.is_expired(&now))
+            .collect::<Vec<(String, SessionRecord)>>();
+        active.sort_by(|(_, first), (_, second)| first.get_issued().cmp(second.get_issued()));
+
+        active
+    }
+    /// End the session `session_id`, returning its record if it existed
+    pub fn end(&self, session_id: &str) -> Option<SessionRecord> {
+        self.store.delete(session_id)
+    }
+    /// End every session of `subject`, returning the number of sessions ended
+    pub fn end_all(&self, subject: &str) -> usize {
+        self.store
+            .sessions_of(subject)
+            .iter()
+            .filter(|(session_id, _)| self.store.delete(session_id).is_some())
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod manager_tests {
+    use super::SessionManager;
+    use crate::{
+        FixedClock, LiteSessionData, LiteSessionError, LiteSessionToken, MemorySessionStore,
+        ServerKey, SessionStore, TokenOutcome,
+    };
+    use core::time::Duration;
+    use std::sync::Arc;
+    use tai64::TAI64N;
+
+    #[test]
+    fn session_manager() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let store = MemorySessionStore::new();
+        let mut manager = SessionManager::new(&store);
+        manager.max_sessions(2);
+        assert_eq!(manager.get_max_sessions(), Some(2));
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut tokens = Vec::new();
+        for session_id in ["laptop", "phone", "tablet"].iter() {
+            let mut token = LiteSessionToken::default();
+            token.hmac_data(data.clone());
+            let (built, evicted) = manager.issue(&server_key, &mut token, session_id)?;
+            tokens.push(built);
+            if *session_id == "tablet" {
+                assert_eq!(evicted, vec!["laptop".to_owned()]);
+            } else {
+                assert!(evicted.is_empty());
+            }
+        }
+
+        let active = manager
+            .active_sessions("foo_user")
+            .into_iter()
+            .map(|(session_id, _)| session_id)
+            .collect::<Vec<String>>();
+        assert_eq!(active, vec!["phone".to_owned(), "tablet".to_owned()]);
+
+        let mut verifier = LiteSessionToken::default();
+        let (outcome, _) =
+            verifier.from_string_with_store(&server_key, &store, "laptop", &tokens[0])?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        let (outcome, _) =
+            verifier.from_string_with_store(&server_key, &store, "phone", &tokens[1])?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        assert!(manager.end("phone").is_some());
+        assert_eq!(manager.end_all("foo_user"), 1);
+        assert!(store.sessions_of("foo_user").is_empty());
+
+        // Concurrent logins never leave more sessions than the limit
+        std::thread::scope(|scope| {
+            for index in 0..8 {
+                let (manager, data, server_key) = (&manager, &data, &server_key);
+                scope.spawn(move || {
+                    let mut token = LiteSessionToken::default();
+                    token.hmac_data(data.clone());
+                    manager.issue(server_key, &mut token, &format!("device{}", index))
+                });
+            }
+        });
+        assert_eq!(manager.active_sessions("foo_user").len(), 2);
+
+        // The active sessions are counted at the time of the clock of the manager
+        manager.clock(Arc::new(FixedClock::new(
+            TAI64N::now() + Duration::from_secs(2 * 24 * 3600),
+        )));
+        assert!(manager.active_sessions("foo_user").is_empty());
+
+        Ok(())
+    }
+}
@@ -1,14 +1,70 @@
+use core::fmt::{self, Debug, Display};
+use secrecy::{ExposeSecret, Secret};
+
+const TLS_EXPORTER_CONTEXT: &str = "lite-session 2021-03-01 tls exporter channel binding";
+const DEVICE_FINGERPRINT_CONTEXT: &str = "lite-session 2021-03-01 device fingerprint binding";
+
+/// A hash identifying the device a token is issued to, like a hash of a key held in the
+/// secure enclave of a mobile device. The caller-supplied hash is normalized to 32 bytes,
+/// held in a `secrecy::Secret`, redacted from `Debug` output and compared in constant time
+pub struct DeviceFingerprint(Secret<[u8; 32]>);
+
+impl DeviceFingerprint {
+    /// Create the fingerprint of the device identified by `device_hash`
+    pub fn new(device_hash: &[u8]) -> Self {
+        let mut fingerprint = [0_u8; 32];
+        blake3::derive_key(DEVICE_FINGERPRINT_CONTEXT, device_hash, &mut fingerprint);
+
+        DeviceFingerprint(Secret::new(fingerprint))
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        self.0.expose_secret()
+    }
+
+    // A fingerprint of chosen bytes, to test that they cannot pass for another binding
+    #[cfg(test)]
+    pub(crate) fn from_bytes(fingerprint: [u8; 32]) -> Self {
+        DeviceFingerprint(Secret::new(fingerprint))
+    }
+}
+
+impl Debug for DeviceFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DeviceFingerprint([REDACTED])")
+    }
+}
+
+impl core::cmp::PartialEq for DeviceFingerprint {
+    fn eq(&self, other: &Self) -> bool {
+        constant_time_eq::constant_time_eq(self.as_bytes(), other.as_bytes())
+    }
+}
+
+impl core::clone::Clone for DeviceFingerprint {
+    fn clone(&self) -> Self {
+        DeviceFingerprint(Secret::new(*self.0.expose_secret()))
+    }
+}
+
 ///This describes which mode to use for the authentication/authorization.
 /// Use `LiteSessionMode::Passive` to bypass session ID pinning of the user session
 /// to the token. This is vulnerable to `Denning-Sacco Attack`
 ///
 /// Use `LiteSessionMode::SessionID(id)` to pin the current user session ID
 /// to the token. This prevents `Denning-Sacco Attack`. An example of a sessionID
-///is the Transport Layer Security(TLS) `session key`
-#[derive(Debug)]
+///is the Transport Layer Security(TLS) `session key`.
+/// The session ID is a secret so it is held in a `secrecy::Secret`, redacted
+/// from `Debug` and `Display` output and compared in constant time
+///
+/// Use `LiteSessionMode::DeviceBound(fingerprint)` to fold the fingerprint of the device
+/// into the MAC and the derivation of the encryption key so that a token replayed from
+/// another device fails verification
 pub enum LiteSessionMode {
     /// SessionID of the transport protocol to be used as part of the mac
-    SessionID(String),
+    SessionID(Secret<String>),
+    /// Fingerprint of the device the token is bound to
+    DeviceBound(DeviceFingerprint),
     /// Ignores the transport protocol SessionID eg. TLS SessionID
     Passive,
 }
@@ -19,16 +75,38 @@ impl Default for LiteSessionMode {
     }
 }
 
+impl Debug for LiteSessionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SessionID(_) => write!(f, "SessionID([REDACTED])"),
+            Self::DeviceBound(_) => write!(f, "DeviceBound([REDACTED])"),
+            Self::Passive => write!(f, "Passive"),
+        }
+    }
+}
+
+impl Display for LiteSessionMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SessionID(_) => write!(f, "LiteSessionMode::SessionID([REDACTED])"),
+            Self::DeviceBound(_) => write!(f, "LiteSessionMode::DeviceBound([REDACTED])"),
+            Self::Passive => write!(f, "LiteSessionMode::Passive"),
+        }
+    }
+}
+
 impl core::cmp::PartialEq for LiteSessionMode {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (LiteSessionMode::Passive, LiteSessionMode::Passive) => true,
             (LiteSessionMode::SessionID(id1), LiteSessionMode::SessionID(id2)) => {
-                if id1 == id2 {
-                    true
-                } else {
-                    false
-                }
+                constant_time_eq::constant_time_eq(
+                    id1.expose_secret().as_bytes(),
+                    id2.expose_secret().as_bytes(),
+                )
+            }
+            (LiteSessionMode::DeviceBound(device1), LiteSessionMode::DeviceBound(device2)) => {
+                device1 == device2
             }
             _ => false,
         }
@@ -39,7 +117,88 @@ impl core::clone::Clone for LiteSessionMode {
     fn clone(&self) -> Self {
         match self {
             LiteSessionMode::Passive => LiteSessionMode::Passive,
-            LiteSessionMode::SessionID(id) => LiteSessionMode::SessionID(id.clone()),
+            LiteSessionMode::SessionID(id) => {
+                LiteSessionMode::SessionID(Secret::new(id.expose_secret().clone()))
+            }
+            LiteSessionMode::DeviceBound(device) => LiteSessionMode::DeviceBound(device.clone()),
         }
     }
 }
+
+impl LiteSessionMode {
+    /// The RFC 5705 exporter label to request the keying material passed to `tls_exporter()`
+    pub const TLS_EXPORTER_LABEL: &'static str = "EXPORTER-lite-session-channel-binding";
+    /// The length of the keying material to export for `tls_exporter()`
+    pub const TLS_EXPORTER_LEN: usize = 32;
+
+    /// Create a `LiteSessionMode::SessionID` holding `id` as a secret
+    pub fn session_id(id: &str) -> Self {
+        LiteSessionMode::SessionID(Secret::new(id.into()))
+    }
+    /// Create a `LiteSessionMode::SessionID` bound to a TLS connection from the keying material
+    /// exported with `LiteSessionMode::TLS_EXPORTER_LABEL` and no context as defined by RFC 5705,
+    /// like `rustls` `export_keying_material()`. The session ID is mixed into the MAC so a
+    /// token built in this mode is not authentic over any other TLS connection
+    pub fn tls_exporter(keying_material: &[u8]) -> Self {
+        let mut binding = [0_u8; 32];
+        blake3::derive_key(TLS_EXPORTER_CONTEXT, keying_material, &mut binding);
+
+        LiteSessionMode::session_id(&hex::encode(binding))
+    }
+    /// Create a `LiteSessionMode::DeviceBound` for the device identified by `device_hash`
+    pub fn device_bound(device_hash: &[u8]) -> Self {
+        LiteSessionMode::DeviceBound(DeviceFingerprint::new(device_hash))
+    }
+}
+
+#[cfg(test)]
+mod mode_tests {
+    use super::{DeviceFingerprint, LiteSessionMode};
+
+    #[test]
+    fn session_id_redaction() {
+        let mode = LiteSessionMode::session_id("foobarbaz");
+
+        assert_eq!(format!("{:?}", mode), "SessionID([REDACTED])");
+        assert_eq!(
+            format!("{}", mode),
+            "LiteSessionMode::SessionID([REDACTED])"
+        );
+        assert!(!format!("{:?}", mode).contains("foobarbaz"));
+
+        assert_eq!(mode, LiteSessionMode::session_id("foobarbaz"));
+        assert_eq!(mode.clone(), mode);
+        assert_ne!(mode, LiteSessionMode::session_id("garbage"));
+        assert_ne!(mode, LiteSessionMode::Passive);
+    }
+
+    #[test]
+    fn tls_exporter() {
+        let exported = [7_u8; LiteSessionMode::TLS_EXPORTER_LEN];
+        let mode = LiteSessionMode::tls_exporter(&exported);
+
+        assert_eq!(mode, LiteSessionMode::tls_exporter(&exported));
+        assert_ne!(mode, LiteSessionMode::tls_exporter(&[8_u8; 32]));
+        assert_ne!(mode, LiteSessionMode::session_id(&hex::encode(exported)));
+        assert_eq!(format!("{:?}", mode), "SessionID([REDACTED])");
+    }
+
+    #[test]
+    fn device_bound() {
+        let mode = LiteSessionMode::device_bound(b"foo-device");
+
+        assert_eq!(format!("{:?}", mode), "DeviceBound([REDACTED])");
+        assert_eq!(
+            format!("{}", mode),
+            "LiteSessionMode::DeviceBound([REDACTED])"
+        );
+        assert_eq!(
+            mode,
+            LiteSessionMode::DeviceBound(DeviceFingerprint::new(b"foo-device"))
+        );
+        assert_eq!(mode.clone(), mode);
+        assert_ne!(mode, LiteSessionMode::device_bound(b"bar-device"));
+        assert_ne!(mode, LiteSessionMode::Passive);
+        assert_ne!(mode, LiteSessionMode::session_id("foo-device"));
+    }
+}
@@ -0,0 +1,155 @@
+use crate::LiteSessionError;
+use std::net::IpAddr;
+
+/// The client IP address or CIDR range a token is bound to. The canonical form of the
+/// network is mixed into the MAC instead of being stored in the token, so a token presented
+/// from outside the network is rejected. Clients behind proxies or NAT may change their
+/// address between requests so the binding is only suitable where the address is stable,
+/// like an admin panel reached over a VPN
+///
+/// ```
+/// use std::net::IpAddr;
+///
+/// pub struct NetworkBinding {
+///     network: IpAddr,
+///     prefix: u8,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct NetworkBinding {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl core::cmp::PartialEq for NetworkBinding {
+    fn eq(&self, other: &Self) -> bool {
+        self.network == other.network && self.prefix == other.prefix
+    }
+}
+
+impl core::clone::Clone for NetworkBinding {
+    fn clone(&self) -> Self {
+        Self {
+            network: self.network,
+            prefix: self.prefix,
+        }
+    }
+}
+
+impl NetworkBinding {
+    /// Bind to the single address `address`
+    pub fn ip(address: IpAddr) -> Self {
+        Self {
+            network: address,
+            prefix: NetworkBinding::max_prefix(&address),
+        }
+    }
+    /// Bind to the network of `address` with the first `prefix` bits fixed.
+    /// The host bits of `address` are cleared
+    pub fn cidr(address: IpAddr, prefix: u8) -> Result<Self, LiteSessionError> {
+        if prefix > NetworkBinding::max_prefix(&address) {
+            return Err(LiteSessionError::InvalidNetworkBinding);
+        }
+
+        Ok(Self {
+            network: NetworkBinding::mask(&address, prefix),
+            prefix,
+        })
+    }
+    /// Get the address of the network
+    pub fn get_network(&self) -> &IpAddr {
+        &self.network
+    }
+    /// Get the number of fixed bits of the network
+    pub fn get_prefix(&self) -> u8 {
+        self.prefix
+    }
+    /// Check whether `address` belongs to the network
+    pub fn contains(&self, address: &IpAddr) -> bool {
+        match (self.network, address) {
+            (IpAddr::V4(_), IpAddr::V4(_)) | (IpAddr::V6(_), IpAddr::V6(_)) => {
+                NetworkBinding::mask(address, self.prefix) == self.network
+            }
+            _ => false,
+        }
+    }
+    /// Serialize the binding into its canonical `address/prefix` form
+    pub fn to_string(binding: &NetworkBinding) -> String {
+        format!("{}/{}", binding.network, binding.prefix)
+    }
+    /// Deserialize a binding from an address like `10.0.0.7` or a CIDR range like `10.0.0.0/24`
+    pub fn from_string(value: &str) -> Result<Self, LiteSessionError> {
+        let mut parts = value.splitn(2, '/');
+        let address = match parts.next().map(|address| address.parse::<IpAddr>()) {
+            Some(Ok(address)) => address,
+            _ => return Err(LiteSessionError::InvalidNetworkBinding),
+        };
+
+        match parts.next() {
+            None => Ok(NetworkBinding::ip(address)),
+            Some(prefix) => match prefix.parse::<u8>() {
+                Ok(prefix) => NetworkBinding::cidr(address, prefix),
+                Err(_) => Err(LiteSessionError::InvalidNetworkBinding),
+            },
+        }
+    }
+
+    fn max_prefix(address: &IpAddr) -> u8 {
+        match address {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        }
+    }
+
+    fn mask(address: &IpAddr, prefix: u8) -> IpAddr {
+        match address {
+            IpAddr::V4(address) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(prefix)).unwrap_or(0);
+                IpAddr::V4((u32::from(*address) & mask).into())
+            }
+            IpAddr::V6(address) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(prefix)).unwrap_or(0);
+                IpAddr::V6((u128::from(*address) & mask).into())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod network_tests {
+    use super::NetworkBinding;
+    use crate::LiteSessionError;
+    use std::net::IpAddr;
+
+    #[test]
+    fn network_binding() -> Result<(), LiteSessionError> {
+        let client: IpAddr = "10.0.0.7".parse().unwrap();
+        let range = NetworkBinding::from_string("10.0.0.99/24")?;
+        assert_eq!(NetworkBinding::to_string(&range), "10.0.0.0/24");
+        assert!(range.contains(&client));
+        assert!(!range.contains(&"10.0.1.7".parse().unwrap()));
+        assert!(!range.contains(&"::ffff:10.0.0.7".parse().unwrap()));
+
+        let host = NetworkBinding::from_string("10.0.0.7")?;
+        assert_eq!(host, NetworkBinding::ip(client));
+        assert_eq!(NetworkBinding::to_string(&host), "10.0.0.7/32");
+        assert!(host.contains(&client));
+        assert!(!host.contains(&"10.0.0.8".parse().unwrap()));
+
+        let everything = NetworkBinding::from_string("::/0")?;
+        assert!(everything.contains(&"2001:db8::1".parse().unwrap()));
+        let v6 = NetworkBinding::from_string("2001:db8::1/32")?;
+        assert_eq!(NetworkBinding::to_string(&v6), "2001:db8::/32");
+
+        assert_eq!(
+            NetworkBinding::from_string("10.0.0.0/33"),
+            Err(LiteSessionError::InvalidNetworkBinding)
+        );
+        assert_eq!(
+            NetworkBinding::from_string("foo/8"),
+            Err(LiteSessionError::InvalidNetworkBinding)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,79 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// How usernames are normalized when set on `LiteSessionData` so that visually identical
+/// usernames produce identical tokens. Normalize the usernames tokens are compared with
+/// using `normalize()` with the same mode
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[derive(Debug, Default)]
+pub enum UsernameNormalization {
+    /// Keep usernames as provided
+    None,
+    /// Unicode Normalization Form C
+    #[default]
+    Nfc,
+    /// Unicode Normalization Form C of the Unicode lowercase mapping of the username
+    /// so that usernames differing only in case are identical
+    NfcCaseFold,
+}
+
+impl core::cmp::PartialEq for UsernameNormalization {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (UsernameNormalization::None, UsernameNormalization::None)
+                | (UsernameNormalization::Nfc, UsernameNormalization::Nfc)
+                | (
+                    UsernameNormalization::NfcCaseFold,
+                    UsernameNormalization::NfcCaseFold
+                )
+        )
+    }
+}
+
+impl core::clone::Clone for UsernameNormalization {
+    fn clone(&self) -> Self {
+        match self {
+            UsernameNormalization::None => UsernameNormalization::None,
+            UsernameNormalization::Nfc => UsernameNormalization::Nfc,
+            UsernameNormalization::NfcCaseFold => UsernameNormalization::NfcCaseFold,
+        }
+    }
+}
+
+impl UsernameNormalization {
+    /// Normalize `username` according to this mode
+    pub fn normalize(&self, username: &str) -> String {
+        match self {
+            UsernameNormalization::None => username.into(),
+            UsernameNormalization::Nfc => username.nfc().collect(),
+            UsernameNormalization::NfcCaseFold => username.to_lowercase().nfc().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod normalize_tests {
+    use super::UsernameNormalization;
+
+    #[test]
+    fn username_normalization() {
+        let decomposed = "Jose\u{301}";
+        let composed = "Jos\u{e9}";
+
+        assert_eq!(
+            UsernameNormalization::None.normalize(decomposed),
+            decomposed
+        );
+        assert_eq!(UsernameNormalization::Nfc.normalize(decomposed), composed);
+        assert_eq!(UsernameNormalization::Nfc.normalize(composed), composed);
+        assert_eq!(
+            UsernameNormalization::NfcCaseFold.normalize(decomposed),
+            "jos\u{e9}"
+        );
+        assert_eq!(
+            UsernameNormalization::NfcCaseFold.normalize("JOSE\u{301}"),
+            "jos\u{e9}"
+        );
+    }
+}
@@ -0,0 +1,709 @@
+use crate::compression::COMPRESSED_MARKER;
+use crate::envelope;
+use crate::{
+    ConfidentialityMode, KeyRing, LiteSessionError, MacLength, TenantId, TokenCipher, TokenFormat,
+    TokenVersion,
+};
+use tai64::{TAI64N, UNIX_EPOCH_TAI64N};
+
+/// The fields of a token in the order they appear on the wire
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParserState {
+    Version,
+    Identifier,
+    Issued,
+    Expiry,
+    Ciphertext,
+    Nonce,
+    Confidentiality,
+    Hmac,
+    Footer,
+    Done,
+}
+
+// The parameters of a version header field
+struct VersionHeader<'a> {
+    version: TokenVersion,
+    cipher: TokenCipher,
+    key_id: Option<&'a str>,
+    tenant_id: Option<&'a str>,
+    key_commitment: Option<&'a str>,
+}
+
+impl ParserState {
+    fn next(self) -> Self {
+        match self {
+            ParserState::Version => ParserState::Identifier,
+            ParserState::Identifier => ParserState::Issued,
+            ParserState::Issued => ParserState::Expiry,
+            ParserState::Expiry => ParserState::Ciphertext,
+            ParserState::Ciphertext => ParserState::Nonce,
+            ParserState::Nonce => ParserState::Confidentiality,
+            ParserState::Confidentiality => ParserState::Hmac,
+            ParserState::Hmac => ParserState::Footer,
+            ParserState::Footer | ParserState::Done => ParserState::Done,
+        }
+    }
+
+    fn field(self) -> &'static str {
+        match self {
+            ParserState::Version => "version",
+            ParserState::Identifier => "identifier",
+            ParserState::Issued => "issued",
+            ParserState::Expiry => "expiry",
+            ParserState::Ciphertext => "ciphertext",
+            ParserState::Nonce => "nonce",
+            ParserState::Confidentiality => "confidentiality",
+            ParserState::Hmac => "hmac",
+            ParserState::Footer => "footer",
+            ParserState::Done => "token",
+        }
+    }
+}
+
+/// The field of a token that failed validation and the reason it failed
+///
+/// ```
+/// use lite_session::LiteSessionError;
+///
+/// pub struct TokenParseError {
+///     field: &'static str,
+///     error: LiteSessionError,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TokenParseError {
+    field: &'static str,
+    error: LiteSessionError,
+}
+
+impl core::cmp::PartialEq for TokenParseError {
+    fn eq(&self, other: &Self) -> bool {
+        self.field == other.field && self.error == other.error
+    }
+}
+
+impl TokenParseError {
+    /// Get the name of the field that failed validation, `token` if the token as a whole is invalid
+    pub fn get_field(&self) -> &'static str {
+        self.field
+    }
+    /// Get the reason the field failed validation
+    pub fn get_error(&self) -> &LiteSessionError {
+        &self.error
+    }
+    /// Convert into the reason the field failed validation
+    pub fn into_error(self) -> LiteSessionError {
+        self.error
+    }
+}
+
+/// The validated fields of a token, borrowed from the token string without copying.
+/// None of the fields have been authenticated
+///
+/// ```
+/// use lite_session::{TokenCipher, TokenVersion};
+/// use tai64::TAI64N;
+///
+/// pub struct ParsedToken<'a> {
+///     version: TokenVersion,
+///     cipher: TokenCipher,
+///     key_id: Option<&'a str>,
+///     tenant_id: Option<&'a str>,
+///     key_commitment: Option<&'a str>,
+///     identifier: &'a str,
+///     issued: TAI64N,
+///     expiry: TAI64N,
+///     ciphertext: &'a str,
+///     nonce: &'a str,
+///     confidentiality: &'a str,
+///     hmac: &'a str,
+///     footer: Option<String>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ParsedToken<'a> {
+    version: TokenVersion,
+    cipher: TokenCipher,
+    key_id: Option<&'a str>,
+    tenant_id: Option<&'a str>,
+    key_commitment: Option<&'a str>,
+    identifier: &'a str,
+    issued: TAI64N,
+    expiry: TAI64N,
+    ciphertext: &'a str,
+    nonce: &'a str,
+    confidentiality: &'a str,
+    hmac: &'a str,
+    footer: Option<String>,
+}
+
+impl<'a> ParsedToken<'a> {
+    /// Get the version of the token, `TokenVersion::V0` for tokens without a version prefix
+    pub fn get_version(&self) -> &TokenVersion {
+        &self.version
+    }
+    /// Get the cipher selected by the version header and the nonce
+    pub fn get_cipher(&self) -> &TokenCipher {
+        &self.cipher
+    }
+    /// Get the ID of the `KeyRing` key the token claims to be built with
+    pub fn get_key_id(&self) -> Option<&'a str> {
+        self.key_id
+    }
+    /// Get the `TenantId` the token claims to be issued for
+    pub fn get_tenant_id(&self) -> Option<&'a str> {
+        self.tenant_id
+    }
+    /// Get the hex encoded commitment to the derived key of the token
+    pub fn get_key_commitment(&self) -> Option<&'a str> {
+        self.key_commitment
+    }
+    /// Get the identifier
+    pub fn get_identifier(&self) -> &'a str {
+        self.identifier
+    }
+    /// Get the time the token was issued
+    pub fn get_issued(&self) -> &TAI64N {
+        &self.issued
+    }
+    /// Get the time the token expires
+    pub fn get_expiry(&self) -> &TAI64N {
+        &self.expiry
+    }
+    /// Get the hex encoded ciphertext
+    pub fn get_ciphertext(&self) -> &'a str {
+        self.ciphertext
+    }
+    /// Get the nonce
+    pub fn get_nonce(&self) -> &'a str {
+        self.nonce
+    }
+    /// Get the `ConfidentialityMode` string
+    pub fn get_confidentiality(&self) -> &'a str {
+        self.confidentiality
+    }
+    /// Get the hex encoded MAC
+    pub fn get_hmac(&self) -> &'a str {
+        self.hmac
+    }
+    /// Get the decoded public footer. Like every other field it is not authenticated until
+    /// the token is verified, yet it can be read by load balancers that do not hold the server key
+    pub fn get_footer(&self) -> &Option<String> {
+        &self.footer
+    }
+}
+
+/// Parses a token field by field, validating each field as soon as it is read.
+/// Parsing stops at the first invalid field and never reads past the last field
+/// so adversarial inputs cost at most one pass over the token.
+/// The layout of the fields is chosen by the optional `TokenVersion` prefix.
+/// Tokens using either the configured `TokenFormat` or `TokenFormat::Legacy` are accepted
+#[derive(Debug)]
+pub struct TokenParser {
+    format: TokenFormat,
+    max_len: usize,
+    max_identifier_len: usize,
+}
+
+impl Default for TokenParser {
+    fn default() -> Self {
+        Self {
+            format: TokenFormat::default(),
+            max_len: 1024 * 1024,
+            max_identifier_len: 256,
+        }
+    }
+}
+
+impl TokenParser {
+    /// Set the format of the tokens to parse
+    pub fn format(&mut self, format: TokenFormat) -> &mut Self {
+        self.format = format;
+
+        self
+    }
+    /// Set the maximum length of a token in bytes
+    pub fn max_len(&mut self, max_len: usize) -> &mut Self {
+        self.max_len = max_len;
+
+        self
+    }
+    /// Set the maximum length of the identifier in bytes
+    pub fn max_identifier_len(&mut self, max_identifier_len: usize) -> &mut Self {
+        self.max_identifier_len = max_identifier_len;
+
+        self
+    }
+    /// Parse and validate the fields of `token`
+    pub fn parse<'a>(&self, token: &'a str) -> Result<ParsedToken<'a>, TokenParseError> {
+        if token.len() > self.max_len {
+            return Err(TokenParser::failure(
+                ParserState::Done,
+                LiteSessionError::TokenSizeTooLarge,
+            ));
+        }
+
+        let separator = match TokenFormat::detect(token) {
+            Some(TokenFormat::Legacy) => TokenFormat::LEGACY_SEPARATOR,
+            Some(format) if format == self.format => format.separator(),
+            Some(_) => {
+                return Err(TokenParser::failure(
+                    ParserState::Done,
+                    LiteSessionError::InvalidTokenFormat,
+                ))
+            }
+            None => {
+                return Err(TokenParser::failure(
+                    ParserState::Done,
+                    LiteSessionError::TokenFieldsLengthError,
+                ))
+            }
+        };
+        let mut parsed = ParsedToken {
+            version: TokenVersion::V0,
+            cipher: TokenCipher::default(),
+            key_id: None,
+            tenant_id: None,
+            key_commitment: None,
+            identifier: "",
+            issued: UNIX_EPOCH_TAI64N,
+            expiry: UNIX_EPOCH_TAI64N,
+            ciphertext: "",
+            nonce: "",
+            confidentiality: "",
+            hmac: "",
+            footer: None,
+        };
+        let mut state = ParserState::Version;
+        let mut rest = token;
+
+        loop {
+            let (field, remainder) = match rest.find(separator) {
+                Some(index) => (&rest[..index], Some(&rest[index + separator.len_utf8()..])),
+                None => (rest, None),
+            };
+
+            match state {
+                // Tokens without a version prefix start directly with the identifier
+                ParserState::Version if !TokenVersion::is_prefix(field) => {
+                    state = ParserState::Identifier;
+                    continue;
+                }
+                ParserState::Version => {
+                    let header = TokenParser::version(field)?;
+                    parsed.version = header.version;
+                    parsed.cipher = header.cipher;
+                    parsed.key_id = header.key_id;
+                    parsed.tenant_id = header.tenant_id;
+                    parsed.key_commitment = header.key_commitment;
+                }
+                ParserState::Identifier => parsed.identifier = self.identifier(field)?,
+                ParserState::Issued => parsed.issued = TokenParser::tai_time(state, field)?,
+                ParserState::Expiry => parsed.expiry = TokenParser::tai_time(state, field)?,
+                ParserState::Ciphertext => parsed.ciphertext = TokenParser::ciphertext(field)?,
+                ParserState::Nonce => {
+                    parsed.cipher = TokenParser::cipher(field, &parsed.cipher)?;
+                    parsed.nonce = field;
+                }
+                ParserState::Confidentiality => {
+                    parsed.confidentiality = TokenParser::confidentiality(field)?
+                }
+                ParserState::Hmac => parsed.hmac = TokenParser::hmac(field)?,
+                ParserState::Footer => parsed.footer = Some(TokenParser::footer(field)?),
+                ParserState::Done => (),
+            }
+
+            state = state.next();
+            match (state, remainder) {
+                // The footer is optional and only part of versioned layouts
+                (ParserState::Footer, None) | (ParserState::Done, None) => return Ok(parsed),
+                (ParserState::Footer, Some(_)) if parsed.version == TokenVersion::V0 => {
+                    return Err(TokenParser::failure(
+                        ParserState::Done,
+                        LiteSessionError::TokenFieldsLengthError,
+                    ))
+                }
+                (_, Some(remainder)) if state != ParserState::Done => rest = remainder,
+                _ => {
+                    return Err(TokenParser::failure(
+                        ParserState::Done,
+                        LiteSessionError::TokenFieldsLengthError,
+                    ))
+                }
+            }
+        }
+    }
+
+    // The parameters follow the prefix in a fixed order, the rounds and then the key ID
+    fn version(field: &str) -> Result<VersionHeader<'_>, TokenParseError> {
+        let failure = || {
+            TokenParser::failure(
+                ParserState::Version,
+                LiteSessionError::UnsupportedTokenVersion,
+            )
+        };
+        let mut parameters = field.split('-');
+        let version = match parameters.next().and_then(TokenVersion::from_prefix) {
+            Some(version) => version,
+            None => return Err(failure()),
+        };
+
+        let mut parameter = parameters.next();
+        let mut cipher = TokenCipher::default();
+        if let Some(header) =
+            parameter.filter(|parameter| parameter.starts_with('r') || parameter.starts_with('a'))
+        {
+            cipher = TokenCipher::from_header(header).ok_or_else(failure)?;
+            parameter = parameters.next();
+        }
+        // `TokenCipher::Aes256Gcm` relies on the length-prefixed MAC of `ls2`
+        if cipher == TokenCipher::Aes256Gcm && version != TokenVersion::V2 {
+            return Err(failure());
+        }
+        let mut key_id = None;
+        if let Some(header) = parameter.filter(|parameter| parameter.starts_with('k')) {
+            match header.strip_prefix('k') {
+                Some(id) if KeyRing::is_key_id(id) => key_id = Some(id),
+                _ => return Err(failure()),
+            }
+            parameter = parameters.next();
+        }
+        let mut tenant_id = None;
+        if let Some(header) = parameter.filter(|parameter| parameter.starts_with('t')) {
+            match header.strip_prefix('t') {
+                Some(id) if TenantId::is_tenant_id(id) => tenant_id = Some(id),
+                _ => return Err(failure()),
+            }
+            parameter = parameters.next();
+        }
+        let mut key_commitment = None;
+        if let Some(header) = parameter {
+            match header.strip_prefix('c') {
+                Some(commitment) if commitment.len() == 64 && TokenParser::is_hex(commitment) => {
+                    key_commitment = Some(commitment)
+                }
+                _ => return Err(failure()),
+            }
+            parameter = parameters.next();
+        }
+
+        match parameter {
+            Some(_) => Err(failure()),
+            None => Ok(VersionHeader {
+                version,
+                cipher,
+                key_id,
+                tenant_id,
+                key_commitment,
+            }),
+        }
+    }
+
+    fn identifier<'a>(&self, field: &'a str) -> Result<&'a str, TokenParseError> {
+        if field.is_empty() || field.len() > self.max_identifier_len {
+            return Err(TokenParser::failure(
+                ParserState::Identifier,
+                LiteSessionError::TokenFieldsLengthError,
+            ));
+        }
+
+        Ok(field)
+    }
+
+    fn tai_time(state: ParserState, field: &str) -> Result<TAI64N, TokenParseError> {
+        let tai_bytes = match hex::decode(field) {
+            Ok(bytes) => bytes,
+            Err(_) => {
+                return Err(TokenParser::failure(
+                    state,
+                    LiteSessionError::InvalidHexString,
+                ))
+            }
+        };
+        match TAI64N::from_slice(&tai_bytes) {
+            Ok(tai_time) => Ok(tai_time),
+            Err(_) => Err(TokenParser::failure(
+                state,
+                LiteSessionError::InvalidTai64NTime,
+            )),
+        }
+    }
+
+    fn ciphertext(field: &str) -> Result<&str, TokenParseError> {
+        let data = match envelope::split(field) {
+            Some(Ok((wrapped_key, data))) if TokenParser::is_hex(wrapped_key) => data,
+            Some(_) => {
+                return Err(TokenParser::failure(
+                    ParserState::Ciphertext,
+                    LiteSessionError::MissingWrappedKey,
+                ))
+            }
+            None => field,
+        };
+        let hex = data.strip_prefix(COMPRESSED_MARKER).unwrap_or(data);
+
+        match TokenParser::is_hex(hex) {
+            true => Ok(field),
+            false => Err(TokenParser::failure(
+                ParserState::Ciphertext,
+                LiteSessionError::InvalidHexString,
+            )),
+        }
+    }
+
+    // The cipher of the version header only applies to `TokenCipher::ChaCha` nonces,
+    // `TokenCipher::Aes256Gcm` has no legacy alphanumeric nonces
+    fn cipher(field: &str, header_cipher: &TokenCipher) -> Result<TokenCipher, TokenParseError> {
+        match TokenCipher::from_nonce(field) {
+            Some(TokenCipher::ChaCha(_))
+                if header_cipher == &TokenCipher::Aes256Gcm && field.len() != 24 =>
+            {
+                Err(TokenParser::failure(
+                    ParserState::Nonce,
+                    LiteSessionError::NonceLengthError,
+                ))
+            }
+            Some(TokenCipher::ChaCha(_)) => Ok(header_cipher.clone()),
+            Some(TokenCipher::XChaCha20) if header_cipher.header().is_none() => {
+                Ok(TokenCipher::XChaCha20)
+            }
+            Some(_) => Err(TokenParser::failure(
+                ParserState::Version,
+                LiteSessionError::UnsupportedTokenVersion,
+            )),
+            None => Err(TokenParser::failure(
+                ParserState::Nonce,
+                LiteSessionError::NonceLengthError,
+            )),
+        }
+    }
+
+    fn confidentiality(field: &str) -> Result<&str, TokenParseError> {
+        let high = ConfidentialityMode::to_string(&ConfidentialityMode::High);
+        let low = ConfidentialityMode::to_string(&ConfidentialityMode::Low);
+
+        if field == high || field == low {
+            Ok(field)
+        } else {
+            Err(TokenParser::failure(
+                ParserState::Confidentiality,
+                LiteSessionError::InvalidConfidentialityMode,
+            ))
+        }
+    }
+
+    fn hmac(field: &str) -> Result<&str, TokenParseError> {
+        if !TokenParser::is_hex(field) {
+            return Err(TokenParser::failure(
+                ParserState::Hmac,
+                LiteSessionError::InvalidHexString,
+            ));
+        }
+
+        match MacLength::from_len(field.len() / 2) {
+            Some(_) => Ok(field),
+            None => Err(TokenParser::failure(
+                ParserState::Hmac,
+                LiteSessionError::InvalidBytesForBlake3,
+            )),
+        }
+    }
+
+    fn footer(field: &str) -> Result<String, TokenParseError> {
+        let footer = base64::decode_config(field, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok());
+
+        match footer {
+            Some(footer) if !footer.is_empty() => Ok(footer),
+            _ => Err(TokenParser::failure(
+                ParserState::Footer,
+                LiteSessionError::InvalidBase64String,
+            )),
+        }
+    }
+
+    fn is_hex(field: &str) -> bool {
+        !field.is_empty()
+            && field.len().is_multiple_of(2)
+            && field.bytes().all(|byte| byte.is_ascii_hexdigit())
+    }
+
+    fn failure(state: ParserState, error: LiteSessionError) -> TokenParseError {
+        TokenParseError {
+            field: state.field(),
+            error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod parser_tests {
+    use super::TokenParser;
+    use crate::{
+        LiteSessionData, LiteSessionError, LiteSessionToken, Rounds, ServerKey, TokenCipher,
+        TokenVersion,
+    };
+
+    #[test]
+    fn token_parser() -> Result<(), LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&ServerKey::new([0_u8; 32]))?;
+
+        let parser = TokenParser::default();
+        let parsed = parser.parse(&session_token).unwrap();
+        assert_eq!(parsed.get_identifier(), token.get_identifier());
+        assert_eq!(parsed.get_expiry(), token.get_expiry());
+        assert_eq!(parsed.get_hmac().len(), 64);
+        assert_eq!(parsed.get_version(), &TokenVersion::V2);
+
+        let fields = session_token.split('⊕').collect::<Vec<&str>>();
+        let replace = |index: usize, value: &str| {
+            let mut fields = fields.clone();
+            fields[index] = value;
+            fields.join("⊕")
+        };
+        let failure = |token: &str| {
+            parser
+                .parse(token)
+                .map(|_| ())
+                .map_err(|error| (error.field, error.error))
+        };
+
+        assert_eq!(
+            failure(&fields[..7].join("⊕")),
+            Err(("token", LiteSessionError::TokenFieldsLengthError))
+        );
+        assert_eq!(
+            parser
+                .parse(&fields[1..].join("⊕"))
+                .map(|parsed| parsed.get_version().clone()),
+            Ok(TokenVersion::V0)
+        );
+        assert_eq!(
+            failure(&format!("{}⊕Zm9v", fields[1..].join("⊕"))),
+            Err(("token", LiteSessionError::TokenFieldsLengthError))
+        );
+        assert_eq!(
+            parser
+                .parse(&format!("{}⊕Zm9v", session_token))
+                .map(|parsed| parsed.get_footer().clone()),
+            Ok(Some("foo".to_owned()))
+        );
+        assert_eq!(
+            failure(&format!("{}⊕Zm9v⊕Zm9v", session_token)),
+            Err(("token", LiteSessionError::TokenFieldsLengthError))
+        );
+        assert_eq!(
+            failure(&format!("{}⊕", session_token)),
+            Err(("footer", LiteSessionError::InvalidBase64String))
+        );
+        assert_eq!(
+            failure(&replace(0, "ls9")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            parser
+                .parse(&replace(0, "ls2-r20"))
+                .map(|parsed| parsed.get_cipher().clone()),
+            Ok(TokenCipher::ChaCha(Rounds::R20))
+        );
+        assert_eq!(
+            parser
+                .parse(&replace(0, "ls2-aes-kfoo"))
+                .map(|parsed| parsed.get_cipher().clone()),
+            Ok(TokenCipher::Aes256Gcm)
+        );
+        assert_eq!(
+            failure(&replace(0, "ls1-aes")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            failure(&replace(0, "ls2-aes256")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            parser
+                .parse(&replace(0, "ls2-r12-kfoo"))
+                .map(|parsed| parsed.get_key_id()),
+            Ok(Some("foo"))
+        );
+        assert_eq!(
+            parser
+                .parse(&replace(0, "ls2-kfoo-tbar"))
+                .map(|parsed| (parsed.get_key_id(), parsed.get_tenant_id())),
+            Ok((Some("foo"), Some("bar")))
+        );
+        let commitment = "ab".repeat(32);
+        assert_eq!(
+            parser
+                .parse(&replace(0, &format!("ls2-tbar-c{}", commitment)))
+                .map(|parsed| parsed.get_key_commitment()),
+            Ok(Some(commitment.as_str()))
+        );
+        assert_eq!(
+            failure(&replace(0, "ls2-cabab")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            failure(&replace(0, "ls2-tbar-kfoo")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            failure(&replace(0, "ls2-kfoo-r12")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            failure(&replace(0, "ls2-r7")),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        let xchacha_nonce = TokenCipher::XChaCha20.nonce();
+        assert_eq!(
+            failure(&replace(5, &xchacha_nonce).replacen("ls2", "ls2-r20", 1)),
+            Err(("version", LiteSessionError::UnsupportedTokenVersion))
+        );
+        assert_eq!(
+            failure(&replace(1, "")),
+            Err(("identifier", LiteSessionError::TokenFieldsLengthError))
+        );
+        assert_eq!(
+            failure(&replace(3, "zz")),
+            Err(("expiry", LiteSessionError::InvalidHexString))
+        );
+        assert_eq!(
+            failure(&replace(4, "abc")),
+            Err(("ciphertext", LiteSessionError::InvalidHexString))
+        );
+        assert_eq!(
+            failure(&replace(5, "foo")),
+            Err(("nonce", LiteSessionError::NonceLengthError))
+        );
+        assert_eq!(
+            failure(&replace(6, "ConfidentialityMode::None")),
+            Err((
+                "confidentiality",
+                LiteSessionError::InvalidConfidentialityMode
+            ))
+        );
+        assert_eq!(
+            failure(&replace(7, "abcd")),
+            Err(("hmac", LiteSessionError::InvalidBytesForBlake3))
+        );
+
+        let mut small = TokenParser::default();
+        small.max_len(16);
+        assert_eq!(
+            small
+                .parse(&session_token)
+                .map(|_| ())
+                .map_err(|error| error.get_field()),
+            Err("token")
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use std::{
+    io::Write,
+    path::Path,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+// Tells apart the staging files of the writes of this process
+static STAGING: AtomicU64 = AtomicU64::new(0);
+
+// Replace the file at `path` with `contents`. The contents are written to a staging file
+// next to it, unique to this write so that concurrent writers never share one, and renamed
+// over it so that a crash never leaves a half written file behind
+pub(crate) fn replace_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let mut staging = path.to_path_buf().into_os_string();
+    staging.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        STAGING.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    let written = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&staging)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| std::fs::rename(&staging, path));
+    if written.is_err() {
+        let _ = std::fs::remove_file(&staging);
+    }
+
+    written
+}
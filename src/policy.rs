@@ -0,0 +1,94 @@
+use crate::{capability::scope_precedence, Role, RoleHierarchy};
+
+/// Maps roles to the capabilities they are allowed, checked by `LiteSessionToken::authorize`.
+/// A role is allowed the capabilities granted to it and to every role it implies in the
+/// `RoleHierarchy` of the policy and grants may use wildcards like `billing:*`
+///
+/// ```
+/// use lite_session::{Policy, Role};
+///
+/// let mut policy = Policy::default();
+/// policy
+///     .grant(Role::User, "files:read")
+///     .grant(Role::Admin, "billing:*");
+///
+/// assert!(policy.allows(&Role::SuperUser, "files:read"));
+/// assert!(policy.allows(&Role::Admin, "billing:refund"));
+/// assert!(!policy.allows(&Role::User, "billing:refund"));
+/// ```
+#[derive(Debug, Default)]
+pub struct Policy {
+    grants: Vec<(Role, String)>,
+    hierarchy: RoleHierarchy,
+}
+
+impl core::cmp::PartialEq for Policy {
+    fn eq(&self, other: &Self) -> bool {
+        self.grants == other.grants && self.hierarchy == other.hierarchy
+    }
+}
+
+impl core::clone::Clone for Policy {
+    fn clone(&self) -> Self {
+        Self {
+            grants: self.grants.clone(),
+            hierarchy: self.hierarchy.clone(),
+        }
+    }
+}
+
+impl Policy {
+    /// Allow `role` and the roles implying it the `capability`
+    pub fn grant(&mut self, role: Role, capability: &str) -> &mut Self {
+        self.grants.push((role, capability.into()));
+
+        self
+    }
+    /// Replace the default `RoleHierarchy`
+    pub fn hierarchy(&mut self, hierarchy: RoleHierarchy) -> &mut Self {
+        self.hierarchy = hierarchy;
+
+        self
+    }
+    /// Get the grants of the policy
+    pub fn get_grants(&self) -> &Vec<(Role, String)> {
+        &self.grants
+    }
+    /// Get the `RoleHierarchy`
+    pub fn get_hierarchy(&self) -> &RoleHierarchy {
+        &self.hierarchy
+    }
+    /// Check whether `role` is allowed `capability`
+    pub fn allows(&self, role: &Role, capability: &str) -> bool {
+        self.grants.iter().any(|(granted_role, granted)| {
+            self.hierarchy.implies(role, granted_role)
+                && scope_precedence(granted, capability).is_some()
+        })
+    }
+}
+
+#[cfg(test)]
+mod policy_tests {
+    use super::Policy;
+    use crate::{Role, RoleHierarchy};
+
+    #[test]
+    fn policy() {
+        let mut policy = Policy::default();
+        policy
+            .grant(Role::User, "files:read")
+            .grant(Role::Admin, "billing:*")
+            .grant(Role::SlaveNode, "*");
+
+        assert!(policy.allows(&Role::User, "files:read"));
+        assert!(policy.allows(&Role::SuperUser, "billing:refund"));
+        assert!(!policy.allows(&Role::User, "files:write"));
+        assert!(!policy.allows(&Role::Admin, "network:tcp"));
+        assert!(policy.allows(&Role::MasterNode, "network:tcp"));
+        assert!(!policy.allows(&Role::Custom("Auditor".into()), "files:read"));
+
+        policy.hierarchy(RoleHierarchy::empty());
+        assert!(!policy.allows(&Role::SuperUser, "files:read"));
+        assert!(policy.allows(&Role::Admin, "billing:refund"));
+    }
+}
@@ -0,0 +1,218 @@
+use crate::LiteSessionError;
+use tai64::TAI64N;
+
+const PROOF_CONTEXT: &str = "lite-session 2021-03-01 proof of possession";
+
+/// Verifies the signature of a `ProofOfPossession` made with the private key of the client,
+/// like an Ed25519 or ECDSA P-256 verifier. Implement this trait with the signature scheme
+/// used by the clients
+pub trait ProofSignatureVerifier: core::fmt::Debug + Send + Sync {
+    /// Check that `signature` over `message` was made with the private key of `public_key`
+    fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool;
+}
+
+/// A short-lived proof signed by the client over the HTTP method and URI of a request,
+/// in the spirit of DPoP in RFC 9449. A token confirming the public key of the client with
+/// `LiteSessionData::proof_key()` is only accepted by
+/// `LiteSessionToken::from_string_with_proof()` together with a fresh proof signed by the
+/// matching private key, so a stolen token cannot be used without the key
+///
+/// ```
+/// use tai64::TAI64N;
+///
+/// pub struct ProofOfPossession {
+///     public_key: Vec<u8>,
+///     method: String,
+///     uri: String,
+///     issued: TAI64N,
+///     signature: Vec<u8>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ProofOfPossession {
+    public_key: Vec<u8>,
+    method: String,
+    uri: String,
+    issued: TAI64N,
+    signature: Vec<u8>,
+}
+
+impl core::cmp::PartialEq for ProofOfPossession {
+    fn eq(&self, other: &Self) -> bool {
+        self.public_key == other.public_key
+            && self.method == other.method
+            && self.uri == other.uri
+            && self.issued == other.issued
+            && self.signature == other.signature
+    }
+}
+
+impl core::clone::Clone for ProofOfPossession {
+    fn clone(&self) -> Self {
+        Self {
+            public_key: self.public_key.clone(),
+            method: self.method.clone(),
+            uri: self.uri.clone(),
+            issued: self.issued,
+            signature: self.signature.clone(),
+        }
+    }
+}
+
+impl ProofOfPossession {
+    /// The number of seconds a proof is accepted for after it was issued
+    pub const MAX_AGE_SECS: u64 = 60;
+
+    /// Create an unsigned proof for a request with the HTTP `method` to `uri` issued now
+    /// by the client holding the private key of `public_key`
+    pub fn new(public_key: &[u8], method: &str, uri: &str) -> Self {
+        Self {
+            public_key: public_key.to_vec(),
+            method: method.to_ascii_uppercase(),
+            uri: uri.into(),
+            issued: TAI64N::now(),
+            signature: Vec::default(),
+        }
+    }
+    /// Set the time the proof was issued
+    pub fn issued(&mut self, issued: TAI64N) -> &mut Self {
+        self.issued = issued;
+
+        self
+    }
+    /// Set the signature of the client over `signing_input()`
+    pub fn signature(&mut self, signature: &[u8]) -> &mut Self {
+        self.signature = signature.to_vec();
+
+        self
+    }
+    /// Get the public key of the client
+    pub fn get_public_key(&self) -> &[u8] {
+        &self.public_key
+    }
+    /// Get the HTTP method of the request
+    pub fn get_method(&self) -> &String {
+        &self.method
+    }
+    /// Get the URI of the request
+    pub fn get_uri(&self) -> &String {
+        &self.uri
+    }
+    /// Get the time the proof was issued
+    pub fn get_issued(&self) -> &TAI64N {
+        &self.issued
+    }
+    /// Get the signature of the client
+    pub fn get_signature(&self) -> &[u8] {
+        &self.signature
+    }
+    /// Get the message the client signs to prove possession of its key when presenting `token`
+    pub fn signing_input(&self, token: &str) -> Vec<u8> {
+        let issued = self.issued.to_bytes();
+        let token_hash = blake3::hash(token.as_bytes());
+        let fields: [&[u8]; 6] = [
+            PROOF_CONTEXT.as_bytes(),
+            &self.public_key,
+            self.method.as_bytes(),
+            self.uri.as_bytes(),
+            &issued,
+            token_hash.as_bytes(),
+        ];
+
+        let mut message: Vec<u8> = Vec::new();
+        for field in fields.iter() {
+            message.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            message.extend_from_slice(field);
+        }
+
+        message
+    }
+    /// Get the thumbprint of `public_key` confirmed by the token
+    pub fn thumbprint(public_key: &[u8]) -> [u8; 32] {
+        *blake3::hash(public_key).as_bytes()
+    }
+    /// Serialize the proof into a single line suitable for an HTTP header
+    pub fn to_string(proof: &ProofOfPossession) -> String {
+        format!(
+            "{}.{}.{}.{}.{}.{}",
+            ProofOfPossession::magic(),
+            base64::encode_config(&proof.public_key, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&proof.method, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&proof.uri, base64::URL_SAFE_NO_PAD),
+            hex::encode(proof.issued.to_bytes()),
+            base64::encode_config(&proof.signature, base64::URL_SAFE_NO_PAD)
+        )
+    }
+    /// Deserialize a proof from its single line form
+    pub fn from_string(value: &str) -> Result<Self, LiteSessionError> {
+        let fields = value.split('.').collect::<Vec<&str>>();
+        if fields.len() != 6_usize || fields[0] != ProofOfPossession::magic() {
+            return Err(LiteSessionError::InvalidProof);
+        }
+
+        let public_key = ProofOfPossession::bytes(fields[1])?;
+        let method = ProofOfPossession::text(fields[2])?;
+        let uri = ProofOfPossession::text(fields[3])?;
+        let issued = match hex::decode(fields[4]).map(|bytes| TAI64N::from_slice(&bytes)) {
+            Ok(Ok(issued)) => issued,
+            _ => return Err(LiteSessionError::InvalidProof),
+        };
+        let signature = ProofOfPossession::bytes(fields[5])?;
+
+        Ok(Self {
+            public_key,
+            method,
+            uri,
+            issued,
+            signature,
+        })
+    }
+
+    fn bytes(encoded: &str) -> Result<Vec<u8>, LiteSessionError> {
+        match base64::decode_config(encoded, base64::URL_SAFE_NO_PAD) {
+            Ok(bytes) => Ok(bytes),
+            Err(_) => Err(LiteSessionError::InvalidProof),
+        }
+    }
+
+    fn text(encoded: &str) -> Result<String, LiteSessionError> {
+        match String::from_utf8(ProofOfPossession::bytes(encoded)?) {
+            Ok(text) => Ok(text),
+            Err(_) => Err(LiteSessionError::InvalidProof),
+        }
+    }
+
+    fn magic() -> &'static str {
+        "lspop1"
+    }
+}
+
+#[cfg(test)]
+mod proof_tests {
+    use super::ProofOfPossession;
+    use crate::LiteSessionError;
+
+    #[test]
+    fn proof_of_possession() -> Result<(), LiteSessionError> {
+        let mut proof = ProofOfPossession::new(b"foo-public-key", "post", "/transfers");
+        assert_eq!(proof.get_method(), "POST");
+        assert_ne!(
+            proof.signing_input("foo-token"),
+            proof.signing_input("bar-token")
+        );
+
+        proof.signature(b"foo-signature");
+        let serialized = ProofOfPossession::to_string(&proof);
+        assert_eq!(ProofOfPossession::from_string(&serialized)?, proof);
+        assert_eq!(
+            ProofOfPossession::from_string("lspop1.AAAA"),
+            Err(LiteSessionError::InvalidProof)
+        );
+        assert_eq!(
+            ProofOfPossession::from_string(&serialized.replacen("lspop1", "lspop2", 1)),
+            Err(LiteSessionError::InvalidProof)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+};
+
+/// The quota claim carried in the data section of a token.
+/// It describes the maximum number of requests allowed within a window of time
+///
+/// ```
+/// pub struct Quota {
+///     max_requests: u64,
+///     window: u64,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct Quota {
+    max_requests: u64,
+    window: u64,
+}
+
+impl Default for Quota {
+    fn default() -> Self {
+        Self {
+            max_requests: u64::MAX,
+            window: 60,
+        }
+    }
+}
+
+impl core::cmp::PartialEq for Quota {
+    fn eq(&self, other: &Self) -> bool {
+        self.max_requests == other.max_requests && self.window == other.window
+    }
+}
+
+impl core::clone::Clone for Quota {
+    fn clone(&self) -> Self {
+        Self {
+            max_requests: self.max_requests,
+            window: self.window,
+        }
+    }
+}
+
+impl Quota {
+    /// Create a new quota allowing `max_requests` within a window of `window_in_secs` seconds.
+    /// A window of `0` seconds is treated as a single window spanning the lifetime of the token
+    pub fn new(max_requests: u64, window_in_secs: u64) -> Self {
+        Self {
+            max_requests,
+            window: window_in_secs,
+        }
+    }
+    /// Get the maximum number of requests allowed in a window
+    pub fn get_max_requests(&self) -> u64 {
+        self.max_requests
+    }
+    /// Get the size of the window in seconds
+    pub fn get_window(&self) -> u64 {
+        self.window
+    }
+    /// Compute the start of the window containing `now_in_secs`
+    pub fn window_start(&self, now_in_secs: u64) -> u64 {
+        match now_in_secs.checked_div(self.window) {
+            Some(windows) => windows * self.window,
+            None => 0,
+        }
+    }
+    /// Compute the end of the window containing `now_in_secs` for a token expiring at
+    /// `expiry_in_secs`, the end of the single window of a quota with a window of `0` seconds
+    pub fn window_end(&self, now_in_secs: u64, expiry_in_secs: u64) -> u64 {
+        match self.window {
+            0 => expiry_in_secs,
+            window => self.window_start(now_in_secs).saturating_add(window),
+        }
+    }
+    /// Convert the quota into the string attached to the data section of a token
+    pub fn to_string(quota: &Quota) -> String {
+        let mut quota_str = String::default();
+        quota_str.push_str(&quota.max_requests.to_string());
+        quota_str.push('/');
+        quota_str.push_str(&quota.window.to_string());
+
+        quota_str
+    }
+    /// Convert a quota string from the data section of a token into a `Quota`
+    pub fn from_string(value: &str) -> Option<Self> {
+        let mut parts = value.splitn(2, '/');
+        let max_requests = parts.next()?.parse::<u64>().ok()?;
+        let window = parts.next()?.parse::<u64>().ok()?;
+
+        Some(Self {
+            max_requests,
+            window,
+        })
+    }
+}
+
+/// A counter store consulted by the verifier to enforce `Quota` claims.
+/// Implement this trait for shared stores like Redis to meter requests across servers
+pub trait QuotaStore {
+    /// Record a request by the token with `identifier` in the window from `window_start`
+    /// to `window_end` (seconds since the UNIX epoch) and return the number of requests
+    /// recorded in that window including this one. The counter can be dropped once
+    /// `window_end` has passed, like with `EXPIREAT` in Redis
+    fn increment(&self, identifier: &str, window_start: u64, window_end: u64) -> u64;
+}
+
+/// An in-memory `QuotaStore` suitable for a single server.
+/// The counters of windows that have ended are dropped as new requests are recorded
+#[derive(Debug, Default)]
+pub struct MemoryQuotaStore {
+    counters: Mutex<QuotaCounters>,
+}
+
+// The window start, window end and count of every token, indexed by the end of the
+// window so ended windows are dropped from the front
+#[derive(Debug, Default)]
+struct QuotaCounters {
+    windows: HashMap<String, (u64, u64, u64)>,
+    by_end: BTreeSet<(u64, String)>,
+}
+
+impl MemoryQuotaStore {
+    /// Create a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl QuotaStore for MemoryQuotaStore {
+    fn increment(&self, identifier: &str, window_start: u64, window_end: u64) -> u64 {
+        let mut counters = match self.counters.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let QuotaCounters { windows, by_end } = &mut *counters;
+
+        // No request is recorded before the start of its window, so every window
+        // that ended by then is over
+        while let Some((end, _)) = by_end.first() {
+            if *end > window_start {
+                break;
+            }
+            if let Some((_, ended)) = by_end.pop_first() {
+                windows.remove(&ended);
+            }
+        }
+
+        let counter = windows
+            .entry(identifier.to_owned())
+            .or_insert((window_start, window_end, 0));
+        if counter.0 != window_start {
+            by_end.remove(&(counter.1, identifier.to_owned()));
+            *counter = (window_start, window_end, 0);
+        }
+        by_end.insert((counter.1, identifier.to_owned()));
+        counter.2 = counter.2.saturating_add(1);
+
+        counter.2
+    }
+}
+
+#[cfg(test)]
+mod quota_tests {
+    use super::{MemoryQuotaStore, Quota, QuotaStore};
+
+    #[test]
+    fn quota_tests() {
+        let quota = Quota::new(100, 60);
+        let quota_str = Quota::to_string(&quota);
+        assert_eq!(quota_str, "100/60");
+        assert_eq!(Quota::from_string(&quota_str), Some(quota.clone()));
+        assert_eq!(Quota::from_string("100"), None);
+        assert_eq!(Quota::from_string("foo/60"), None);
+
+        assert_eq!(quota.window_start(125), 120);
+        assert_eq!(Quota::new(5, 0).window_start(125), 0);
+        assert_eq!(quota.window_end(125, 3600), 180);
+        assert_eq!(Quota::new(5, 0).window_end(125, 3600), 3600);
+
+        let store = MemoryQuotaStore::new();
+        assert_eq!(store.increment("foo", 120, 180), 1);
+        assert_eq!(store.increment("foo", 120, 180), 2);
+        assert_eq!(store.increment("bar", 120, 180), 1);
+        assert_eq!(store.increment("baz", 0, 3600), 1);
+        assert_eq!(store.increment("foo", 180, 240), 1);
+
+        // The windows that ended are dropped
+        let windows = |store: &MemoryQuotaStore| {
+            let counters = store.counters.lock().unwrap();
+            assert_eq!(counters.windows.len(), counters.by_end.len());
+            counters.windows.len()
+        };
+        assert_eq!(windows(&store), 2);
+        assert_eq!(store.increment("foo", 3600, 3660), 1);
+        assert_eq!(windows(&store), 1);
+    }
+}
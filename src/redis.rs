@@ -0,0 +1,927 @@
+use crate::{LiteSessionError, RevocationSnapshot, RevocationStore, SessionRecord, SessionStore};
+use tai64::TAI64N;
+
+// Claim the key of a revocation and only then enter it in the sorted set under the next
+// sequence number, so that a race has a single winner and no half added revocation is seen
+const CLAIM_REVOCATION: &str = "if redis.call('SET', KEYS[1], ARGV[1], 'NX', 'EX', ARGV[2]) then \
+local sequence = redis.call('INCR', KEYS[2]) \
+redis.call('ZADD', KEYS[3], sequence, ARGV[3]) \
+return 1 \
+end \
+return 0";
+
+/// A write command queued between `MULTI` and `EXEC` by `RedisConnection::multi_exec()`
+#[derive(Debug)]
+pub enum RedisCommand {
+    /// `SET key value`
+    Set(String, String),
+    /// `SET key value EX seconds`
+    SetEx(String, String, u64),
+    /// `DEL key`
+    Del(String),
+    /// `ZADD key score member`
+    ZAdd(String, u64, String),
+    /// `ZREM key member`
+    ZRem(String, String),
+    /// `SADD key member`
+    SAdd(String, String),
+    /// `SREM key member`
+    SRem(String, String),
+}
+
+impl core::cmp::PartialEq for RedisCommand {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RedisCommand::Set(key, value), RedisCommand::Set(key2, value2)) => {
+                key == key2 && value == value2
+            }
+            (
+                RedisCommand::SetEx(key, value, seconds),
+                RedisCommand::SetEx(key2, value2, seconds2),
+            ) => key == key2 && value == value2 && seconds == seconds2,
+            (RedisCommand::Del(key), RedisCommand::Del(key2)) => key == key2,
+            (RedisCommand::ZAdd(key, score, member), RedisCommand::ZAdd(key2, score2, member2)) => {
+                key == key2 && score == score2 && member == member2
+            }
+            (RedisCommand::ZRem(key, member), RedisCommand::ZRem(key2, member2))
+            | (RedisCommand::SAdd(key, member), RedisCommand::SAdd(key2, member2))
+            | (RedisCommand::SRem(key, member), RedisCommand::SRem(key2, member2)) => {
+                key == key2 && member == member2
+            }
+            _ => false,
+        }
+    }
+}
+
+impl core::clone::Clone for RedisCommand {
+    fn clone(&self) -> Self {
+        match self {
+            RedisCommand::Set(key, value) => RedisCommand::Set(key.clone(), value.clone()),
+            RedisCommand::SetEx(key, value, seconds) => {
+                RedisCommand::SetEx(key.clone(), value.clone(), *seconds)
+            }
+            RedisCommand::Del(key) => RedisCommand::Del(key.clone()),
+            RedisCommand::ZAdd(key, score, member) => {
+                RedisCommand::ZAdd(key.clone(), *score, member.clone())
+            }
+            RedisCommand::ZRem(key, member) => RedisCommand::ZRem(key.clone(), member.clone()),
+            RedisCommand::SAdd(key, member) => RedisCommand::SAdd(key.clone(), member.clone()),
+            RedisCommand::SRem(key, member) => RedisCommand::SRem(key.clone(), member.clone()),
+        }
+    }
+}
+
+/// The Redis commands the Redis backed stores are built on. Implement it for the connection
+/// or pool of the Redis client in use, returning `LiteSessionError::RedisCommandFailed`
+/// when a command cannot be run. It is implemented for the `redis::Client` of the redis crate
+pub trait RedisConnection: core::fmt::Debug + Send + Sync {
+    /// `GET key`
+    fn get(&self, key: &str) -> Result<Option<String>, LiteSessionError>;
+    /// `SET key value`
+    fn set(&self, key: &str, value: &str) -> Result<(), LiteSessionError>;
+    /// `SET key value EX seconds`
+    fn set_ex(&self, key: &str, value: &str, seconds: u64) -> Result<(), LiteSessionError>;
+    /// `DEL key`
+    fn del(&self, key: &str) -> Result<(), LiteSessionError>;
+    /// `INCR key`
+    fn incr(&self, key: &str) -> Result<u64, LiteSessionError>;
+    /// `ZADD key score member`
+    fn zadd(&self, key: &str, score: u64, member: &str) -> Result<(), LiteSessionError>;
+    /// `ZRANGEBYSCORE key min +inf WITHSCORES`
+    fn zrangebyscore(&self, key: &str, min: u64) -> Result<Vec<(String, u64)>, LiteSessionError>;
+    /// `ZREM key member`
+    fn zrem(&self, key: &str, member: &str) -> Result<(), LiteSessionError>;
+    /// `SADD key member`
+    fn sadd(&self, key: &str, member: &str) -> Result<(), LiteSessionError>;
+    /// `SREM key member`
+    fn srem(&self, key: &str, member: &str) -> Result<(), LiteSessionError>;
+    /// `SMEMBERS key`
+    fn smembers(&self, key: &str) -> Result<Vec<String>, LiteSessionError>;
+    /// `MULTI`, `commands` and `EXEC` on a single connection, so that other clients
+    /// see either every command applied or none of them
+    fn multi_exec(&self, commands: &[RedisCommand]) -> Result<(), LiteSessionError>;
+    /// `EVAL script numkeys keys args`, returning the integer reply of the Lua `script`.
+    /// Redis runs the script without interleaving the commands of other clients
+    fn eval(&self, script: &str, keys: &[String], args: &[String])
+        -> Result<i64, LiteSessionError>;
+}
+
+/// Open a connection for each command, pool the connections by implementing
+/// `RedisConnection` for the pool in use
+impl RedisConnection for ::redis::Client {
+    fn get(&self, key: &str) -> Result<Option<String>, LiteSessionError> {
+        query(self, ::redis::cmd("GET").arg(key))
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), LiteSessionError> {
+        query(self, ::redis::cmd("SET").arg(key).arg(value))
+    }
+
+    fn set_ex(&self, key: &str, value: &str, seconds: u64) -> Result<(), LiteSessionError> {
+        query(
+            self,
+            ::redis::cmd("SET")
+                .arg(key)
+                .arg(value)
+                .arg("EX")
+                .arg(seconds),
+        )
+    }
+
+    fn del(&self, key: &str) -> Result<(), LiteSessionError> {
+        query(self, ::redis::cmd("DEL").arg(key))
+    }
+
+    fn incr(&self, key: &str) -> Result<u64, LiteSessionError> {
+        query(self, ::redis::cmd("INCR").arg(key))
+    }
+
+    fn zadd(&self, key: &str, score: u64, member: &str) -> Result<(), LiteSessionError> {
+        query(self, ::redis::cmd("ZADD").arg(key).arg(score).arg(member))
+    }
+
+    fn zrangebyscore(&self, key: &str, min: u64) -> Result<Vec<(String, u64)>, LiteSessionError> {
+        query(
+            self,
+            ::redis::cmd("ZRANGEBYSCORE")
+                .arg(key)
+                .arg(min)
+                .arg("+inf")
+                .arg("WITHSCORES"),
+        )
+    }
+
+    fn zrem(&self, key: &str, member: &str) -> Result<(), LiteSessionError> {
+        query(self, ::redis::cmd("ZREM").arg(key).arg(member))
+    }
+
+    fn sadd(&self, key: &str, member: &str) -> Result<(), LiteSessionError> {
+        query(self, ::redis::cmd("SADD").arg(key).arg(member))
+    }
+
+    fn srem(&self, key: &str, member: &str) -> Result<(), LiteSessionError> {
+        query(self, ::redis::cmd("SREM").arg(key).arg(member))
+    }
+
+    fn smembers(&self, key: &str) -> Result<Vec<String>, LiteSessionError> {
+        query(self, ::redis::cmd("SMEMBERS").arg(key))
+    }
+
+    fn multi_exec(&self, commands: &[RedisCommand]) -> Result<(), LiteSessionError> {
+        let mut pipeline = ::redis::pipe();
+        pipeline.atomic();
+        commands.iter().for_each(|command| {
+            let queued = match command {
+                RedisCommand::Set(key, value) => pipeline.cmd("SET").arg(key).arg(value),
+                RedisCommand::SetEx(key, value, seconds) => pipeline
+                    .cmd("SET")
+                    .arg(key)
+                    .arg(value)
+                    .arg("EX")
+                    .arg(*seconds),
+                RedisCommand::Del(key) => pipeline.cmd("DEL").arg(key),
+                RedisCommand::ZAdd(key, score, member) => {
+                    pipeline.cmd("ZADD").arg(key).arg(*score).arg(member)
+                }
+                RedisCommand::ZRem(key, member) => pipeline.cmd("ZREM").arg(key).arg(member),
+                RedisCommand::SAdd(key, member) => pipeline.cmd("SADD").arg(key).arg(member),
+                RedisCommand::SRem(key, member) => pipeline.cmd("SREM").arg(key).arg(member),
+            };
+            queued.ignore();
+        });
+
+        let mut connection = self
+            .get_connection()
+            .map_err(|_| LiteSessionError::RedisCommandFailed)?;
+        pipeline
+            .query(&mut connection)
+            .map_err(|_| LiteSessionError::RedisCommandFailed)
+    }
+
+    fn eval(
+        &self,
+        script: &str,
+        keys: &[String],
+        args: &[String],
+    ) -> Result<i64, LiteSessionError> {
+        query(
+            self,
+            ::redis::cmd("EVAL")
+                .arg(script)
+                .arg(keys.len())
+                .arg(keys)
+                .arg(args),
+        )
+    }
+}
+
+fn query<T: ::redis::FromRedisValue>(
+    client: &::redis::Client,
+    command: &::redis::Cmd,
+) -> Result<T, LiteSessionError> {
+    let mut connection = client
+        .get_connection()
+        .map_err(|_| LiteSessionError::RedisCommandFailed)?;
+
+    command
+        .query(&mut connection)
+        .map_err(|_| LiteSessionError::RedisCommandFailed)
+}
+
+/// A `RevocationStore` shared by every server through Redis. Each revocation is kept under
+/// its own key expiring together with its token, while a sorted set ordered by sequence
+/// number serves `export()`. A token is treated as revoked when Redis cannot be reached.
+/// `try_add()` and `try_export()` return the errors the `RevocationStore` methods
+/// cannot report
+///
+/// ```
+/// pub struct RedisRevocationStore<C> {
+///     connection: C,
+///     prefix: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RedisRevocationStore<C> {
+    connection: C,
+    prefix: String,
+}
+
+impl<C: RedisConnection> RedisRevocationStore<C> {
+    /// Create a store on `connection` with its keys prefixed by `lite-session:`
+    pub fn new(connection: C) -> Self {
+        Self {
+            connection,
+            prefix: "lite-session:".into(),
+        }
+    }
+    /// Prefix the keys of the store with `prefix` to share a Redis database with other data
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.prefix = prefix.into();
+
+        self
+    }
+    /// Get the connection of the store
+    pub fn get_connection(&self) -> &C {
+        &self.connection
+    }
+    /// Get the prefix of the keys of the store
+    pub fn get_prefix(&self) -> &String {
+        &self.prefix
+    }
+    /// Remove the revocations whose tokens have already expired from the sorted set
+    /// used by `export()`. Their own keys already expired in Redis
+    pub fn prune(&self) -> Result<(), LiteSessionError> {
+        let now = TAI64N::now();
+        for (member, _) in self.connection.zrangebyscore(&self.index(), 0)? {
+            match RedisRevocationStore::<C>::entry(&member) {
+                Some((_, expiry)) if expiry > now => (),
+                _ => self.connection.zrem(&self.index(), &member)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Revoke `identifier` like `revoke()`, returning the error `revoke()` ignores
+    pub fn try_add(&self, identifier: &str, expiry: TAI64N) -> Result<(), LiteSessionError> {
+        let sequence = self.connection.incr(&self.sequence_key())?;
+        let mut commands = Vec::new();
+        self.add(identifier, expiry, sequence, &mut commands);
+
+        self.connection.multi_exec(&commands)
+    }
+    /// Get the revocations added after `since` like `export()`, returning the error
+    /// `export()` turns into an empty delta
+    pub fn try_export(&self, since: u64) -> Result<RevocationSnapshot, LiteSessionError> {
+        let horizon = TAI64N::now();
+        let sequence = self.sequence()?;
+        let entries = self
+            .connection
+            .zrangebyscore(&self.index(), since + 1)?
+            .iter()
+            .filter_map(|(member, _)| RedisRevocationStore::<C>::entry(member))
+            .filter(|(_, expiry)| *expiry > horizon)
+            .collect();
+
+        Ok(RevocationSnapshot::new(since, sequence, horizon, entries))
+    }
+    /// Import `snapshot`, the same as `import()`.
+    /// A full snapshot replaces the revocations in a single `MULTI`/`EXEC` so verifiers
+    /// never see the store emptied
+    pub fn try_import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError> {
+        if snapshot.get_since() > self.sequence()? {
+            return Err(LiteSessionError::RevocationSnapshotGap);
+        }
+
+        let mut commands = Vec::new();
+        if !snapshot.is_delta() {
+            for (member, _) in self.connection.zrangebyscore(&self.index(), 0)? {
+                if let Some((identifier, _)) = RedisRevocationStore::<C>::entry(&member) {
+                    commands.push(RedisCommand::Del(self.revoked(&identifier)));
+                }
+                commands.push(RedisCommand::ZRem(self.index(), member));
+            }
+        }
+
+        for (identifier, expiry) in snapshot.get_entries() {
+            self.add(identifier, *expiry, snapshot.get_sequence(), &mut commands);
+        }
+        if snapshot.get_sequence() > self.sequence()? {
+            commands.push(RedisCommand::Set(
+                self.sequence_key(),
+                snapshot.get_sequence().to_string(),
+            ));
+        }
+
+        self.connection.multi_exec(&commands)
+    }
+
+    // Queue the key of the revocation and its entry in the sorted set
+    fn add(
+        &self,
+        identifier: &str,
+        expiry: TAI64N,
+        sequence: u64,
+        commands: &mut Vec<RedisCommand>,
+    ) {
+        let seconds = match expiry.duration_since(&TAI64N::now()) {
+            Ok(remaining) => remaining.as_secs().max(1),
+            Err(_) => return,
+        };
+        let expiry = hex::encode(expiry.to_bytes());
+
+        commands.push(RedisCommand::SetEx(
+            self.revoked(identifier),
+            expiry.clone(),
+            seconds,
+        ));
+        commands.push(RedisCommand::ZAdd(
+            self.index(),
+            sequence,
+            format!("{} {}", identifier, expiry),
+        ));
+    }
+
+    fn add_new(&self, identifier: &str, expiry: TAI64N) -> Result<bool, LiteSessionError> {
+        let seconds = match expiry.duration_since(&TAI64N::now()) {
+            Ok(remaining) => remaining.as_secs().max(1),
+            Err(_) => return Ok(false),
+        };
+        let expiry = hex::encode(expiry.to_bytes());
+
+        let claimed = self.connection.eval(
+            CLAIM_REVOCATION,
+            &[self.revoked(identifier), self.sequence_key(), self.index()],
+            &[
+                expiry.clone(),
+                seconds.to_string(),
+                format!("{} {}", identifier, expiry),
+            ],
+        )?;
+
+        Ok(claimed == 1)
+    }
+
+    fn sequence(&self) -> Result<u64, LiteSessionError> {
+        match self.connection.get(&self.sequence_key())? {
+            Some(sequence) => match sequence.parse::<u64>() {
+                Ok(sequence) => Ok(sequence),
+                Err(_) => Err(LiteSessionError::RedisCommandFailed),
+            },
+            None => Ok(0),
+        }
+    }
+
+    fn entry(member: &str) -> Option<(String, TAI64N)> {
+        let mut parts = member.rsplitn(2, ' ');
+        let expiry = hex::decode(parts.next()?).ok()?;
+        let expiry = TAI64N::from_slice(&expiry).ok()?;
+
+        Some((parts.next()?.to_owned(), expiry))
+    }
+
+    fn revoked(&self, identifier: &str) -> String {
+        format!("{}revoked:{}", self.prefix, identifier)
+    }
+
+    fn index(&self) -> String {
+        format!("{}revocations", self.prefix)
+    }
+
+    fn sequence_key(&self) -> String {
+        format!("{}revocation-sequence", self.prefix)
+    }
+}
+
+impl<C: RedisConnection> RevocationStore for RedisRevocationStore<C> {
+    fn revoke(&self, identifier: &str, expiry: TAI64N) {
+        // The error is returned by `try_add()`
+        let _ = self.try_add(identifier, expiry);
+    }
+
+    fn try_revoke(&self, identifier: &str, expiry: TAI64N) -> bool {
+        self.add_new(identifier, expiry).unwrap_or(false)
+    }
+
+    fn is_revoked(&self, identifier: &str) -> bool {
+        // Fail closed, an unreachable store must not let revoked tokens through
+        match self.connection.get(&self.revoked(identifier)) {
+            Ok(revoked) => revoked.is_some(),
+            Err(_) => true,
+        }
+    }
+
+    fn export(&self, since: u64) -> RevocationSnapshot {
+        match self.try_export(since) {
+            Ok(snapshot) => snapshot,
+            // An empty delta, an empty full snapshot would clear the revocations of the importer
+            Err(_) => RevocationSnapshot::new(since.max(1), since, TAI64N::now(), Vec::default()),
+        }
+    }
+
+    fn import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError> {
+        self.try_import(snapshot)
+    }
+}
+
+/// A `SessionStore` shared by every server through Redis. Each record is kept under its own
+/// key expiring together with the session, and a set per subject lists their session IDs
+/// for `sessions_of()`. A session is treated as missing when Redis cannot be reached.
+/// The `try_` methods return the errors the `SessionStore` methods cannot report
+///
+/// ```
+/// pub struct RedisSessionStore<C> {
+///     connection: C,
+///     prefix: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RedisSessionStore<C> {
+    connection: C,
+    prefix: String,
+}
+
+impl<C: RedisConnection> RedisSessionStore<C> {
+    /// Create a store on `connection` with its keys prefixed by `lite-session:`
+    pub fn new(connection: C) -> Self {
+        Self {
+            connection,
+            prefix: "lite-session:".into(),
+        }
+    }
+    /// Prefix the keys of the store with `prefix` to share a Redis database with other data
+    pub fn prefix(&mut self, prefix: &str) -> &mut Self {
+        self.prefix = prefix.into();
+
+        self
+    }
+    /// Get the connection of the store
+    pub fn get_connection(&self) -> &C {
+        &self.connection
+    }
+    /// Get the prefix of the keys of the store
+    pub fn get_prefix(&self) -> &String {
+        &self.prefix
+    }
+
+    /// Get the record of `session_id` like `get()`, returning the error `get()` treats
+    /// as a missing session
+    pub fn try_get(&self, session_id: &str) -> Result<Option<SessionRecord>, LiteSessionError> {
+        match self.connection.get(&self.session(session_id))? {
+            Some(line) => match SessionRecord::from_string(&line)? {
+                (stored, record) if stored == session_id => Ok(Some(record)),
+                _ => Err(LiteSessionError::InvalidSessionRecord),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Store the record of `session_id` like `put()`, returning the error `put()` ignores.
+    /// The record and the set of its subject are updated in a single `MULTI`/`EXEC`
+    pub fn try_put(
+        &self,
+        session_id: &str,
+        record: &SessionRecord,
+    ) -> Result<(), LiteSessionError> {
+        let mut commands = Vec::new();
+        if let Some(previous) = self.try_get(session_id)? {
+            commands.push(RedisCommand::SRem(
+                self.subject(previous.get_subject()),
+                session_id.into(),
+            ));
+        }
+
+        match record.get_expiry().duration_since(&TAI64N::now()) {
+            Ok(remaining) => {
+                commands.push(RedisCommand::SetEx(
+                    self.session(session_id),
+                    SessionRecord::to_string(session_id, record),
+                    remaining.as_secs().max(1),
+                ));
+                commands.push(RedisCommand::SAdd(
+                    self.subject(record.get_subject()),
+                    session_id.into(),
+                ));
+            }
+            Err(_) => commands.push(RedisCommand::Del(self.session(session_id))),
+        }
+
+        self.connection.multi_exec(&commands)
+    }
+    /// Delete the record of `session_id` like `delete()`, returning the error `delete()`
+    /// treats as a missing session. The record and the set of its subject are updated in
+    /// a single `MULTI`/`EXEC`
+    pub fn try_delete(&self, session_id: &str) -> Result<Option<SessionRecord>, LiteSessionError> {
+        let record = self.try_get(session_id)?;
+        if let Some(record) = &record {
+            self.connection.multi_exec(&[
+                RedisCommand::Del(self.session(session_id)),
+                RedisCommand::SRem(self.subject(record.get_subject()), session_id.into()),
+            ])?;
+        }
+
+        Ok(record)
+    }
+
+    /// Get the sessions of `subject` like `sessions_of()`, returning the error
+    /// `sessions_of()` treats as no sessions
+    pub fn try_sessions_of(
+        &self,
+        subject: &str,
+    ) -> Result<Vec<(String, SessionRecord)>, LiteSessionError> {
+        let mut sessions = Vec::new();
+        for session_id in self.connection.smembers(&self.subject(subject))? {
+            // The record expired in Redis while the set of the subject still lists it
+            match self.try_get(&session_id)? {
+                Some(record) if record.get_subject() == subject => {
+                    sessions.push((session_id, record))
+                }
+                _ => self.connection.srem(&self.subject(subject), &session_id)?,
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    fn session(&self, session_id: &str) -> String {
+        format!("{}session:{}", self.prefix, session_id)
+    }
+
+    fn subject(&self, subject: &str) -> String {
+        format!("{}subject:{}", self.prefix, subject)
+    }
+}
+
+impl<C: RedisConnection> SessionStore for RedisSessionStore<C> {
+    fn get(&self, session_id: &str) -> Option<SessionRecord> {
+        self.try_get(session_id).unwrap_or(None)
+    }
+
+    fn put(&self, session_id: &str, record: SessionRecord) {
+        // The error is returned by `try_put()`
+        let _ = self.try_put(session_id, &record);
+    }
+
+    fn delete(&self, session_id: &str) -> Option<SessionRecord> {
+        self.try_delete(session_id).unwrap_or(None)
+    }
+
+    fn sessions_of(&self, subject: &str) -> Vec<(String, SessionRecord)> {
+        self.try_sessions_of(subject).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod redis_tests {
+    use super::{
+        RedisCommand, RedisConnection, RedisRevocationStore, RedisSessionStore, CLAIM_REVOCATION,
+    };
+    use crate::{
+        LiteSessionError, MemoryRevocationStore, RevocationStore, SessionRecord, SessionStore,
+    };
+    use core::time::Duration;
+    use std::{
+        collections::{BTreeMap, HashMap},
+        sync::Mutex,
+    };
+    use tai64::TAI64N;
+
+    // Keeps every key in memory and records the TTL it was set with
+    #[derive(Debug, Default)]
+    struct MemoryRedis {
+        strings: Mutex<HashMap<String, (String, Option<u64>)>>,
+        sorted: Mutex<HashMap<String, BTreeMap<String, u64>>>,
+        sets: Mutex<HashMap<String, Vec<String>>>,
+        down: Mutex<bool>,
+        aborting: Mutex<bool>,
+    }
+
+    impl MemoryRedis {
+        fn ttl(&self, key: &str) -> Option<u64> {
+            self.strings
+                .lock()
+                .unwrap()
+                .get(key)
+                .and_then(|(_, ttl)| *ttl)
+        }
+
+        fn check(&self) -> Result<(), LiteSessionError> {
+            match *self.down.lock().unwrap() {
+                true => Err(LiteSessionError::RedisCommandFailed),
+                false => Ok(()),
+            }
+        }
+    }
+
+    impl RedisConnection for MemoryRedis {
+        fn get(&self, key: &str) -> Result<Option<String>, LiteSessionError> {
+            self.check()?;
+            Ok(self
+                .strings
+                .lock()
+                .unwrap()
+                .get(key)
+                .map(|(value, _)| value.clone()))
+        }
+
+        fn set(&self, key: &str, value: &str) -> Result<(), LiteSessionError> {
+            self.check()?;
+            self.strings
+                .lock()
+                .unwrap()
+                .insert(key.into(), (value.into(), None));
+            Ok(())
+        }
+
+        fn set_ex(&self, key: &str, value: &str, seconds: u64) -> Result<(), LiteSessionError> {
+            self.check()?;
+            self.strings
+                .lock()
+                .unwrap()
+                .insert(key.into(), (value.into(), Some(seconds)));
+            Ok(())
+        }
+
+        fn del(&self, key: &str) -> Result<(), LiteSessionError> {
+            self.check()?;
+            self.strings.lock().unwrap().remove(key);
+            Ok(())
+        }
+
+        fn incr(&self, key: &str) -> Result<u64, LiteSessionError> {
+            let next = match self.get(key)? {
+                Some(value) => value.parse::<u64>().unwrap() + 1,
+                None => 1,
+            };
+            self.set(key, &next.to_string())?;
+            Ok(next)
+        }
+
+        fn zadd(&self, key: &str, score: u64, member: &str) -> Result<(), LiteSessionError> {
+            self.check()?;
+            self.sorted
+                .lock()
+                .unwrap()
+                .entry(key.into())
+                .or_default()
+                .insert(member.into(), score);
+            Ok(())
+        }
+
+        fn zrangebyscore(
+            &self,
+            key: &str,
+            min: u64,
+        ) -> Result<Vec<(String, u64)>, LiteSessionError> {
+            self.check()?;
+            let mut members = self
+                .sorted
+                .lock()
+                .unwrap()
+                .get(key)
+                .map(|members| {
+                    members
+                        .iter()
+                        .filter(|(_, score)| **score >= min)
+                        .map(|(member, score)| (member.clone(), *score))
+                        .collect::<Vec<(String, u64)>>()
+                })
+                .unwrap_or_default();
+            members.sort_by_key(|(_, score)| *score);
+            Ok(members)
+        }
+
+        fn zrem(&self, key: &str, member: &str) -> Result<(), LiteSessionError> {
+            self.check()?;
+            if let Some(members) = self.sorted.lock().unwrap().get_mut(key) {
+                members.remove(member);
+            }
+            Ok(())
+        }
+
+        fn sadd(&self, key: &str, member: &str) -> Result<(), LiteSessionError> {
+            self.check()?;
+            let mut sets = self.sets.lock().unwrap();
+            let members = sets.entry(key.into()).or_default();
+            if !members.iter().any(|existing| existing == member) {
+                members.push(member.into());
+            }
+            Ok(())
+        }
+
+        fn srem(&self, key: &str, member: &str) -> Result<(), LiteSessionError> {
+            self.check()?;
+            if let Some(members) = self.sets.lock().unwrap().get_mut(key) {
+                members.retain(|existing| existing != member);
+            }
+            Ok(())
+        }
+
+        fn smembers(&self, key: &str) -> Result<Vec<String>, LiteSessionError> {
+            self.check()?;
+            Ok(self
+                .sets
+                .lock()
+                .unwrap()
+                .get(key)
+                .cloned()
+                .unwrap_or_default())
+        }
+
+        // An aborted transaction applies none of its commands
+        fn multi_exec(&self, commands: &[RedisCommand]) -> Result<(), LiteSessionError> {
+            self.check()?;
+            if *self.aborting.lock().unwrap() {
+                return Err(LiteSessionError::RedisCommandFailed);
+            }
+
+            commands.iter().try_for_each(|command| match command {
+                RedisCommand::Set(key, value) => self.set(key, value),
+                RedisCommand::SetEx(key, value, seconds) => self.set_ex(key, value, *seconds),
+                RedisCommand::Del(key) => self.del(key),
+                RedisCommand::ZAdd(key, score, member) => self.zadd(key, *score, member),
+                RedisCommand::ZRem(key, member) => self.zrem(key, member),
+                RedisCommand::SAdd(key, member) => self.sadd(key, member),
+                RedisCommand::SRem(key, member) => self.srem(key, member),
+            })
+        }
+
+        // Runs the only script of the stores, the tests are single threaded
+        fn eval(
+            &self,
+            script: &str,
+            keys: &[String],
+            args: &[String],
+        ) -> Result<i64, LiteSessionError> {
+            self.check()?;
+            if script != CLAIM_REVOCATION {
+                return Err(LiteSessionError::RedisCommandFailed);
+            }
+            if self.get(&keys[0])?.is_some() {
+                return Ok(0);
+            }
+            self.set_ex(&keys[0], &args[0], args[1].parse().unwrap())?;
+            let sequence = self.incr(&keys[1])?;
+            self.zadd(&keys[2], sequence, &args[2])?;
+            Ok(1)
+        }
+    }
+
+    #[test]
+    fn redis_revocations() {
+        let expiry = TAI64N::now() + Duration::from_secs(600);
+        let store = RedisRevocationStore::new(MemoryRedis::default());
+        store.revoke("foo", expiry);
+        store.revoke("bar", expiry);
+        store.revoke("stale", TAI64N::now());
+        assert!(store.is_revoked("foo"));
+        assert!(!store.is_revoked("stale"));
+        assert!(!store.is_revoked("baz"));
+        let ttl = store
+            .get_connection()
+            .ttl("lite-session:revoked:foo")
+            .unwrap();
+        assert!(ttl > 590 && ttl <= 600);
+
+        let delta = store.export(1);
+        assert_eq!(delta.get_sequence(), 3);
+        assert_eq!(delta.get_entries(), &vec![("bar".to_owned(), expiry)]);
+
+        let memory = MemoryRevocationStore::new();
+        assert!(memory.import(&store.export(0)).is_ok());
+        assert!(memory.is_revoked("foo") && memory.is_revoked("bar"));
+
+        let mut replica = RedisRevocationStore::new(MemoryRedis::default());
+        replica.prefix("replica:");
+        replica.revoke("old", expiry);
+        assert!(replica.import(&store.export(0)).is_ok());
+        assert!(replica.is_revoked("foo"));
+        assert!(!replica.is_revoked("old"));
+        assert_eq!(replica.export(0).get_sequence(), 3);
+        assert_eq!(
+            replica.import(&store.export(4)),
+            Err(LiteSessionError::RevocationSnapshotGap)
+        );
+        assert!(replica.prune().is_ok());
+
+        // A failed full import leaves the revocations as they were
+        assert!(replica.try_add("new", expiry).is_ok());
+        *replica.get_connection().aborting.lock().unwrap() = true;
+        assert_eq!(
+            replica.try_import(&memory.export(0)),
+            Err(LiteSessionError::RedisCommandFailed)
+        );
+        assert!(replica.is_revoked("new") && replica.is_revoked("foo"));
+
+        assert!(store.try_revoke("baz", expiry));
+        assert!(!store.try_revoke("baz", expiry));
+        assert!(!store.try_revoke("foo", expiry));
+        assert_eq!(
+            store.export(3).get_entries(),
+            &vec![("baz".to_owned(), expiry)]
+        );
+
+        *store.get_connection().down.lock().unwrap() = true;
+        assert_eq!(
+            store.try_add("qux", expiry),
+            Err(LiteSessionError::RedisCommandFailed)
+        );
+        assert!(store.try_export(0).is_err());
+        assert!(store.is_revoked("baz"));
+        assert!(!store.try_revoke("qux", expiry));
+        assert!(store.export(0).is_delta());
+    }
+
+    #[test]
+    fn redis_sessions() {
+        let expiry = TAI64N::now() + Duration::from_secs(600);
+        let mut record = SessionRecord::new("abc", TAI64N::now(), expiry);
+        record.subject("foo_user");
+
+        let store = RedisSessionStore::new(MemoryRedis::default());
+        store.put("foo", record.clone());
+        store.put(
+            "stale",
+            SessionRecord::new("def", TAI64N::now(), TAI64N::now()),
+        );
+        assert_eq!(store.get("foo"), Some(record.clone()));
+        assert_eq!(store.get("stale"), None);
+        let ttl = store
+            .get_connection()
+            .ttl("lite-session:session:foo")
+            .unwrap();
+        assert!(ttl > 590 && ttl <= 600);
+        assert_eq!(
+            store.sessions_of("foo_user"),
+            vec![("foo".to_owned(), record.clone())]
+        );
+
+        let mut moved = record.clone();
+        moved.subject("bar_user");
+        store.put("foo", moved.clone());
+        assert!(store.sessions_of("foo_user").is_empty());
+
+        assert_eq!(store.delete("foo"), Some(moved));
+        assert_eq!(store.get("foo"), None);
+        assert!(store.sessions_of("bar_user").is_empty());
+
+        *store.get_connection().aborting.lock().unwrap() = true;
+        assert_eq!(
+            store.try_put("foo", &record),
+            Err(LiteSessionError::RedisCommandFailed)
+        );
+        assert_eq!(store.get("foo"), None);
+        assert!(store.sessions_of("foo_user").is_empty());
+
+        // A failed delete leaves the record listed under its subject
+        *store.get_connection().aborting.lock().unwrap() = false;
+        store.put("foo", record.clone());
+        *store.get_connection().aborting.lock().unwrap() = true;
+        assert_eq!(
+            store.try_delete("foo"),
+            Err(LiteSessionError::RedisCommandFailed)
+        );
+        assert_eq!(
+            store.sessions_of("foo_user"),
+            vec![("foo".to_owned(), record)]
+        );
+    }
+
+    #[test]
+    fn redis_client() {
+        // Nothing listens on port 1, the stores fail closed
+        let client = ::redis::Client::open("redis://127.0.0.1:1/").unwrap();
+        assert_eq!(
+            client.get("lite-session:revoked:foo"),
+            Err(LiteSessionError::RedisCommandFailed)
+        );
+        let store = RedisRevocationStore::new(client);
+        let expiry = TAI64N::now() + Duration::from_secs(600);
+        assert!(store.is_revoked("foo"));
+        assert!(!store.try_revoke("foo", expiry));
+        assert_eq!(
+            store.try_add("foo", expiry),
+            Err(LiteSessionError::RedisCommandFailed)
+        );
+    }
+}
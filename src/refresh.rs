@@ -0,0 +1,240 @@
+use crate::{LiteSessionError, LiteSessionToken, ServerKey};
+
+/// Where a replacement token is attached on the response
+#[derive(Debug)]
+pub enum RefreshDelivery {
+    /// Attach the replacement token in a response header with the provided name
+    Header(String),
+    /// Replace the session cookie with the provided name using a `Set-Cookie` header
+    Cookie(String),
+}
+
+impl Default for RefreshDelivery {
+    fn default() -> Self {
+        RefreshDelivery::Header("X-Refreshed-Token".into())
+    }
+}
+
+impl core::cmp::PartialEq for RefreshDelivery {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RefreshDelivery::Header(name), RefreshDelivery::Header(name2))
+            | (RefreshDelivery::Cookie(name), RefreshDelivery::Cookie(name2)) => name == name2,
+            _ => false,
+        }
+    }
+}
+
+impl core::clone::Clone for RefreshDelivery {
+    fn clone(&self) -> Self {
+        match self {
+            RefreshDelivery::Header(name) => RefreshDelivery::Header(name.clone()),
+            RefreshDelivery::Cookie(name) => RefreshDelivery::Cookie(name.clone()),
+        }
+    }
+}
+
+/// A response header carrying a replacement token
+///
+/// ```
+/// pub struct RefreshHeader {
+///     name: String,
+///     value: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RefreshHeader {
+    name: String,
+    value: String,
+}
+
+impl core::cmp::PartialEq for RefreshHeader {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.value == other.value
+    }
+}
+
+impl RefreshHeader {
+    /// Get the name of the header
+    pub fn get_name(&self) -> &String {
+        &self.name
+    }
+    /// Get the value of the header
+    pub fn get_value(&self) -> &String {
+        &self.value
+    }
+}
+
+/// Decides when middleware re-issues a verified token that is close to expiring
+/// and how the replacement token is attached to the response
+///
+/// ```
+/// use lite_session::RefreshDelivery;
+///
+/// pub struct RefreshPolicy {
+///     delivery: RefreshDelivery,
+///     threshold: u8,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RefreshPolicy {
+    delivery: RefreshDelivery,
+    threshold: u8,
+}
+
+impl Default for RefreshPolicy {
+    fn default() -> Self {
+        Self {
+            delivery: RefreshDelivery::default(),
+            threshold: 20,
+        }
+    }
+}
+
+impl core::cmp::PartialEq for RefreshPolicy {
+    fn eq(&self, other: &Self) -> bool {
+        self.delivery == other.delivery && self.threshold == other.threshold
+    }
+}
+
+impl core::clone::Clone for RefreshPolicy {
+    fn clone(&self) -> Self {
+        Self {
+            delivery: self.delivery.clone(),
+            threshold: self.threshold,
+        }
+    }
+}
+
+impl RefreshPolicy {
+    /// Choose where the replacement token is attached. The default is the `X-Refreshed-Token` header
+    pub fn delivery(&mut self, delivery: RefreshDelivery) -> &mut Self {
+        self.delivery = delivery;
+
+        self
+    }
+    /// Re-issue tokens when less than `percent` of their lifetime remains. The default is `20`
+    pub fn threshold(&mut self, percent: u8) -> &mut Self {
+        self.threshold = percent.min(100);
+
+        self
+    }
+    /// Get where the replacement token is attached
+    pub fn get_delivery(&self) -> &RefreshDelivery {
+        &self.delivery
+    }
+    /// Get the percentage of the lifetime below which tokens are re-issued
+    pub fn get_threshold(&self) -> u8 {
+        self.threshold
+    }
+    /// Check whether the remaining lifetime of a verified token has dropped below the threshold
+    pub fn should_refresh(&self, token: &LiteSessionToken) -> bool {
+        let lifetime = match token.get_expiry().duration_since(token.get_issued()) {
+            Ok(lifetime) => lifetime.as_secs(),
+            Err(_) => return false,
+        };
+        let remaining = match token.get_expiry().duration_since(&token.get_clock().now()) {
+            Ok(remaining) => remaining.as_secs(),
+            Err(_) => 0,
+        };
+
+        u128::from(remaining) * 100 < u128::from(lifetime) * u128::from(self.threshold)
+    }
+    /// Re-issue a verified token with the same lifetime if it should be refreshed, returning
+    /// the response header carrying the replacement token
+    pub fn reissue(
+        &self,
+        token: &mut LiteSessionToken,
+        server_key: &ServerKey,
+    ) -> Result<Option<RefreshHeader>, LiteSessionError> {
+        if !self.should_refresh(token) {
+            return Ok(None);
+        }
+
+        let lifetime = token
+            .get_expiry()
+            .duration_since(token.get_issued())
+            .unwrap_or_default()
+            .as_secs();
+        let replacement = token.renew(server_key, lifetime)?;
+
+        Ok(Some(self.header(&replacement, lifetime)))
+    }
+    /// Build the response header carrying `token` which is valid for `max_age_in_secs`
+    pub fn header(&self, token: &str, max_age_in_secs: u64) -> RefreshHeader {
+        match &self.delivery {
+            RefreshDelivery::Header(name) => RefreshHeader {
+                name: name.clone(),
+                value: token.into(),
+            },
+            RefreshDelivery::Cookie(name) => RefreshHeader {
+                name: "Set-Cookie".into(),
+                value: format!(
+                    "{}={}; Max-Age={}; Path=/; HttpOnly; Secure; SameSite=Strict",
+                    name,
+                    RefreshPolicy::cookie_value(token),
+                    max_age_in_secs
+                ),
+            },
+        }
+    }
+
+    // Percent-encode the bytes that are not allowed in a cookie value
+    fn cookie_value(token: &str) -> String {
+        let mut encoded = String::with_capacity(token.len());
+        token.bytes().for_each(|byte| match byte {
+            0x21 | 0x23..=0x2B | 0x2D..=0x3A | 0x3C..=0x5B | 0x5D..=0x7E if byte != b'%' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        });
+
+        encoded
+    }
+}
+
+#[cfg(test)]
+mod refresh_tests {
+    use super::{RefreshDelivery, RefreshPolicy};
+    use crate::{LiteSessionData, LiteSessionError, LiteSessionToken, ServerKey, TokenOutcome};
+    use core::time::Duration;
+    use tai64::TAI64N;
+
+    #[test]
+    fn refresh_policy() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut fresh = LiteSessionToken::default();
+        fresh.hmac_data(data.clone());
+        fresh.expiry(3600);
+
+        let mut policy = RefreshPolicy::default();
+        assert!(!policy.should_refresh(&fresh));
+        assert_eq!(policy.reissue(&mut fresh, &server_key)?, None);
+
+        let mut stale = LiteSessionToken::default();
+        stale.hmac_data(data);
+        stale.reissue_at(TAI64N::now() - Duration::from_secs(3500), 3600);
+        assert!(policy.should_refresh(&stale));
+
+        let header = policy.reissue(&mut stale, &server_key)?.unwrap();
+        assert_eq!(header.get_name(), "X-Refreshed-Token");
+        assert!(!policy.should_refresh(&stale));
+
+        let mut verifier = LiteSessionToken::default();
+        let (outcome, _) = verifier.from_string(&server_key, header.get_value())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        policy.delivery(RefreshDelivery::Cookie("session".into()));
+        let header = policy.header("a⊕b", 60);
+        assert_eq!(header.get_name(), "Set-Cookie");
+        assert_eq!(
+            header.get_value(),
+            "session=a%E2%8A%95b; Max-Age=60; Path=/; HttpOnly; Secure; SameSite=Strict"
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{LiteSessionError, TokenOutcome};
+
+/// The reason a token was not accepted.
+/// This allows applications to respond differently to each failure, for example
+/// silently refreshing an expired session while raising a security alert on a forged token
+#[derive(Debug)]
+pub enum RejectionReason {
+    /// The MAC of the token does not match the MAC computed using the server key
+    MacMismatch,
+    /// The session held by the token has expired
+    Expired,
+    /// The token has been revoked by the server
+    Revoked,
+    /// The token is authentic but a verification policy failed
+    PolicyFailed {
+        /// The name of the policy that failed
+        which: String,
+    },
+    /// A field of the token cannot be decoded
+    Malformed {
+        /// The name of the field that cannot be decoded
+        field: &'static str,
+    },
+}
+
+impl core::cmp::PartialEq for RejectionReason {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (RejectionReason::MacMismatch, RejectionReason::MacMismatch)
+            | (RejectionReason::Expired, RejectionReason::Expired)
+            | (RejectionReason::Revoked, RejectionReason::Revoked) => true,
+            (
+                RejectionReason::PolicyFailed { which },
+                RejectionReason::PolicyFailed { which: which2 },
+            ) => which == which2,
+            (
+                RejectionReason::Malformed { field },
+                RejectionReason::Malformed { field: field2 },
+            ) => field == field2,
+            _ => false,
+        }
+    }
+}
+
+impl core::clone::Clone for RejectionReason {
+    fn clone(&self) -> Self {
+        match self {
+            RejectionReason::MacMismatch => RejectionReason::MacMismatch,
+            RejectionReason::Expired => RejectionReason::Expired,
+            RejectionReason::Revoked => RejectionReason::Revoked,
+            RejectionReason::PolicyFailed { which } => RejectionReason::PolicyFailed {
+                which: which.clone(),
+            },
+            RejectionReason::Malformed { field } => RejectionReason::Malformed { field },
+        }
+    }
+}
+
+impl RejectionReason {
+    /// Get the reason behind a non-authentic `TokenOutcome`.
+    /// Returns `None` for `TokenOutcome::TokenAuthentic` and `TokenOutcome::TokenAuthorized`
+    pub fn from_outcome(outcome: &TokenOutcome) -> Option<Self> {
+        match outcome {
+            TokenOutcome::TokenAuthentic | TokenOutcome::TokenAuthorized => None,
+            TokenOutcome::TokenRejected => Some(RejectionReason::MacMismatch),
+            TokenOutcome::TokenRevoked => Some(RejectionReason::Revoked),
+            TokenOutcome::BadToken => Some(RejectionReason::Malformed { field: "token" }),
+            TokenOutcome::SessionExpired | TokenOutcome::ExpiredWithinGrace => {
+                Some(RejectionReason::Expired)
+            }
+            TokenOutcome::QuotaExceeded => Some(RejectionReason::PolicyFailed {
+                which: "quota".into(),
+            }),
+            TokenOutcome::StepUpRequired => Some(RejectionReason::PolicyFailed {
+                which: "auth_level".into(),
+            }),
+        }
+    }
+    /// Get the reason behind an error returned while destructuring a token.
+    /// Returns `None` for errors caused by the configuration of the server rather than the token
+    pub fn from_error(error: &LiteSessionError) -> Option<Self> {
+        match error {
+            LiteSessionError::ServerKeyLengthError
+            | LiteSessionError::InvalidRevocationSnapshot
+            | LiteSessionError::InvalidIssuerConfig
+            | LiteSessionError::InvalidKeyId
+            | LiteSessionError::InvalidTenantId
+            | LiteSessionError::ConfigIoError
+            | LiteSessionError::RevocationIoError
+            | LiteSessionError::InvalidSessionRecord
+            | LiteSessionError::SessionStoreIoError
+            | LiteSessionError::InvalidNetworkBinding
+            | LiteSessionError::RedisCommandFailed
+            | LiteSessionError::SqlStatementFailed
+            | LiteSessionError::RevocationSnapshotGap
+            | LiteSessionError::ReplayGuardFull => None,
+            LiteSessionError::TokenSizeTooLarge
+            | LiteSessionError::TokenFieldsLengthError
+            | LiteSessionError::InvalidBase64String
+            | LiteSessionError::InvalidTokenFormat
+            | LiteSessionError::InvalidJwt
+            | LiteSessionError::InvalidCwt
+            | LiteSessionError::InvalidTokenChunk
+            | LiteSessionError::InvalidTokenEncoding
+            | LiteSessionError::UnavailableCipher
+            | LiteSessionError::TokenNotAuthentic => {
+                Some(RejectionReason::Malformed { field: "token" })
+            }
+            LiteSessionError::CompressionError | LiteSessionError::MissingWrappedKey => {
+                Some(RejectionReason::Malformed {
+                    field: "ciphertext",
+                })
+            }
+            LiteSessionError::InvalidProof => Some(RejectionReason::Malformed { field: "proof" }),
+            LiteSessionError::InvalidAttenuation => {
+                Some(RejectionReason::Malformed { field: "caveat" })
+            }
+            LiteSessionError::NonceLengthError => {
+                Some(RejectionReason::Malformed { field: "nonce" })
+            }
+            LiteSessionError::InvalidTai64NTime | LiteSessionError::TimestampOutOfRange => {
+                Some(RejectionReason::Malformed { field: "time" })
+            }
+            LiteSessionError::InvalidBytesForBlake3 => {
+                Some(RejectionReason::Malformed { field: "hmac" })
+            }
+            LiteSessionError::InvalidHexString => Some(RejectionReason::Malformed { field: "hex" }),
+            LiteSessionError::UnsupportedTokenVersion | LiteSessionError::UnknownKeyId => {
+                Some(RejectionReason::Malformed { field: "version" })
+            }
+            LiteSessionError::InvalidConfidentialityMode => Some(RejectionReason::Malformed {
+                field: "confidentiality",
+            }),
+            LiteSessionError::DataFieldsLengthError
+            | LiteSessionError::FromUtf8TokenError
+            | LiteSessionError::InvalidClaim
+            | LiteSessionError::InvalidFieldEscape
+            | LiteSessionError::DataSizeTooLarge => {
+                Some(RejectionReason::Malformed { field: "data" })
+            }
+        }
+    }
+    /// Check whether the rejection indicates tampering or misuse that warrants a security alert
+    /// rather than a routine re-authentication like a silent refresh of an expired session
+    pub fn is_suspicious(&self) -> bool {
+        match self {
+            RejectionReason::Expired | RejectionReason::PolicyFailed { .. } => false,
+            RejectionReason::MacMismatch
+            | RejectionReason::Revoked
+            | RejectionReason::Malformed { .. } => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod rejection_tests {
+    use super::RejectionReason;
+    use crate::{LiteSessionError, TokenOutcome};
+
+    #[test]
+    fn rejection_reasons() {
+        assert_eq!(
+            RejectionReason::from_outcome(&TokenOutcome::TokenAuthentic),
+            None
+        );
+        assert_eq!(
+            RejectionReason::from_outcome(&TokenOutcome::SessionExpired),
+            Some(RejectionReason::Expired)
+        );
+        assert_eq!(
+            RejectionReason::from_outcome(&TokenOutcome::QuotaExceeded),
+            Some(RejectionReason::PolicyFailed {
+                which: "quota".into()
+            })
+        );
+        assert_eq!(
+            RejectionReason::from_error(&LiteSessionError::TokenFieldsLengthError),
+            Some(RejectionReason::Malformed { field: "token" })
+        );
+        assert_eq!(
+            RejectionReason::from_error(&LiteSessionError::ServerKeyLengthError),
+            None
+        );
+
+        assert!(!RejectionReason::Expired.is_suspicious());
+        assert!(RejectionReason::MacMismatch.is_suspicious());
+        assert_ne!(
+            RejectionReason::Malformed { field: "issued" },
+            RejectionReason::Malformed { field: "expiry" }
+        );
+    }
+}
@@ -0,0 +1,147 @@
+use crate::{Clock, LiteSessionError};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+use tai64::TAI64N;
+
+/// A record of the single-use tokens already presented, consulted by `LiteSessionVerifier`
+/// so that a token marked with `LiteSessionData::single_use()` is accepted only once.
+/// Implement this trait for shared stores like Redis to reject replays across servers
+pub trait ReplayGuard: core::fmt::Debug + Send + Sync {
+    /// Record the token with `token_id` which expires at `expiry` as used and check whether
+    /// it was used before. The token is rejected as expired afterwards so it only needs to be
+    /// remembered until `expiry`. Recording and checking must be a single atomic test-and-set,
+    /// like `SET NX` in Redis, so that of several verifications racing on the same token exactly
+    /// one sees `false`. A token that cannot be recorded must return an error rather than be
+    /// accepted, `LiteSessionVerifier` then fails the verification
+    fn seen(&self, token_id: &str, expiry: TAI64N) -> Result<bool, LiteSessionError>;
+}
+
+/// An in-memory `ReplayGuard` suitable for a single server which remembers the
+/// identifiers of at most `capacity` tokens until they expire. Once the guard is full
+/// new tokens are refused with `LiteSessionError::ReplayGuardFull` instead of forgetting
+/// tokens that could still be replayed, so the capacity should exceed the number of
+/// single-use tokens that can be outstanding at once
+///
+/// ```
+/// use core::time::Duration;
+/// use lite_session::{MemoryReplayGuard, ReplayGuard};
+/// use tai64::TAI64N;
+///
+/// let guard = MemoryReplayGuard::new(2);
+/// let expiry = TAI64N::now() + Duration::from_secs(60);
+/// assert_eq!(guard.seen("foo", expiry), Ok(false));
+/// assert_eq!(guard.seen("foo", expiry), Ok(true));
+/// ```
+#[derive(Debug)]
+pub struct MemoryReplayGuard {
+    capacity: usize,
+    clock: Option<Arc<dyn Clock>>,
+    seen: Mutex<SeenTokens>,
+}
+
+// The expiry of every remembered identifier, indexed by expiry so expired ones are dropped
+// from the front
+#[derive(Debug, Default)]
+struct SeenTokens {
+    expiries: HashMap<String, TAI64N>,
+    by_expiry: BTreeSet<(TAI64N, String)>,
+}
+
+impl MemoryReplayGuard {
+    /// Create a guard remembering at most `capacity` token identifiers
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: None,
+            seen: Mutex::default(),
+        }
+    }
+    /// Read the current time from `clock` instead of `SystemClock` when dropping
+    /// the identifiers of expired tokens
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = Some(clock);
+
+        self
+    }
+    /// Get the number of token identifiers the guard remembers at most
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl ReplayGuard for MemoryReplayGuard {
+    fn seen(&self, token_id: &str, expiry: TAI64N) -> Result<bool, LiteSessionError> {
+        let now = match &self.clock {
+            Some(clock) => clock.now(),
+            None => TAI64N::now(),
+        };
+        let mut seen = match self.seen.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        while let Some((oldest, _)) = seen.by_expiry.iter().next() {
+            if *oldest > now {
+                break;
+            }
+            if let Some((_, expired)) = seen.by_expiry.pop_first() {
+                seen.expiries.remove(&expired);
+            }
+        }
+
+        if seen.expiries.contains_key(token_id) {
+            return Ok(true);
+        }
+        if seen.expiries.len() >= self.capacity {
+            return Err(LiteSessionError::ReplayGuardFull);
+        }
+        seen.expiries.insert(token_id.into(), expiry);
+        seen.by_expiry.insert((expiry, token_id.into()));
+
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod replay_tests {
+    use super::{MemoryReplayGuard, ReplayGuard};
+    use crate::{FixedClock, LiteSessionError};
+    use core::time::Duration;
+    use std::sync::Arc;
+    use tai64::TAI64N;
+
+    #[test]
+    fn memory_replay_guard() -> Result<(), LiteSessionError> {
+        let now = TAI64N::now();
+        let mut guard = MemoryReplayGuard::new(2);
+        guard.clock(Arc::new(FixedClock::new(now)));
+        let soon = now + Duration::from_secs(60);
+        let later = now + Duration::from_secs(3600);
+        assert!(!guard.seen("foo", later)?);
+        assert!(!guard.seen("bar", soon)?);
+        assert!(guard.seen("foo", later)?);
+
+        // Live tokens are never forgotten, a full guard refuses new ones instead
+        assert_eq!(
+            guard.seen("baz", later),
+            Err(LiteSessionError::ReplayGuardFull)
+        );
+        assert!(guard.seen("bar", soon)?);
+
+        // Expired tokens make room
+        guard.clock(Arc::new(FixedClock::new(soon)));
+        assert!(!guard.seen("baz", later)?);
+        assert!(guard.seen("foo", later)?);
+        assert!(guard.seen("baz", later)?);
+
+        let guard = MemoryReplayGuard::new(0);
+        assert_eq!(
+            guard.seen("foo", later),
+            Err(LiteSessionError::ReplayGuardFull)
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use core::time::Duration;
+use std::time::Instant;
+
+/// A single check performed while verifying a token
+///
+/// ```
+/// use core::time::Duration;
+///
+/// pub struct VerificationCheck {
+///     name: &'static str,
+///     passed: bool,
+///     elapsed: Duration,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct VerificationCheck {
+    name: &'static str,
+    passed: bool,
+    elapsed: Duration,
+}
+
+impl core::cmp::PartialEq for VerificationCheck {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.passed == other.passed && self.elapsed == other.elapsed
+    }
+}
+
+impl core::clone::Clone for VerificationCheck {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name,
+            passed: self.passed,
+            elapsed: self.elapsed,
+        }
+    }
+}
+
+impl VerificationCheck {
+    /// Get the name of the check, one of `structure`, `expiry`, `key`, `hmac`, `decryption`
+    /// and `claims` in the order they are performed
+    pub fn get_name(&self) -> &'static str {
+        self.name
+    }
+    /// Check whether the token passed the check
+    pub fn is_passed(&self) -> bool {
+        self.passed
+    }
+    /// Get the time the check took
+    pub fn get_elapsed(&self) -> &Duration {
+        &self.elapsed
+    }
+}
+
+/// The checks performed by the last verification of a token, read with
+/// `LiteSessionToken::get_report()`. Verification stops at the first failed check
+/// so the checks after it are missing from the report
+///
+/// ```
+/// use lite_session::VerificationCheck;
+/// use std::time::Instant;
+///
+/// pub struct VerificationReport {
+///     checks: Vec<VerificationCheck>,
+///     pending: Option<(&'static str, Instant)>,
+/// }
+/// ```
+#[derive(Debug, Default)]
+pub struct VerificationReport {
+    checks: Vec<VerificationCheck>,
+    pending: Option<(&'static str, Instant)>,
+}
+
+impl core::cmp::PartialEq for VerificationReport {
+    fn eq(&self, other: &Self) -> bool {
+        self.checks == other.checks
+    }
+}
+
+impl core::clone::Clone for VerificationReport {
+    fn clone(&self) -> Self {
+        Self {
+            checks: self.checks.clone(),
+            pending: self.pending,
+        }
+    }
+}
+
+impl VerificationReport {
+    /// Get the checks in the order they were performed
+    pub fn get_checks(&self) -> &Vec<VerificationCheck> {
+        &self.checks
+    }
+    /// Get the check named `name` if it was performed
+    pub fn get_check(&self, name: &str) -> Option<&VerificationCheck> {
+        self.checks.iter().find(|check| check.name == name)
+    }
+    /// Get the check that rejected the token
+    pub fn get_failed_check(&self) -> Option<&VerificationCheck> {
+        self.checks.iter().find(|check| !check.passed)
+    }
+    /// Get the time all the checks took
+    pub fn get_elapsed(&self) -> Duration {
+        self.checks.iter().map(|check| check.elapsed).sum()
+    }
+
+    // Start the check `name`, recording the check in progress as passed
+    pub(crate) fn begin(&mut self, name: &'static str) {
+        self.end(true);
+        self.pending = Some((name, Instant::now()));
+    }
+
+    // Record the check in progress with the result of the verification
+    pub(crate) fn end(&mut self, passed: bool) {
+        if let Some((name, started)) = self.pending.take() {
+            self.checks.push(VerificationCheck {
+                name,
+                passed,
+                elapsed: started.elapsed(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod report_tests {
+    use super::VerificationReport;
+
+    #[test]
+    fn verification_report() {
+        let mut report = VerificationReport::default();
+        report.end(false);
+        assert!(report.get_checks().is_empty());
+
+        report.begin("structure");
+        report.begin("expiry");
+        report.end(false);
+        report.end(true);
+
+        let names: Vec<&str> = report
+            .get_checks()
+            .iter()
+            .map(|check| check.get_name())
+            .collect();
+        assert_eq!(names, vec!["structure", "expiry"]);
+        assert!(report.get_check("structure").unwrap().is_passed());
+        assert_eq!(
+            report.get_failed_check().map(|check| check.get_name()),
+            Some("expiry")
+        );
+        assert_eq!(report.get_check("hmac"), None);
+        assert!(report.get_elapsed() >= *report.get_checks()[0].get_elapsed());
+    }
+}
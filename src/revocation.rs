@@ -0,0 +1,608 @@
+use crate::{escape, persist, LiteSessionError};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tai64::TAI64N;
+
+/// A store of revoked token identifiers.
+/// Each revoked identifier is kept together with the expiry of its token so that
+/// the entry can be dropped once the token would have expired anyway
+pub trait RevocationStore {
+    /// Revoke the token with `identifier` which expires at `expiry`
+    fn revoke(&self, identifier: &str, expiry: TAI64N);
+    /// Revoke the token with `identifier` which expires at `expiry` unless it has already
+    /// been revoked, returning whether it was newly revoked. The check and the revocation
+    /// must be a single atomic step, like `SET NX` in Redis, so that of several callers racing
+    /// on the same identifier exactly one sees `true`
+    fn try_revoke(&self, identifier: &str, expiry: TAI64N) -> bool;
+    /// Check whether the token with `identifier` has been revoked
+    fn is_revoked(&self, identifier: &str) -> bool;
+    /// Export the revocations recorded after sequence number `since`.
+    /// Passing `0` exports a full snapshot
+    fn export(&self, since: u64) -> RevocationSnapshot;
+    /// Import a snapshot exported by another store.
+    /// A full snapshot replaces the current revocations while a delta is merged into them.
+    /// A delta starting after the last sequence number the store knows of is refused with
+    /// `LiteSessionError::RevocationSnapshotGap` since the revocations in between were missed,
+    /// import a full snapshot to resync
+    fn import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError>;
+}
+
+/// A compact, serializable batch of revocations used to sync edge verifiers periodically
+/// instead of querying the revocation store for every request
+///
+/// ```
+/// use tai64::TAI64N;
+///
+/// pub struct RevocationSnapshot {
+///     since: u64,
+///     sequence: u64,
+///     horizon: TAI64N,
+///     entries: Vec<(String, TAI64N)>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct RevocationSnapshot {
+    since: u64,
+    sequence: u64,
+    horizon: TAI64N,
+    entries: Vec<(String, TAI64N)>,
+}
+
+impl Default for RevocationSnapshot {
+    fn default() -> Self {
+        Self {
+            since: 0,
+            sequence: 0,
+            horizon: TAI64N::now(),
+            entries: Vec::default(),
+        }
+    }
+}
+
+impl core::cmp::PartialEq for RevocationSnapshot {
+    fn eq(&self, other: &Self) -> bool {
+        self.since == other.since
+            && self.sequence == other.sequence
+            && self.horizon == other.horizon
+            && self.entries == other.entries
+    }
+}
+
+impl core::clone::Clone for RevocationSnapshot {
+    fn clone(&self) -> Self {
+        Self {
+            since: self.since,
+            sequence: self.sequence,
+            horizon: self.horizon,
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl RevocationSnapshot {
+    // Used by the stores of other modules, `export()` is the public way to take a snapshot
+    #[cfg(any(feature = "redis", feature = "sql"))]
+    pub(crate) fn new(
+        since: u64,
+        sequence: u64,
+        horizon: TAI64N,
+        entries: Vec<(String, TAI64N)>,
+    ) -> Self {
+        Self {
+            since,
+            sequence,
+            horizon,
+            entries,
+        }
+    }
+    /// The sequence number this snapshot builds upon. `0` indicates a full snapshot
+    pub fn get_since(&self) -> u64 {
+        self.since
+    }
+    /// The sequence number of the last revocation contained in this snapshot.
+    /// Pass it as `since` on the next export to receive only the newer revocations
+    pub fn get_sequence(&self) -> u64 {
+        self.sequence
+    }
+    /// The time the snapshot was taken. Revocations of tokens expiring before it are omitted
+    pub fn get_horizon(&self) -> &TAI64N {
+        &self.horizon
+    }
+    /// The revoked identifiers and the expiry of their tokens
+    pub fn get_entries(&self) -> &Vec<(String, TAI64N)> {
+        &self.entries
+    }
+    /// Check whether this snapshot is a delta rather than a full snapshot
+    pub fn is_delta(&self) -> bool {
+        self.since != 0
+    }
+    /// Serialize the snapshot into its compact text form.
+    /// The first line holds the header and every other line holds one revoked identifier,
+    /// percent-escaped so that an identifier cannot span lines
+    pub fn to_string(snapshot: &RevocationSnapshot) -> String {
+        let mut serialized = String::default();
+        serialized.push_str(RevocationSnapshot::magic());
+        serialized.push(' ');
+        serialized.push_str(&snapshot.since.to_string());
+        serialized.push(' ');
+        serialized.push_str(&snapshot.sequence.to_string());
+        serialized.push(' ');
+        serialized.push_str(&hex::encode(snapshot.horizon.to_bytes()));
+
+        snapshot.entries.iter().for_each(|(identifier, expiry)| {
+            serialized.push('\n');
+            serialized.push_str(&escape::escape_reserved(identifier, &['%', '\n', '\r']));
+            serialized.push(' ');
+            serialized.push_str(&hex::encode(expiry.to_bytes()));
+        });
+
+        serialized
+    }
+    /// Deserialize a snapshot from its compact text form. Snapshots serialized before the
+    /// identifiers were escaped are read with their identifiers as they are
+    pub fn from_string(value: &str) -> Result<Self, LiteSessionError> {
+        let mut lines = value.split('\n');
+
+        let header = match lines.next() {
+            Some(header) => header.split(' ').collect::<Vec<&str>>(),
+            None => return Err(LiteSessionError::InvalidRevocationSnapshot),
+        };
+        let escaped = match header.first() {
+            Some(&magic) if magic == RevocationSnapshot::magic() => true,
+            Some(&"lsrev1") => false,
+            _ => return Err(LiteSessionError::InvalidRevocationSnapshot),
+        };
+        if header.len() != 4_usize {
+            return Err(LiteSessionError::InvalidRevocationSnapshot);
+        }
+
+        let since = match header[1].parse::<u64>() {
+            Ok(since) => since,
+            Err(_) => return Err(LiteSessionError::InvalidRevocationSnapshot),
+        };
+        let sequence = match header[2].parse::<u64>() {
+            Ok(sequence) => sequence,
+            Err(_) => return Err(LiteSessionError::InvalidRevocationSnapshot),
+        };
+        let horizon = RevocationSnapshot::tai_time(header[3])?;
+
+        let mut entries = Vec::new();
+        for line in lines {
+            let mut parts = line.rsplitn(2, ' ');
+            let expiry = match parts.next() {
+                Some(expiry) => RevocationSnapshot::tai_time(expiry)?,
+                None => return Err(LiteSessionError::InvalidRevocationSnapshot),
+            };
+            let identifier = match parts.next() {
+                Some(identifier) if escaped => match escape::unescape(identifier) {
+                    Ok(identifier) => identifier,
+                    Err(_) => return Err(LiteSessionError::InvalidRevocationSnapshot),
+                },
+                Some(identifier) => identifier.to_owned(),
+                None => return Err(LiteSessionError::InvalidRevocationSnapshot),
+            };
+            if identifier.is_empty() {
+                return Err(LiteSessionError::InvalidRevocationSnapshot);
+            }
+            entries.push((identifier, expiry));
+        }
+
+        Ok(Self {
+            since,
+            sequence,
+            horizon,
+            entries,
+        })
+    }
+
+    fn tai_time(hex_str: &str) -> Result<TAI64N, LiteSessionError> {
+        let tai_bytes = match hex::decode(hex_str) {
+            Ok(bytes) => bytes,
+            Err(_) => return Err(LiteSessionError::InvalidHexString),
+        };
+        match TAI64N::from_slice(&tai_bytes) {
+            Ok(tai_time) => Ok(tai_time),
+            Err(_) => Err(LiteSessionError::InvalidTai64NTime),
+        }
+    }
+
+    fn magic() -> &'static str {
+        "lsrev2"
+    }
+}
+
+/// An in-memory `RevocationStore`
+#[derive(Debug, Default)]
+pub struct MemoryRevocationStore {
+    inner: Mutex<MemoryRevocations>,
+}
+
+#[derive(Debug, Default)]
+struct MemoryRevocations {
+    sequence: u64,
+    revoked: HashMap<String, (TAI64N, u64)>,
+}
+
+impl MemoryRevocationStore {
+    /// Create a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Remove the revocations whose tokens have already expired
+    pub fn prune(&self) {
+        let now = TAI64N::now();
+        self.lock().revoked.retain(|_, (expiry, _)| *expiry > now);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoryRevocations> {
+        match self.inner.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl RevocationStore for MemoryRevocationStore {
+    fn revoke(&self, identifier: &str, expiry: TAI64N) {
+        let mut inner = self.lock();
+        inner.sequence += 1;
+        let sequence = inner.sequence;
+        inner
+            .revoked
+            .insert(identifier.to_owned(), (expiry, sequence));
+    }
+
+    fn try_revoke(&self, identifier: &str, expiry: TAI64N) -> bool {
+        let mut inner = self.lock();
+        if inner.revoked.contains_key(identifier) {
+            return false;
+        }
+
+        inner.sequence += 1;
+        let sequence = inner.sequence;
+        inner
+            .revoked
+            .insert(identifier.to_owned(), (expiry, sequence));
+
+        true
+    }
+
+    fn is_revoked(&self, identifier: &str) -> bool {
+        self.lock().revoked.contains_key(identifier)
+    }
+
+    fn export(&self, since: u64) -> RevocationSnapshot {
+        let horizon = TAI64N::now();
+        let inner = self.lock();
+
+        let mut entries = inner
+            .revoked
+            .iter()
+            .filter(|(_, (expiry, sequence))| *sequence > since && *expiry > horizon)
+            .map(|(identifier, (expiry, sequence))| (*sequence, identifier.clone(), *expiry))
+            .collect::<Vec<(u64, String, TAI64N)>>();
+        entries.sort();
+
+        RevocationSnapshot {
+            since,
+            sequence: inner.sequence,
+            horizon,
+            entries: entries
+                .into_iter()
+                .map(|(_, identifier, expiry)| (identifier, expiry))
+                .collect(),
+        }
+    }
+
+    fn import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError> {
+        let mut inner = self.lock();
+
+        if snapshot.since > inner.sequence {
+            return Err(LiteSessionError::RevocationSnapshotGap);
+        }
+        if !snapshot.is_delta() {
+            inner.revoked.clear();
+        }
+
+        let sequence = snapshot.sequence;
+        snapshot.entries.iter().for_each(|(identifier, expiry)| {
+            inner
+                .revoked
+                .insert(identifier.clone(), (*expiry, sequence));
+        });
+        inner.sequence = inner.sequence.max(sequence);
+
+        Ok(())
+    }
+}
+
+/// A `RevocationStore` kept in memory and persisted to a file as a full `RevocationSnapshot`
+/// after every change, so that revocations survive a restart of a single server.
+/// Changes are written one at a time. A change that cannot be written is kept in memory,
+/// `try_add()`, `try_claim()` and `import()` return the write error and `try_revoke()` fails
+/// closed on it, call `save()` to retry and learn whether the file is up to date
+#[derive(Debug)]
+pub struct FileRevocationStore {
+    path: PathBuf,
+    memory: MemoryRevocationStore,
+    writing: Mutex<()>,
+}
+
+impl FileRevocationStore {
+    /// Open the store persisted at `path`, starting empty if the file does not exist yet
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LiteSessionError> {
+        let path = path.as_ref().to_path_buf();
+        let memory = MemoryRevocationStore::new();
+
+        match std::fs::read_to_string(&path) {
+            Ok(value) => memory.import(&RevocationSnapshot::from_string(&value)?)?,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(_) => return Err(LiteSessionError::RevocationIoError),
+        }
+
+        Ok(Self {
+            path,
+            memory,
+            writing: Mutex::new(()),
+        })
+    }
+    /// Get the path of the file
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+    /// Write the revocations whose tokens have not expired to the file
+    pub fn save(&self) -> Result<(), LiteSessionError> {
+        self.change(|_| Ok(()))
+    }
+    /// Revoke the token with `identifier` like `revoke()`, returning the error
+    /// `revoke()` cannot report when the file cannot be written
+    pub fn try_add(&self, identifier: &str, expiry: TAI64N) -> Result<(), LiteSessionError> {
+        self.change(|memory| {
+            memory.revoke(identifier, expiry);
+
+            Ok(())
+        })
+    }
+    /// Revoke the token with `identifier` unless it has already been revoked like
+    /// `try_revoke()`, returning whether it was newly revoked or the error `try_revoke()`
+    /// turns into `false` when the file cannot be written
+    pub fn try_claim(&self, identifier: &str, expiry: TAI64N) -> Result<bool, LiteSessionError> {
+        let mut revoked = false;
+        self.change(|memory| {
+            revoked = memory.try_revoke(identifier, expiry);
+
+            Ok(())
+        })?;
+
+        Ok(revoked)
+    }
+
+    // Apply `change` and write the result while holding the write lock, so that a slower
+    // writer never renames an older snapshot over the file after a newer one
+    fn change(
+        &self,
+        change: impl FnOnce(&MemoryRevocationStore) -> Result<(), LiteSessionError>,
+    ) -> Result<(), LiteSessionError> {
+        let _writing = match self.writing.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        change(&self.memory)?;
+
+        let snapshot = RevocationSnapshot::to_string(&self.memory.export(0));
+        persist::replace_file(&self.path, &snapshot)
+            .map_err(|_| LiteSessionError::RevocationIoError)
+    }
+}
+
+impl RevocationStore for FileRevocationStore {
+    fn revoke(&self, identifier: &str, expiry: TAI64N) {
+        // The error is returned by `try_add()`
+        let _ = self.try_add(identifier, expiry);
+    }
+
+    // Fail closed, a claim that cannot be written may be lost on a restart
+    fn try_revoke(&self, identifier: &str, expiry: TAI64N) -> bool {
+        self.try_claim(identifier, expiry).unwrap_or(false)
+    }
+
+    fn is_revoked(&self, identifier: &str) -> bool {
+        self.memory.is_revoked(identifier)
+    }
+
+    fn export(&self, since: u64) -> RevocationSnapshot {
+        self.memory.export(since)
+    }
+
+    fn import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError> {
+        self.change(|memory| memory.import(snapshot))
+    }
+}
+
+#[cfg(test)]
+mod revocation_tests {
+    use super::{FileRevocationStore, MemoryRevocationStore, RevocationSnapshot, RevocationStore};
+    use crate::LiteSessionError;
+    use core::time::Duration;
+    use tai64::TAI64N;
+
+    #[test]
+    fn revocation_sync() -> Result<(), LiteSessionError> {
+        let expiry = TAI64N::now() + Duration::from_secs(3600);
+        let expired = TAI64N::now() - Duration::from_secs(3600);
+
+        let origin = MemoryRevocationStore::new();
+        origin.revoke("foo", expiry);
+        origin.revoke("bar", expiry);
+        origin.revoke("stale", expired);
+        assert!(origin.is_revoked("foo"));
+        assert!(!origin.is_revoked("baz"));
+
+        let full = origin.export(0);
+        assert!(!full.is_delta());
+        assert_eq!(full.get_sequence(), 3);
+        assert_eq!(
+            full.get_entries(),
+            &vec![("foo".to_owned(), expiry), ("bar".to_owned(), expiry)]
+        );
+
+        let serialized = RevocationSnapshot::to_string(&full);
+        let deserialized = RevocationSnapshot::from_string(&serialized)?;
+        assert_eq!(deserialized, full);
+
+        let edge = MemoryRevocationStore::new();
+        edge.import(&deserialized)?;
+        assert!(edge.is_revoked("foo"));
+        assert!(edge.is_revoked("bar"));
+        assert!(!edge.is_revoked("stale"));
+
+        origin.revoke("baz", expiry);
+        let delta = origin.export(full.get_sequence());
+        assert!(delta.is_delta());
+        assert_eq!(delta.get_entries(), &vec![("baz".to_owned(), expiry)]);
+
+        edge.import(&RevocationSnapshot::from_string(
+            &RevocationSnapshot::to_string(&delta),
+        )?)?;
+        assert!(edge.is_revoked("foo"));
+        assert!(edge.is_revoked("baz"));
+        assert_eq!(edge.export(0).get_sequence(), delta.get_sequence());
+
+        // A delta after a missed one is refused until a full snapshot is imported
+        origin.revoke("qux", expiry);
+        let missed = origin.export(delta.get_sequence());
+        origin.revoke("quux", expiry);
+        assert_eq!(
+            edge.import(&origin.export(missed.get_sequence())),
+            Err(LiteSessionError::RevocationSnapshotGap)
+        );
+        assert!(!edge.is_revoked("quux"));
+        assert_eq!(edge.export(0).get_sequence(), delta.get_sequence());
+        edge.import(&origin.export(0))?;
+        assert!(edge.is_revoked("qux") && edge.is_revoked("quux"));
+
+        // An identifier cannot inject entries into the serialized snapshot
+        let injecting = MemoryRevocationStore::new();
+        injecting.revoke(
+            &format!("foo\nbar {}", hex::encode(expiry.to_bytes())),
+            expiry,
+        );
+        let snapshot = injecting.export(0);
+        let serialized = RevocationSnapshot::to_string(&snapshot);
+        assert_eq!(serialized.lines().count(), 2);
+        assert_eq!(RevocationSnapshot::from_string(&serialized)?, snapshot);
+        let legacy = format!(
+            "lsrev1 0 1 {}\nfoo% {}",
+            hex::encode(expiry.to_bytes()),
+            hex::encode(expiry.to_bytes())
+        );
+        assert_eq!(
+            RevocationSnapshot::from_string(&legacy)?.get_entries(),
+            &vec![("foo%".to_owned(), expiry)]
+        );
+
+        assert_eq!(
+            RevocationSnapshot::from_string("lsrev1 0 1"),
+            Err(LiteSessionError::InvalidRevocationSnapshot)
+        );
+        assert_eq!(
+            RevocationSnapshot::from_string(&format!("{}\nfoo", serialized)),
+            Err(LiteSessionError::InvalidHexString)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn try_revoke() {
+        let expiry = TAI64N::now() + Duration::from_secs(3600);
+        let store = MemoryRevocationStore::new();
+        assert!(store.try_revoke("foo", expiry));
+        assert!(!store.try_revoke("foo", expiry));
+        assert!(store.is_revoked("foo"));
+        assert_eq!(store.export(0).get_sequence(), 1);
+    }
+
+    #[test]
+    fn file_revocation_store() -> Result<(), LiteSessionError> {
+        let path = std::env::temp_dir().join("lite_session_revocations.lsrev");
+        let _ = std::fs::remove_file(&path);
+        let expiry = TAI64N::now() + Duration::from_secs(3600);
+
+        let store = FileRevocationStore::open(&path)?;
+        assert!(!store.is_revoked("foo"));
+        store.revoke("foo", expiry);
+        assert!(store.is_revoked("foo"));
+
+        let reopened = FileRevocationStore::open(&path)?;
+        assert!(reopened.is_revoked("foo"));
+        assert!(!reopened.is_revoked("bar"));
+        assert_eq!(reopened.get_path(), path.as_path());
+
+        assert!(std::fs::write(&path, "foo").is_ok());
+        assert_eq!(
+            FileRevocationStore::open(&path).map(|_| ()),
+            Err(LiteSessionError::InvalidRevocationSnapshot)
+        );
+        assert_eq!(
+            FileRevocationStore::open(std::env::temp_dir()).map(|_| ()),
+            Err(LiteSessionError::RevocationIoError)
+        );
+        let _ = std::fs::remove_file(&path);
+
+        // Concurrent changes are all written and leave no staging files behind
+        let store = std::sync::Arc::new(FileRevocationStore::open(&path)?);
+        let claims = (0..8)
+            .map(|index| {
+                let store = store.clone();
+                std::thread::spawn(move || {
+                    (
+                        store.try_claim("foo", expiry),
+                        store.try_claim(&format!("bar{}", index), expiry),
+                    )
+                })
+            })
+            .map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(claims.iter().filter(|(foo, _)| foo == &Ok(true)).count(), 1);
+        assert!(claims.iter().all(|(_, bar)| bar == &Ok(true)));
+        assert_eq!(
+            FileRevocationStore::open(&path)?
+                .export(0)
+                .get_entries()
+                .len(),
+            9
+        );
+        let staging = std::fs::read_dir(std::env::temp_dir())
+            .map_err(|_| LiteSessionError::RevocationIoError)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_string_lossy()
+                    .starts_with("lite_session_revocations.lsrev.")
+            })
+            .count();
+        assert_eq!(staging, 0);
+        let _ = std::fs::remove_file(&path);
+
+        // A change that cannot be written is reported and claims fail closed
+        let unwritable = FileRevocationStore::open(path.join("missing.lsrev"))?;
+        assert_eq!(
+            unwritable.try_add("foo", expiry),
+            Err(LiteSessionError::RevocationIoError)
+        );
+        assert!(unwritable.is_revoked("foo"));
+        assert_eq!(
+            unwritable.try_claim("bar", expiry),
+            Err(LiteSessionError::RevocationIoError)
+        );
+        assert!(!unwritable.try_revoke("baz", expiry));
+        assert_eq!(unwritable.save(), Err(LiteSessionError::RevocationIoError));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+//! A Rocket request guard yielding the verified session of a request, enabled by the `rocket` feature
+
+use crate::{AuthenticatedSession, HttpRejection, LiteSessionVerifier};
+use ::rocket::{
+    http::Status,
+    request::{FromRequest, Outcome, Request},
+};
+use std::sync::Arc;
+
+/// Guard a Rocket route with the verified session of its request. The token is taken from the
+/// bearer token or the cookie named by `LiteSessionVerifier::cookie_name()` and verified with the
+/// `Arc<LiteSessionVerifier>` managed by the Rocket instance. Requests without an accepted token
+/// fail with the status of their `HttpRejection`, a missing verifier with `500`
+#[::rocket::async_trait]
+impl<'r> FromRequest<'r> for AuthenticatedSession {
+    type Error = HttpRejection;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let verifier = match request.rocket().state::<Arc<LiteSessionVerifier>>() {
+            Some(verifier) => verifier,
+            None => {
+                return Outcome::Error((Status::InternalServerError, HttpRejection::new(500, None)))
+            }
+        };
+        // HTTP/2 clients may split the cookies over several headers
+        let cookie = request
+            .headers()
+            .get("Cookie")
+            .collect::<Vec<&str>>()
+            .join("; ");
+
+        match AuthenticatedSession::from_request(
+            verifier,
+            request.headers().get_one("Authorization"),
+            Some(cookie.as_str()).filter(|cookie| !cookie.is_empty()),
+            verifier.get_cookie_name(),
+        ) {
+            Ok(session) => Outcome::Success(session),
+            Err(rejection) => rejection_outcome(rejection),
+        }
+    }
+}
+
+/// Fail a guard with the status of `rejection`, used by `require_role!` and `require_acl!`
+#[doc(hidden)]
+pub fn rejection_outcome<S>(rejection: HttpRejection) -> Outcome<S, HttpRejection> {
+    Outcome::Error((Status::new(rejection.get_status()), rejection))
+}
+
+/// Declare a Rocket request guard holding an `AuthenticatedSession` whose role is at least
+/// `role`, failing with `403` for sessions of a lower role
+///
+/// ```
+/// lite_session::require_role!(pub AdminSession, lite_session::Role::Admin);
+///
+/// #[rocket::get("/admin")]
+/// fn admin(session: AdminSession) -> String {
+///     session.0.get_data().get_username().clone()
+/// }
+/// ```
+#[macro_export]
+macro_rules! require_role {
+    ($vis:vis $guard:ident, $role:expr) => {
+        /// A request guard for sessions holding the role it was declared with
+        #[derive(Debug)]
+        $vis struct $guard(pub $crate::AuthenticatedSession);
+
+        #[::rocket::async_trait]
+        impl<'r> ::rocket::request::FromRequest<'r> for $guard {
+            type Error = $crate::HttpRejection;
+
+            async fn from_request(
+                request: &'r ::rocket::request::Request<'_>,
+            ) -> ::rocket::request::Outcome<Self, Self::Error> {
+                let session = ::rocket::outcome::try_outcome!(
+                    request.guard::<$crate::AuthenticatedSession>().await
+                );
+                match session.require_role($role).map(|_| ()) {
+                    Ok(()) => ::rocket::outcome::Outcome::Success($guard(session)),
+                    Err(rejection) => $crate::rejection_outcome(rejection),
+                }
+            }
+        }
+    };
+}
+
+/// Declare a Rocket request guard holding an `AuthenticatedSession` whose access control list
+/// grants `capability`, failing with `403` for sessions without it
+///
+/// ```
+/// lite_session::require_acl!(pub FileWriter, "files:write");
+///
+/// #[rocket::post("/files")]
+/// fn upload(session: FileWriter) -> String {
+///     session.0.get_data().get_username().clone()
+/// }
+/// ```
+#[macro_export]
+macro_rules! require_acl {
+    ($vis:vis $guard:ident, $capability:expr) => {
+        /// A request guard for sessions granted the capability it was declared with
+        #[derive(Debug)]
+        $vis struct $guard(pub $crate::AuthenticatedSession);
+
+        #[::rocket::async_trait]
+        impl<'r> ::rocket::request::FromRequest<'r> for $guard {
+            type Error = $crate::HttpRejection;
+
+            async fn from_request(
+                request: &'r ::rocket::request::Request<'_>,
+            ) -> ::rocket::request::Outcome<Self, Self::Error> {
+                let session = ::rocket::outcome::try_outcome!(
+                    request.guard::<$crate::AuthenticatedSession>().await
+                );
+                match session.require_acl($capability).map(|_| ()) {
+                    Ok(()) => ::rocket::outcome::Outcome::Success($guard(session)),
+                    Err(rejection) => $crate::rejection_outcome(rejection),
+                }
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod rocket_tests {
+    use crate::{
+        AuthenticatedSession, KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken,
+        LiteSessionVerifier, Role, ServerKey,
+    };
+    use ::rocket::{http::Header, local::blocking::Client};
+    use std::sync::Arc;
+
+    crate::require_role!(AdminSession, Role::Admin);
+    crate::require_acl!(FileWriter, "files:write");
+
+    #[::rocket::get("/session")]
+    fn session(session: AuthenticatedSession) -> String {
+        session.get_data().get_username().clone()
+    }
+
+    #[::rocket::get("/admin")]
+    fn admin(session: AdminSession) -> String {
+        session.0.get_data().get_username().clone()
+    }
+
+    #[::rocket::get("/files")]
+    fn files(session: FileWriter) -> String {
+        session.0.get_data().get_username().clone()
+    }
+
+    #[test]
+    fn request_guards() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_admin")
+            .role(Role::Admin)
+            .add_acl("files:read");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.cookie_name("sid");
+        let rocket = ::rocket::build()
+            .manage(Arc::new(verifier))
+            .mount("/", ::rocket::routes![session, admin, files]);
+        let client = Client::untracked(rocket).unwrap();
+        let bearer = || Header::new("Authorization", format!("Bearer {}", session_token));
+
+        let response = client.get("/session").header(bearer()).dispatch();
+        assert_eq!(response.status().code, 200);
+        assert_eq!(response.into_string().unwrap(), "foo_admin");
+        let response = client
+            .get("/session")
+            .header(Header::new("Cookie", format!("sid={}", session_token)))
+            .dispatch();
+        assert_eq!(response.status().code, 200);
+        assert_eq!(client.get("/session").dispatch().status().code, 401);
+
+        let response = client.get("/admin").header(bearer()).dispatch();
+        assert_eq!(response.into_string().unwrap(), "foo_admin");
+        assert_eq!(
+            client
+                .get("/files")
+                .header(bearer())
+                .dispatch()
+                .status()
+                .code,
+            403
+        );
+
+        let unmanaged = ::rocket::build().mount("/", ::rocket::routes![session]);
+        let client = Client::untracked(unmanaged).unwrap();
+        let response = client.get("/session").header(bearer()).dispatch();
+        assert_eq!(response.status().code, 500);
+
+        Ok(())
+    }
+}
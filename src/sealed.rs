@@ -0,0 +1,126 @@
+use crate::{
+    ConfidentialityMode, LiteSessionData, LiteSessionMode, LiteSessionToken, TokenVersion,
+};
+use tai64::TAI64N;
+
+/// A built token together with the token string it was serialized to.
+/// It only exposes read-only accessors so the fields cannot be changed after the
+/// token string has been issued
+/// ```
+/// use lite_session::{LiteSessionData, LiteSessionToken, ServerKey};
+///
+/// let mut data = LiteSessionData::default();
+/// data.username("foo_user").add_acl("Network-TCP");
+///
+/// let mut token = LiteSessionToken::default();
+/// token.hmac_data(data);
+/// let sealed = token.seal(&ServerKey::new([0_u8; 32])).unwrap();
+/// assert_eq!(sealed.get_hmac_data().get_username(), "foo_user");
+/// ```
+#[derive(Debug)]
+pub struct SealedToken {
+    token: LiteSessionToken,
+    session_token: String,
+}
+
+impl core::cmp::PartialEq for SealedToken {
+    fn eq(&self, other: &Self) -> bool {
+        self.token == other.token && self.session_token == other.session_token
+    }
+}
+
+impl core::clone::Clone for SealedToken {
+    fn clone(&self) -> Self {
+        Self {
+            token: self.token.clone(),
+            session_token: self.session_token.clone(),
+        }
+    }
+}
+
+impl core::fmt::Display for SealedToken {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{}", self.session_token)
+    }
+}
+
+impl SealedToken {
+    pub(crate) fn new(token: LiteSessionToken, session_token: String) -> Self {
+        Self {
+            token,
+            session_token,
+        }
+    }
+    /// Get the token string
+    pub fn as_str(&self) -> &str {
+        &self.session_token
+    }
+    /// Take the token string
+    pub fn into_string(self) -> String {
+        self.session_token
+    }
+    /// Get the identifier
+    pub fn get_identifier(&self) -> &String {
+        self.token.get_identifier()
+    }
+    /// Get the issued time
+    pub fn get_issued(&self) -> &TAI64N {
+        self.token.get_issued()
+    }
+    /// Get the expiry time
+    pub fn get_expiry(&self) -> &TAI64N {
+        self.token.get_expiry()
+    }
+    /// Get the data of the token
+    pub fn get_hmac_data(&self) -> &LiteSessionData {
+        self.token.get_hmac_data()
+    }
+    /// Get the `ConfidentialityMode`
+    pub fn get_confidentiality(&self) -> &ConfidentialityMode {
+        self.token.get_confidentiality()
+    }
+    /// Get the `LiteSessionMode`
+    pub fn get_mode(&self) -> &LiteSessionMode {
+        self.token.get_mode()
+    }
+    /// Get the `TokenVersion`
+    pub fn get_version(&self) -> &TokenVersion {
+        self.token.get_version()
+    }
+    /// Get the public footer
+    pub fn get_footer(&self) -> &Option<String> {
+        self.token.get_footer()
+    }
+}
+
+#[cfg(test)]
+mod sealed_tests {
+    use crate::{
+        ConfidentialityMode, LiteSessionData, LiteSessionError, LiteSessionToken, ServerKey,
+        TokenOutcome,
+    };
+
+    #[test]
+    fn sealed_token() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).confidential(false);
+        let identifier = token.get_identifier().clone();
+        let sealed = token.seal(&server_key)?;
+
+        assert_eq!(sealed.get_identifier(), &identifier);
+        assert_eq!(sealed.get_hmac_data(), &data);
+        assert_eq!(sealed.get_confidentiality(), &ConfidentialityMode::High);
+        assert_eq!(sealed.to_string(), sealed.as_str());
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, sealed.as_str())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_expiry(), sealed.get_expiry());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,872 @@
+use crate::{LiteSessionError, RevocationSnapshot, RevocationStore, SessionRecord, SessionStore};
+use tai64::TAI64N;
+
+const NEXT_SEQUENCE: &str =
+    "UPDATE lite_session_revocation_sequence SET sequence = sequence + 1 WHERE id = 1";
+const SELECT_SEQUENCE: &str = "SELECT sequence FROM lite_session_revocation_sequence WHERE id = 1";
+const RAISE_SEQUENCE: &str =
+    "UPDATE lite_session_revocation_sequence SET sequence = ? WHERE id = 1 AND sequence < ?";
+const SELECT_REVOCATION: &str =
+    "SELECT identifier FROM lite_session_revocations WHERE identifier = ? AND expires_at >= ?";
+const EXPORT_REVOCATIONS: &str = "SELECT identifier, expiry FROM lite_session_revocations WHERE sequence > ? AND expires_at >= ? ORDER BY sequence";
+const CLEAR_REVOCATIONS: &str = "DELETE FROM lite_session_revocations";
+const PRUNE_REVOCATIONS: &str = "DELETE FROM lite_session_revocations WHERE expires_at < ?";
+const DELETE_SESSION: &str = "DELETE FROM lite_session_sessions WHERE session_id = ?";
+const SELECT_SESSION: &str =
+    "SELECT record FROM lite_session_sessions WHERE session_id = ? AND expires_at >= ?";
+const SELECT_SESSIONS_OF: &str =
+    "SELECT session_id, record FROM lite_session_sessions WHERE subject = ? AND expires_at >= ?";
+const PRUNE_SESSIONS: &str = "DELETE FROM lite_session_sessions WHERE expires_at < ?";
+
+const CREATE_REVOCATIONS: &str = "CREATE TABLE IF NOT EXISTS lite_session_revocations (identifier VARCHAR(255) NOT NULL PRIMARY KEY, expiry VARCHAR(24) NOT NULL, expires_at BIGINT NOT NULL, sequence BIGINT NOT NULL)";
+const CREATE_SEQUENCE: &str = "CREATE TABLE IF NOT EXISTS lite_session_revocation_sequence (id INTEGER NOT NULL PRIMARY KEY, sequence BIGINT NOT NULL)";
+const CREATE_SESSIONS: &str = "CREATE TABLE IF NOT EXISTS lite_session_sessions (session_id VARCHAR(255) NOT NULL PRIMARY KEY, subject VARCHAR(255) NOT NULL, record TEXT NOT NULL, expires_at BIGINT NOT NULL)";
+const CREATE_SESSIONS_MYSQL: &str = "CREATE TABLE IF NOT EXISTS lite_session_sessions (session_id VARCHAR(255) NOT NULL PRIMARY KEY, subject VARCHAR(255) NOT NULL, record TEXT NOT NULL, expires_at BIGINT NOT NULL, INDEX lite_session_sessions_subject (subject))";
+const CREATE_SESSIONS_INDEX: &str =
+    "CREATE INDEX IF NOT EXISTS lite_session_sessions_subject ON lite_session_sessions (subject)";
+
+/// The SQL database a SQL backed store runs on, deciding the placeholders and
+/// the migration statements
+#[derive(Debug)]
+pub enum SqlDialect {
+    /// PostgreSQL with `$1` placeholders
+    Postgres,
+    /// MySQL or MariaDB with `?` placeholders
+    MySql,
+    /// SQLite with `?` placeholders
+    Sqlite,
+}
+
+impl core::cmp::PartialEq for SqlDialect {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (SqlDialect::Postgres, SqlDialect::Postgres)
+                | (SqlDialect::MySql, SqlDialect::MySql)
+                | (SqlDialect::Sqlite, SqlDialect::Sqlite)
+        )
+    }
+}
+
+impl core::clone::Clone for SqlDialect {
+    fn clone(&self) -> Self {
+        match self {
+            SqlDialect::Postgres => SqlDialect::Postgres,
+            SqlDialect::MySql => SqlDialect::MySql,
+            SqlDialect::Sqlite => SqlDialect::Sqlite,
+        }
+    }
+}
+
+impl SqlDialect {
+    /// Rewrite the `?` placeholders of `statement` into the placeholders of the dialect
+    pub fn statement(&self, statement: &str) -> String {
+        match self {
+            SqlDialect::Postgres => {
+                let mut rewritten = String::with_capacity(statement.len() + 8);
+                let mut position = 0;
+                statement.chars().for_each(|character| match character {
+                    '?' => {
+                        position += 1;
+                        rewritten.push('$');
+                        rewritten.push_str(&position.to_string());
+                    }
+                    character => rewritten.push(character),
+                });
+
+                rewritten
+            }
+            SqlDialect::MySql | SqlDialect::Sqlite => statement.into(),
+        }
+    }
+
+    fn seed_sequence(&self) -> &'static str {
+        match self {
+            SqlDialect::Postgres => "INSERT INTO lite_session_revocation_sequence (id, sequence) VALUES (1, 0) ON CONFLICT DO NOTHING",
+            SqlDialect::MySql => "INSERT IGNORE INTO lite_session_revocation_sequence (id, sequence) VALUES (1, 0)",
+            SqlDialect::Sqlite => "INSERT OR IGNORE INTO lite_session_revocation_sequence (id, sequence) VALUES (1, 0)",
+        }
+    }
+
+    fn upsert_revocation(&self) -> &'static str {
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => "INSERT INTO lite_session_revocations (identifier, expiry, expires_at, sequence) VALUES (?, ?, ?, ?) ON CONFLICT (identifier) DO UPDATE SET expiry = excluded.expiry, expires_at = excluded.expires_at, sequence = excluded.sequence",
+            SqlDialect::MySql => "INSERT INTO lite_session_revocations (identifier, expiry, expires_at, sequence) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE expiry = VALUES(expiry), expires_at = VALUES(expires_at), sequence = VALUES(sequence)",
+        }
+    }
+
+    fn upsert_session(&self) -> &'static str {
+        match self {
+            SqlDialect::Postgres | SqlDialect::Sqlite => "INSERT INTO lite_session_sessions (session_id, subject, record, expires_at) VALUES (?, ?, ?, ?) ON CONFLICT (session_id) DO UPDATE SET subject = excluded.subject, record = excluded.record, expires_at = excluded.expires_at",
+            SqlDialect::MySql => "INSERT INTO lite_session_sessions (session_id, subject, record, expires_at) VALUES (?, ?, ?, ?) ON DUPLICATE KEY UPDATE subject = VALUES(subject), record = VALUES(record), expires_at = VALUES(expires_at)",
+        }
+    }
+
+    fn insert_ignore_revocation(&self) -> &'static str {
+        match self {
+            SqlDialect::Postgres => "INSERT INTO lite_session_revocations (identifier, expiry, expires_at, sequence) VALUES (?, ?, ?, ?) ON CONFLICT (identifier) DO NOTHING",
+            SqlDialect::MySql => "INSERT IGNORE INTO lite_session_revocations (identifier, expiry, expires_at, sequence) VALUES (?, ?, ?, ?)",
+            SqlDialect::Sqlite => "INSERT OR IGNORE INTO lite_session_revocations (identifier, expiry, expires_at, sequence) VALUES (?, ?, ?, ?)",
+        }
+    }
+
+    fn execute(
+        &self,
+        connection: &dyn SqlConnection,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<u64, LiteSessionError> {
+        connection.execute(&self.statement(statement), params)
+    }
+
+    fn query(
+        &self,
+        connection: &dyn SqlConnection,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError> {
+        connection.query(&self.statement(statement), params)
+    }
+}
+
+/// A value bound to a placeholder or read from a column
+#[derive(Debug)]
+pub enum SqlValue {
+    /// A `VARCHAR` or `TEXT` value
+    Text(String),
+    /// A `BIGINT` value
+    Integer(i64),
+}
+
+impl core::cmp::PartialEq for SqlValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (SqlValue::Text(value), SqlValue::Text(other)) => value == other,
+            (SqlValue::Integer(value), SqlValue::Integer(other)) => value == other,
+            _ => false,
+        }
+    }
+}
+
+impl core::clone::Clone for SqlValue {
+    fn clone(&self) -> Self {
+        match self {
+            SqlValue::Text(value) => SqlValue::Text(value.clone()),
+            SqlValue::Integer(value) => SqlValue::Integer(*value),
+        }
+    }
+}
+
+impl SqlValue {
+    fn text(value: Option<&SqlValue>) -> Result<&str, LiteSessionError> {
+        match value {
+            Some(SqlValue::Text(value)) => Ok(value),
+            _ => Err(LiteSessionError::SqlStatementFailed),
+        }
+    }
+
+    fn integer(value: Option<&SqlValue>) -> Result<i64, LiteSessionError> {
+        match value {
+            Some(SqlValue::Integer(value)) => Ok(*value),
+            _ => Err(LiteSessionError::SqlStatementFailed),
+        }
+    }
+}
+
+/// The statements the SQL backed stores are built on. Implement it for the connection or
+/// pool of the SQL client in use, like a sqlx `AnyPool`, returning
+/// `LiteSessionError::SqlStatementFailed` when a statement cannot be run.
+/// The `sqlx` feature implements it for a sqlx `AnyPool` with `SqlxConnection`
+pub trait SqlConnection: core::fmt::Debug + Send + Sync {
+    /// Run `statement` with `params` bound to its placeholders, returning the rows affected
+    fn execute(&self, statement: &str, params: &[SqlValue]) -> Result<u64, LiteSessionError>;
+    /// Run the query `statement` with `params` bound to its placeholders, returning its rows
+    fn query(
+        &self,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError>;
+    /// Run `work` on a single connection inside a transaction, committing it when `work`
+    /// succeeds and rolling it back when it fails, so that other connections never see
+    /// part of its writes
+    fn transaction(
+        &self,
+        work: &mut dyn FnMut(&dyn SqlConnection) -> Result<(), LiteSessionError>,
+    ) -> Result<(), LiteSessionError>;
+}
+
+/// A `RevocationStore` shared by every server through a SQL database, for deployments
+/// without Redis. Each revocation keeps the expiry of its token so `prune()` can delete it
+/// once the token expired. A token is treated as revoked when the database cannot be reached.
+/// `try_add()` and `try_export()` return the errors the `RevocationStore` methods
+/// cannot report
+///
+/// ```
+/// use lite_session::SqlDialect;
+///
+/// pub struct SqlRevocationStore<C> {
+///     connection: C,
+///     dialect: SqlDialect,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SqlRevocationStore<C> {
+    connection: C,
+    dialect: SqlDialect,
+}
+
+impl<C: SqlConnection> SqlRevocationStore<C> {
+    /// Create a store on `connection` to a database of `dialect`
+    pub fn new(connection: C, dialect: SqlDialect) -> Self {
+        Self {
+            connection,
+            dialect,
+        }
+    }
+    /// Get the connection of the store
+    pub fn get_connection(&self) -> &C {
+        &self.connection
+    }
+    /// Get the dialect of the database
+    pub fn get_dialect(&self) -> &SqlDialect {
+        &self.dialect
+    }
+    /// Get the statements creating the tables of the store, for teams running
+    /// their own migration tool
+    pub fn migrations(&self) -> Vec<&'static str> {
+        vec![
+            CREATE_REVOCATIONS,
+            CREATE_SEQUENCE,
+            self.dialect.seed_sequence(),
+        ]
+    }
+    /// Create the tables of the store unless they exist
+    pub fn migrate(&self) -> Result<(), LiteSessionError> {
+        for statement in self.migrations() {
+            self.connection.execute(statement, &[])?;
+        }
+
+        Ok(())
+    }
+    /// Delete the revocations whose tokens have already expired
+    pub fn prune(&self) -> Result<u64, LiteSessionError> {
+        self.execute(
+            PRUNE_REVOCATIONS,
+            &[SqlValue::Integer(unix_secs(&TAI64N::now()))],
+        )
+    }
+
+    /// Revoke `identifier` like `revoke()`, returning the error `revoke()` ignores
+    pub fn try_add(&self, identifier: &str, expiry: &TAI64N) -> Result<(), LiteSessionError> {
+        self.add_next(identifier, expiry, self.dialect.upsert_revocation())
+            .map(|_| ())
+    }
+    /// Get the revocations added after `since` like `export()`, returning the error
+    /// `export()` turns into an empty delta
+    pub fn try_export(&self, since: u64) -> Result<RevocationSnapshot, LiteSessionError> {
+        let horizon = TAI64N::now();
+        let sequence = sequence(&self.dialect, &self.connection)?;
+
+        let mut entries = Vec::new();
+        let rows = self.dialect.query(
+            &self.connection,
+            EXPORT_REVOCATIONS,
+            &[
+                SqlValue::Integer(since as i64),
+                SqlValue::Integer(unix_secs(&horizon)),
+            ],
+        )?;
+        for row in rows {
+            let identifier = SqlValue::text(row.first())?;
+            let expiry = match hex::decode(SqlValue::text(row.get(1))?)
+                .map(|bytes| TAI64N::from_slice(&bytes))
+            {
+                Ok(Ok(expiry)) => expiry,
+                _ => return Err(LiteSessionError::SqlStatementFailed),
+            };
+            if expiry > horizon {
+                entries.push((identifier.to_owned(), expiry));
+            }
+        }
+
+        Ok(RevocationSnapshot::new(since, sequence, horizon, entries))
+    }
+    /// Import `snapshot`, the same as `import()`.
+    /// A full snapshot replaces the revocations in a single transaction so verifiers
+    /// never see the store emptied
+    pub fn try_import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError> {
+        let dialect = &self.dialect;
+
+        self.connection.transaction(&mut |connection| {
+            if snapshot.get_since() > sequence(dialect, connection)? {
+                return Err(LiteSessionError::RevocationSnapshotGap);
+            }
+            if !snapshot.is_delta() {
+                dialect.execute(connection, CLEAR_REVOCATIONS, &[])?;
+            }
+            for (identifier, expiry) in snapshot.get_entries() {
+                dialect.execute(
+                    connection,
+                    dialect.upsert_revocation(),
+                    &revocation(identifier, expiry, snapshot.get_sequence()),
+                )?;
+            }
+            let sequence = SqlValue::Integer(snapshot.get_sequence() as i64);
+            dialect.execute(connection, RAISE_SEQUENCE, &[sequence.clone(), sequence])?;
+
+            Ok(())
+        })
+    }
+
+    // Bump the sequence and write the revocation with `statement` in one transaction.
+    // The update locks the row of the sequence until the commit so every revocation
+    // gets a sequence of its own
+    fn add_next(
+        &self,
+        identifier: &str,
+        expiry: &TAI64N,
+        statement: &str,
+    ) -> Result<u64, LiteSessionError> {
+        let dialect = &self.dialect;
+        let mut written = 0;
+
+        self.connection.transaction(&mut |connection| {
+            dialect.execute(connection, NEXT_SEQUENCE, &[])?;
+            let sequence = sequence(dialect, connection)?;
+            written = dialect.execute(
+                connection,
+                statement,
+                &revocation(identifier, expiry, sequence),
+            )?;
+
+            Ok(())
+        })?;
+
+        Ok(written)
+    }
+
+    fn execute(&self, statement: &str, params: &[SqlValue]) -> Result<u64, LiteSessionError> {
+        self.dialect.execute(&self.connection, statement, params)
+    }
+
+    fn query(
+        &self,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError> {
+        self.dialect.query(&self.connection, statement, params)
+    }
+}
+
+impl<C: SqlConnection> RevocationStore for SqlRevocationStore<C> {
+    fn revoke(&self, identifier: &str, expiry: TAI64N) {
+        // The error is returned by `try_add()`
+        let _ = self.try_add(identifier, &expiry);
+    }
+
+    // The primary key on the identifier lets exactly one of several racing inserts through
+    fn try_revoke(&self, identifier: &str, expiry: TAI64N) -> bool {
+        let statement = self.dialect.insert_ignore_revocation();
+
+        matches!(self.add_next(identifier, &expiry, statement), Ok(1))
+    }
+
+    fn is_revoked(&self, identifier: &str) -> bool {
+        let params = [
+            SqlValue::Text(identifier.into()),
+            SqlValue::Integer(unix_secs(&TAI64N::now())),
+        ];
+
+        // Fail closed, an unreachable store must not let revoked tokens through
+        match self.query(SELECT_REVOCATION, &params) {
+            Ok(rows) => !rows.is_empty(),
+            Err(_) => true,
+        }
+    }
+
+    fn export(&self, since: u64) -> RevocationSnapshot {
+        match self.try_export(since) {
+            Ok(snapshot) => snapshot,
+            // An empty delta, an empty full snapshot would clear the revocations of the importer
+            Err(_) => RevocationSnapshot::new(since.max(1), since, TAI64N::now(), Vec::default()),
+        }
+    }
+
+    fn import(&self, snapshot: &RevocationSnapshot) -> Result<(), LiteSessionError> {
+        self.try_import(snapshot)
+    }
+}
+
+/// A `SessionStore` shared by every server through a SQL database, for deployments
+/// without Redis. Each record keeps the expiry of its session so `prune()` can delete it
+/// once the session expired. A session is treated as missing when the database cannot
+/// be reached. The `try_` methods return the errors the `SessionStore` methods cannot report
+///
+/// ```
+/// use lite_session::SqlDialect;
+///
+/// pub struct SqlSessionStore<C> {
+///     connection: C,
+///     dialect: SqlDialect,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SqlSessionStore<C> {
+    connection: C,
+    dialect: SqlDialect,
+}
+
+impl<C: SqlConnection> SqlSessionStore<C> {
+    /// Create a store on `connection` to a database of `dialect`
+    pub fn new(connection: C, dialect: SqlDialect) -> Self {
+        Self {
+            connection,
+            dialect,
+        }
+    }
+    /// Get the connection of the store
+    pub fn get_connection(&self) -> &C {
+        &self.connection
+    }
+    /// Get the dialect of the database
+    pub fn get_dialect(&self) -> &SqlDialect {
+        &self.dialect
+    }
+    /// Get the statements creating the table of the store, for teams running
+    /// their own migration tool
+    pub fn migrations(&self) -> Vec<&'static str> {
+        match self.dialect {
+            SqlDialect::MySql => vec![CREATE_SESSIONS_MYSQL],
+            SqlDialect::Postgres | SqlDialect::Sqlite => {
+                vec![CREATE_SESSIONS, CREATE_SESSIONS_INDEX]
+            }
+        }
+    }
+    /// Create the table of the store unless it exists
+    pub fn migrate(&self) -> Result<(), LiteSessionError> {
+        for statement in self.migrations() {
+            self.connection.execute(statement, &[])?;
+        }
+
+        Ok(())
+    }
+    /// Delete the records of the sessions that have already expired
+    pub fn prune(&self) -> Result<u64, LiteSessionError> {
+        self.execute(
+            PRUNE_SESSIONS,
+            &[SqlValue::Integer(unix_secs(&TAI64N::now()))],
+        )
+    }
+
+    /// Get the record of `session_id` like `get()`, returning the error `get()` treats
+    /// as a missing session
+    pub fn try_get(&self, session_id: &str) -> Result<Option<SessionRecord>, LiteSessionError> {
+        let params = [
+            SqlValue::Text(session_id.into()),
+            SqlValue::Integer(unix_secs(&TAI64N::now())),
+        ];
+
+        match self.query(SELECT_SESSION, &params)?.first() {
+            Some(row) => Ok(self.record(session_id, SqlValue::text(row.first())?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Store the record of `session_id` like `put()`, returning the error `put()` ignores
+    pub fn try_put(
+        &self,
+        session_id: &str,
+        record: &SessionRecord,
+    ) -> Result<(), LiteSessionError> {
+        self.execute(
+            self.dialect.upsert_session(),
+            &[
+                SqlValue::Text(session_id.into()),
+                SqlValue::Text(record.get_subject().clone()),
+                SqlValue::Text(SessionRecord::to_string(session_id, record)),
+                SqlValue::Integer(unix_secs(record.get_expiry())),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Delete the record of `session_id` like `delete()`, returning the error `delete()`
+    /// treats as a missing session
+    pub fn try_delete(&self, session_id: &str) -> Result<Option<SessionRecord>, LiteSessionError> {
+        let record = self.try_get(session_id)?;
+        self.execute(DELETE_SESSION, &[SqlValue::Text(session_id.into())])?;
+
+        Ok(record)
+    }
+
+    /// Get the sessions of `subject` like `sessions_of()`, returning the error
+    /// `sessions_of()` treats as no sessions
+    pub fn try_sessions_of(
+        &self,
+        subject: &str,
+    ) -> Result<Vec<(String, SessionRecord)>, LiteSessionError> {
+        let params = [
+            SqlValue::Text(subject.into()),
+            SqlValue::Integer(unix_secs(&TAI64N::now())),
+        ];
+
+        let mut sessions = Vec::new();
+        for row in self.query(SELECT_SESSIONS_OF, &params)? {
+            let session_id = SqlValue::text(row.first())?;
+            if let Some(record) = self.record(session_id, SqlValue::text(row.get(1))?) {
+                sessions.push((session_id.to_owned(), record));
+            }
+        }
+
+        Ok(sessions)
+    }
+
+    // The record of `session_id` unless it is corrupt or expired within the last second
+    fn record(&self, session_id: &str, line: &str) -> Option<SessionRecord> {
+        match SessionRecord::from_string(line) {
+            Ok((stored, record)) if stored == session_id && !record.is_expired(&TAI64N::now()) => {
+                Some(record)
+            }
+            _ => None,
+        }
+    }
+
+    fn execute(&self, statement: &str, params: &[SqlValue]) -> Result<u64, LiteSessionError> {
+        self.dialect.execute(&self.connection, statement, params)
+    }
+
+    fn query(
+        &self,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError> {
+        self.dialect.query(&self.connection, statement, params)
+    }
+}
+
+impl<C: SqlConnection> SessionStore for SqlSessionStore<C> {
+    fn get(&self, session_id: &str) -> Option<SessionRecord> {
+        self.try_get(session_id).unwrap_or(None)
+    }
+
+    fn put(&self, session_id: &str, record: SessionRecord) {
+        // The error is returned by `try_put()`
+        let _ = self.try_put(session_id, &record);
+    }
+
+    fn delete(&self, session_id: &str) -> Option<SessionRecord> {
+        self.try_delete(session_id).unwrap_or(None)
+    }
+
+    fn sessions_of(&self, subject: &str) -> Vec<(String, SessionRecord)> {
+        self.try_sessions_of(subject).unwrap_or_default()
+    }
+}
+
+// The current sequence of the revocations
+fn sequence(dialect: &SqlDialect, connection: &dyn SqlConnection) -> Result<u64, LiteSessionError> {
+    let rows = dialect.query(connection, SELECT_SEQUENCE, &[])?;
+
+    Ok(SqlValue::integer(rows.first().and_then(|row| row.first()))? as u64)
+}
+
+// The parameters of a revocation row
+fn revocation(identifier: &str, expiry: &TAI64N, sequence: u64) -> [SqlValue; 4] {
+    [
+        SqlValue::Text(identifier.into()),
+        SqlValue::Text(hex::encode(expiry.to_bytes())),
+        SqlValue::Integer(unix_secs(expiry)),
+        SqlValue::Integer(sequence as i64),
+    ]
+}
+
+// Seconds since the UNIX epoch, stored next to each row so the database can compare expiries
+fn unix_secs(time: &TAI64N) -> i64 {
+    match time.duration_since(&tai64::UNIX_EPOCH_TAI64N) {
+        Ok(elapsed) => elapsed.as_secs() as i64,
+        Err(_) => 0,
+    }
+}
+
+#[cfg(test)]
+mod sql_tests {
+    use super::*;
+    use crate::MemoryRevocationStore;
+    use core::time::Duration;
+    use std::sync::Mutex;
+
+    // Runs the statements of the stores against rows kept in memory
+    #[derive(Debug, Default)]
+    struct MemorySql {
+        sequence: Mutex<Option<i64>>,
+        revocations: Mutex<Vec<Vec<SqlValue>>>,
+        sessions: Mutex<Vec<Vec<SqlValue>>>,
+        down: Mutex<bool>,
+        failing: Mutex<Option<&'static str>>,
+        transaction: Mutex<()>,
+    }
+
+    impl SqlConnection for MemorySql {
+        fn execute(&self, statement: &str, params: &[SqlValue]) -> Result<u64, LiteSessionError> {
+            if *self.down.lock().unwrap() || *self.failing.lock().unwrap() == Some(statement) {
+                return Err(LiteSessionError::SqlStatementFailed);
+            }
+            let mut sequence = self.sequence.lock().unwrap();
+            let mut revocations = self.revocations.lock().unwrap();
+            let mut sessions = self.sessions.lock().unwrap();
+            let before = revocations.len() + sessions.len();
+
+            match statement {
+                CREATE_REVOCATIONS | CREATE_SEQUENCE | CREATE_SESSIONS | CREATE_SESSIONS_INDEX => {}
+                seed if seed == SqlDialect::Sqlite.seed_sequence() => {
+                    sequence.get_or_insert(0);
+                }
+                NEXT_SEQUENCE => *sequence.as_mut().unwrap() += 1,
+                RAISE_SEQUENCE => {
+                    let raised = SqlValue::integer(params.first())?;
+                    let current = sequence.as_mut().unwrap();
+                    *current = (*current).max(raised);
+                }
+                upsert if upsert == SqlDialect::Sqlite.upsert_revocation() => {
+                    revocations.retain(|row| row[0] != params[0]);
+                    revocations.push(params.to_vec());
+                    return Ok(1);
+                }
+                insert if insert == SqlDialect::Sqlite.insert_ignore_revocation() => {
+                    if revocations.iter().any(|row| row[0] == params[0]) {
+                        return Ok(0);
+                    }
+                    revocations.push(params.to_vec());
+                    return Ok(1);
+                }
+                CLEAR_REVOCATIONS => revocations.clear(),
+                PRUNE_REVOCATIONS => revocations.retain(|row| !less(&row[2], &params[0])),
+                upsert if upsert == SqlDialect::Sqlite.upsert_session() => {
+                    sessions.retain(|row| row[0] != params[0]);
+                    sessions.push(params.to_vec());
+                    return Ok(1);
+                }
+                DELETE_SESSION => sessions.retain(|row| row[0] != params[0]),
+                PRUNE_SESSIONS => sessions.retain(|row| !less(&row[3], &params[0])),
+                _ => return Err(LiteSessionError::SqlStatementFailed),
+            }
+
+            Ok((before as i64 - (revocations.len() + sessions.len()) as i64).unsigned_abs())
+        }
+
+        fn query(
+            &self,
+            statement: &str,
+            params: &[SqlValue],
+        ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError> {
+            if *self.down.lock().unwrap() {
+                return Err(LiteSessionError::SqlStatementFailed);
+            }
+            let revocations = self.revocations.lock().unwrap();
+            let sessions = self.sessions.lock().unwrap();
+
+            let rows = match statement {
+                SELECT_SEQUENCE => vec![vec![SqlValue::Integer(
+                    self.sequence.lock().unwrap().unwrap(),
+                )]],
+                SELECT_REVOCATION => revocations
+                    .iter()
+                    .filter(|row| row[0] == params[0] && !less(&row[2], &params[1]))
+                    .map(|row| vec![row[0].clone()])
+                    .collect(),
+                EXPORT_REVOCATIONS => {
+                    let mut rows = revocations
+                        .iter()
+                        .filter(|row| less(&params[0], &row[3]) && !less(&row[2], &params[1]))
+                        .cloned()
+                        .collect::<Vec<Vec<SqlValue>>>();
+                    rows.sort_by_key(|row| SqlValue::integer(row.get(3)).unwrap());
+                    rows.into_iter()
+                        .map(|row| vec![row[0].clone(), row[1].clone()])
+                        .collect()
+                }
+                SELECT_SESSION => sessions
+                    .iter()
+                    .filter(|row| row[0] == params[0] && !less(&row[3], &params[1]))
+                    .map(|row| vec![row[2].clone()])
+                    .collect(),
+                SELECT_SESSIONS_OF => sessions
+                    .iter()
+                    .filter(|row| row[1] == params[0] && !less(&row[3], &params[1]))
+                    .map(|row| vec![row[0].clone(), row[2].clone()])
+                    .collect(),
+                _ => return Err(LiteSessionError::SqlStatementFailed),
+            };
+
+            Ok(rows)
+        }
+
+        // Transactions run one at a time and restore the rows when they fail
+        fn transaction(
+            &self,
+            work: &mut dyn FnMut(&dyn SqlConnection) -> Result<(), LiteSessionError>,
+        ) -> Result<(), LiteSessionError> {
+            let _transaction = self.transaction.lock().unwrap();
+            let sequence = *self.sequence.lock().unwrap();
+            let revocations = self.revocations.lock().unwrap().clone();
+            let sessions = self.sessions.lock().unwrap().clone();
+
+            let outcome = work(self);
+            if outcome.is_err() {
+                *self.sequence.lock().unwrap() = sequence;
+                *self.revocations.lock().unwrap() = revocations;
+                *self.sessions.lock().unwrap() = sessions;
+            }
+
+            outcome
+        }
+    }
+
+    fn less(value: &SqlValue, other: &SqlValue) -> bool {
+        SqlValue::integer(Some(value)).unwrap() < SqlValue::integer(Some(other)).unwrap()
+    }
+
+    #[test]
+    fn dialects() {
+        assert_eq!(
+            SqlDialect::Postgres.statement(DELETE_SESSION),
+            "DELETE FROM lite_session_sessions WHERE session_id = $1"
+        );
+        assert_eq!(
+            SqlDialect::Postgres.statement(RAISE_SEQUENCE),
+            "UPDATE lite_session_revocation_sequence SET sequence = $1 WHERE id = 1 AND sequence < $2"
+        );
+        assert_eq!(SqlDialect::MySql.statement(DELETE_SESSION), DELETE_SESSION);
+
+        let sessions = SqlSessionStore::new(MemorySql::default(), SqlDialect::MySql);
+        assert_eq!(sessions.migrations(), vec![CREATE_SESSIONS_MYSQL]);
+        let revocations = SqlRevocationStore::new(MemorySql::default(), SqlDialect::Postgres);
+        assert!(revocations.migrations()[2].ends_with("ON CONFLICT DO NOTHING"));
+    }
+
+    #[test]
+    fn sql_revocations() -> Result<(), LiteSessionError> {
+        let expiry = TAI64N::now() + Duration::from_secs(600);
+        let store = SqlRevocationStore::new(MemorySql::default(), SqlDialect::Sqlite);
+        store.migrate()?;
+        store.revoke("foo", expiry);
+        store.revoke("bar", expiry);
+        store.revoke("stale", TAI64N::now() - Duration::from_secs(5));
+        assert!(store.is_revoked("foo"));
+        assert!(!store.is_revoked("stale"));
+        assert!(!store.is_revoked("baz"));
+        assert_eq!(store.prune()?, 1);
+
+        let delta = store.export(1);
+        assert_eq!(delta.get_sequence(), 3);
+        assert_eq!(delta.get_entries(), &vec![("bar".to_owned(), expiry)]);
+
+        let memory = MemoryRevocationStore::new();
+        assert!(memory.import(&store.export(0)).is_ok());
+        assert!(memory.is_revoked("foo") && memory.is_revoked("bar"));
+
+        let replica = SqlRevocationStore::new(MemorySql::default(), SqlDialect::Sqlite);
+        replica.migrate()?;
+        replica.revoke("old", expiry);
+        assert!(replica.import(&store.export(0)).is_ok());
+        assert!(replica.is_revoked("foo"));
+        assert!(!replica.is_revoked("old"));
+        assert_eq!(replica.export(0).get_sequence(), 3);
+        assert_eq!(
+            replica.import(&store.export(4)),
+            Err(LiteSessionError::RevocationSnapshotGap)
+        );
+
+        // A failed full import leaves the revocations as they were
+        replica.try_add("new", &expiry)?;
+        *replica.get_connection().failing.lock().unwrap() = Some(RAISE_SEQUENCE);
+        assert_eq!(
+            replica.try_import(&memory.export(0)),
+            Err(LiteSessionError::SqlStatementFailed)
+        );
+        assert!(replica.is_revoked("new") && replica.is_revoked("foo"));
+        *replica.get_connection().failing.lock().unwrap() = None;
+
+        assert!(store.try_revoke("baz", expiry));
+        assert!(!store.try_revoke("baz", expiry));
+        assert!(!store.try_revoke("foo", expiry));
+
+        *store.get_connection().down.lock().unwrap() = true;
+        assert_eq!(
+            store.try_add("qux", &expiry),
+            Err(LiteSessionError::SqlStatementFailed)
+        );
+        assert!(store.try_export(0).is_err());
+        assert!(store.is_revoked("baz"));
+        assert!(!store.try_revoke("qux", expiry));
+        assert!(store.export(0).is_delta());
+
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_revocations() -> Result<(), LiteSessionError> {
+        let expiry = TAI64N::now() + Duration::from_secs(600);
+        let store = SqlRevocationStore::new(MemorySql::default(), SqlDialect::Sqlite);
+        store.migrate()?;
+
+        std::thread::scope(|scope| {
+            (0..8).for_each(|index| {
+                let store = &store;
+                scope.spawn(move || store.try_add(&format!("foo{}", index), &expiry));
+            })
+        });
+
+        // Every revocation got a sequence of its own
+        assert_eq!(store.try_export(0)?.get_sequence(), 8);
+        for since in 0..8 {
+            assert_eq!(
+                store.try_export(since)?.get_entries().len(),
+                8 - since as usize
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn sql_sessions() -> Result<(), LiteSessionError> {
+        let expiry = TAI64N::now() + Duration::from_secs(600);
+        let mut record = SessionRecord::new("abc", TAI64N::now(), expiry);
+        record.subject("foo_user");
+
+        let store = SqlSessionStore::new(MemorySql::default(), SqlDialect::Sqlite);
+        store.migrate()?;
+        store.put("foo", record.clone());
+        store.put(
+            "stale",
+            SessionRecord::new("def", TAI64N::now(), TAI64N::now() - Duration::from_secs(5)),
+        );
+        assert_eq!(store.get("foo"), Some(record.clone()));
+        assert_eq!(store.get("stale"), None);
+        assert_eq!(
+            store.sessions_of("foo_user"),
+            vec![("foo".to_owned(), record.clone())]
+        );
+        assert_eq!(store.prune()?, 1);
+
+        let mut moved = record.clone();
+        moved.subject("bar_user");
+        store.put("foo", moved.clone());
+        assert!(store.sessions_of("foo_user").is_empty());
+
+        assert_eq!(store.delete("foo"), Some(moved));
+        assert_eq!(store.get("foo"), None);
+        assert!(store.sessions_of("bar_user").is_empty());
+
+        *store.get_connection().down.lock().unwrap() = true;
+        assert_eq!(
+            store.try_put("foo", &record),
+            Err(LiteSessionError::SqlStatementFailed)
+        );
+        assert!(store.try_get("foo").is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,245 @@
+use crate::{LiteSessionError, SqlConnection, SqlValue};
+use ::sqlx::{
+    any::{Any, AnyArguments, AnyRow},
+    query::Query,
+    AnyPool, Row, Transaction,
+};
+use core::future::Future;
+use std::sync::Mutex;
+use tokio::runtime::Handle;
+
+/// A `SqlConnection` running the statements of the SQL backed stores on a sqlx `AnyPool`,
+/// enabled by the `sqlx` feature. The stores are synchronous so each statement blocks on
+/// `handle` until it completes. Called from inside a Tokio runtime the wait moves the worker
+/// off the runtime with `block_in_place()`, which needs the multi-threaded runtime.
+/// Install the drivers with `sqlx::any::install_default_drivers()` before connecting the pool
+///
+/// ```no_run
+/// use lite_session::{SqlDialect, SqlRevocationStore, SqlxConnection};
+/// use sqlx::AnyPool;
+///
+/// let runtime = tokio::runtime::Runtime::new().unwrap();
+/// sqlx::any::install_default_drivers();
+/// let pool = runtime
+///     .block_on(AnyPool::connect("postgres://localhost/sessions"))
+///     .unwrap();
+/// let store = SqlRevocationStore::new(
+///     SqlxConnection::new(pool, runtime.handle().clone()),
+///     SqlDialect::Postgres,
+/// );
+/// store.migrate().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SqlxConnection {
+    pool: AnyPool,
+    handle: Handle,
+}
+
+impl core::clone::Clone for SqlxConnection {
+    fn clone(&self) -> Self {
+        Self {
+            pool: self.pool.clone(),
+            handle: self.handle.clone(),
+        }
+    }
+}
+
+impl SqlxConnection {
+    /// Run the statements on `pool`, blocking on the runtime of `handle`
+    pub fn new(pool: AnyPool, handle: Handle) -> Self {
+        Self { pool, handle }
+    }
+    /// Get the pool
+    pub fn get_pool(&self) -> &AnyPool {
+        &self.pool
+    }
+    /// Get the handle of the runtime the statements run on
+    pub fn get_handle(&self) -> &Handle {
+        &self.handle
+    }
+}
+
+impl SqlConnection for SqlxConnection {
+    fn execute(&self, statement: &str, params: &[SqlValue]) -> Result<u64, LiteSessionError> {
+        block_on(&self.handle, bind(statement, params).execute(&self.pool))
+            .map(|done| done.rows_affected())
+            .map_err(|_| LiteSessionError::SqlStatementFailed)
+    }
+
+    fn query(
+        &self,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError> {
+        block_on(&self.handle, bind(statement, params).fetch_all(&self.pool))
+            .map_err(|_| LiteSessionError::SqlStatementFailed)?
+            .iter()
+            .map(columns)
+            .collect()
+    }
+
+    fn transaction(
+        &self,
+        work: &mut dyn FnMut(&dyn SqlConnection) -> Result<(), LiteSessionError>,
+    ) -> Result<(), LiteSessionError> {
+        let transaction = block_on(&self.handle, self.pool.begin())
+            .map_err(|_| LiteSessionError::SqlStatementFailed)?;
+        let transaction = SqlxTransaction {
+            transaction: Mutex::new(transaction),
+            handle: self.handle.clone(),
+        };
+        let outcome = work(&transaction);
+
+        let transaction = transaction
+            .transaction
+            .into_inner()
+            .map_err(|_| LiteSessionError::SqlStatementFailed)?;
+        match outcome {
+            Ok(()) => block_on(&self.handle, transaction.commit())
+                .map_err(|_| LiteSessionError::SqlStatementFailed),
+            Err(error) => {
+                // The failure of `work` is the error worth reporting, the database rolls
+                // the transaction back on its own if the rollback cannot be sent
+                let _ = block_on(&self.handle, transaction.rollback());
+                Err(error)
+            }
+        }
+    }
+}
+
+// The statements of `SqlConnection::transaction()`, all run on the connection of the transaction
+struct SqlxTransaction {
+    transaction: Mutex<Transaction<'static, Any>>,
+    handle: Handle,
+}
+
+impl core::fmt::Debug for SqlxTransaction {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("SqlxTransaction").finish_non_exhaustive()
+    }
+}
+
+impl SqlConnection for SqlxTransaction {
+    fn execute(&self, statement: &str, params: &[SqlValue]) -> Result<u64, LiteSessionError> {
+        let mut transaction = self
+            .transaction
+            .lock()
+            .map_err(|_| LiteSessionError::SqlStatementFailed)?;
+
+        block_on(
+            &self.handle,
+            bind(statement, params).execute(&mut **transaction),
+        )
+        .map(|done| done.rows_affected())
+        .map_err(|_| LiteSessionError::SqlStatementFailed)
+    }
+
+    fn query(
+        &self,
+        statement: &str,
+        params: &[SqlValue],
+    ) -> Result<Vec<Vec<SqlValue>>, LiteSessionError> {
+        let mut transaction = self
+            .transaction
+            .lock()
+            .map_err(|_| LiteSessionError::SqlStatementFailed)?;
+
+        block_on(
+            &self.handle,
+            bind(statement, params).fetch_all(&mut **transaction),
+        )
+        .map_err(|_| LiteSessionError::SqlStatementFailed)?
+        .iter()
+        .map(columns)
+        .collect()
+    }
+
+    // Work nested in the transaction joins it
+    fn transaction(
+        &self,
+        work: &mut dyn FnMut(&dyn SqlConnection) -> Result<(), LiteSessionError>,
+    ) -> Result<(), LiteSessionError> {
+        work(self)
+    }
+}
+
+fn block_on<F: Future>(handle: &Handle, future: F) -> F::Output {
+    match Handle::try_current() {
+        Ok(_) => tokio::task::block_in_place(|| handle.block_on(future)),
+        Err(_) => handle.block_on(future),
+    }
+}
+
+fn bind<'q>(statement: &'q str, params: &'q [SqlValue]) -> Query<'q, Any, AnyArguments<'q>> {
+    params
+        .iter()
+        .fold(::sqlx::query(statement), |query, param| match param {
+            SqlValue::Text(value) => query.bind(value.as_str()),
+            SqlValue::Integer(value) => query.bind(*value),
+        })
+}
+
+// The stores only read `BIGINT` and text columns
+fn columns(row: &AnyRow) -> Result<Vec<SqlValue>, LiteSessionError> {
+    (0..row.len())
+        .map(|index| {
+            row.try_get::<i64, _>(index)
+                .map(SqlValue::Integer)
+                .or_else(|_| row.try_get::<String, _>(index).map(SqlValue::Text))
+                .map_err(|_| LiteSessionError::SqlStatementFailed)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod sqlx_tests {
+    use super::SqlxConnection;
+    use crate::{
+        LiteSessionError, RevocationStore, SessionRecord, SessionStore, SqlDialect,
+        SqlRevocationStore, SqlSessionStore,
+    };
+    use ::sqlx::any::{install_default_drivers, AnyPoolOptions};
+    use core::time::Duration;
+    use tai64::TAI64N;
+
+    #[test]
+    fn sqlite_stores() -> Result<(), LiteSessionError> {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        install_default_drivers();
+        // Every connection to `sqlite::memory:` opens a database of its own
+        let pool = runtime
+            .block_on(
+                AnyPoolOptions::new()
+                    .max_connections(1)
+                    .idle_timeout(None)
+                    .max_lifetime(None)
+                    .connect("sqlite::memory:"),
+            )
+            .unwrap();
+        let connection = SqlxConnection::new(pool, runtime.handle().clone());
+        let expiry = TAI64N::now() + Duration::from_secs(60);
+
+        let revocations = SqlRevocationStore::new(connection.clone(), SqlDialect::Sqlite);
+        revocations.migrate()?;
+        revocations.migrate()?;
+        revocations.try_add("foo", &expiry)?;
+        assert!(revocations.is_revoked("foo"));
+        assert!(!revocations.is_revoked("bar"));
+        let snapshot = revocations.try_export(0)?;
+        assert_eq!(snapshot.get_sequence(), 1);
+        assert_eq!(snapshot.get_entries().len(), 1);
+
+        let sessions = SqlSessionStore::new(connection, SqlDialect::Sqlite);
+        sessions.migrate()?;
+        let record = SessionRecord::new("abc", TAI64N::now(), expiry);
+        sessions.put("foo", record.clone());
+        assert_eq!(sessions.try_get("foo")?, Some(record.clone()));
+        assert_eq!(sessions.delete("foo"), Some(record));
+        assert_eq!(sessions.try_get("foo")?, None);
+
+        // Inside the runtime the statements leave the worker with `block_in_place()`
+        runtime.block_on(async { assert!(revocations.is_revoked("foo")) });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+use crate::{persist, LiteSessionError};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use tai64::TAI64N;
+
+/// The server-side record of a session anchoring the tokens issued in
+/// `LiteSessionMode::SessionID` mode. Deleting the record ends the session
+/// even though its tokens have not expired
+///
+/// ```
+/// use tai64::TAI64N;
+///
+/// pub struct SessionRecord {
+///     identifier: String,
+///     subject: String,
+///     issued: TAI64N,
+///     expiry: TAI64N,
+///     value: String,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SessionRecord {
+    identifier: String,
+    subject: String,
+    issued: TAI64N,
+    expiry: TAI64N,
+    value: String,
+}
+
+impl core::cmp::PartialEq for SessionRecord {
+    fn eq(&self, other: &Self) -> bool {
+        self.identifier == other.identifier
+            && self.subject == other.subject
+            && self.issued == other.issued
+            && self.expiry == other.expiry
+            && self.value == other.value
+    }
+}
+
+impl core::clone::Clone for SessionRecord {
+    fn clone(&self) -> Self {
+        Self {
+            identifier: self.identifier.clone(),
+            subject: self.subject.clone(),
+            issued: self.issued,
+            expiry: self.expiry,
+            value: self.value.clone(),
+        }
+    }
+}
+
+impl SessionRecord {
+    /// Create the record of the session held by the token with `identifier` issued at `issued`
+    /// and expiring at `expiry`. The session belongs to no subject until `subject()` is set
+    pub fn new(identifier: &str, issued: TAI64N, expiry: TAI64N) -> Self {
+        Self {
+            identifier: identifier.into(),
+            subject: String::default(),
+            issued,
+            expiry,
+            value: String::default(),
+        }
+    }
+    /// Set the user the session belongs to, their subject ID or username
+    pub fn subject(&mut self, subject: &str) -> &mut Self {
+        self.subject = subject.into();
+
+        self
+    }
+    /// Set the time the session was issued
+    pub fn issued(&mut self, issued: TAI64N) -> &mut Self {
+        self.issued = issued;
+
+        self
+    }
+    /// Keep application state like a shopping cart with the session
+    pub fn value(&mut self, value: &str) -> &mut Self {
+        self.value = value.into();
+
+        self
+    }
+    /// Get the identifier of the token holding the session
+    pub fn get_identifier(&self) -> &String {
+        &self.identifier
+    }
+    /// Get the user the session belongs to
+    pub fn get_subject(&self) -> &String {
+        &self.subject
+    }
+    /// Get the time the session was issued
+    pub fn get_issued(&self) -> &TAI64N {
+        &self.issued
+    }
+    /// Get the time the session expires
+    pub fn get_expiry(&self) -> &TAI64N {
+        &self.expiry
+    }
+    /// Get the application state
+    pub fn get_value(&self) -> &String {
+        &self.value
+    }
+    /// Check whether the session has expired at `now`
+    pub fn is_expired(&self, now: &TAI64N) -> bool {
+        self.expiry <= *now
+    }
+    /// Serialize the record of `session_id` into a single line
+    pub fn to_string(session_id: &str, record: &SessionRecord) -> String {
+        format!(
+            "{} {} {} {} {} {}",
+            base64::encode_config(session_id, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&record.identifier, base64::URL_SAFE_NO_PAD),
+            base64::encode_config(&record.subject, base64::URL_SAFE_NO_PAD),
+            hex::encode(record.issued.to_bytes()),
+            hex::encode(record.expiry.to_bytes()),
+            base64::encode_config(&record.value, base64::URL_SAFE_NO_PAD)
+        )
+    }
+    /// Deserialize a line into the session ID and its record
+    pub fn from_string(value: &str) -> Result<(String, Self), LiteSessionError> {
+        let fields = value.split(' ').collect::<Vec<&str>>();
+        if fields.len() != 6_usize {
+            return Err(LiteSessionError::InvalidSessionRecord);
+        }
+
+        let session_id = SessionRecord::text(fields[0])?;
+        let identifier = SessionRecord::text(fields[1])?;
+        let subject = SessionRecord::text(fields[2])?;
+        let issued = SessionRecord::time(fields[3])?;
+        let expiry = SessionRecord::time(fields[4])?;
+        let value = SessionRecord::text(fields[5])?;
+
+        Ok((
+            session_id,
+            Self {
+                identifier,
+                subject,
+                issued,
+                expiry,
+                value,
+            },
+        ))
+    }
+
+    fn time(encoded: &str) -> Result<TAI64N, LiteSessionError> {
+        match hex::decode(encoded).map(|bytes| TAI64N::from_slice(&bytes)) {
+            Ok(Ok(time)) => Ok(time),
+            _ => Err(LiteSessionError::InvalidSessionRecord),
+        }
+    }
+
+    fn text(encoded: &str) -> Result<String, LiteSessionError> {
+        match base64::decode_config(encoded, base64::URL_SAFE_NO_PAD)
+            .ok()
+            .and_then(|bytes| String::from_utf8(bytes).ok())
+        {
+            Some(text) => Ok(text),
+            None => Err(LiteSessionError::InvalidSessionRecord),
+        }
+    }
+}
+
+/// A store of session records keyed by the session ID of `LiteSessionMode::SessionID`,
+/// giving the session ID mode a server-side anchor.
+/// Implement this trait for shared stores like Redis to share sessions across servers
+pub trait SessionStore: core::fmt::Debug + Send + Sync {
+    /// Get the record of the session `session_id`
+    fn get(&self, session_id: &str) -> Option<SessionRecord>;
+    /// Store the record of the session `session_id`, replacing any existing record
+    fn put(&self, session_id: &str, record: SessionRecord);
+    /// Delete the record of the session `session_id`, returning it if it existed
+    fn delete(&self, session_id: &str) -> Option<SessionRecord>;
+    /// Get the session IDs and records of the sessions belonging to `subject`
+    fn sessions_of(&self, subject: &str) -> Vec<(String, SessionRecord)>;
+}
+
+/// An in-memory `SessionStore` suitable for a single server
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, SessionRecord>>,
+}
+
+impl MemorySessionStore {
+    /// Create a new empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Remove the records of the sessions that have already expired
+    pub fn prune(&self) {
+        let now = TAI64N::now();
+        self.lock().retain(|_, record| !record.is_expired(&now));
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, SessionRecord>> {
+        match self.sessions.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        }
+    }
+}
+
+impl SessionStore for MemorySessionStore {
+    fn get(&self, session_id: &str) -> Option<SessionRecord> {
+        self.lock().get(session_id).cloned()
+    }
+
+    fn put(&self, session_id: &str, record: SessionRecord) {
+        self.lock().insert(session_id.into(), record);
+    }
+
+    fn delete(&self, session_id: &str) -> Option<SessionRecord> {
+        self.lock().remove(session_id)
+    }
+
+    fn sessions_of(&self, subject: &str) -> Vec<(String, SessionRecord)> {
+        self.lock()
+            .iter()
+            .filter(|(_, record)| record.subject == subject)
+            .map(|(session_id, record)| (session_id.clone(), record.clone()))
+            .collect()
+    }
+}
+
+/// A `SessionStore` kept in memory and persisted to a file with one `SessionRecord` per line
+/// after every change, so that sessions survive a restart of a single server.
+/// Changes are written one at a time. A change that cannot be written is kept in memory,
+/// `try_put()` and `try_delete()` return the write error, call `save()` to retry and
+/// learn whether the file is up to date
+#[derive(Debug)]
+pub struct FileSessionStore {
+    path: PathBuf,
+    memory: MemorySessionStore,
+    writing: Mutex<()>,
+}
+
+impl FileSessionStore {
+    /// Open the store persisted at `path`, starting empty if the file does not exist yet
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, LiteSessionError> {
+        let path = path.as_ref().to_path_buf();
+        let memory = MemorySessionStore::new();
+
+        match std::fs::read_to_string(&path) {
+            Ok(value) => {
+                for line in value.lines().filter(|line| !line.is_empty()) {
+                    let (session_id, record) = SessionRecord::from_string(line)?;
+                    memory.put(&session_id, record);
+                }
+            }
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => (),
+            Err(_) => return Err(LiteSessionError::SessionStoreIoError),
+        }
+
+        Ok(Self {
+            path,
+            memory,
+            writing: Mutex::new(()),
+        })
+    }
+    /// Get the path of the file
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+    /// Write the records of the sessions that have not expired to the file
+    pub fn save(&self) -> Result<(), LiteSessionError> {
+        self.change(|_| ())
+    }
+    /// Store the record of `session_id` like `put()`, returning the error `put()` ignores
+    /// when the file cannot be written
+    pub fn try_put(
+        &self,
+        session_id: &str,
+        record: &SessionRecord,
+    ) -> Result<(), LiteSessionError> {
+        self.change(|memory| memory.put(session_id, record.clone()))
+    }
+    /// Delete the record of `session_id` like `delete()`, returning the error `delete()`
+    /// ignores when the file cannot be written
+    pub fn try_delete(&self, session_id: &str) -> Result<Option<SessionRecord>, LiteSessionError> {
+        let mut record = None;
+        self.change(|memory| record = memory.delete(session_id))?;
+
+        Ok(record)
+    }
+
+    // Apply `change` and write the result while holding the write lock, so that a slower
+    // writer never renames older records over the file after newer ones
+    fn change(&self, change: impl FnOnce(&MemorySessionStore)) -> Result<(), LiteSessionError> {
+        let _writing = match self.writing.lock() {
+            Ok(guard) => guard,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        change(&self.memory);
+
+        self.memory.prune();
+        let lines = self
+            .memory
+            .lock()
+            .iter()
+            .map(|(session_id, record)| SessionRecord::to_string(session_id, record))
+            .collect::<Vec<String>>();
+        persist::replace_file(&self.path, &lines.join("\n"))
+            .map_err(|_| LiteSessionError::SessionStoreIoError)
+    }
+}
+
+impl SessionStore for FileSessionStore {
+    fn get(&self, session_id: &str) -> Option<SessionRecord> {
+        self.memory.get(session_id)
+    }
+
+    fn put(&self, session_id: &str, record: SessionRecord) {
+        // The error is returned by `try_put()`
+        let _ = self.try_put(session_id, &record);
+    }
+
+    fn delete(&self, session_id: &str) -> Option<SessionRecord> {
+        // The error is returned by `try_delete()`
+        let mut record = None;
+        let _ = self.change(|memory| record = memory.delete(session_id));
+
+        record
+    }
+
+    fn sessions_of(&self, subject: &str) -> Vec<(String, SessionRecord)> {
+        self.memory.sessions_of(subject)
+    }
+}
+
+#[cfg(test)]
+mod store_tests {
+    use super::{FileSessionStore, MemorySessionStore, SessionRecord, SessionStore};
+    use crate::LiteSessionError;
+    use core::time::Duration;
+    use tai64::TAI64N;
+
+    #[test]
+    fn session_record() -> Result<(), LiteSessionError> {
+        let mut record = SessionRecord::new(
+            "abc",
+            TAI64N::now(),
+            TAI64N::now() + Duration::from_secs(60),
+        );
+        record.value("cart=3 items").subject("foo_user");
+        assert!(!record.is_expired(&TAI64N::now()));
+        assert_eq!(record.get_subject(), "foo_user");
+
+        let line = SessionRecord::to_string("tls session", &record);
+        assert_eq!(line.split(' ').count(), 6);
+        assert_eq!(
+            SessionRecord::from_string(&line)?,
+            ("tls session".to_owned(), record)
+        );
+        assert_eq!(
+            SessionRecord::from_string("abc def"),
+            Err(LiteSessionError::InvalidSessionRecord)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn session_stores() -> Result<(), LiteSessionError> {
+        let expiry = TAI64N::now() + Duration::from_secs(60);
+        let mut record = SessionRecord::new("abc", TAI64N::now(), expiry);
+        record.subject("foo_user");
+
+        let memory = MemorySessionStore::new();
+        memory.put("foo", record.clone());
+        memory.put(
+            "stale",
+            SessionRecord::new("def", TAI64N::now(), TAI64N::now()),
+        );
+        assert_eq!(memory.get("foo"), Some(record.clone()));
+        assert_eq!(
+            memory.sessions_of("foo_user"),
+            vec![("foo".to_owned(), record.clone())]
+        );
+        memory.prune();
+        assert_eq!(memory.get("stale"), None);
+        assert_eq!(memory.delete("foo"), Some(record.clone()));
+        assert_eq!(memory.get("foo"), None);
+
+        let path = std::env::temp_dir().join("lite_session_sessions.lss");
+        let _ = std::fs::remove_file(&path);
+        let file = FileSessionStore::open(&path)?;
+        file.put("foo", record.clone());
+        file.put("bar", SessionRecord::new("def", TAI64N::now(), expiry));
+        assert!(file.delete("bar").is_some());
+
+        let reopened = FileSessionStore::open(&path)?;
+        assert_eq!(reopened.get("foo"), Some(record.clone()));
+        assert_eq!(reopened.get("bar"), None);
+        assert_eq!(
+            reopened.sessions_of("foo_user"),
+            vec![("foo".to_owned(), record.clone())]
+        );
+        assert_eq!(reopened.get_path(), path.as_path());
+        let _ = std::fs::remove_file(&path);
+
+        // Concurrent changes are all written
+        let file = std::sync::Arc::new(FileSessionStore::open(&path)?);
+        let handles = (0..8)
+            .map(|index| {
+                let (file, record) = (file.clone(), record.clone());
+                std::thread::spawn(move || file.try_put(&format!("foo{}", index), &record))
+            })
+            .collect::<Vec<_>>();
+        for handle in handles {
+            assert_eq!(handle.join().unwrap(), Ok(()));
+        }
+        assert_eq!(
+            FileSessionStore::open(&path)?.sessions_of("foo_user").len(),
+            8
+        );
+        let _ = std::fs::remove_file(&path);
+
+        // A change that cannot be written is reported and kept in memory
+        let unwritable = FileSessionStore::open(path.join("missing.lss"))?;
+        assert_eq!(
+            unwritable.try_put("foo", &record),
+            Err(LiteSessionError::SessionStoreIoError)
+        );
+        assert_eq!(unwritable.get("foo"), Some(record.clone()));
+        assert_eq!(
+            unwritable.try_delete("foo"),
+            Err(LiteSessionError::SessionStoreIoError)
+        );
+        assert_eq!(unwritable.get("foo"), None);
+        unwritable.put("foo", record.clone());
+        assert_eq!(unwritable.delete("foo"), Some(record));
+
+        assert_eq!(
+            FileSessionStore::open(std::env::temp_dir()).map(|_| ()),
+            Err(LiteSessionError::SessionStoreIoError)
+        );
+
+        Ok(())
+    }
+}
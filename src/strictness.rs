@@ -0,0 +1,43 @@
+/// Whether a malformed token is an error or an outcome of verification.
+/// `ParsePolicy::Strict` returns malformed tokens as `Err(LiteSessionError)` so that they can
+/// be answered with `400 Bad Request` while `ParsePolicy::Lenient` returns them as
+/// `Ok(TokenOutcome::BadToken)` like every other token that is not authentic.
+/// Errors caused by the configuration of the server are returned in both modes
+#[derive(Debug, Default)]
+pub enum ParsePolicy {
+    /// Malformed tokens are errors
+    #[default]
+    Strict,
+    /// Malformed tokens are `TokenOutcome::BadToken`
+    Lenient,
+}
+
+impl core::cmp::PartialEq for ParsePolicy {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (ParsePolicy::Strict, ParsePolicy::Strict)
+                | (ParsePolicy::Lenient, ParsePolicy::Lenient)
+        )
+    }
+}
+
+impl core::clone::Clone for ParsePolicy {
+    fn clone(&self) -> Self {
+        match self {
+            ParsePolicy::Strict => ParsePolicy::Strict,
+            ParsePolicy::Lenient => ParsePolicy::Lenient,
+        }
+    }
+}
+
+#[cfg(test)]
+mod strictness_tests {
+    use super::ParsePolicy;
+
+    #[test]
+    fn parse_policy() {
+        assert_eq!(ParsePolicy::default(), ParsePolicy::Strict);
+        assert_ne!(ParsePolicy::Lenient.clone(), ParsePolicy::Strict);
+    }
+}
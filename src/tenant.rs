@@ -0,0 +1,144 @@
+use crate::{LiteSessionError, ServerKey};
+use secrecy::{zeroize::Zeroize, ExposeSecret};
+
+const TENANT_KEY_CONTEXT: &str = "lite-session 2021-03-01 ls2 tenant key";
+
+/// The tenant a token was issued for, carried in the version header like `ls2-tfoo`.
+/// Tenant IDs are ASCII alphanumeric and at most `TenantId::MAX_LEN` bytes long
+///
+/// ```
+/// use lite_session::TenantId;
+///
+/// let tenant_id = TenantId::new("acme").unwrap();
+/// assert_eq!(tenant_id.as_str(), "acme");
+/// assert!(TenantId::new("acme-corp").is_err());
+/// ```
+#[derive(Debug)]
+pub struct TenantId(pub(crate) String);
+
+impl core::cmp::PartialEq for TenantId {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl core::clone::Clone for TenantId {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl TenantId {
+    /// The maximum length of a tenant ID in bytes
+    pub const MAX_LEN: usize = 32;
+
+    /// Check and wrap a tenant ID
+    pub fn new(tenant_id: &str) -> Result<Self, LiteSessionError> {
+        match TenantId::is_tenant_id(tenant_id) {
+            true => Ok(Self(tenant_id.into())),
+            false => Err(LiteSessionError::InvalidTenantId),
+        }
+    }
+    /// Get the tenant ID
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    /// Check whether `tenant_id` can be used as a tenant ID
+    pub fn is_tenant_id(tenant_id: &str) -> bool {
+        !tenant_id.is_empty()
+            && tenant_id.len() <= TenantId::MAX_LEN
+            && tenant_id.bytes().all(|byte| byte.is_ascii_alphanumeric())
+    }
+}
+
+/// Derives a subkey for every tenant from a master key using Blake3 `derive_key`.
+/// The subkey is used to encrypt and authenticate the tokens of that tenant so that
+/// a leaked tenant subkey cannot forge the tokens of another tenant
+///
+/// ```
+/// use lite_session::{
+///     LiteSessionData, LiteSessionToken, ServerKey, TenantId, TenantKeyring, TokenOutcome,
+/// };
+///
+/// let tenants = TenantKeyring::new(ServerKey::generate());
+/// let mut data = LiteSessionData::default();
+/// data.username("foo_user").add_acl("Network-TCP");
+///
+/// let mut token = LiteSessionToken::default();
+/// token.hmac_data(data).tenant_id(TenantId::new("acme").unwrap());
+/// let session_token = token.build_secure_with_tenant(&tenants).unwrap();
+/// assert!(session_token.starts_with("ls2-tacme"));
+///
+/// let mut destructured = LiteSessionToken::default();
+/// let (outcome, _) = destructured
+///     .from_string_with_tenant(&tenants, &session_token)
+///     .unwrap();
+/// assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+/// ```
+#[derive(Debug)]
+pub struct TenantKeyring {
+    master_key: ServerKey,
+}
+
+impl TenantKeyring {
+    /// Derive the tenant subkeys from `master_key`
+    pub fn new(master_key: ServerKey) -> Self {
+        Self { master_key }
+    }
+    /// Get the subkey of `tenant_id`
+    pub fn tenant_key(&self, tenant_id: &TenantId) -> ServerKey {
+        // The master key has a fixed length so the tenant ID needs no length prefix
+        let mut key_material = self.master_key.expose_secret().to_vec();
+        key_material.extend_from_slice(tenant_id.as_str().as_bytes());
+
+        let mut tenant_key = [0_u8; 32];
+        blake3::derive_key(TENANT_KEY_CONTEXT, &key_material, &mut tenant_key);
+        let server_key = ServerKey::new(tenant_key);
+        key_material.zeroize();
+        tenant_key.zeroize();
+
+        server_key
+    }
+}
+
+#[cfg(test)]
+mod tenant_tests {
+    use super::{TenantId, TenantKeyring};
+    use crate::{LiteSessionError, ServerKey};
+    use secrecy::ExposeSecret;
+
+    #[test]
+    fn tenant_keys() -> Result<(), LiteSessionError> {
+        let tenants = TenantKeyring::new(ServerKey::new([0_u8; 32]));
+        let foo = TenantId::new("foo")?;
+        let bar = TenantId::new("bar")?;
+
+        assert_eq!(
+            tenants.tenant_key(&foo).expose_secret(),
+            tenants.tenant_key(&foo.clone()).expose_secret()
+        );
+        assert_ne!(
+            tenants.tenant_key(&foo).expose_secret(),
+            tenants.tenant_key(&bar).expose_secret()
+        );
+        assert_ne!(tenants.tenant_key(&foo).expose_secret(), &[0_u8; 32]);
+        assert_ne!(
+            tenants.tenant_key(&foo).expose_secret(),
+            TenantKeyring::new(ServerKey::new([1_u8; 32]))
+                .tenant_key(&foo)
+                .expose_secret()
+        );
+
+        assert_eq!(TenantId::new(""), Err(LiteSessionError::InvalidTenantId));
+        assert_eq!(
+            TenantId::new(&"a".repeat(33)),
+            Err(LiteSessionError::InvalidTenantId)
+        );
+        assert_eq!(
+            TenantId::new("foo⊕bar"),
+            Err(LiteSessionError::InvalidTenantId)
+        );
+
+        Ok(())
+    }
+}
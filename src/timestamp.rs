@@ -0,0 +1,90 @@
+use core::time::Duration;
+use tai64::TAI64N;
+use timelite::LiteDuration;
+
+/// The plausible range of the `issued` and `expiry` timestamps of a token.
+/// Timestamps outside the range indicate a fabricated or corrupted token
+///
+/// ```
+/// use tai64::TAI64N;
+///
+/// pub struct TimestampRange {
+///     not_before: TAI64N,
+///     max_ahead: u64,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct TimestampRange {
+    not_before: TAI64N,
+    max_ahead: u64,
+}
+
+impl Default for TimestampRange {
+    /// Timestamps must not be before `2020-01-01T00:00:00Z` and not more than 10 years ahead
+    fn default() -> Self {
+        Self {
+            not_before: tai64::UNIX_EPOCH_TAI64N + Duration::from_secs(1_577_836_800),
+            max_ahead: LiteDuration::years(10),
+        }
+    }
+}
+
+impl core::cmp::PartialEq for TimestampRange {
+    fn eq(&self, other: &Self) -> bool {
+        self.not_before == other.not_before && self.max_ahead == other.max_ahead
+    }
+}
+
+impl core::clone::Clone for TimestampRange {
+    fn clone(&self) -> Self {
+        Self {
+            not_before: self.not_before,
+            max_ahead: self.max_ahead,
+        }
+    }
+}
+
+impl TimestampRange {
+    /// Create a range starting at `not_before` and ending `max_ahead_in_secs` seconds
+    /// after the current time of the server
+    pub fn new(not_before: TAI64N, max_ahead_in_secs: u64) -> Self {
+        Self {
+            not_before,
+            max_ahead: max_ahead_in_secs,
+        }
+    }
+    /// Get the earliest plausible timestamp
+    pub fn get_not_before(&self) -> &TAI64N {
+        &self.not_before
+    }
+    /// Get how far ahead of the current time a timestamp can be in seconds
+    pub fn get_max_ahead(&self) -> u64 {
+        self.max_ahead
+    }
+    /// Check whether `time` falls within the range given the current time `now`
+    pub fn contains(&self, time: &TAI64N, now: &TAI64N) -> bool {
+        *time >= self.not_before && *time <= *now + Duration::from_secs(self.max_ahead)
+    }
+}
+
+#[cfg(test)]
+mod timestamp_tests {
+    use super::TimestampRange;
+    use core::time::Duration;
+    use tai64::TAI64N;
+
+    #[test]
+    fn timestamp_range() {
+        let now = TAI64N::now();
+        let range = TimestampRange::default();
+
+        assert!(range.contains(&now, &now));
+        assert!(range.contains(&(now + Duration::from_secs(3600)), &now));
+        assert!(!range.contains(&TAI64N::from_slice(&[0_u8; 12]).unwrap(), &now));
+        assert!(!range.contains(&(now + Duration::from_secs(20 * 365 * 86400)), &now));
+
+        let range = TimestampRange::new(now, 60);
+        assert!(!range.contains(&(now - Duration::from_secs(1)), &now));
+        assert!(!range.contains(&(now + Duration::from_secs(61)), &now));
+    }
+}
@@ -1,18 +1,60 @@
 use crate::{
-    CipherText, ConfidentialityMode, LiteSessionData, LiteSessionError, LiteSessionMode,
-    SessionTokenRng, TokenOutcome,
+    AccessDecision, AttenuatedToken, AttributePolicy, CipherText, Clock, ConfidentialityMode,
+    ConfigIssue, EntropySource, IdentifierStrategy, KeyProvider, KeyRing, LiteSessionData,
+    LiteSessionError, LiteSessionMode, LiteSessionTokenBuilder, MacLength, NetworkBinding, NoData,
+    NoExpiry, OsEntropy, ParsePolicy, Policy, ProofOfPossession, ProofSignatureVerifier,
+    QuotaStore, RefreshPolicy, RejectionReason, Role, RoleTtls, Rounds, SealedToken, ServerKey,
+    SessionRecord, SessionStore, SessionTokenRng, SystemClock, TenantId, TenantKeyring,
+    TimestampRange, TokenChunks, TokenCipher, TokenClaims, TokenEncoding, TokenFormat,
+    TokenOutcome, TokenParser, TokenVersion, VerificationReport,
 };
 
+use crate::caveat;
+use crate::compression::{self, COMPRESSED_MARKER};
+use crate::envelope;
+use crate::epoch::epoch_user;
+#[cfg(feature = "fips")]
+use crate::fips;
 use core::time::Duration;
-use std::convert::TryInto;
+use secrecy::{zeroize::Zeroize, ExposeSecret};
+use std::{convert::TryInto, net::IpAddr, sync::Arc};
 use tai64::TAI64N;
 use timelite::LiteDuration;
 
+// version, confidentiality, issued, expiry, nonce, MAC length and the identifier, data and footer lengths
+const HEX_HEADER_LEN: usize = 1 + 1 + 12 + 12 + 12 + 1 + 2 + 4 + 2;
+// Flags of the confidentiality byte of the hex layout
+const HEX_FLAG_HIGH: u8 = 1;
+const HEX_FLAG_COMPRESSED: u8 = 2;
+const HEX_FLAG_XCHACHA20: u8 = 4;
+const HEX_FLAG_ROUNDS12: u8 = 8;
+const HEX_FLAG_ROUNDS20: u8 = 16;
+// Both rounds flags together select `TokenCipher::Aes256Gcm`
+const HEX_FLAG_AES256GCM: u8 = HEX_FLAG_ROUNDS12 | HEX_FLAG_ROUNDS20;
+const HEX_FLAG_KEY_ID: u8 = 32;
+const HEX_FLAG_TENANT_ID: u8 = 64;
+const HEX_FLAG_BINARY_NONCE: u8 = 128;
+const ENCRYPTION_KEY_CONTEXT: &str = "lite-session 2021-03-01 ls2 token encryption key";
+const MAC_CONTEXT: &str = "lite-session 2021-03-01 ls2 token mac";
+// Presence bytes of the bound session field of the ls2 MAC, telling the binding modes apart
+const MAC_MODE_SESSION_ID: u8 = 1;
+const MAC_MODE_DEVICE_BOUND: u8 = 2;
+const KEY_COMMITMENT_CONTEXT: &str = "lite-session 2021-03-01 ls2 key commitment";
+const CSRF_CONTEXT: &str = "lite-session 2021-03-01 csrf token";
+// Set in the version byte of the hex layout when the key commitment follows the tenant ID
+const HEX_VERSION_KEY_COMMITMENT: u8 = 128;
+// Set in the version byte of the hex layout when the data starts with a wrapped data key
+const HEX_VERSION_ENVELOPE: u8 = 64;
+// Large enough for a few hundred ACL entries while staying under common proxy header limits
+pub(crate) const DEFAULT_MAX_DATA_SIZE: usize = 4 * 1024;
+
 /// The token strucuture that performs token operations
 ///
 /// ```
+/// use core::time::Duration;
+/// use std::sync::Arc;
 /// use tai64::TAI64N;
-/// use lite_session::{LiteSessionData, ConfidentialityMode, LiteSessionMode};
+/// use lite_session::{Clock, LiteSessionData, ParsePolicy, RefreshPolicy, VerificationReport, ConfidentialityMode, LiteSessionMode, MacLength, NetworkBinding, RejectionReason, RoleTtls, TimestampRange, TokenFormat, TokenVersion, TokenEncoding, TokenCipher, TenantId, EntropySource};
 /// use blake3::Hash;
 ///
 /// pub struct LiteSessionToken {
@@ -23,6 +65,37 @@ use timelite::LiteDuration;
 ///     confidentiality: ConfidentialityMode,
 ///     hmac: blake3::Hash,
 ///     mode: LiteSessionMode,
+///     mac_length: MacLength,
+///     mac_fallback: Option<(MacLength, TAI64N)>,
+///     rejection: Option<RejectionReason>,
+///     timestamp_range: Option<TimestampRange>,
+///     role_ttls: RoleTtls,
+///     format: TokenFormat,
+///     dummy_verification: bool,
+///     version: TokenVersion,
+///     footer: Option<String>,
+///     encoding: TokenEncoding,
+///     compression_threshold: Option<usize>,
+///     cipher: TokenCipher,
+///     key_id: Option<String>,
+///     tenant_id: Option<TenantId>,
+///     entropy: Option<Arc<dyn EntropySource>>,
+///     key_commitment: bool,
+///     envelope: bool,
+///     required_audience: Option<String>,
+///     allowed_issuers: Option<Vec<String>>,
+///     max_data_size: usize,
+///     leeway: Duration,
+///     clock: Option<Arc<dyn Clock>>,
+///     max_age: Option<Duration>,
+///     sliding: Option<RefreshPolicy>,
+///     refreshed: Option<String>,
+///     expired_grace: Duration,
+///     report: VerificationReport,
+///     parse_policy: ParsePolicy,
+///     network: Option<NetworkBinding>,
+///     caveats: Vec<String>,
+///     confirming: Option<&'static str>,
 /// }
 /// ````
 #[derive(Debug)]
@@ -34,11 +107,42 @@ pub struct LiteSessionToken {
     confidentiality: ConfidentialityMode,
     hmac: blake3::Hash,
     mode: LiteSessionMode,
+    mac_length: MacLength,
+    mac_fallback: Option<(MacLength, TAI64N)>,
+    rejection: Option<RejectionReason>,
+    timestamp_range: Option<TimestampRange>,
+    role_ttls: RoleTtls,
+    format: TokenFormat,
+    dummy_verification: bool,
+    version: TokenVersion,
+    footer: Option<String>,
+    encoding: TokenEncoding,
+    compression_threshold: Option<usize>,
+    cipher: TokenCipher,
+    key_id: Option<String>,
+    tenant_id: Option<TenantId>,
+    entropy: Option<Arc<dyn EntropySource>>,
+    key_commitment: bool,
+    envelope: bool,
+    required_audience: Option<String>,
+    allowed_issuers: Option<Vec<String>>,
+    max_data_size: usize,
+    leeway: Duration,
+    clock: Option<Arc<dyn Clock>>,
+    max_age: Option<Duration>,
+    sliding: Option<RefreshPolicy>,
+    refreshed: Option<String>,
+    expired_grace: Duration,
+    report: VerificationReport,
+    parse_policy: ParsePolicy,
+    network: Option<NetworkBinding>,
+    caveats: Vec<String>,
+    confirming: Option<&'static str>,
 }
 
 impl Default for LiteSessionToken {
     fn default() -> Self {
-        let now = TAI64N::now();
+        let now = SystemClock.now();
         let default_expiry = LiteDuration::hours(24);
         let hmac_default = blake3::hash(b"");
 
@@ -50,6 +154,37 @@ impl Default for LiteSessionToken {
             confidentiality: ConfidentialityMode::default(),
             hmac: hmac_default,
             mode: LiteSessionMode::Passive,
+            mac_length: MacLength::default(),
+            mac_fallback: Option::default(),
+            rejection: Option::default(),
+            timestamp_range: Option::default(),
+            role_ttls: RoleTtls::default(),
+            format: TokenFormat::default(),
+            dummy_verification: bool::default(),
+            version: TokenVersion::default(),
+            footer: Option::default(),
+            encoding: TokenEncoding::default(),
+            compression_threshold: Option::default(),
+            cipher: TokenCipher::default(),
+            key_id: None,
+            tenant_id: None,
+            entropy: None,
+            key_commitment: false,
+            envelope: false,
+            required_audience: Option::default(),
+            allowed_issuers: Option::default(),
+            max_data_size: DEFAULT_MAX_DATA_SIZE,
+            leeway: Duration::default(),
+            clock: None,
+            max_age: None,
+            sliding: None,
+            refreshed: None,
+            expired_grace: Duration::default(),
+            report: VerificationReport::default(),
+            parse_policy: ParsePolicy::default(),
+            network: None,
+            caveats: Vec::default(),
+            confirming: None,
         }
     }
 }
@@ -80,17 +215,117 @@ impl core::clone::Clone for LiteSessionToken {
             confidentiality: self.confidentiality.clone(),
             hmac: self.hmac.clone(),
             mode: self.mode.clone(),
+            mac_length: self.mac_length.clone(),
+            mac_fallback: self.mac_fallback.clone(),
+            rejection: self.rejection.clone(),
+            timestamp_range: self.timestamp_range.clone(),
+            role_ttls: self.role_ttls.clone(),
+            format: self.format.clone(),
+            dummy_verification: self.dummy_verification,
+            version: self.version.clone(),
+            footer: self.footer.clone(),
+            encoding: self.encoding.clone(),
+            compression_threshold: self.compression_threshold,
+            cipher: self.cipher.clone(),
+            key_id: self.key_id.clone(),
+            tenant_id: self.tenant_id.clone(),
+            entropy: self.entropy.clone(),
+            key_commitment: self.key_commitment,
+            envelope: self.envelope,
+            required_audience: self.required_audience.clone(),
+            allowed_issuers: self.allowed_issuers.clone(),
+            max_data_size: self.max_data_size,
+            leeway: self.leeway,
+            clock: self.clock.clone(),
+            max_age: self.max_age,
+            sliding: self.sliding.clone(),
+            refreshed: self.refreshed.clone(),
+            expired_grace: self.expired_grace,
+            report: self.report.clone(),
+            parse_policy: self.parse_policy.clone(),
+            network: self.network.clone(),
+            caveats: self.caveats.clone(),
+            confirming: self.confirming,
         }
     }
 }
 
 impl LiteSessionToken {
-    /// Add an custom identifier for the token
+    /// Add an custom identifier for the token. Building the token fails with
+    /// `LiteSessionError::InvalidTokenFormat` if the identifier is empty or contains a
+    /// newline, a token or data section separator
     pub fn identifier(&mut self, identifier: &str) -> &mut Self {
         self.identifier = identifier.into();
 
         self
     }
+    /// Draw the identifier and nonces of the token from `entropy` instead of `OsEntropy`.
+    /// The identifier is regenerated from the new source
+    pub fn entropy_source(&mut self, entropy: Arc<dyn EntropySource>) -> &mut Self {
+        self.identifier = SessionTokenRng::alphanumeric_from(entropy.as_ref());
+        self.entropy = Some(entropy);
+
+        self
+    }
+    /// Read the current time from `clock` instead of `SystemClock` when issuing and verifying.
+    /// The token is re-issued at the time of the new clock keeping its lifetime
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        let lifetime = self
+            .expiry
+            .duration_since(&self.issued)
+            .unwrap_or_default()
+            .as_secs();
+        self.reissue_at(clock.now(), lifetime);
+        self.clock = Some(clock);
+
+        self
+    }
+    /// Commit to the derived key by adding a hash of it to the version header.
+    /// The commitment is checked before decrypting so that a token can never
+    /// be decrypted under a different key than the one it was built with
+    pub fn key_commitment(&mut self, key_commitment: bool) -> &mut Self {
+        self.key_commitment = key_commitment;
+
+        self
+    }
+    /// Set the maximum size in bytes of the encoded `LiteSessionData`, `4 KiB` by default.
+    /// `build_secure()` fails with `LiteSessionError::DataSizeTooLarge` for larger data and
+    /// verifying a token with larger data fails with it as a `RejectionReason::Malformed` token
+    pub fn max_data_size(&mut self, max_data_size: usize) -> &mut Self {
+        self.max_data_size = max_data_size;
+
+        self
+    }
+    /// Encrypt the data section under a random data key and wrap the data key under the
+    /// derived key inside the token. Envelope encrypted tokens can be moved to a new server
+    /// key with `rewrap()` without re-encrypting the data section.
+    /// Only `ConfidentialityMode::High` tokens are envelope encrypted
+    pub fn envelope(&mut self, envelope: bool) -> &mut Self {
+        self.envelope = envelope;
+
+        self
+    }
+    /// Issue the token for a tenant. Build it with `build_secure_with_tenant()`
+    /// so that it is encrypted and authenticated using the subkey of the tenant
+    pub fn tenant_id(&mut self, tenant_id: TenantId) -> &mut Self {
+        self.tenant_id = Some(tenant_id);
+
+        self
+    }
+    /// Bind the token to the client IP address or CIDR range `binding`, mixed into the MAC.
+    /// Verify it with `from_string_with_client_ip()`
+    pub fn network_binding(&mut self, binding: NetworkBinding) -> &mut Self {
+        self.network = Some(binding);
+
+        self
+    }
+    /// Generate a new identifier for the token using the provided `IdentifierStrategy`
+    /// and the `Clock` of the token
+    pub fn identifier_strategy(&mut self, strategy: &dyn IdentifierStrategy) -> &mut Self {
+        self.identifier = strategy.generate_with_clock(self.get_clock());
+
+        self
+    }
     /// Add a custom expiry time for the token. Default exipry is 24 hours
     pub fn expiry(&mut self, expiry_in_secs: u64) -> &mut Self {
         self.expiry = self.issued + Duration::from_secs(expiry_in_secs);
@@ -115,251 +350,4795 @@ impl LiteSessionToken {
 
         self
     }
-    /// Set the session mode to either use a `SessionID` or not
+    /// Set the session mode to either use a `SessionID` or not.
+    /// The session ID is mixed into the MAC so the token is only authentic
+    /// when verified with the same session ID, see `from_string_with_session()`.
+    /// Only `TokenVersion::V2` tokens can be bound to a session
     pub fn mode(&mut self, mode: LiteSessionMode) -> &mut Self {
         self.mode = mode;
 
         self
     }
 
+    /// Set the length of the MAC emitted by `build_secure` and expected by `from_string`.
+    /// The default is `MacLength::Full`
+    pub fn mac_length(&mut self, mac_length: MacLength) -> &mut Self {
+        self.mac_length = mac_length;
+
+        self
+    }
+    /// Accept tokens carrying a MAC of `mac_length` in addition to the configured `mac_length`
+    /// until the time `until`. This allows moving a deployment between compact and full MACs
+    /// without invalidating the tokens issued before the switch
+    pub fn mac_fallback(&mut self, mac_length: MacLength, until: TAI64N) -> &mut Self {
+        self.mac_fallback = Some((mac_length, until));
+
+        self
+    }
+    /// Verify tokens that expired less than `grace` ago as `TokenOutcome::ExpiredWithinGrace`
+    /// instead of `TokenOutcome::SessionExpired` if they are authentic, so that they can be
+    /// allowed read-only access or trigger a silent re-authentication
+    pub fn expired_grace(&mut self, grace: Duration) -> &mut Self {
+        self.expired_grace = grace;
+
+        self
+    }
+    /// Re-issue tokens verified by `from_string()` when their remaining lifetime drops below
+    /// the threshold of `policy`, keeping their lifetime and `ConfidentialityMode`. Get the
+    /// replacement token with `get_refreshed()` and attach it to the response.
+    /// The replacement carries the start of the session in the `auth_time` claim so that
+    /// `max_age()` still ends the session, unversioned tokens cannot carry the claim and are
+    /// not re-issued when a maximum age is set
+    pub fn sliding(&mut self, policy: RefreshPolicy) -> &mut Self {
+        self.sliding = Some(policy);
+
+        self
+    }
+    /// Treat tokens issued more than `max_age` ago, or whose `auth_time` claim is older than
+    /// `max_age`, as expired whatever their expiry
+    pub fn max_age(&mut self, max_age: Duration) -> &mut Self {
+        self.max_age = Some(max_age);
+
+        self
+    }
+    /// Choose whether malformed tokens are errors or `TokenOutcome::BadToken`.
+    /// The default is `ParsePolicy::Strict`
+    pub fn parse_policy(&mut self, parse_policy: ParsePolicy) -> &mut Self {
+        self.parse_policy = parse_policy;
+
+        self
+    }
+    /// Tolerate clocks drifting between servers by `leeway` when verifying a token.
+    /// A token is expired once its expiry is `leeway` behind the current time and a token
+    /// issued more than `leeway` ahead of the current time is malformed.
+    /// There is no leeway by default
+    pub fn leeway(&mut self, leeway: Duration) -> &mut Self {
+        self.leeway = leeway;
+
+        self
+    }
+    /// Opt-in to rejecting tokens whose `issued` or `expiry` times fall outside `range`
+    /// with `LiteSessionError::TimestampOutOfRange` when destructured by `from_string`
+    pub fn timestamp_range(&mut self, range: TimestampRange) -> &mut Self {
+        self.timestamp_range = Some(range);
+
+        self
+    }
+    /// Reject tokens whose data does not name `audience` as the service they are intended for.
+    /// Such tokens are `TokenOutcome::TokenRejected` with `RejectionReason::PolicyFailed`
+    pub fn require_audience(&mut self, audience: &str) -> &mut Self {
+        self.required_audience = Some(audience.into());
+
+        self
+    }
+    /// Reject tokens whose data does not name one of `issuers` as the service that issued them.
+    /// Such tokens are `TokenOutcome::TokenRejected` with `RejectionReason::PolicyFailed`
+    pub fn allowed_issuers(&mut self, issuers: &[&str]) -> &mut Self {
+        self.allowed_issuers = Some(issuers.iter().map(|issuer| (*issuer).into()).collect());
+
+        self
+    }
+    /// Cap the lifetime of the token based on the role in its data.
+    /// `build_secure` shortens the expiry of the token to the maximum lifetime of its role
+    pub fn role_ttls(&mut self, role_ttls: RoleTtls) -> &mut Self {
+        self.role_ttls = role_ttls;
+
+        self
+    }
+    /// Set the separator placed between the fields of the token by `build_secure`.
+    /// `from_string` accepts tokens using either this format or `TokenFormat::Legacy`
+    pub fn format(&mut self, format: TokenFormat) -> &mut Self {
+        self.format = format;
+
+        self
+    }
+    /// Derive the encryption key, decrypt and compute the MAC of a throwaway buffer the size
+    /// of the token whenever `from_string` returns before verifying the MAC of a malformed,
+    /// out of range or expired token. This flattens the timing differences between rejected
+    /// and verified tokens at the cost of doing the work for every rejected token
+    pub fn dummy_verification(&mut self, enabled: bool) -> &mut Self {
+        self.dummy_verification = enabled;
+
+        self
+    }
+    /// Set the `TokenVersion` emitted by `build_secure`. The default is the latest version.
+    /// Emitting `TokenVersion::V0` keeps tokens readable by verifiers that predate versioning
+    /// while a fleet is upgraded. `from_string` accepts every supported version
+    pub fn version(&mut self, version: TokenVersion) -> &mut Self {
+        self.version = version;
+
+        self
+    }
+    /// Attach a public footer like a key ID or tenant hint that is authenticated by the MAC
+    /// but never encrypted, so load balancers can read it using `TokenParser` without the
+    /// server key. Footers are only emitted by versioned tokens
+    pub fn footer(&mut self, footer: &str) -> &mut Self {
+        self.footer = match footer.is_empty() {
+            true => None,
+            false => Some(footer.into()),
+        };
+
+        self
+    }
+    /// Choose the `TokenEncoding` of the built token. `from_string` decodes tokens using it
+    /// and detects the encoding if it is `TokenEncoding::Plain`
+    pub fn encoding(&mut self, encoding: TokenEncoding) -> &mut Self {
+        self.encoding = encoding;
+
+        self
+    }
+    /// Choose the `TokenCipher` used to encrypt the data section
+    pub fn cipher(&mut self, cipher: TokenCipher) -> &mut Self {
+        self.cipher = cipher;
+
+        self
+    }
+    /// Deflate-compress the data section before encryption when the data is longer than
+    /// `threshold` bytes and compressing makes it shorter.
+    /// Only `ConfidentialityMode::High` tokens are compressed
+    #[cfg(feature = "compression")]
+    pub fn compression(&mut self, threshold: usize) -> &mut Self {
+        self.compression_threshold = Some(threshold);
+
+        self
+    }
+    /// Get the identifier
+    pub fn get_identifier(&self) -> &String {
+        &self.identifier
+    }
+    /// Get the time the token was issued
+    pub fn get_issued(&self) -> &TAI64N {
+        &self.issued
+    }
+    /// Get the time the token expires
+    pub fn get_expiry(&self) -> &TAI64N {
+        &self.expiry
+    }
+    /// Get the data describing the token and its capabilities
+    pub fn get_hmac_data(&self) -> &LiteSessionData {
+        &self.hmac_data
+    }
+    /// Get the security mode
+    pub fn get_confidentiality(&self) -> &ConfidentialityMode {
+        &self.confidentiality
+    }
+    /// Get the public footer
+    pub fn get_footer(&self) -> &Option<String> {
+        &self.footer
+    }
+    /// Get the `TokenVersion` of the token
+    pub fn get_version(&self) -> &TokenVersion {
+        &self.version
+    }
+    /// Get the `TokenEncoding`
+    pub fn get_encoding(&self) -> &TokenEncoding {
+        &self.encoding
+    }
+    /// Get the `TokenCipher`
+    pub fn get_cipher(&self) -> &TokenCipher {
+        &self.cipher
+    }
+    /// Get the ID of the `KeyProvider` key the token was built or verified with
+    pub fn get_key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+    /// Get the `EntropySource` of the identifier and nonces
+    pub fn get_entropy(&self) -> &dyn EntropySource {
+        match &self.entropy {
+            Some(entropy) => entropy.as_ref(),
+            None => &OsEntropy,
+        }
+    }
+    /// Get the `Clock` of the token
+    pub fn get_clock(&self) -> &dyn Clock {
+        match &self.clock {
+            Some(clock) => clock.as_ref(),
+            None => &SystemClock,
+        }
+    }
+    /// Check whether rejected tokens go through a dummy verification
+    pub fn get_dummy_verification(&self) -> bool {
+        self.dummy_verification
+    }
+    /// Check whether the token commits to its derived key
+    pub fn get_key_commitment(&self) -> bool {
+        self.key_commitment
+    }
+    /// Check whether the data section is encrypted under a wrapped data key
+    pub fn get_envelope(&self) -> bool {
+        self.envelope
+    }
+    /// Get the grace period after the expiry of a token
+    pub fn get_expired_grace(&self) -> &Duration {
+        &self.expired_grace
+    }
+    /// Get the checks performed by the last verification of the token
+    pub fn get_report(&self) -> &VerificationReport {
+        &self.report
+    }
+    /// Get the `RefreshPolicy` of the sliding session
+    pub fn get_sliding(&self) -> &Option<RefreshPolicy> {
+        &self.sliding
+    }
+    /// Get the replacement token issued by the last `from_string()` of a sliding session
+    pub fn get_refreshed(&self) -> Option<&str> {
+        self.refreshed.as_deref()
+    }
+    /// Check whether the remaining lifetime of the token has dropped below the threshold
+    /// of the sliding session or of the default `RefreshPolicy`
+    pub fn should_refresh(&self) -> bool {
+        match &self.sliding {
+            Some(policy) => policy.should_refresh(self),
+            None => RefreshPolicy::default().should_refresh(self),
+        }
+    }
+    /// Get the maximum age of a token accepted by `from_string`
+    pub fn get_max_age(&self) -> &Option<Duration> {
+        &self.max_age
+    }
+    /// Get the `ParsePolicy`
+    pub fn get_parse_policy(&self) -> &ParsePolicy {
+        &self.parse_policy
+    }
+    /// Get the tolerated clock drift
+    pub fn get_leeway(&self) -> &Duration {
+        &self.leeway
+    }
+    /// Get the maximum size in bytes of the encoded `LiteSessionData`
+    pub fn get_max_data_size(&self) -> usize {
+        self.max_data_size
+    }
+    /// Get the `TenantId` the token was issued for
+    pub fn get_tenant_id(&self) -> Option<&TenantId> {
+        self.tenant_id.as_ref()
+    }
+    /// Get the client IP address or CIDR range the token is bound to
+    pub fn get_network_binding(&self) -> Option<&NetworkBinding> {
+        self.network.as_ref()
+    }
+    /// Get the session mode
+    pub fn get_mode(&self) -> &LiteSessionMode {
+        &self.mode
+    }
+    /// Get the reason the last token destructured by `from_string` was not authentic.
+    /// This is `None` if the token was authentic or the server configuration caused an error
+    pub fn get_rejection(&self) -> &Option<RejectionReason> {
+        &self.rejection
+    }
+
     fn compute_hmac(&self, server_key: &[u8; 32], ciphertext: &str, nonce: &str) -> blake3::Hash {
-        //Blake3HMAC(identifier|issued|expiry|ciphertext|nonce|ConfidentialityMode, k)
+        match self.version {
+            TokenVersion::V0 | TokenVersion::V1 => {
+                self.compute_legacy_hmac(server_key, ciphertext, nonce)
+            }
+            TokenVersion::V2 => self.compute_framed_hmac(server_key, ciphertext, nonce),
+        }
+    }
+
+    // Every field is fed to the keyed hasher after a presence byte and its length
+    // so that no two different sets of fields produce the same input.
+    // The presence byte of the last field tags the mode binding it, so that a device
+    // fingerprint never stands in for a session ID spelling the same bytes
+    fn compute_framed_hmac(
+        &self,
+        server_key: &[u8; 32],
+        ciphertext: &str,
+        nonce: &str,
+    ) -> blake3::Hash {
+        //Blake3HMAC(context|version|identifier|issued|expiry|ciphertext|nonce|ConfidentialityMode|footer|mode|sessionID, k)
+
+        let header = self.header(server_key);
+        let issued = self.issued.to_bytes();
+        let expiry = self.expiry.to_bytes();
+        let confidentiality = ConfidentialityMode::to_string(&self.confidentiality);
+        let (mode, session_id) = match &self.mode {
+            LiteSessionMode::SessionID(session_id) => (
+                MAC_MODE_SESSION_ID,
+                Some(session_id.expose_secret().as_bytes()),
+            ),
+            LiteSessionMode::DeviceBound(device) => {
+                (MAC_MODE_DEVICE_BOUND, Some(device.as_bytes()))
+            }
+            LiteSessionMode::Passive => (0, None),
+        };
+        let fields: [Option<&[u8]>; 9] = [
+            Some(MAC_CONTEXT.as_bytes()),
+            header.as_ref().map(|header| header.as_bytes()),
+            Some(self.identifier.as_bytes()),
+            Some(&issued),
+            Some(&expiry),
+            Some(ciphertext.as_bytes()),
+            Some(nonce.as_bytes()),
+            Some(confidentiality.as_bytes()),
+            self.footer.as_ref().map(|footer| footer.as_bytes()),
+        ];
+
+        let mut message: Vec<u8> = Vec::new();
+        for field in fields.iter() {
+            match field {
+                Some(field) => {
+                    message.push(1);
+                    message.extend_from_slice(&(field.len() as u64).to_be_bytes());
+                    message.extend_from_slice(field);
+                }
+                None => message.push(0),
+            }
+        }
+        message.push(mode);
+        if let Some(session_id) = session_id {
+            message.extend_from_slice(&(session_id.len() as u64).to_be_bytes());
+            message.extend_from_slice(session_id);
+        }
+        // Appended only when bound so the MAC of other tokens stays unchanged
+        if let Some(network) = &self.network {
+            let network = NetworkBinding::to_string(network);
+            message.push(1);
+            message.extend_from_slice(&(network.len() as u64).to_be_bytes());
+            message.extend_from_slice(network.as_bytes());
+        }
+
+        match self.cipher {
+            #[cfg(feature = "fips")]
+            TokenCipher::Aes256Gcm => blake3::Hash::from(fips::hmac_sha256(server_key, &message)),
+            _ => blake3::keyed_hash(server_key, &message),
+        }
+    }
+
+    fn compute_legacy_hmac(
+        &self,
+        server_key: &[u8; 32],
+        ciphertext: &str,
+        nonce: &str,
+    ) -> blake3::Hash {
+        //Blake3HMAC(version|identifier|issued|expiry|ciphertext|nonce|ConfidentialityMode|footer, k)
 
         let issue_time = hex::encode(self.issued.to_bytes());
         let expiry_time = hex::encode(self.expiry.to_bytes());
 
         let mut prepare_hmac = String::default();
+        if let Some(header) = self.header(server_key) {
+            prepare_hmac.push_str(&header);
+        }
         prepare_hmac.push_str(&self.identifier);
         prepare_hmac.push_str(&issue_time);
         prepare_hmac.push_str(&expiry_time);
         prepare_hmac.push_str(&ciphertext);
         prepare_hmac.push_str(&nonce);
         prepare_hmac.push_str(&ConfidentialityMode::to_string(&self.confidentiality));
+        if let Some(footer) = &self.footer {
+            prepare_hmac.push_str(footer);
+        }
         let hmac = blake3::keyed_hash(&server_key, &prepare_hmac.as_bytes());
 
         hmac
     }
 
-    /// Build the token with `High Confidentiality`
-    pub fn build_secure(&mut self, server_key: &[u8]) -> Result<String, LiteSessionError> {
-        match server_key.len() {
-            32_usize => (),
-            _ => return Err(LiteSessionError::ServerKeyLengthError),
+    /// Build the token with `High Confidentiality` and seal it into a `SealedToken`
+    /// so that it cannot be changed after the token string has been issued
+    pub fn seal(mut self, server_key: &ServerKey) -> Result<SealedToken, LiteSessionError> {
+        let session_token = self.build_secure(server_key)?;
+
+        Ok(SealedToken::new(self, session_token))
+    }
+    /// Start a `LiteSessionTokenBuilder` which only allows building the token once
+    /// its data and expiry have been set
+    pub fn builder() -> LiteSessionTokenBuilder<NoData, NoExpiry> {
+        LiteSessionTokenBuilder::new()
+    }
+    /// Build the token with `High Confidentiality`, encrypting the data section even if
+    /// `confidential(false)` was set
+    pub fn build_secure(&mut self, server_key: &ServerKey) -> Result<String, LiteSessionError> {
+        self.confidentiality = ConfidentialityMode::High;
+
+        self.build(server_key)
+    }
+    /// Build the token with `High Confidentiality` using the current key of a `KeyProvider`
+    /// like a `KeyRing`. The ID of the current key is embedded in the version header so that
+    /// `from_string_with_provider()` can select the key after the keys are rotated
+    pub fn build_secure_with_provider(
+        &mut self,
+        key_provider: &dyn KeyProvider,
+    ) -> Result<String, LiteSessionError> {
+        let (key_id, server_key) = key_provider.current_key()?;
+        if !KeyRing::is_key_id(&key_id) {
+            return Err(LiteSessionError::InvalidKeyId);
+        }
+        self.key_id = Some(key_id);
+
+        self.build_secure(&server_key)
+    }
+    /// Build the token with `High Confidentiality` bound to the transport session with
+    /// `session_id` like `LiteSessionMode::SessionID`, anchoring the session with a
+    /// `SessionRecord` in `store` so that it can be ended by deleting the record
+    pub fn build_secure_with_store(
+        &mut self,
+        server_key: &ServerKey,
+        store: &dyn SessionStore,
+        session_id: &str,
+    ) -> Result<String, LiteSessionError> {
+        self.mode = LiteSessionMode::session_id(session_id);
+        let token = self.build_secure(server_key)?;
+        let mut record = SessionRecord::new(&self.identifier, self.issued, self.expiry);
+        record.subject(epoch_user(&self.hmac_data));
+        store.put(session_id, record);
+
+        Ok(token)
+    }
+    /// Build the token with `High Confidentiality` using the subkey the `TenantKeyring` derives
+    /// for the `TenantId` set by `tenant_id()`. The tenant ID is embedded in the version header
+    /// so that `from_string_with_tenant()` can derive the same subkey
+    pub fn build_secure_with_tenant(
+        &mut self,
+        tenant_keyring: &TenantKeyring,
+    ) -> Result<String, LiteSessionError> {
+        let server_key = match &self.tenant_id {
+            Some(tenant_id) => tenant_keyring.tenant_key(tenant_id),
+            None => return Err(LiteSessionError::InvalidTenantId),
+        };
+
+        self.build_secure(&server_key)
+    }
+    /// Build the token according to its `ConfidentialityMode` and encode it using its
+    /// `TokenEncoding`. `ConfidentialityMode::High` encrypts the data section while
+    /// `ConfidentialityMode::Low` places the hex encoded plaintext data in the data section,
+    /// which is still authenticated by the MAC.
+    /// `ConfidentialityMode::Low` tokens can only be built with a versioned layout
+    pub fn build(&mut self, server_key: &ServerKey) -> Result<String, LiteSessionError> {
+        let token = self.build_plain(server_key)?;
+
+        match self.encoding {
+            TokenEncoding::Plain => Ok(token),
+            TokenEncoding::Hex => self.hex_blob(&token),
+            _ => Ok(self.encoding.encode(&token)),
+        }
+    }
+
+    fn build_plain(&mut self, server_key: &ServerKey) -> Result<String, LiteSessionError> {
+        if !self.format.is_valid() {
+            return Err(LiteSessionError::InvalidTokenFormat);
+        }
+        // A separator inside a custom identifier would shift the fields of the token
+        let separator = self.format.separator();
+        if self.identifier.is_empty()
+            || self.identifier.contains(|character| {
+                matches!(character, '\n' | '⥂' | '⊕' | '⇅') || character == separator
+            })
+        {
+            return Err(LiteSessionError::InvalidTokenFormat);
+        }
+        if (self.footer.is_some()
+            || self.confidentiality == ConfidentialityMode::Low
+            || self.cipher.header().is_some()
+            || self.key_id.is_some()
+            || self.tenant_id.is_some()
+            || self.key_commitment
+            || self.envelope)
+            && self.version.prefix().is_none()
+        {
+            return Err(LiteSessionError::UnsupportedTokenVersion);
+        }
+        if (self.mode != LiteSessionMode::Passive
+            || self.network.is_some()
+            || self.cipher == TokenCipher::Aes256Gcm)
+            && self.version != TokenVersion::V2
+        {
+            return Err(LiteSessionError::UnsupportedTokenVersion);
+        }
+        if !self.cipher.is_available() {
+            return Err(LiteSessionError::UnavailableCipher);
+        }
+        // Unversioned tokens read four field data as it is, see `destructure_with_version()`
+        if self.version == TokenVersion::V0 && !self.hmac_data.is_legacy_compatible() {
+            return Err(LiteSessionError::UnsupportedTokenVersion);
+        }
+        if let Some(max_ttl) = self.role_ttls.get(self.hmac_data.get_role()) {
+            let max_expiry = self.issued + Duration::from_secs(max_ttl);
+            if self.expiry > max_expiry {
+                self.expiry = max_expiry;
+            }
+        }
+
+        let plaintext = self.hmac_data.build().into_bytes();
+        if plaintext.len() > self.max_data_size {
+            return Err(LiteSessionError::DataSizeTooLarge);
         }
-        // identifier⊕issued⊕expiry⊕ciphertext⊕nonce⊕confidentiality⊕hmac
+
+        let server_key = server_key.expose_secret();
+        let mut ciphertext = CipherText::default();
+        match self.confidentiality {
+            ConfidentialityMode::High => {
+                let encryption_key = self.get_key(server_key);
+                let mut data_key = encryption_key;
+                if self.envelope {
+                    self.get_entropy().fill(&mut data_key);
+                }
+                let compressed = self.compress(&plaintext);
+                ciphertext.encrypt_bytes(
+                    compressed.as_deref().unwrap_or(&plaintext),
+                    &data_key,
+                    &self.cipher,
+                    self.get_entropy(),
+                )?;
+                if compressed.is_some() {
+                    ciphertext.cipher.insert(0, COMPRESSED_MARKER);
+                }
+                if self.envelope {
+                    let nonce = TokenCipher::nonce_bytes(&ciphertext.nonce)?;
+                    let wrapped_key =
+                        envelope::wrap(&data_key, &encryption_key, &nonce, &self.cipher)?;
+                    ciphertext.cipher.insert_str(0, &wrapped_key);
+                }
+                data_key.zeroize();
+            }
+            ConfidentialityMode::Low => {
+                ciphertext.cipher = hex::encode(&plaintext);
+                ciphertext.nonce = self.cipher.nonce_from(self.get_entropy());
+            }
+        }
+
+        Ok(self.assemble(server_key, &ciphertext))
+    }
+
+    // version⊕identifier⊕issued⊕expiry⊕ciphertext⊕nonce⊕confidentiality⊕hmac⊕footer
+    fn assemble(&mut self, server_key: &[u8; 32], ciphertext: &CipherText) -> String {
         let issue_time = hex::encode(self.issued.to_bytes());
         let expiry_time = hex::encode(self.expiry.to_bytes());
 
-        let server_key: [u8; 32] = self.transform_key(server_key)?;
-        let mut cipher_data = CipherText::default();
-        let ciphertext = cipher_data.encrypt(&self.hmac_data, &self.get_key(&server_key))?;
-
-        let hmac = self.compute_hmac(&server_key, &ciphertext.cipher, &ciphertext.nonce);
+        let hmac = self.compute_hmac(server_key, &ciphertext.cipher, &ciphertext.nonce);
         self.hmac = hmac;
-        let hmac_hex = hex::encode(&hmac.as_bytes());
+        let hmac_hex = hex::encode(&hmac.as_bytes()[..self.mac_length.byte_len()]);
 
+        let separator = self.format.separator();
         let mut token = String::default();
+        if let Some(header) = self.header(server_key) {
+            token.push_str(&header);
+            token.push(separator);
+        }
         token.push_str(&self.identifier);
-        token.push(LiteSessionToken::separator());
+        token.push(separator);
         token.push_str(&issue_time);
-        token.push(LiteSessionToken::separator());
+        token.push(separator);
         token.push_str(&expiry_time);
-        token.push(LiteSessionToken::separator());
+        token.push(separator);
         token.push_str(&ciphertext.cipher);
-        token.push(LiteSessionToken::separator());
+        token.push(separator);
         token.push_str(&ciphertext.nonce);
-        token.push(LiteSessionToken::separator());
+        token.push(separator);
         token.push_str(&ConfidentialityMode::to_string(&self.confidentiality));
-        token.push(LiteSessionToken::separator());
+        token.push(separator);
         token.push_str(&hmac_hex);
+        if let Some(footer) = &self.footer {
+            token.push(separator);
+            token.push_str(&base64::encode_config(footer, base64::URL_SAFE_NO_PAD));
+        }
 
-        Ok(token)
+        token
     }
-    /// Destructure and autheticate a token
-    pub fn from_string(
+    /// Build the token with `High Confidentiality` encoded as URL-safe Base64 without padding
+    /// so that it can be carried in HTTP headers, query strings and `Authorization: Bearer` flows
+    pub fn build_secure_base64(
         &mut self,
-        server_key: &[u8],
-        token: &str,
-    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
-        if token.len() > 1024 * 1024 {
-            return Err(LiteSessionError::TokenSizeTooLarge);
-        }
-
-        let fields = token.split("⊕").collect::<Vec<&str>>();
-        if fields.len() != 7_usize {
-            return Err(LiteSessionError::TokenFieldsLengthError);
-        }
+        server_key: &ServerKey,
+    ) -> Result<String, LiteSessionError> {
+        self.confidentiality = ConfidentialityMode::High;
+        let token = self.build_plain(server_key)?;
 
-        let identifier = fields[0];
-        let issued_hex = fields[1];
-        let expiry_hex = fields[2];
-        let ciphertext_hex = fields[3];
-        let nonce = fields[4];
-        let confidentiality = fields[5];
-        let hmac_hex = fields[6];
+        Ok(base64::encode_config(token, base64::URL_SAFE_NO_PAD))
+    }
+    /// Build the token according to its `ConfidentialityMode` and encode it as a single hex blob
+    /// with fixed-width fields instead of separators. The blob starts with a header holding the
+    /// version, confidentiality, issued and expiry times, nonce, MAC length and the lengths of
+    /// the identifier, data and footer, followed by the identifier, data, MAC and footer bytes
+    pub fn build_hex(&mut self, server_key: &ServerKey) -> Result<String, LiteSessionError> {
+        let token = self.build_plain(server_key)?;
 
-        let issued = self.tai_time(issued_hex)?;
-        let expiry = self.tai_time(expiry_hex)?;
+        self.hex_blob(&token)
+    }
 
-        if expiry <= TAI64N::now() {
-            return Ok((TokenOutcome::SessionExpired, self));
+    #[cfg(feature = "compression")]
+    fn compress(&self, plaintext: &[u8]) -> Option<Vec<u8>> {
+        match self.compression_threshold {
+            Some(threshold) if plaintext.len() > threshold => {
+                let compressed = compression::deflate(plaintext);
+                match compressed.len() < plaintext.len() {
+                    true => Some(compressed),
+                    false => None,
+                }
+            }
+            _ => None,
         }
+    }
 
-        let server_key: [u8; 32] = self.transform_key(server_key)?;
-
-        self.identifier = identifier.into();
-        self.issued = issued;
-        self.expiry = expiry;
-        self.confidentiality = ConfidentialityMode::from_string(confidentiality);
+    #[cfg(not(feature = "compression"))]
+    fn compress(&self, _plaintext: &[u8]) -> Option<Vec<u8>> {
+        None
+    }
 
-        let mut ciphertext_bytes = match hex::decode(ciphertext_hex) {
-            Ok(bytes) => bytes,
-            Err(_) => return Err(LiteSessionError::InvalidHexString),
+    fn hex_blob(&self, token: &str) -> Result<String, LiteSessionError> {
+        let parsed = match TokenParser::default()
+            .format(self.format.clone())
+            .parse(token)
+        {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(error.into_error()),
         };
 
-        let encryption_key = self.get_key(&server_key);
-        self.hmac_data = CipherText::default().decrypt(
-            &encryption_key,
-            &mut ciphertext_bytes,
-            nonce.as_bytes(),
-        )?;
+        let (wrapped_key, data) = match envelope::split(parsed.get_ciphertext()) {
+            Some(split) => {
+                let (wrapped_key, data) = split?;
+                (Some(wrapped_key), data)
+            }
+            None => (None, parsed.get_ciphertext()),
+        };
+        let (compressed, data) = match data.strip_prefix(COMPRESSED_MARKER) {
+            Some(data) => (true, data),
+            None => (false, data),
+        };
+        // The wrapped data key is stored at the start of the data
+        let mut data = hex::decode(data).map_err(|_| LiteSessionError::InvalidHexString)?;
+        if let Some(wrapped_key) = wrapped_key {
+            let wrapped_key =
+                hex::decode(wrapped_key).map_err(|_| LiteSessionError::InvalidHexString)?;
+            data.splice(..0, wrapped_key);
+        }
+        let hmac =
+            hex::decode(parsed.get_hmac()).map_err(|_| LiteSessionError::InvalidHexString)?;
+        let footer = parsed.get_footer().clone().unwrap_or_default();
 
-        let hmac = self.compute_hmac(&server_key, ciphertext_hex, nonce);
+        let identifier_len: u16 = parsed
+            .get_identifier()
+            .len()
+            .try_into()
+            .map_err(|_| LiteSessionError::TokenFieldsLengthError)?;
+        let data_len: u32 = data
+            .len()
+            .try_into()
+            .map_err(|_| LiteSessionError::TokenSizeTooLarge)?;
+        let footer_len: u16 = footer
+            .len()
+            .try_into()
+            .map_err(|_| LiteSessionError::TokenFieldsLengthError)?;
 
-        if hmac != self.to_hmac(&hmac_hex)? {
-            return Ok((TokenOutcome::TokenRejected, self));
-        } else {
-            self.hmac = hmac;
+        let mut blob: Vec<u8> = Vec::with_capacity(
+            HEX_HEADER_LEN + parsed.get_identifier().len() + data.len() + hmac.len() + footer.len(),
+        );
+        let version = match parsed.get_version() {
+            TokenVersion::V0 => 0,
+            TokenVersion::V1 => 1,
+            TokenVersion::V2 => 2,
+        };
+        let key_commitment = match parsed.get_key_commitment() {
+            Some(key_commitment) => {
+                hex::decode(key_commitment).map_err(|_| LiteSessionError::InvalidHexString)?
+            }
+            None => Vec::new(),
+        };
+        let version = match key_commitment.is_empty() {
+            true => version,
+            false => version | HEX_VERSION_KEY_COMMITMENT,
+        };
+        blob.push(match wrapped_key {
+            Some(_) => version | HEX_VERSION_ENVELOPE,
+            None => version,
+        });
+        let nonce = TokenCipher::nonce_bytes(parsed.get_nonce())?;
+        let mut flags = match self.confidentiality {
+            ConfidentialityMode::Low => 0,
+            ConfidentialityMode::High => HEX_FLAG_HIGH,
+        };
+        if compressed {
+            flags |= HEX_FLAG_COMPRESSED;
+        }
+        match parsed.get_cipher() {
+            TokenCipher::ChaCha(Rounds::R8) => (),
+            TokenCipher::ChaCha(Rounds::R12) => flags |= HEX_FLAG_ROUNDS12,
+            TokenCipher::ChaCha(Rounds::R20) => flags |= HEX_FLAG_ROUNDS20,
+            TokenCipher::Aes256Gcm => flags |= HEX_FLAG_AES256GCM,
+            TokenCipher::XChaCha20 => flags |= HEX_FLAG_XCHACHA20,
         }
+        if parsed.get_key_id().is_some() {
+            flags |= HEX_FLAG_KEY_ID;
+        }
+        if parsed.get_tenant_id().is_some() {
+            flags |= HEX_FLAG_TENANT_ID;
+        }
+        // Legacy `ChaCha` nonces are alphanumeric characters stored as-is
+        if parsed.get_cipher() != &TokenCipher::XChaCha20 && parsed.get_nonce().len() != 12 {
+            flags |= HEX_FLAG_BINARY_NONCE;
+        }
+        blob.push(flags);
+        blob.extend_from_slice(&parsed.get_issued().to_bytes());
+        blob.extend_from_slice(&parsed.get_expiry().to_bytes());
+        blob.extend_from_slice(&nonce[..12]);
+        blob.push(hmac.len() as u8);
+        blob.extend_from_slice(&identifier_len.to_be_bytes());
+        blob.extend_from_slice(&data_len.to_be_bytes());
+        blob.extend_from_slice(&footer_len.to_be_bytes());
+        // The rest of an `XChaCha20` nonce and the length prefixed key and tenant IDs follow the header
+        blob.extend_from_slice(&nonce[12..]);
+        if let Some(key_id) = parsed.get_key_id() {
+            blob.push(key_id.len() as u8);
+            blob.extend_from_slice(key_id.as_bytes());
+        }
+        if let Some(tenant_id) = parsed.get_tenant_id() {
+            blob.push(tenant_id.len() as u8);
+            blob.extend_from_slice(tenant_id.as_bytes());
+        }
+        blob.extend_from_slice(&key_commitment);
+        blob.extend_from_slice(parsed.get_identifier().as_bytes());
+        blob.extend_from_slice(&data);
+        blob.extend_from_slice(&hmac);
+        blob.extend_from_slice(footer.as_bytes());
 
-        Ok((TokenOutcome::TokenAuthentic, self))
+        Ok(hex::encode(blob))
     }
-    /// Make a mutable `LiteSessionToken` immutable
-    pub fn immutable(&mut self) -> &Self {
-        self
+    /// Hash a token with Blake3 into a hex identifier that correlates log entries
+    /// about the token without revealing it
+    pub fn fingerprint(token: &str) -> String {
+        blake3::hash(token.as_bytes()).to_hex().to_string()
     }
+    /// Destructure and autheticate a token encoded using the `TokenEncoding` set by
+    /// `encoding()`. The encoding is detected from the token if it is `TokenEncoding::Plain`
+    pub fn from_string(
+        &mut self,
+        server_key: &ServerKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.rejection = None;
+        self.refreshed = None;
 
-    fn transform_key(&self, server_key: &[u8]) -> Result<[u8; 32], LiteSessionError> {
-        match server_key.try_into() {
-            Ok(key) => Ok(key),
-            Err(_) => return Err(LiteSessionError::ServerKeyLengthError),
+        let outcome = self
+            .verify_encoded(&VerificationKey::ServerKey(server_key), token)?
+            .0;
+        if outcome == TokenOutcome::TokenAuthentic
+            && self.sliding.is_some()
+            && self.should_refresh()
+        {
+            self.refreshed = self.slide(server_key)?;
         }
-    }
-
-    fn get_key(&self, key: &[u8; 32]) -> [u8; 32] {
-        let mut raw_key = String::default();
-
-        let identifier = self.identifier.clone();
-        let issued = hex::encode(self.issued.to_bytes());
-        let expiry = hex::encode(self.expiry.to_bytes());
-        let confidentiality = ConfidentialityMode::to_string(&self.confidentiality);
-
-        raw_key.push_str(&identifier);
-        raw_key.push_str(&issued);
-        raw_key.push_str(&expiry);
-        raw_key.push_str(&confidentiality);
-        let encryption_key = blake3::keyed_hash(key, raw_key.as_bytes());
 
-        encryption_key.as_bytes().clone()
+        Ok((outcome, self))
     }
+    /// Destructure and authenticate a token like `from_string()` into a new token with the
+    /// default settings, leaving no partially populated token behind on failure
+    pub fn parse(
+        server_key: &ServerKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, LiteSessionToken), LiteSessionError> {
+        let mut parsed = LiteSessionToken::default();
+        let (outcome, _) = parsed.from_string(server_key, token)?;
 
-    fn tai_time(&self, hex_str: &str) -> Result<TAI64N, LiteSessionError> {
-        let tai_bytes = match hex::decode(hex_str) {
-            Ok(bytes) => bytes,
-            Err(_) => return Err(LiteSessionError::InvalidHexString),
-        };
-        match TAI64N::from_slice(&tai_bytes) {
-            Ok(tai_time) => Ok(tai_time),
-            Err(_) => return Err(LiteSessionError::InvalidTai64NTime),
-        }
+        Ok((outcome, parsed))
     }
-
-    fn to_hmac(&self, hash_hex: &str) -> Result<blake3::Hash, LiteSessionError> {
-        let hash_bytes = match hex::decode(hash_hex) {
-            Err(_) => return Err(LiteSessionError::InvalidHexString),
-            Ok(bytes) => bytes,
-        };
-        let hash_array: [u8; blake3::OUT_LEN] = match hash_bytes[..].try_into() {
-            Err(_) => return Err(LiteSessionError::InvalidBytesForBlake3),
-            Ok(bytes) => bytes,
+    /// Read the public fields of a token without the server key and without decrypting it,
+    /// like the identifier or the tenant to route the token to the service holding its key.
+    /// The fields are not authenticated until the token is verified with `from_string()`
+    pub fn peek(token: &str) -> Result<TokenClaims, LiteSessionError> {
+        let token = LiteSessionToken::default().plain_token(token)?;
+        let parsed = match TokenParser::default().parse(&token) {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(error.into_error()),
         };
-        let hash: blake3::Hash = hash_array.into();
 
-        Ok(hash)
+        Ok(TokenClaims::from_parsed(&parsed))
     }
+    /// Destructure and authenticate a token like `from_string()` bound to the transport
+    /// session with `session_id`, like the TLS session key. Tokens issued for another
+    /// session are rejected with `RejectionReason::MacMismatch`
+    pub fn from_string_with_session(
+        &mut self,
+        server_key: &ServerKey,
+        session_id: &str,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        let previous = core::mem::replace(&mut self.mode, LiteSessionMode::session_id(session_id));
+        let outcome = self
+            .from_string(server_key, token)
+            .map(|(outcome, _)| outcome);
+        self.mode = previous;
 
-    fn separator() -> char {
-        '⊕'
+        Ok((outcome?, self))
     }
-}
+    /// Destructure and authenticate a token like `from_string()` bound to the network of the
+    /// client at `client_ip`. The token must have been bound to the range set by
+    /// `network_binding()` containing `client_ip`, or to `client_ip` itself when no range is set.
+    /// Clients outside the range are rejected with `RejectionReason::PolicyFailed` and tokens
+    /// bound to another network with `RejectionReason::MacMismatch`
+    pub fn from_string_with_client_ip(
+        &mut self,
+        server_key: &ServerKey,
+        client_ip: IpAddr,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        let binding = match &self.network {
+            Some(network) if !network.contains(&client_ip) => {
+                self.reject(RejectionReason::PolicyFailed {
+                    which: "network".into(),
+                });
+                return Ok((TokenOutcome::TokenRejected, self));
+            }
+            Some(network) => network.clone(),
+            None => NetworkBinding::ip(client_ip),
+        };
 
-#[cfg(test)]
-mod token_tests {
-    use super::LiteSessionToken;
-    use crate::{
-        ConfidentialityMode, LiteSessionData, LiteSessionError, LiteSessionMode, Role, TokenOutcome,
-    };
+        let previous = self.network.replace(binding);
+        let outcome = self
+            .from_string(server_key, token)
+            .map(|(outcome, _)| outcome);
+        self.network = previous;
 
-    #[test]
-    fn tokens() -> Result<(), LiteSessionError> {
-        let mut token = LiteSessionToken::default();
+        Ok((outcome?, self))
+    }
+    /// Destructure and authenticate a token like `from_string()` presented over a mutual TLS
+    /// connection with the client certificate hashed to `thumbprint`. Tokens that do not confirm
+    /// that certificate with `LiteSessionData::certificate_thumbprint()` are rejected with
+    /// `RejectionReason::PolicyFailed`. Every other verification rejects tokens confirming
+    /// a certificate since it cannot see the certificate of the client
+    pub fn from_string_with_certificate(
+        &mut self,
+        server_key: &ServerKey,
+        thumbprint: &[u8],
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.confirming = Some("cnf");
+        let outcome = self
+            .from_string(server_key, token)
+            .map(|(outcome, _)| outcome);
+        self.confirming = None;
+        let outcome = outcome?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, self));
+        }
+
+        let confirmed = match self.hmac_data.get_certificate_thumbprint() {
+            Some(confirmation) => constant_time_eq::constant_time_eq(confirmation, thumbprint),
+            None => false,
+        };
+        if !confirmed {
+            self.refreshed = None;
+            self.reject(RejectionReason::PolicyFailed {
+                which: "cnf".into(),
+            });
+            return Ok((TokenOutcome::TokenRejected, self));
+        }
+
+        Ok((outcome, self))
+    }
+    /// Destructure and authenticate a token like `from_string()` presented with the serialized
+    /// `ProofOfPossession` `proof` for a request with the HTTP `method` to `uri`. The token must
+    /// confirm the public key of the proof with `LiteSessionData::proof_key()` and the proof must
+    /// be signed by the matching private key within `ProofOfPossession::MAX_AGE_SECS`,
+    /// otherwise the token is rejected with `RejectionReason::PolicyFailed`. Every other
+    /// verification rejects tokens confirming a public key since it checks no proof
+    pub fn from_string_with_proof(
+        &mut self,
+        server_key: &ServerKey,
+        verifier: &dyn ProofSignatureVerifier,
+        proof: &str,
+        method: &str,
+        uri: &str,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.confirming = Some("dpop");
+        let outcome = self
+            .from_string(server_key, token)
+            .map(|(outcome, _)| outcome);
+        self.confirming = None;
+        let outcome = outcome?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, self));
+        }
+
+        if !self.proves_possession(verifier, proof, method, uri, token) {
+            self.refreshed = None;
+            self.reject(RejectionReason::PolicyFailed {
+                which: "dpop".into(),
+            });
+            return Ok((TokenOutcome::TokenRejected, self));
+        }
+
+        Ok((outcome, self))
+    }
+    /// Destructure and authenticate an `AttenuatedToken` serialized with its caveats, or a
+    /// token that was never attenuated, for a request to `path`. The MAC chain of the caveats
+    /// is verified like the MAC of a token and a token whose caveats are not all satisfied is
+    /// rejected with `RejectionReason::PolicyFailed`. Tokens carrying caveats are never
+    /// refreshed by `sliding()` since the replacement would drop the caveats
+    pub fn from_string_attenuated(
+        &mut self,
+        server_key: &ServerKey,
+        path: &str,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.refreshed = None;
+
+        let attenuated = match AttenuatedToken::from_string(token) {
+            Ok(attenuated) => attenuated,
+            Err(error) => {
+                let error = self.malformed("caveat", error);
+                return self.apply_parse_policy(Err(error));
+            }
+        };
+
+        self.caveats = attenuated.get_caveats().clone();
+        let outcome = self
+            .from_string(server_key, attenuated.get_token())
+            .map(|(outcome, _)| outcome);
+        self.caveats = Vec::default();
+        let outcome = outcome?;
+        // A sliding refresh would carry none of the caveats and widen the delegated access
+        if !attenuated.get_caveats().is_empty() {
+            self.refreshed = None;
+        }
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, self));
+        }
+
+        let now = self
+            .get_clock()
+            .now()
+            .duration_since(&tai64::UNIX_EPOCH_TAI64N)
+            .unwrap_or_default()
+            .as_secs();
+        if !attenuated
+            .get_caveats()
+            .iter()
+            .all(|caveat| caveat::is_satisfied(caveat, now, path))
+        {
+            self.reject(RejectionReason::PolicyFailed {
+                which: "caveat".into(),
+            });
+            return Ok((TokenOutcome::TokenRejected, self));
+        }
+
+        Ok((outcome, self))
+    }
+    /// Destructure and authenticate a token like `from_string()` bound to the device
+    /// identified by `device_hash`. Tokens issued to another device are rejected with
+    /// `RejectionReason::MacMismatch`
+    pub fn from_string_with_device(
+        &mut self,
+        server_key: &ServerKey,
+        device_hash: &[u8],
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        let previous =
+            core::mem::replace(&mut self.mode, LiteSessionMode::device_bound(device_hash));
+        let outcome = self
+            .from_string(server_key, token)
+            .map(|(outcome, _)| outcome);
+        self.mode = previous;
+
+        Ok((outcome?, self))
+    }
+    /// Destructure and authenticate a token like `from_string_with_session()` and check that
+    /// `store` holds the record of the session issued with the token. Tokens whose session
+    /// has been deleted, has expired or is held by another token are `TokenOutcome::TokenRevoked`
+    /// with `RejectionReason::Revoked`
+    pub fn from_string_with_store(
+        &mut self,
+        server_key: &ServerKey,
+        store: &dyn SessionStore,
+        session_id: &str,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        let (outcome, _) = self.from_string_with_session(server_key, session_id, token)?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, self));
+        }
+
+        let anchored = match store.get(session_id) {
+            Some(record) => {
+                record.get_identifier() == &self.identifier
+                    && !record.is_expired(&self.get_clock().now())
+            }
+            None => false,
+        };
+        if !anchored {
+            self.refreshed = None;
+            self.rejection = Some(RejectionReason::Revoked);
+            return Ok((TokenOutcome::TokenRevoked, self));
+        }
+
+        Ok((outcome, self))
+    }
+    /// Destructure and authenticate a token like `from_string()` using the key of the
+    /// `KeyProvider` matching the key ID of the token. Tokens without a key ID use the current key
+    pub fn from_string_with_provider(
+        &mut self,
+        key_provider: &dyn KeyProvider,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.rejection = None;
+
+        self.verify_encoded(&VerificationKey::Provider(key_provider), token)
+    }
+    /// Destructure and authenticate a token like `from_string()` using the subkey the
+    /// `TenantKeyring` derives for the `TenantId` in the version header of the token.
+    /// Tokens without a tenant ID are rejected as malformed
+    pub fn from_string_with_tenant(
+        &mut self,
+        tenant_keyring: &TenantKeyring,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.rejection = None;
+
+        self.verify_encoded(&VerificationKey::Tenant(tenant_keyring), token)
+    }
+    /// Authenticate a token built with `envelope()` using `server_key` and wrap its data key
+    /// under `new_key` without re-encrypting the data section, so that outstanding tokens can
+    /// follow a rotation of the server key. `new_key_id` replaces the key ID in the version
+    /// header so that `from_string_with_provider()` selects `new_key`, `None` leaves the
+    /// replacement without a key ID. The tenant ID of the token is kept.
+    /// The replacement token is only returned if the token is `TokenOutcome::TokenAuthentic`
+    pub fn rewrap(
+        &mut self,
+        server_key: &ServerKey,
+        new_key_id: Option<&str>,
+        new_key: &ServerKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, Option<String>), LiteSessionError> {
+        self.rejection = None;
+        if let Some(new_key_id) = new_key_id {
+            if !KeyRing::is_key_id(new_key_id) {
+                return Err(LiteSessionError::InvalidKeyId);
+            }
+        }
+
+        let token = self.plain_token(token)?;
+        let outcome = self
+            .verify_plain(&VerificationKey::ServerKey(server_key), &token)
+            .map(|(outcome, _)| outcome);
+        let (outcome, _) = self.apply_parse_policy(outcome)?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, None));
+        }
+
+        let parsed = match TokenParser::default()
+            .format(self.format.clone())
+            .parse(&token)
+        {
+            Ok(parsed) => parsed,
+            Err(error) => return Err(error.into_error()),
+        };
+        let (wrapped_key, data) = match envelope::split(parsed.get_ciphertext()) {
+            Some(split) => split?,
+            None => return Err(LiteSessionError::MissingWrappedKey),
+        };
+        let nonce = TokenCipher::nonce_bytes(parsed.get_nonce())?;
+
+        let mut encryption_key = self.get_key(server_key.expose_secret());
+        let mut data_key = envelope::unwrap(wrapped_key, &encryption_key, &nonce, &self.cipher)?;
+        encryption_key = self.get_key(new_key.expose_secret());
+        let mut wrapped_data = envelope::wrap(&data_key, &encryption_key, &nonce, &self.cipher)?;
+        wrapped_data.push_str(data);
+        let ciphertext = CipherText {
+            cipher: wrapped_data,
+            nonce: parsed.get_nonce().into(),
+        };
+        data_key.zeroize();
+        encryption_key.zeroize();
+
+        self.key_id = new_key_id.map(Into::into);
+        let token = self.assemble(new_key.expose_secret(), &ciphertext);
+        let token = match self.encoding {
+            TokenEncoding::Plain => token,
+            TokenEncoding::Hex => self.hex_blob(&token)?,
+            _ => self.encoding.encode(&token),
+        };
+
+        Ok((outcome, Some(token)))
+    }
+
+    fn verify_encoded(
+        &mut self,
+        key: &VerificationKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        let outcome = match self.plain_token(token) {
+            Ok(token) => self.verify_plain(key, &token).map(|(outcome, _)| outcome),
+            Err(error) => Err(error),
+        };
+
+        self.apply_parse_policy(outcome)
+    }
+
+    // Turn an error caused by a malformed token into `TokenOutcome::BadToken` for `ParsePolicy::Lenient`
+    fn apply_parse_policy(
+        &mut self,
+        outcome: Result<TokenOutcome, LiteSessionError>,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        match outcome {
+            Ok(outcome) => Ok((outcome, self)),
+            Err(_)
+                if self.parse_policy == ParsePolicy::Lenient
+                    && matches!(self.rejection, Some(RejectionReason::Malformed { .. })) =>
+            {
+                Ok((TokenOutcome::BadToken, self))
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    // Decode a token using its `TokenEncoding` into the plain token with separators
+    fn plain_token(&mut self, token: &str) -> Result<String, LiteSessionError> {
+        let encoding = match self.encoding {
+            TokenEncoding::Plain => TokenEncoding::detect(token).unwrap_or_default(),
+            _ => self.encoding.clone(),
+        };
+        match encoding {
+            TokenEncoding::Plain => Ok(token.into()),
+            TokenEncoding::Hex => self.plain_from_hex(token),
+            _ => {
+                if token.len() > 1024 * 1024 {
+                    return Err(self.malformed("token", LiteSessionError::TokenSizeTooLarge));
+                }
+
+                match encoding.decode(token) {
+                    Ok(token) => Ok(token),
+                    Err(error) => Err(self.malformed("token", error)),
+                }
+            }
+        }
+    }
+
+    fn verify_plain(
+        &mut self,
+        key: &VerificationKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.report = VerificationReport::default();
+        let outcome = self.verify_checks(key, token).map(|(outcome, _)| outcome);
+        self.report.end(matches!(
+            outcome,
+            Ok(TokenOutcome::TokenAuthentic) | Ok(TokenOutcome::ExpiredWithinGrace)
+        ));
+
+        match outcome {
+            Ok(outcome) => Ok((outcome, self)),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn verify_checks(
+        &mut self,
+        key: &VerificationKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.report.begin("structure");
+        let parsed = match TokenParser::default()
+            .format(self.format.clone())
+            .parse(token)
+        {
+            Ok(parsed) => parsed,
+            Err(error) => {
+                self.verify_dummy(key, token);
+                let field = error.get_field();
+                return Err(self.malformed(field, error.into_error()));
+            }
+        };
+        let issued = *parsed.get_issued();
+        let expiry = *parsed.get_expiry();
+
+        let now = self.get_clock().now();
+        if let Some(range) = &self.timestamp_range {
+            if !range.contains(&issued, &now) {
+                self.verify_dummy(key, token);
+                return Err(self.malformed("issued", LiteSessionError::TimestampOutOfRange));
+            }
+            if !range.contains(&expiry, &now) {
+                self.verify_dummy(key, token);
+                return Err(self.malformed("expiry", LiteSessionError::TimestampOutOfRange));
+            }
+        }
+
+        // Inverted timestamps or a token issued ahead of the clock can only be forged
+        self.report.begin("expiry");
+        if issued > expiry || issued > now + self.leeway {
+            self.verify_dummy(key, token);
+            return Err(self.malformed("issued", LiteSessionError::TimestampOutOfRange));
+        }
+        let max_age_exceeded = match self.max_age {
+            Some(max_age) => issued + max_age + self.leeway <= now,
+            None => false,
+        };
+        let expired = expiry + self.leeway <= now;
+        let within_grace =
+            expired && !max_age_exceeded && expiry + self.leeway + self.expired_grace > now;
+        if (expired && !within_grace) || max_age_exceeded {
+            self.verify_dummy(key, token);
+            self.rejection = Some(RejectionReason::Expired);
+            return Ok((TokenOutcome::SessionExpired, self));
+        }
+
+        self.report.begin("key");
+        let provided_key = match key.provide(parsed.get_key_id(), parsed.get_tenant_id()) {
+            Ok(provided_key) => provided_key,
+            Err(LiteSessionError::UnknownKeyId) => {
+                self.verify_dummy(key, token);
+                return Err(self.malformed("version", LiteSessionError::UnknownKeyId));
+            }
+            Err(error) => return Err(error),
+        };
+        let server_key = match provided_key.as_ref().or_else(|| key.server_key()) {
+            Some(server_key) => server_key.expose_secret(),
+            None => return Err(LiteSessionError::UnknownKeyId),
+        };
+
+        self.report.begin("hmac");
+        // The MAC is checked on a candidate holding the fields of the token so that neither
+        // the state of the token nor the decryption is touched by a forged token
+        let mut candidate = self.clone();
+        candidate.version = parsed.get_version().clone();
+        candidate.key_id = parsed.get_key_id().map(Into::into);
+        candidate.tenant_id = parsed
+            .get_tenant_id()
+            .map(|tenant_id| TenantId(tenant_id.into()));
+        candidate.footer = parsed.get_footer().clone();
+        candidate.identifier = parsed.get_identifier().into();
+        candidate.issued = issued;
+        candidate.expiry = expiry;
+        candidate.confidentiality = ConfidentialityMode::from_string(parsed.get_confidentiality());
+        candidate.cipher = parsed.get_cipher().clone();
+        if !candidate.cipher.is_available() {
+            return Err(self.malformed("version", LiteSessionError::UnavailableCipher));
+        }
+        candidate.key_commitment = parsed.get_key_commitment().is_some();
+
+        // A token committing to another key is never decrypted
+        if let Some(key_commitment) = parsed.get_key_commitment() {
+            if !constant_time_eq::constant_time_eq(
+                key_commitment.as_bytes(),
+                candidate.commit_key(server_key).as_bytes(),
+            ) {
+                self.rejection = Some(RejectionReason::MacMismatch);
+                return Ok((TokenOutcome::TokenRejected, self));
+            }
+        }
+
+        let mut hmac =
+            candidate.compute_hmac(server_key, parsed.get_ciphertext(), parsed.get_nonce());
+        // The MAC of an attenuated token is chained off the MAC of the token by every caveat
+        if !candidate.caveats.is_empty() {
+            let mut signature = hmac.as_bytes()[..(parsed.get_hmac().len() / 2).min(32)].to_vec();
+            for caveat in candidate.caveats.iter() {
+                signature = caveat::chain(&signature, caveat);
+            }
+            let mut chained = [0_u8; 32];
+            chained[..signature.len()].copy_from_slice(&signature);
+            hmac = blake3::Hash::from(chained);
+        }
+        let hmac_matches = match candidate.verify_hmac(&hmac, parsed.get_hmac()) {
+            Ok(hmac_matches) => hmac_matches,
+            Err(error) => return Err(self.malformed("hmac", error)),
+        };
+        if !hmac_matches {
+            self.rejection = Some(RejectionReason::MacMismatch);
+            return Ok((TokenOutcome::TokenRejected, self));
+        }
+        *self = candidate;
+        self.hmac = hmac;
+        self.envelope = false;
+
+        self.report.begin("decryption");
+        let (wrapped_key, data) = match envelope::split(parsed.get_ciphertext()) {
+            Some(Ok((wrapped_key, data))) => (Some(wrapped_key), data),
+            Some(Err(error)) => return Err(self.malformed("ciphertext", error)),
+            None => (None, parsed.get_ciphertext()),
+        };
+        self.envelope = wrapped_key.is_some();
+        let (compressed, ciphertext_hex) = match data.strip_prefix(COMPRESSED_MARKER) {
+            Some(ciphertext_hex) => (true, ciphertext_hex),
+            None => (false, data),
+        };
+        let mut ciphertext_bytes = match hex::decode(ciphertext_hex) {
+            Ok(bytes) => bytes,
+            Err(_) => return Err(self.malformed("ciphertext", LiteSessionError::InvalidHexString)),
+        };
+
+        // Unversioned `ConfidentialityMode::Low` tokens were still encrypted
+        self.hmac_data = if self.confidentiality == ConfidentialityMode::Low
+            && self.version.prefix().is_some()
+        {
+            if compressed {
+                return Err(self.malformed("ciphertext", LiteSessionError::CompressionError));
+            }
+            if self.envelope {
+                return Err(self.malformed("ciphertext", LiteSessionError::MissingWrappedKey));
+            }
+            if ciphertext_bytes.len() > self.max_data_size {
+                return Err(self.malformed("ciphertext", LiteSessionError::DataSizeTooLarge));
+            }
+            let plaintext = match String::from_utf8(ciphertext_bytes) {
+                Ok(plaintext) => plaintext,
+                Err(_) => {
+                    return Err(self.malformed("ciphertext", LiteSessionError::FromUtf8TokenError))
+                }
+            };
+            match LiteSessionData::default().destructure(&plaintext) {
+                Ok(data) => data,
+                Err(error) => return Err(self.malformed("ciphertext", error)),
+            }
+        } else {
+            let encryption_key = self.get_key(server_key);
+            let nonce = match TokenCipher::nonce_bytes(parsed.get_nonce()) {
+                Ok(nonce) => nonce,
+                Err(error) => return Err(self.malformed("nonce", error)),
+            };
+            let mut data_key = match wrapped_key {
+                Some(wrapped_key) => {
+                    match envelope::unwrap(wrapped_key, &encryption_key, &nonce, &self.cipher) {
+                        Ok(data_key) => data_key,
+                        Err(error) => return Err(self.malformed("ciphertext", error)),
+                    }
+                }
+                None => encryption_key,
+            };
+            let data = self.decrypt_data(&data_key, &mut ciphertext_bytes, &nonce, compressed);
+            data_key.zeroize();
+            match data {
+                Ok(data) => data,
+                Err(error) => return Err(self.malformed("ciphertext", error)),
+            }
+        };
+
+        self.hmac_data.remove_expired_acl(&now);
+
+        // A token re-issued by a sliding session is as old as the session
+        if let (Some(max_age), Some(auth_time)) = (self.max_age, self.hmac_data.get_auth_time()) {
+            if *auth_time + max_age + self.leeway <= now {
+                self.rejection = Some(RejectionReason::Expired);
+                return Ok((TokenOutcome::SessionExpired, self));
+            }
+        }
+
+        self.report.begin("claims");
+        if let Some(which) = self.failed_claim() {
+            self.rejection = Some(RejectionReason::PolicyFailed {
+                which: which.into(),
+            });
+            return Ok((TokenOutcome::TokenRejected, self));
+        }
+        if within_grace {
+            self.rejection = Some(RejectionReason::Expired);
+            return Ok((TokenOutcome::ExpiredWithinGrace, self));
+        }
+
+        Ok((TokenOutcome::TokenAuthentic, self))
+    }
+    /// Derive the CSRF secret of the session held by a token built or verified as authentic,
+    /// for double-submit protection without storing extra state. The secret is derived from
+    /// the MAC so it cannot be computed without the server key
+    pub fn derive_csrf_token(&self) -> String {
+        let mut key_material: Vec<u8> = Vec::new();
+        for field in [self.identifier.as_bytes(), self.hmac.as_bytes()].iter() {
+            key_material.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            key_material.extend_from_slice(field);
+        }
+
+        let mut csrf_token = [0_u8; 32];
+        blake3::derive_key(CSRF_CONTEXT, &key_material, &mut csrf_token);
+
+        hex::encode(csrf_token)
+    }
+    /// Check in constant time that `candidate`, like the value of a CSRF form field or header,
+    /// is the CSRF secret derived by `derive_csrf_token()`
+    pub fn verify_csrf(&self, candidate: &str) -> bool {
+        constant_time_eq::constant_time_eq(
+            self.derive_csrf_token().as_bytes(),
+            candidate.as_bytes(),
+        )
+    }
+    /// Enforce the `Quota` claim of a verified token by recording the request in the `store`.
+    /// Returns `TokenOutcome::QuotaExceeded` if the requests in the current window exceed the quota,
+    /// otherwise `TokenOutcome::TokenAuthorized`. Tokens without a quota claim are always authorized
+    /// and tokens rejected by the verification are `TokenOutcome::TokenRejected` without being counted
+    pub fn enforce_quota(&self, store: &(impl QuotaStore + ?Sized)) -> TokenOutcome {
+        if self.rejection.is_some() {
+            return TokenOutcome::TokenRejected;
+        }
+
+        let quota = match self.hmac_data.get_quota() {
+            Some(quota) => quota,
+            None => return TokenOutcome::TokenAuthorized,
+        };
+
+        let unix_secs = |time: &TAI64N| {
+            time.duration_since(&tai64::UNIX_EPOCH_TAI64N)
+                .unwrap_or_default()
+                .as_secs()
+        };
+        let now = unix_secs(&self.get_clock().now());
+        let window_end = quota.window_end(now, unix_secs(&self.expiry));
+
+        if store.increment(&self.identifier, quota.window_start(now), window_end)
+            > quota.get_max_requests()
+        {
+            TokenOutcome::QuotaExceeded
+        } else {
+            TokenOutcome::TokenAuthorized
+        }
+    }
+    /// Authorize a token built or verified as authentic for the `required` capability.
+    /// Returns `TokenOutcome::TokenAuthorized` if `policy` allows it to the role of the token
+    /// or the access control list grants it and no deny entry matches it, otherwise
+    /// `TokenOutcome::TokenRejected` with a `RejectionReason::PolicyFailed` rejection
+    pub fn authorize(&mut self, policy: &Policy, required: &str) -> TokenOutcome {
+        if self.rejection.is_some() {
+            return TokenOutcome::TokenRejected;
+        }
+
+        let data = &self.hmac_data;
+        if data.has_capability(required)
+            || (!data.is_denied(required) && policy.allows(data.get_role(), required))
+        {
+            TokenOutcome::TokenAuthorized
+        } else {
+            self.rejection = Some(RejectionReason::PolicyFailed {
+                which: "authorization".into(),
+            });
+            TokenOutcome::TokenRejected
+        }
+    }
+    /// Evaluate `policy` against the data of a token built or verified as authentic,
+    /// the decision is always denied for a token that failed verification.
+    /// A denied decision rejects the token with a `RejectionReason::PolicyFailed`
+    /// naming the first predicate that did not hold
+    pub fn evaluate_attributes(&mut self, policy: &AttributePolicy) -> AccessDecision {
+        let mut decision = policy.evaluate(&self.hmac_data);
+
+        match (&self.rejection, decision.get_failed_rule()) {
+            (Some(_), _) => decision.reject(),
+            (None, Some(failed_rule)) => {
+                self.rejection = Some(RejectionReason::PolicyFailed {
+                    which: failed_rule.clone(),
+                })
+            }
+            (None, None) => (),
+        }
+
+        decision
+    }
+    /// Destructure and autheticate a token built using `build_secure_base64()`
+    pub fn from_base64(
+        &mut self,
+        server_key: &ServerKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.rejection = None;
+        self.refreshed = None;
+
+        let outcome = match self.plain_from_base64(token) {
+            Ok(token) => self
+                .verify_plain(&VerificationKey::ServerKey(server_key), &token)
+                .map(|(outcome, _)| outcome),
+            Err(error) => Err(error),
+        };
+
+        self.apply_parse_policy(outcome)
+    }
+
+    fn plain_from_base64(&mut self, token: &str) -> Result<String, LiteSessionError> {
+        if token.len() > 1024 * 1024 {
+            return Err(self.malformed("token", LiteSessionError::TokenSizeTooLarge));
+        }
+
+        let token_bytes = match base64::decode_config(token, base64::URL_SAFE_NO_PAD) {
+            Ok(token_bytes) => token_bytes,
+            Err(_) => return Err(self.malformed("token", LiteSessionError::InvalidBase64String)),
+        };
+        match String::from_utf8(token_bytes) {
+            Ok(token) => Ok(token),
+            Err(_) => Err(self.malformed("token", LiteSessionError::FromUtf8TokenError)),
+        }
+    }
+    /// Destructure and authenticate a token built by `build_hex`. The fields are read at fixed
+    /// offsets and checked against the header lengths before authenticating the token
+    pub fn from_hex(
+        &mut self,
+        server_key: &ServerKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.rejection = None;
+
+        self.verify_hex(&VerificationKey::ServerKey(server_key), token)
+    }
+
+    fn verify_hex(
+        &mut self,
+        key: &VerificationKey,
+        token: &str,
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        let outcome = match self.plain_from_hex(token) {
+            Ok(token) => self.verify_plain(key, &token).map(|(outcome, _)| outcome),
+            Err(error) => Err(error),
+        };
+
+        self.apply_parse_policy(outcome)
+    }
+
+    fn plain_from_hex(&mut self, token: &str) -> Result<String, LiteSessionError> {
+        if token.len() > 1024 * 1024 {
+            return Err(self.malformed("token", LiteSessionError::TokenSizeTooLarge));
+        }
+
+        let blob = match hex::decode(token) {
+            Ok(blob) => blob,
+            Err(_) => return Err(self.malformed("token", LiteSessionError::InvalidHexString)),
+        };
+        if blob.len() < HEX_HEADER_LEN {
+            return Err(self.malformed("token", LiteSessionError::TokenFieldsLengthError));
+        }
+
+        let version = match blob[0] & !(HEX_VERSION_KEY_COMMITMENT | HEX_VERSION_ENVELOPE) {
+            0 => TokenVersion::V0,
+            1 => TokenVersion::V1,
+            2 => TokenVersion::V2,
+            _ => return Err(self.malformed("version", LiteSessionError::UnsupportedTokenVersion)),
+        };
+        // Every bit of the flags byte is assigned
+        let flags = blob[1];
+        let confidentiality = match flags & HEX_FLAG_HIGH {
+            0 => ConfidentialityMode::Low,
+            _ => ConfidentialityMode::High,
+        };
+        let cipher = match flags & (HEX_FLAG_XCHACHA20 | HEX_FLAG_ROUNDS12 | HEX_FLAG_ROUNDS20) {
+            0 => TokenCipher::ChaCha(Rounds::R8),
+            HEX_FLAG_ROUNDS12 => TokenCipher::ChaCha(Rounds::R12),
+            HEX_FLAG_ROUNDS20 => TokenCipher::ChaCha(Rounds::R20),
+            HEX_FLAG_XCHACHA20 => TokenCipher::XChaCha20,
+            HEX_FLAG_AES256GCM => TokenCipher::Aes256Gcm,
+            _ => return Err(self.malformed("version", LiteSessionError::UnsupportedTokenVersion)),
+        };
+        let nonce_extension_len = match cipher {
+            TokenCipher::XChaCha20 => 12,
+            TokenCipher::ChaCha(_) | TokenCipher::Aes256Gcm => 0,
+        };
+        let hmac_len = blob[38] as usize;
+        let identifier_len = u16::from_be_bytes([blob[39], blob[40]]) as usize;
+        let data_len = u32::from_be_bytes([blob[41], blob[42], blob[43], blob[44]]) as usize;
+        let footer_len = u16::from_be_bytes([blob[45], blob[46]]) as usize;
+        let key_id_start = HEX_HEADER_LEN + nonce_extension_len;
+        let key_id_len = match (flags & HEX_FLAG_KEY_ID, blob.get(key_id_start)) {
+            (0, _) => 0,
+            (_, Some(key_id_len)) => 1 + *key_id_len as usize,
+            (_, None) => {
+                return Err(self.malformed("token", LiteSessionError::TokenFieldsLengthError))
+            }
+        };
+
+        let tenant_id_start = key_id_start + key_id_len;
+        let tenant_id_len = match (flags & HEX_FLAG_TENANT_ID, blob.get(tenant_id_start)) {
+            (0, _) => 0,
+            (_, Some(tenant_id_len)) => 1 + *tenant_id_len as usize,
+            (_, None) => {
+                return Err(self.malformed("token", LiteSessionError::TokenFieldsLengthError))
+            }
+        };
+
+        let key_commitment_start = tenant_id_start + tenant_id_len;
+        let key_commitment_len = match blob[0] & HEX_VERSION_KEY_COMMITMENT {
+            0 => 0,
+            _ => 32,
+        };
+
+        if blob.len()
+            != HEX_HEADER_LEN
+                + nonce_extension_len
+                + key_id_len
+                + tenant_id_len
+                + key_commitment_len
+                + identifier_len
+                + data_len
+                + hmac_len
+                + footer_len
+        {
+            return Err(self.malformed("token", LiteSessionError::TokenFieldsLengthError));
+        }
+
+        let identifier_start = key_commitment_start + key_commitment_len;
+        let identifier_end = identifier_start + identifier_len;
+        let data_end = identifier_end + data_len;
+        let hmac_end = data_end + hmac_len;
+
+        let identifier = match core::str::from_utf8(&blob[identifier_start..identifier_end]) {
+            Ok(identifier) => identifier,
+            Err(_) => {
+                return Err(self.malformed("identifier", LiteSessionError::FromUtf8TokenError))
+            }
+        };
+        let nonce = match (nonce_extension_len, flags & HEX_FLAG_BINARY_NONCE) {
+            (0, 0) => match core::str::from_utf8(&blob[26..38]) {
+                Ok(nonce) => nonce.to_owned(),
+                Err(_) => return Err(self.malformed("nonce", LiteSessionError::NonceLengthError)),
+            },
+            (0, _) => hex::encode(&blob[26..38]),
+            (_, 0) => {
+                let mut nonce = hex::encode(&blob[26..38]);
+                nonce.push_str(&hex::encode(&blob[HEX_HEADER_LEN..key_id_start]));
+                nonce
+            }
+            _ => return Err(self.malformed("nonce", LiteSessionError::NonceLengthError)),
+        };
+        let key_id = match key_id_len {
+            0 => None,
+            _ => match core::str::from_utf8(&blob[key_id_start + 1..tenant_id_start]) {
+                Ok(key_id) => Some(key_id),
+                Err(_) => {
+                    return Err(self.malformed("version", LiteSessionError::UnsupportedTokenVersion))
+                }
+            },
+        };
+        let tenant_id = match tenant_id_len {
+            0 => None,
+            _ => match core::str::from_utf8(&blob[tenant_id_start + 1..key_commitment_start]) {
+                Ok(tenant_id) => Some(tenant_id),
+                Err(_) => {
+                    return Err(self.malformed("version", LiteSessionError::UnsupportedTokenVersion))
+                }
+            },
+        };
+        let footer = &blob[hmac_end..];
+
+        let separator = self.format.separator();
+        let mut fields: Vec<String> = Vec::new();
+        let key_commitment = match key_commitment_len {
+            0 => None,
+            _ => Some(hex::encode(&blob[key_commitment_start..identifier_start])),
+        };
+        if let Some(header) = version.header(&cipher, key_id, tenant_id, key_commitment.as_deref())
+        {
+            fields.push(header);
+        }
+        fields.push(identifier.to_owned());
+        fields.push(hex::encode(&blob[2..14]));
+        fields.push(hex::encode(&blob[14..26]));
+        let mut data_start = identifier_end;
+        let mut data = String::default();
+        if blob[0] & HEX_VERSION_ENVELOPE != 0 {
+            if data_end - data_start < envelope::WRAPPED_KEY_LEN {
+                return Err(self.malformed("ciphertext", LiteSessionError::MissingWrappedKey));
+            }
+            data.push(envelope::ENVELOPE_MARKER);
+            data.push_str(&hex::encode(
+                &blob[data_start..data_start + envelope::WRAPPED_KEY_LEN],
+            ));
+            data_start += envelope::WRAPPED_KEY_LEN;
+        }
+        if flags & HEX_FLAG_COMPRESSED != 0 {
+            data.push(COMPRESSED_MARKER);
+        }
+        data.push_str(&hex::encode(&blob[data_start..data_end]));
+        fields.push(data);
+        fields.push(nonce);
+        fields.push(ConfidentialityMode::to_string(&confidentiality).to_owned());
+        fields.push(hex::encode(&blob[data_end..hmac_end]));
+        if !footer.is_empty() {
+            fields.push(base64::encode_config(footer, base64::URL_SAFE_NO_PAD));
+        }
+
+        Ok(fields.join(&separator.to_string()))
+    }
+    /// Reassemble a token split by `TokenChunks` and destructure and authenticate it
+    pub fn from_chunks(
+        &mut self,
+        server_key: &ServerKey,
+        chunks: &[&str],
+    ) -> Result<(TokenOutcome, &Self), LiteSessionError> {
+        self.rejection = None;
+
+        let token = match TokenChunks::reassemble(chunks) {
+            Ok(token) => token,
+            Err(error) => return Err(self.malformed("token", error)),
+        };
+
+        self.from_string(server_key, &token)
+    }
+    /// Re-issue a token built or verified as authentic at the current time of its `Clock`
+    /// valid for `expiry_in_secs`, keeping the identifier and data. Fails with
+    /// `LiteSessionError::TokenNotAuthentic` if verifying the token recorded a rejection
+    pub fn renew(
+        &mut self,
+        server_key: &ServerKey,
+        expiry_in_secs: u64,
+    ) -> Result<String, LiteSessionError> {
+        if self.rejection.is_some() {
+            return Err(LiteSessionError::TokenNotAuthentic);
+        }
+
+        let now = self.get_clock().now();
+        self.reissue_at(now, expiry_in_secs);
+
+        self.build_secure(server_key)
+    }
+    /// Like `renew()` but draws a new identifier from the `EntropySource` of the token
+    /// so that the renewed token can be told apart from the one it replaces
+    pub fn renew_rotating(
+        &mut self,
+        server_key: &ServerKey,
+        expiry_in_secs: u64,
+    ) -> Result<String, LiteSessionError> {
+        if self.rejection.is_some() {
+            return Err(LiteSessionError::TokenNotAuthentic);
+        }
+
+        self.identifier = SessionTokenRng::alphanumeric_from(self.get_entropy());
+
+        self.renew(server_key, expiry_in_secs)
+    }
+    /// Check the assembled token for misconfigurations before it is issued by `build_secure`.
+    /// Returns every `ConfigIssue` found, use `ConfigIssue::is_error()` to separate the
+    /// issues that prevent issuance from the warnings
+    pub fn validate_config(&self) -> Vec<ConfigIssue> {
+        let mut issues: Vec<ConfigIssue> = Vec::new();
+        let data = &self.hmac_data;
+
+        if self.expiry <= self.issued {
+            issues.push(ConfigIssue::ZeroTtl);
+        }
+        if data.get_acl().is_empty() {
+            issues.push(ConfigIssue::EmptyAcl);
+        }
+        if data.get_username().is_empty() {
+            issues.push(ConfigIssue::EmptyUsername);
+        }
+        if self.confidentiality == ConfidentialityMode::Low
+            && (!data.get_username().is_empty()
+                || data.get_subject_id().is_some()
+                || !data.get_tags().is_empty()
+                || data.get_quota().is_some()
+                || data.get_family().is_some()
+                || data.get_payload().is_some()
+                || data.get_attachment().is_some()
+                || !data.get_attributes().is_empty())
+        {
+            issues.push(ConfigIssue::LowConfidentialityWithClaims);
+        }
+        if self.mode == LiteSessionMode::Passive && *data.get_role() == Role::SuperUser {
+            issues.push(ConfigIssue::PassiveSuperUser);
+        }
+        if let Some(max_ttl) = self.role_ttls.get(data.get_role()) {
+            if self.expiry > self.issued + Duration::from_secs(max_ttl) {
+                issues.push(ConfigIssue::TtlExceedsRoleCap);
+            }
+        }
+        if !self.format.is_valid() {
+            issues.push(ConfigIssue::InvalidTokenFormat);
+        }
+
+        issues
+    }
+    /// Borrow a mutable `LiteSessionToken` immutably.
+    /// Use `seal` to build a token that cannot be changed after it has been issued
+    pub fn immutable(&mut self) -> &Self {
+        self
+    }
+
+    // Re-issue a token of a sliding session at the current time of its `Clock` with the same
+    // lifetime and `ConfidentialityMode`, recording when the session started
+    fn slide(&mut self, server_key: &ServerKey) -> Result<Option<String>, LiteSessionError> {
+        if self.version == TokenVersion::V0 {
+            if self.max_age.is_some() {
+                return Ok(None);
+            }
+        } else if self.hmac_data.get_auth_time().is_none() {
+            let issued = self.issued;
+            self.hmac_data.auth_time(issued);
+        }
+
+        let lifetime = self
+            .expiry
+            .duration_since(&self.issued)
+            .unwrap_or_default()
+            .as_secs();
+        let now = self.get_clock().now();
+        self.reissue_at(now, lifetime);
+
+        self.build(server_key).map(Some)
+    }
+
+    pub(crate) fn reissue_at(&mut self, issued: TAI64N, lifetime_in_secs: u64) -> &mut Self {
+        self.issued = issued;
+        self.expiry = issued + Duration::from_secs(lifetime_in_secs);
+
+        self
+    }
+
+    pub(crate) fn reject(&mut self, reason: RejectionReason) -> &mut Self {
+        self.rejection = Some(reason);
+
+        self
+    }
+
+    fn proves_possession(
+        &self,
+        verifier: &dyn ProofSignatureVerifier,
+        proof: &str,
+        method: &str,
+        uri: &str,
+        token: &str,
+    ) -> bool {
+        let proof = match ProofOfPossession::from_string(proof) {
+            Ok(proof) => proof,
+            Err(_) => return false,
+        };
+        let confirmed = match self.hmac_data.get_proof_key() {
+            Some(proof_key) => constant_time_eq::constant_time_eq(
+                proof_key,
+                &ProofOfPossession::thumbprint(proof.get_public_key()),
+            ),
+            None => false,
+        };
+
+        let now = self.get_clock().now();
+        let issued = *proof.get_issued();
+        let fresh = issued <= now + self.leeway
+            && issued + Duration::from_secs(ProofOfPossession::MAX_AGE_SECS) + self.leeway > now;
+
+        confirmed
+            && fresh
+            && proof.get_method().eq_ignore_ascii_case(method)
+            && proof.get_uri() == uri
+            && verifier.verify(
+                proof.get_public_key(),
+                &proof.signing_input(token),
+                proof.get_signature(),
+            )
+    }
+
+    // The first audience or issuer requirement the authentic token does not meet
+    fn failed_claim(&self) -> Option<&'static str> {
+        if let Some(audience) = &self.required_audience {
+            if self.hmac_data.get_audience().as_ref() != Some(audience) {
+                return Some("audience");
+            }
+        }
+        if let Some(issuers) = &self.allowed_issuers {
+            match self.hmac_data.get_issuer() {
+                Some(issuer) if issuers.contains(issuer) => (),
+                _ => return Some("issuer"),
+            }
+        }
+        // A token bound to a client certificate is only accepted where the certificate is checked
+        if self.hmac_data.get_certificate_thumbprint().is_some() && self.confirming != Some("cnf") {
+            return Some("cnf");
+        }
+        // A sender constrained token is only accepted together with a verified proof
+        if self.hmac_data.get_proof_key().is_some() && self.confirming != Some("dpop") {
+            return Some("dpop");
+        }
+
+        None
+    }
+
+    fn malformed(&mut self, field: &'static str, error: LiteSessionError) -> LiteSessionError {
+        self.rejection = Some(RejectionReason::Malformed { field });
+
+        error
+    }
+
+    fn decrypt_data(
+        &self,
+        encryption_key: &[u8],
+        ciphertext: &mut [u8],
+        nonce: &[u8],
+        compressed: bool,
+    ) -> Result<LiteSessionData, LiteSessionError> {
+        let plaintext_len =
+            CipherText::default().decrypt_bytes(encryption_key, ciphertext, nonce, &self.cipher)?;
+        let plaintext = &ciphertext[..plaintext_len];
+        let plaintext = match compressed {
+            true => compression::inflate(plaintext)?,
+            false => plaintext.to_vec(),
+        };
+        if plaintext.len() > self.max_data_size {
+            return Err(LiteSessionError::DataSizeTooLarge);
+        }
+        let plaintext = match String::from_utf8(plaintext) {
+            Ok(plaintext) => plaintext,
+            Err(_) => return Err(LiteSessionError::FromUtf8TokenError),
+        };
+
+        LiteSessionData::default().destructure_with_version(&plaintext, &self.version)
+    }
+
+    fn verify_dummy(&self, key: &VerificationKey, token: &str) {
+        if !self.dummy_verification {
+            return;
+        }
+
+        let provided_key = key.dummy_key();
+        let server_key = match provided_key.as_ref().or_else(|| key.server_key()) {
+            Some(server_key) => server_key.expose_secret(),
+            None => &[0_u8; 32],
+        };
+        let token_bytes = &token.as_bytes()[..token.len().min(1024 * 1024)];
+
+        let encryption_key = self.get_key(server_key);
+        let mut ciphertext_bytes = token_bytes[..token_bytes.len() / 2].to_vec();
+        let decrypted =
+            CipherText::default().decrypt(&encryption_key, &mut ciphertext_bytes, &[0_u8; 12]);
+
+        let hmac = blake3::keyed_hash(server_key, token_bytes);
+        let hmac_matches =
+            constant_time_eq::constant_time_eq(hmac.as_bytes(), self.hmac.as_bytes());
+
+        std::hint::black_box((decrypted.is_ok(), hmac_matches));
+    }
+
+    fn get_key(&self, key: &[u8; 32]) -> [u8; 32] {
+        match self.version {
+            TokenVersion::V0 | TokenVersion::V1 => self.get_legacy_key(key),
+            TokenVersion::V2 => self.derive_key(key),
+        }
+    }
+
+    // The version header of the token, with the key commitment if it is enabled
+    fn header(&self, server_key: &[u8; 32]) -> Option<String> {
+        let key_commitment = match self.key_commitment {
+            true => Some(self.commit_key(server_key)),
+            false => None,
+        };
+
+        self.version.header(
+            &self.cipher,
+            self.key_id.as_deref(),
+            self.tenant_id.as_ref().map(TenantId::as_str),
+            key_commitment.as_deref(),
+        )
+    }
+
+    fn commit_key(&self, server_key: &[u8; 32]) -> String {
+        let mut encryption_key = self.get_key(server_key);
+        let key_commitment = self.derive(KEY_COMMITMENT_CONTEXT, &encryption_key);
+        encryption_key.zeroize();
+
+        hex::encode(key_commitment)
+    }
+
+    // Blake3 `derive_key`, or `HMAC-SHA-256` keyed with the key material for `TokenCipher::Aes256Gcm`
+    fn derive(&self, context: &str, key_material: &[u8]) -> [u8; 32] {
+        match self.cipher {
+            #[cfg(feature = "fips")]
+            TokenCipher::Aes256Gcm => fips::hmac_sha256(key_material, context.as_bytes()),
+            _ => {
+                let mut derived_key = [0_u8; 32];
+                blake3::derive_key(context, key_material, &mut derived_key);
+
+                derived_key
+            }
+        }
+    }
+
+    // The server key and every field are length-prefixed so field boundaries cannot shift
+    fn derive_key(&self, key: &[u8; 32]) -> [u8; 32] {
+        let issued = self.issued.to_bytes();
+        let expiry = self.expiry.to_bytes();
+        let confidentiality = ConfidentialityMode::to_string(&self.confidentiality);
+        let fields: [&[u8]; 5] = [
+            key,
+            self.identifier.as_bytes(),
+            &issued,
+            &expiry,
+            confidentiality.as_bytes(),
+        ];
+
+        let mut key_material: Vec<u8> = Vec::new();
+        for field in fields.iter() {
+            key_material.extend_from_slice(&(field.len() as u64).to_be_bytes());
+            key_material.extend_from_slice(field);
+        }
+        // Appended only in device bound mode so keys of other tokens stay unchanged
+        if let LiteSessionMode::DeviceBound(device) = &self.mode {
+            key_material.extend_from_slice(&(device.as_bytes().len() as u64).to_be_bytes());
+            key_material.extend_from_slice(device.as_bytes());
+        }
+        let encryption_key = self.derive(ENCRYPTION_KEY_CONTEXT, &key_material);
+        key_material.zeroize();
+
+        encryption_key
+    }
+
+    fn get_legacy_key(&self, key: &[u8; 32]) -> [u8; 32] {
+        let mut raw_key = String::default();
+
+        let identifier = self.identifier.clone();
+        let issued = hex::encode(self.issued.to_bytes());
+        let expiry = hex::encode(self.expiry.to_bytes());
+        let confidentiality = ConfidentialityMode::to_string(&self.confidentiality);
+
+        raw_key.push_str(&identifier);
+        raw_key.push_str(&issued);
+        raw_key.push_str(&expiry);
+        raw_key.push_str(&confidentiality);
+        let encryption_key = blake3::keyed_hash(key, raw_key.as_bytes());
+
+        encryption_key.as_bytes().clone()
+    }
+
+    fn verify_hmac(&self, hmac: &blake3::Hash, hash_hex: &str) -> Result<bool, LiteSessionError> {
+        let hash_bytes = match hex::decode(hash_hex) {
+            Err(_) => return Err(LiteSessionError::InvalidHexString),
+            Ok(bytes) => bytes,
+        };
+        let mac_length = match MacLength::from_len(hash_bytes.len()) {
+            None => return Err(LiteSessionError::InvalidBytesForBlake3),
+            Some(mac_length) => mac_length,
+        };
+
+        if mac_length != self.mac_length {
+            let now = self.get_clock().now();
+            match &self.mac_fallback {
+                Some((fallback, until)) if *fallback == mac_length && now < *until => (),
+                _ => return Ok(false),
+            }
+        }
+
+        Ok(constant_time_eq::constant_time_eq(
+            &hmac.as_bytes()[..mac_length.byte_len()],
+            &hash_bytes,
+        ))
+    }
+}
+
+/// The key used to authenticate a token, either a single server key, a `KeyProvider`
+/// or a `TenantKeyring`
+enum VerificationKey<'a> {
+    ServerKey(&'a ServerKey),
+    Provider(&'a dyn KeyProvider),
+    Tenant(&'a TenantKeyring),
+}
+
+impl<'a> VerificationKey<'a> {
+    // Keys of a `KeyProvider` are fetched by the key ID of the token, or the current key.
+    // Tenant subkeys are derived from the tenant ID of the token
+    fn provide(
+        &self,
+        key_id: Option<&str>,
+        tenant_id: Option<&str>,
+    ) -> Result<Option<ServerKey>, LiteSessionError> {
+        match *self {
+            VerificationKey::ServerKey(_) => Ok(None),
+            VerificationKey::Provider(key_provider) => match key_id {
+                Some(key_id) => key_provider.key_by_id(key_id).map(Some),
+                None => key_provider
+                    .current_key()
+                    .map(|(_, server_key)| Some(server_key)),
+            },
+            VerificationKey::Tenant(tenant_keyring) => match (key_id, tenant_id) {
+                (None, Some(tenant_id)) => {
+                    Ok(Some(tenant_keyring.tenant_key(&TenantId(tenant_id.into()))))
+                }
+                _ => Err(LiteSessionError::UnknownKeyId),
+            },
+        }
+    }
+
+    fn server_key(&self) -> Option<&'a ServerKey> {
+        match *self {
+            VerificationKey::ServerKey(server_key) => Some(server_key),
+            VerificationKey::Provider(_) | VerificationKey::Tenant(_) => None,
+        }
+    }
+
+    // Dummy verification fetches the current key of a `KeyProvider` like the MAC check
+    // does for tokens without a key ID, so that a rejected token also pays for the lookup
+    fn dummy_key(&self) -> Option<ServerKey> {
+        match *self {
+            VerificationKey::Provider(key_provider) => key_provider
+                .current_key()
+                .ok()
+                .map(|(_, server_key)| server_key),
+            VerificationKey::ServerKey(_) | VerificationKey::Tenant(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod token_tests {
+    use super::LiteSessionToken;
+    use crate::{
+        AttenuatedToken, AttributePolicy, CipherText, DeviceFingerprint, EntropySource, FixedClock,
+        MemorySessionStore, NetworkBinding, ParsePolicy, ProofOfPossession, ProofSignatureVerifier,
+        QuotaStore, RefreshPolicy, ServerKey, SessionStore, TokenParser,
+    };
+    use crate::{
+        ConfidentialityMode, ConfigIssue, KeyProvider, KeyRing, LiteSessionData, LiteSessionError,
+        LiteSessionMode, MacLength, MemoryQuotaStore, Policy, Quota, RejectionReason, Role,
+        RoleTtls, Rounds, TenantId, TenantKeyring, TimestampRange, TokenChunks, TokenCipher,
+        TokenEncoding, TokenFormat, TokenOutcome, TokenVersion,
+    };
+    use secrecy::ExposeSecret;
+    use std::{convert::TryInto, net::IpAddr, sync::Arc};
+
+    #[test]
+    fn mac_migration() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        token.mac_length(MacLength::Truncated);
+        let truncated_token = token.build_secure(&server_key)?;
+        token.mac_length(MacLength::Full);
+        let full_token = token.build_secure(&server_key)?;
+        assert_eq!(full_token.len() - truncated_token.len(), 32_usize);
+
+        let mut verifier = LiteSessionToken::default();
+        let outcome = verifier.from_string(&server_key, &full_token)?;
+        assert_eq!(outcome.0, TokenOutcome::TokenAuthentic);
+        let outcome = verifier.from_string(&server_key, &truncated_token)?;
+        assert_eq!(outcome.0, TokenOutcome::TokenRejected);
+        assert_eq!(
+            verifier.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+
+        let window = tai64::TAI64N::now() + core::time::Duration::from_secs(60);
+        verifier.mac_fallback(MacLength::Truncated, window);
+        let outcome = verifier.from_string(&server_key, &truncated_token)?;
+        assert_eq!(outcome.0, TokenOutcome::TokenAuthentic);
+        let outcome = verifier.from_string(&server_key, &full_token)?;
+        assert_eq!(outcome.0, TokenOutcome::TokenAuthentic);
+
+        let closed = tai64::TAI64N::now() - core::time::Duration::from_secs(60);
+        verifier.mac_fallback(MacLength::Truncated, closed);
+        let outcome = verifier.from_string(&server_key, &truncated_token)?;
+        assert_eq!(outcome.0, TokenOutcome::TokenRejected);
+
+        let mut tampered = truncated_token.clone();
+        tampered.truncate(tampered.len() - 2);
+        assert_eq!(
+            verifier.from_string(&server_key, &tampered),
+            Err(LiteSessionError::InvalidBytesForBlake3)
+        );
+        assert_eq!(
+            verifier.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "hmac" })
+        );
+        verifier.from_string(&server_key, &full_token)?;
+        assert_eq!(verifier.get_rejection(), &None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn timestamp_range() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        token.expiry(timelite::LiteDuration::years(50));
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut verifier = LiteSessionToken::default();
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        verifier.timestamp_range(TimestampRange::default());
+        assert_eq!(
+            verifier.from_string(&server_key, &session_token),
+            Err(LiteSessionError::TimestampOutOfRange)
+        );
+        assert_eq!(
+            verifier.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "expiry" })
+        );
+
+        token.expiry(timelite::LiteDuration::hours(1));
+        let session_token = token.build_secure(&server_key)?;
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn role_ttls() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.role(Role::SuperUser);
+        data.add_acl("Network-TCP");
+
+        let mut ttls = RoleTtls::default();
+        ttls.insert(Role::SuperUser, 3600);
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        token.role_ttls(ttls);
+        token.expiry(timelite::LiteDuration::days(7));
+        token.build_secure(&server_key)?;
+        assert_eq!(
+            token.expiry,
+            token.issued + core::time::Duration::from_secs(3600)
+        );
+
+        token.expiry(60);
+        token.build_secure(&server_key)?;
+        assert_eq!(
+            token.expiry,
+            token.issued + core::time::Duration::from_secs(60)
+        );
+
+        data.role(Role::User);
+        token.hmac_data(data);
+        token.expiry(timelite::LiteDuration::days(7));
+        token.build_secure(&server_key)?;
+        assert_eq!(
+            token.expiry,
+            token.issued + core::time::Duration::from_secs(timelite::LiteDuration::days(7))
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_format() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let legacy_token = token.build_secure(&server_key)?;
+
+        token.format(TokenFormat::Ascii('.'));
+        let ascii_token = token.build_secure(&server_key)?;
+        assert!(ascii_token.is_ascii());
+        assert_eq!(ascii_token.split('.').count(), 8);
+        assert!(ascii_token.starts_with("ls2."));
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.format(TokenFormat::Ascii('.'));
+        let (outcome, _) = destructured.from_string(&server_key, &ascii_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = destructured.from_string(&server_key, &legacy_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let mut destructured = LiteSessionToken::default();
+        assert_eq!(
+            destructured.from_string(&server_key, &ascii_token),
+            Err(LiteSessionError::InvalidTokenFormat)
+        );
+
+        token.format(TokenFormat::Ascii('a'));
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::InvalidTokenFormat)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_version() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let versioned_token = token.build_secure(&server_key)?;
+        assert!(versioned_token.starts_with("ls2⊕"));
+
+        token.version(TokenVersion::V0);
+        let unversioned_token = token.build_secure(&server_key)?;
+        assert!(unversioned_token.starts_with(token.get_identifier().as_str()));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &unversioned_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_version(), &TokenVersion::V0);
+        let (outcome, _) = destructured.from_string(&server_key, &versioned_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_version(), &TokenVersion::V2);
+
+        token.version(TokenVersion::V1);
+        let v1_token = token.build_secure(&server_key)?;
+        let (outcome, _) = destructured.from_string(&server_key, &v1_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_version(), &TokenVersion::V1);
+
+        // The version prefix is authenticated so it cannot be stripped
+        let stripped_token = v1_token.trim_start_matches("ls1⊕");
+        let (outcome, _) = destructured.from_string(&server_key, stripped_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn validate_config() {
+        let mut token = LiteSessionToken::default();
+        assert_eq!(
+            token.validate_config(),
+            vec![ConfigIssue::EmptyAcl, ConfigIssue::EmptyUsername]
+        );
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .role(Role::SuperUser)
+            .add_acl("Network-TCP");
+        let mut ttls = RoleTtls::default();
+        ttls.insert(Role::SuperUser, 60);
+        token
+            .hmac_data(data)
+            .confidential(false)
+            .role_ttls(ttls)
+            .format(TokenFormat::Ascii('a'));
+        let issues = token.validate_config();
+        assert_eq!(
+            issues,
+            vec![
+                ConfigIssue::LowConfidentialityWithClaims,
+                ConfigIssue::PassiveSuperUser,
+                ConfigIssue::TtlExceedsRoleCap,
+                ConfigIssue::InvalidTokenFormat,
+            ]
+        );
+        assert_eq!(issues.iter().filter(|issue| issue.is_error()).count(), 1);
+
+        token.expiry(0);
+        assert!(token.validate_config().contains(&ConfigIssue::ZeroTtl));
+    }
+
+    #[test]
+    fn token_footer() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).footer("kid=2021-03;tenant=foo");
+        let session_token = token.build_secure(&server_key)?;
+
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert_eq!(
+            parsed.get_footer(),
+            &Some("kid=2021-03;tenant=foo".to_owned())
+        );
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_footer(), token.get_footer());
+
+        // The footer is authenticated so it cannot be swapped
+        let (signed, _) = session_token.rsplit_once('⊕').unwrap();
+        let forged_token = format!("{}⊕{}", signed, "dGVuYW50PWJhcg");
+        let (outcome, _) = destructured.from_string(&server_key, &forged_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        let (outcome, _) = destructured.from_string(&server_key, signed)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        token.version(TokenVersion::V0);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn dummy_verification() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).expiry(0);
+        let expired_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.dummy_verification(true);
+        let (outcome, _) = destructured.from_string(&server_key, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+        assert_eq!(
+            destructured.from_string(&server_key, "foo"),
+            Err(LiteSessionError::TokenFieldsLengthError)
+        );
+        assert_eq!(
+            ServerKey::from_slice(&[0_u8; 4]).map(|_| ()),
+            Err(LiteSessionError::ServerKeyLengthError)
+        );
+
+        // The dummy path fetches the current key of the provider for rejected tokens
+        #[derive(Debug, Default)]
+        struct CountingProvider(std::sync::atomic::AtomicUsize);
+        impl KeyProvider for CountingProvider {
+            fn current_key(&self) -> Result<(String, ServerKey), LiteSessionError> {
+                self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                Ok(("2021a".into(), ServerKey::new([0_u8; 32])))
+            }
+            fn key_by_id(&self, _key_id: &str) -> Result<ServerKey, LiteSessionError> {
+                Ok(ServerKey::new([0_u8; 32]))
+            }
+        }
+        let provider = CountingProvider::default();
+        let calls = || provider.0.load(std::sync::atomic::Ordering::SeqCst);
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.from_string_with_provider(&provider, &expired_token)?;
+        assert_eq!(calls(), 0_usize);
+        destructured.dummy_verification(true);
+        assert!(destructured.get_dummy_verification());
+        let (outcome, _) = destructured.from_string_with_provider(&provider, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+        assert_eq!(calls(), 1_usize);
+        assert!(destructured
+            .from_string_with_provider(&provider, "foo")
+            .is_err());
+        assert_eq!(calls(), 2_usize);
+
+        Ok(())
+    }
+
+    #[test]
+    fn base64_tokens() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        data.add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_base64(&server_key)?;
+        assert!(session_token
+            .chars()
+            .all(|character| character.is_ascii_alphanumeric()
+                || character == '-'
+                || character == '_'));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_base64(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data().get_username(), "foo_user");
+
+        let mut destructured = LiteSessionToken::default();
+        assert_eq!(
+            destructured
+                .from_base64(&server_key, "foo⊕bar")
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::InvalidBase64String)
+        );
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "token" })
+        );
+
+        destructured.parse_policy(ParsePolicy::Lenient);
+        let (outcome, _) = destructured.from_base64(&server_key, "foo⊕bar")?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        let (outcome, _) = destructured.from_string(&server_key, "foo⊕bar")?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+
+        Ok(())
+    }
+
+    #[test]
+    fn tokens() -> Result<(), LiteSessionError> {
+        let mut token = LiteSessionToken::default();
         assert_eq!(token.identifier.len(), 32_usize);
 
-        let change_expiry = timelite::LiteDuration::hours(32);
-        token.expiry(change_expiry);
+        token.identifier_strategy(&crate::UuidIdentifier);
+        assert_eq!(token.identifier.len(), 36_usize);
+        token.identifier_strategy(&|| "eu-west-1.foo".to_owned());
+        assert_eq!(token.identifier, "eu-west-1.foo");
+
+        let change_expiry = timelite::LiteDuration::hours(32);
+        token.expiry(change_expiry);
+        assert_eq!(
+            token.expiry,
+            token.issued + core::time::Duration::from_secs(change_expiry)
+        );
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        data.role(Role::SuperUser);
+        data.tag("env", "prod");
+        data.add_acl("Network-TCP");
+        data.add_acl("Network-UDP");
+        token.hmac_data(data.clone());
+        assert_eq!(token.hmac_data, data);
+
+        token.confidential(false);
+        assert_eq!(token.confidentiality, ConfidentialityMode::Low);
+        token.confidential(true);
+        assert_eq!(token.confidentiality, ConfidentialityMode::High);
+
+        token.mode(LiteSessionMode::session_id("foobarbaz"));
+        assert_eq!(token.mode, LiteSessionMode::session_id("foobarbaz"));
+        assert_ne!(token.mode, LiteSessionMode::session_id("garbage"));
+        token.mode(LiteSessionMode::Passive);
+        assert_eq!(token.mode, LiteSessionMode::Passive);
+
+        {
+            let bad_key = [0_u8; 5];
+            assert_eq!(
+                ServerKey::from_slice(&bad_key).map(|_| ()),
+                Err(LiteSessionError::ServerKeyLengthError)
+            );
+        }
+
+        {
+            let server_key = ServerKey::new([0_u8; 32]);
+            let session_token = token.build_secure(&server_key)?;
+
+            let mut destructured = LiteSessionToken::default();
+            let outcome = destructured.from_string(&server_key, &session_token)?;
+
+            assert_eq!(outcome, (TokenOutcome::TokenAuthentic, token.immutable()));
+        }
+
+        {
+            let server_key = ServerKey::new([0_u8; 32]);
+            let session_token = token.build_secure(&server_key)?;
+
+            // A forged token is rejected by its MAC before it is decrypted or read into the token
+            let mut destructured = LiteSessionToken::default();
+            let identifier = destructured.get_identifier().clone();
+            let (outcome, _) =
+                destructured.from_string(&ServerKey::new([1_u8; 32]), &session_token)?;
+
+            assert_eq!(outcome, TokenOutcome::TokenRejected);
+            assert_eq!(
+                destructured.get_rejection(),
+                &Some(RejectionReason::MacMismatch)
+            );
+            assert_eq!(destructured.get_identifier(), &identifier);
+            assert_eq!(destructured.get_hmac_data(), &LiteSessionData::default());
+        }
+
+        {
+            let store = MemoryQuotaStore::new();
+            assert_eq!(token.enforce_quota(&store), TokenOutcome::TokenAuthorized);
+
+            let mut data = data.clone();
+            data.quota(Quota::new(2, 0));
+            token.hmac_data(data);
+
+            let server_key = ServerKey::new([0_u8; 32]);
+            let session_token = token.build_secure(&server_key)?;
+            let mut destructured = LiteSessionToken::default();
+            destructured.from_string(&server_key, &session_token)?;
+
+            assert_eq!(
+                destructured.enforce_quota(&store),
+                TokenOutcome::TokenAuthorized
+            );
+            assert_eq!(
+                destructured.enforce_quota(&store),
+                TokenOutcome::TokenAuthorized
+            );
+            assert_eq!(
+                destructured.enforce_quota(&store),
+                TokenOutcome::QuotaExceeded
+            );
+
+            let store = MemoryQuotaStore::new();
+            destructured.from_string(&ServerKey::new([1_u8; 32]), &session_token)?;
+            assert_eq!(
+                destructured.enforce_quota(&store),
+                TokenOutcome::TokenRejected
+            );
+            assert_eq!(store.increment(destructured.get_identifier(), 0, 60), 1);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_acl() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+
+        // An unversioned token built before the fields of the data were escaped
+        let mut legacy = LiteSessionToken::default();
+        legacy.version(TokenVersion::V0);
+        let legacy_data = "foo_user⥂User⥂None⥂admin@example.com⇅!root⇅50%";
+        let mut ciphertext = CipherText::default();
+        ciphertext.encrypt_bytes(
+            legacy_data.as_bytes(),
+            &legacy.get_key(server_key.expose_secret()),
+            &TokenCipher::default(),
+            &crate::OsEntropy,
+        )?;
+        let legacy_token = legacy.assemble(server_key.expose_secret(), &ciphertext);
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &legacy_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let data = destructured.get_hmac_data();
+        assert_eq!(data.get_acl(), &vec!["admin@example.com", "!root", "50%"]);
+        assert!(data.get_acl_deny().is_empty());
+        assert!(data.get_acl_expiry("admin").is_none());
+
+        // The same capabilities survive a round trip through the escaped syntax
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure(&server_key)?;
+        let mut destructured = LiteSessionToken::default();
+        destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(destructured.get_hmac_data(), token.get_hmac_data());
+
+        // Unversioned tokens cannot carry data that only the escaped syntax can express
+        token.version(TokenVersion::V0);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data().get_acl(), &vec!["Network-TCP"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn reserved_identifiers() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+
+        for identifier in ["", "foo\nbar", "foo⥂bar", "foo⊕bar", "foo⇅bar"].iter() {
+            let mut token = LiteSessionToken::default();
+            token.identifier(identifier);
+            assert_eq!(
+                token.build_secure(&server_key),
+                Err(LiteSessionError::InvalidTokenFormat)
+            );
+        }
+
+        let mut token = LiteSessionToken::default();
+        token
+            .version(TokenVersion::V2)
+            .format(TokenFormat::Ascii('.'))
+            .identifier("eu-west-1.foo");
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::InvalidTokenFormat)
+        );
+
+        let mut token = LiteSessionToken::default();
+        token.identifier_strategy(&|| "eu-west-1.foo".to_owned());
+        let session_token = token.build_secure(&server_key)?;
+        let mut destructured = LiteSessionToken::default();
+        destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(destructured.get_identifier(), "eu-west-1.foo");
+
+        // The ULID timestamp is read from the clock of the token
+        let issued =
+            tai64::UNIX_EPOCH_TAI64N + core::time::Duration::from_millis(1_600_000_000_123);
+        token
+            .clock(Arc::new(FixedClock::new(issued)))
+            .identifier_strategy(&crate::UlidIdentifier);
+        assert!(token.get_identifier().starts_with("01EJ3PX03V"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn low_confidentiality() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).confidential(false);
+        let session_token = token.build(&server_key)?;
+
+        // The data section carries the plaintext data
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        let plaintext = String::from_utf8(hex::decode(parsed.get_ciphertext()).unwrap()).unwrap();
+        assert_eq!(plaintext, data.build());
+        assert!(session_token.contains("ConfidentialityMode::Low"));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        // The plaintext data is still authenticated
+        let mut forged_data = data.clone();
+        forged_data.username("bar_user");
+        let forged_token =
+            session_token.replace(parsed.get_ciphertext(), &hex::encode(forged_data.build()));
+        let (outcome, _) = destructured.from_string(&server_key, &forged_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        // `build_secure` always encrypts
+        let session_token = token.build_secure(&server_key)?;
+        assert!(session_token.contains("ConfidentialityMode::High"));
+
+        token.confidential(false).version(TokenVersion::V0);
+        assert_eq!(
+            token.build(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn hex_tokens() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).footer("kid=1");
+        let hex_token = token.build_hex(&server_key)?;
+        assert!(hex_token.bytes().all(|byte| byte.is_ascii_hexdigit()));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+        assert_eq!(destructured.get_footer(), &Some("kid=1".to_owned()));
+
+        token.confidential(false);
+        let hex_token = token.build_hex(&server_key)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(
+            destructured.get_confidentiality(),
+            &ConfidentialityMode::Low
+        );
+
+        // Flipping a byte of the MAC rejects the token
+        let mut forged_token = hex_token.clone();
+        let position = forged_token.len() - 20;
+        let flipped = match &forged_token[position..=position] {
+            "0" => "1",
+            _ => "0",
+        };
+        forged_token.replace_range(position..=position, flipped);
+        let (outcome, _) = destructured.from_hex(&server_key, &forged_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        assert_eq!(
+            destructured
+                .from_hex(&server_key, &hex_token[..hex_token.len() - 2])
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::TokenFieldsLengthError)
+        );
+        assert_eq!(
+            destructured
+                .from_hex(&server_key, "zz")
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::InvalidHexString)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn chunked_tokens() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        (0..200).for_each(|index| {
+            data.add_acl(&format!("Network-Acl-{}", index));
+        });
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure(&server_key)?;
+        assert!(session_token.len() > 4096);
+
+        let chunks = TokenChunks::split(&session_token)?;
+        assert!(chunks.len() > 1);
+        let mut received: Vec<&str> = chunks.get_chunks().iter().map(|c| c.as_str()).collect();
+        received.reverse();
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_chunks(&server_key, &received)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        assert_eq!(
+            destructured
+                .from_chunks(&server_key, &received[1..])
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::InvalidTokenChunk)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn token_encodings() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        for encoding in [
+            TokenEncoding::Hex,
+            TokenEncoding::Base64Url,
+            TokenEncoding::Base32,
+            TokenEncoding::Base58,
+        ]
+        .iter()
+        {
+            let mut token = LiteSessionToken::default();
+            token.hmac_data(data.clone()).encoding(encoding.clone());
+            let session_token = token.build_secure(&server_key)?;
+            assert!(session_token
+                .bytes()
+                .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_'));
+
+            // The encoding is detected
+            let mut destructured = LiteSessionToken::default();
+            let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+            assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+            assert_eq!(destructured.get_hmac_data(), &data);
+
+            // or set explicitly
+            let mut destructured = LiteSessionToken::default();
+            destructured.encoding(encoding.clone());
+            let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+            assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        }
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).encoding(TokenEncoding::Base32);
+        let session_token = token.build_secure(&server_key)?;
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) =
+            destructured.from_string(&server_key, &session_token.to_ascii_lowercase())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        destructured.encoding(TokenEncoding::Base58);
+        assert_eq!(
+            destructured
+                .from_string(&server_key, "0OIl")
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::InvalidTokenEncoding)
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compressed_tokens() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        (0..200).for_each(|index| {
+            data.add_acl(&format!("Network-Acl-{}", index));
+        });
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let uncompressed_token = token.build_secure(&server_key)?;
+        token.compression(1024);
+        let session_token = token.build_secure(&server_key)?;
+        assert!(session_token.len() < uncompressed_token.len() / 2);
+
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert!(parsed.get_ciphertext().starts_with('z'));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        let hex_token = token.build_hex(&server_key)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        // Small data sections are left alone
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert!(!parsed.get_ciphertext().starts_with('z'));
+
+        Ok(())
+    }
+
+    #[test]
+    fn xchacha20_tokens() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).cipher(TokenCipher::XChaCha20);
+        let session_token = token.build_secure(&server_key)?;
+
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert_eq!(parsed.get_nonce().len(), 48);
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+        assert_eq!(destructured.get_cipher(), &TokenCipher::XChaCha20);
+
+        let hex_token = token.build_hex(&server_key)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        // Swapping the nonce for a `ChaCha8` one is never authentic
+        let forged_token = session_token.replace(parsed.get_nonce(), "abcdefghijkl");
+        assert_ne!(
+            destructured
+                .from_string(&server_key, &forged_token)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn framed_mac() {
+        let server_key = [0_u8; 32];
+        let mut token = LiteSessionToken::default();
+
+        // Moving bytes between fields changes the `ls2` MAC but not the legacy one
+        token.version(TokenVersion::V1);
+        assert_eq!(
+            token.compute_hmac(&server_key, "abcd", "ef"),
+            token.compute_hmac(&server_key, "abc", "def")
+        );
+        token.version(TokenVersion::V2);
+        assert_ne!(
+            token.compute_hmac(&server_key, "abcd", "ef"),
+            token.compute_hmac(&server_key, "abc", "def")
+        );
+    }
+
+    #[test]
+    fn key_commitment() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).key_commitment(true);
+        let session_token = token.build_secure(&server_key)?;
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert_eq!(parsed.get_key_commitment().map(str::len), Some(64));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_key_commitment());
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        // A token committing to another key is rejected before decryption
+        let (outcome, _) = destructured.from_string(&ServerKey::new([1_u8; 32]), &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+
+        // The commitment is authenticated by the MAC
+        let commitment = parsed.get_key_commitment().unwrap_or_default();
+        let forged_token = session_token.replacen(commitment, &"0".repeat(64), 1);
+        assert_ne!(
+            destructured
+                .from_string(&server_key, &forged_token)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+
+        let hex_token = token.build_hex(&server_key)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_key_commitment());
+
+        token.version(TokenVersion::V0);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn envelope_encryption() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let new_key = ServerKey::new([1_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).envelope(true);
+        let session_token = token.build_secure(&server_key)?;
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert!(parsed.get_ciphertext().starts_with('w'));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_envelope());
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        // Re-wrapping keeps the encrypted data and only replaces the wrapped key and MAC
+        let (outcome, rewrapped_token) =
+            destructured.rewrap(&server_key, None, &new_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let rewrapped_token = rewrapped_token.unwrap_or_default();
+        let rewrapped = TokenParser::default().parse(&rewrapped_token).unwrap();
+        assert_eq!(
+            rewrapped.get_ciphertext()[65..],
+            parsed.get_ciphertext()[65..]
+        );
+        assert_ne!(
+            rewrapped.get_ciphertext()[..65],
+            parsed.get_ciphertext()[..65]
+        );
+        let (outcome, _) = destructured.from_string(&new_key, &rewrapped_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+        assert_ne!(
+            destructured
+                .from_string(&server_key, &rewrapped_token)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+
+        // Tokens that are not authentic are not re-wrapped
+        assert!(!matches!(
+            destructured.rewrap(&new_key, None, &server_key, &session_token),
+            Ok((_, Some(_)))
+        ));
+
+        let hex_token = token.build_hex(&server_key)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_envelope());
+        destructured.encoding(TokenEncoding::Hex);
+        let (outcome, rewrapped_token) =
+            destructured.rewrap(&server_key, None, &new_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = destructured.from_hex(&new_key, &rewrapped_token.unwrap_or_default())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let unwrapped_token = token.envelope(false).build_secure(&server_key)?;
+        assert_eq!(
+            LiteSessionToken::default().rewrap(&server_key, None, &new_key, &unwrapped_token),
+            Err(LiteSessionError::MissingWrappedKey)
+        );
+
+        // The replacement names the new key so that a `KeyRing` selects it after the rotation
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let ring_token = token.envelope(true).build_secure_with_provider(&ring)?;
+        ring.add_key("2021b", ServerKey::new([1_u8; 32]))?;
+        ring.activate("2021b")?;
+        let (outcome, rewrapped_token) = LiteSessionToken::default().rewrap(
+            &server_key,
+            ring.get_active_id(),
+            &new_key,
+            &ring_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, verified) =
+            destructured.from_string_with_provider(&ring, &rewrapped_token.unwrap_or_default())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(verified.get_key_id(), Some("2021b"));
+        assert_eq!(verified.get_hmac_data(), &data);
+        assert_eq!(
+            LiteSessionToken::default().rewrap(&server_key, Some("2021/b"), &new_key, &ring_token),
+            Err(LiteSessionError::InvalidKeyId)
+        );
+
+        token.envelope(true).version(TokenVersion::V0);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn aes_gcm_tokens() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone()).cipher(TokenCipher::Aes256Gcm);
+
+        #[cfg(not(feature = "fips"))]
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnavailableCipher)
+        );
+
+        #[cfg(feature = "fips")]
+        {
+            let session_token = token.build_secure(&server_key)?;
+            assert!(session_token.starts_with("ls2-aes⊕"));
+            let parsed = TokenParser::default().parse(&session_token).unwrap();
+            assert_eq!(parsed.get_cipher(), &TokenCipher::Aes256Gcm);
+
+            let mut destructured = LiteSessionToken::default();
+            let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+            assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+            assert_eq!(destructured.get_cipher(), &TokenCipher::Aes256Gcm);
+            assert_eq!(destructured.get_hmac_data(), &data);
+
+            // Tampered data is rejected by the MAC before it reaches the authentication tag
+            let forged_token = session_token.replacen(
+                parsed.get_ciphertext(),
+                &"0".repeat(parsed.get_ciphertext().len()),
+                1,
+            );
+            assert_ne!(
+                destructured
+                    .from_string(&server_key, &forged_token)
+                    .map(|(outcome, _)| outcome),
+                Ok(TokenOutcome::TokenAuthentic)
+            );
+
+            let hex_token = token.build_hex(&server_key)?;
+            let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+            assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+            assert_eq!(destructured.get_cipher(), &TokenCipher::Aes256Gcm);
+
+            token.envelope(true).key_commitment(true);
+            let session_token = token.build_secure(&server_key)?;
+            let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+            assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+            assert_eq!(destructured.get_hmac_data(), &data);
+        }
+
+        token.version(TokenVersion::V1);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn audience_issuer() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .audience("api.example.com")
+            .issuer("auth.example.com");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        destructured
+            .require_audience("api.example.com")
+            .allowed_issuers(&["sso.example.com", "auth.example.com"]);
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        destructured.require_audience("billing.example.com");
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "audience".into()
+            })
+        );
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.allowed_issuers(&["sso.example.com"]);
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "issuer".into()
+            })
+        );
+
+        // Tokens without the claims fail the requirements
+        let mut data = LiteSessionData::default();
+        data.add_acl("Network-TCP");
+        let session_token = token.hmac_data(data).build_secure(&server_key)?;
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        let (outcome, _) = LiteSessionToken::default()
+            .require_audience("api.example.com")
+            .from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn authorize() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut policy = Policy::default();
+        policy
+            .grant(Role::User, "files:read")
+            .grant(Role::Admin, "billing:*");
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .role(Role::Admin)
+            .add_acl("reports:export")
+            .add_acl_deny("billing:refund");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(
+            destructured.authorize(&policy, "files:read"),
+            TokenOutcome::TokenAuthorized
+        );
+        assert_eq!(
+            destructured.authorize(&policy, "billing:invoice"),
+            TokenOutcome::TokenAuthorized
+        );
+        assert_eq!(
+            destructured.authorize(&policy, "reports:export"),
+            TokenOutcome::TokenAuthorized
+        );
+        assert_eq!(destructured.get_rejection(), &None);
+        assert_eq!(
+            destructured.authorize(&policy, "billing:refund"),
+            TokenOutcome::TokenRejected
+        );
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "authorization".into()
+            })
+        );
+
+        // A token that failed verification is never authorized
+        let mut destructured = LiteSessionToken::default();
+        let verified = destructured.from_string(&ServerKey::new([1_u8; 32]), &session_token);
+        assert!(!matches!(verified, Ok((TokenOutcome::TokenAuthentic, _))));
+        assert_eq!(
+            destructured.authorize(&policy, "files:read"),
+            TokenOutcome::TokenRejected
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn attribute_policy() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .attribute("region", "eu-west");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(
+            destructured.get_hmac_data().get_attribute("region"),
+            Some(&"eu-west".to_owned())
+        );
+
+        let request_region = String::from("eu-west");
+        let mut policy = AttributePolicy::default();
+        policy.rule("region", move |data| {
+            data.get_attribute("region") == Some(&request_region)
+        });
+        let decision = destructured.evaluate_attributes(&policy);
+        assert!(decision.is_allowed());
+        assert_eq!(decision.get_subject(), "foo_user");
+        assert_eq!(destructured.get_rejection(), &None);
+
+        let mut policy = AttributePolicy::default();
+        policy.rule("residency", |data| {
+            data.get_attribute("region") == Some(&"us-east".to_owned())
+        });
+        assert!(!destructured.evaluate_attributes(&policy).is_allowed());
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "residency".into()
+            })
+        );
+        assert!(!destructured
+            .evaluate_attributes(&AttributePolicy::default())
+            .is_allowed());
+
+        Ok(())
+    }
+
+    #[test]
+    fn timed_capabilities() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let now = tai64::TAI64N::now();
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .add_acl_until("Admin", now + core::time::Duration::from_secs(900))
+            .add_acl_until("Sudo", now - core::time::Duration::from_secs(1));
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let verified = destructured.get_hmac_data();
+        assert_eq!(verified.get_acl(), &vec!["Admin", "Network-TCP"]);
+        assert_eq!(
+            verified.get_acl_expiry("Admin"),
+            data.get_acl_expiry("Admin")
+        );
+        assert_eq!(verified.get_acl_expiry("Sudo"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure(&server_key)?;
+
+        let (outcome, parsed) = LiteSessionToken::parse(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(parsed.get_identifier(), token.get_identifier());
+        assert_eq!(parsed.get_hmac_data(), &data);
+
+        let (outcome, rejected) =
+            LiteSessionToken::parse(&ServerKey::new([1_u8; 32]), &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            rejected.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+        assert_eq!(
+            LiteSessionToken::parse(&server_key, "foo").map(|(outcome, _)| outcome),
+            Err(LiteSessionError::TokenFieldsLengthError)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_policy() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut verifier = LiteSessionToken::default();
+        assert_eq!(verifier.get_parse_policy(), &ParsePolicy::Strict);
+        assert_eq!(
+            verifier
+                .from_string(&server_key, "foo")
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::TokenFieldsLengthError)
+        );
+        assert_eq!(
+            verifier
+                .from_hex(&server_key, "zz")
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::InvalidHexString)
+        );
+
+        verifier.parse_policy(ParsePolicy::Lenient);
+        let (outcome, verified) = verifier.from_string(&server_key, "foo")?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "token" })
+        );
+        let (outcome, _) = verifier.from_hex(&server_key, "zz")?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn session_store() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let store = MemorySessionStore::new();
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_store(&server_key, &store, "tls-session")?;
+        assert_eq!(
+            store
+                .get("tls-session")
+                .map(|record| record.get_identifier().clone()),
+            Some(token.get_identifier().clone())
+        );
+        assert_eq!(
+            store.get("tls-session").map(|record| *record.get_issued()),
+            Some(*token.get_issued())
+        );
+
+        let mut verifier = LiteSessionToken::default();
+        let (outcome, _) =
+            verifier.from_string_with_store(&server_key, &store, "tls-session", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let (outcome, _) = verifier.from_string_with_store(
+            &server_key,
+            &store,
+            "other-session",
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        store.delete("tls-session");
+        let (outcome, verified) =
+            verifier.from_string_with_store(&server_key, &store, "tls-session", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Revoked));
+
+        // A logged out session is not refreshed
+        token.reissue_at(
+            tai64::TAI64N::now() - core::time::Duration::from_secs(3500),
+            3600,
+        );
+        let stale_token = token.build_secure_with_store(&server_key, &store, "tls-session")?;
+        let mut policy = RefreshPolicy::default();
+        policy.threshold(10);
+        verifier.sliding(policy);
+        let (_, verified) =
+            verifier.from_string_with_store(&server_key, &store, "tls-session", &stale_token)?;
+        assert!(verified.get_refreshed().is_some());
+        store.delete("tls-session");
+        let (outcome, verified) =
+            verifier.from_string_with_store(&server_key, &store, "tls-session", &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        assert_eq!(verified.get_refreshed(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn verification_report() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let failed_check = |verifier: &LiteSessionToken| {
+            verifier
+                .get_report()
+                .get_failed_check()
+                .map(|check| check.get_name())
+        };
+
+        let mut verifier = LiteSessionToken::default();
+        verifier.from_string(&server_key, &session_token)?;
+        let names: Vec<&str> = verifier
+            .get_report()
+            .get_checks()
+            .iter()
+            .map(|check| check.get_name())
+            .collect();
+        assert_eq!(
+            names,
+            vec!["structure", "expiry", "key", "hmac", "decryption", "claims"]
+        );
+        assert_eq!(failed_check(&verifier), None);
+
+        verifier.from_string(&ServerKey::new([1_u8; 32]), &session_token)?;
+        assert_eq!(failed_check(&verifier), Some("hmac"));
+        assert_eq!(verifier.get_report().get_check("decryption"), None);
+
+        assert!(verifier.from_string(&server_key, "foo").is_err());
+        assert_eq!(failed_check(&verifier), Some("structure"));
+
+        token.expiry(0);
+        let expired_token = token.build_secure(&server_key)?;
+        verifier.from_string(&server_key, &expired_token)?;
+        assert_eq!(failed_check(&verifier), Some("expiry"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn peek() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).tenant_id(TenantId::new("acme")?);
+        let session_token = token.build_secure(&server_key)?;
+
+        let claims = LiteSessionToken::peek(&session_token)?;
+        assert_eq!(claims.get_identifier(), token.get_identifier());
+        assert_eq!(claims.get_issued(), token.get_issued());
+        assert_eq!(claims.get_expiry(), token.get_expiry());
+        assert_eq!(claims.get_confidentiality(), &ConfidentialityMode::High);
+        assert_eq!(claims.get_tenant_id(), Some("acme"));
+        assert_eq!(claims.get_key_id(), None);
+
+        let hex_token = token.build_hex(&server_key)?;
+        assert_eq!(LiteSessionToken::peek(&hex_token)?, claims);
+        assert_eq!(
+            LiteSessionToken::peek("foo"),
+            Err(LiteSessionError::TokenFieldsLengthError)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn renew() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let issued = tai64::TAI64N::now() - core::time::Duration::from_secs(3000);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .tag("region", "eu-west");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data.clone())
+            .clock(Arc::new(FixedClock::new(issued)))
+            .expiry(3600);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut verified = LiteSessionToken::default();
+        verified.from_string(&server_key, &session_token)?;
+        let identifier = verified.get_identifier().clone();
+        let renewed = verified.renew(&server_key, 600)?;
+        assert_eq!(
+            verified
+                .get_expiry()
+                .duration_since(verified.get_issued())
+                .unwrap_or_default()
+                .as_secs(),
+            600
+        );
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &renewed)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_identifier(), &identifier);
+        assert_eq!(destructured.get_hmac_data(), &data);
+        assert!(destructured.get_issued() > &issued);
+
+        let rotated = destructured.renew_rotating(&server_key, 600)?;
+        let (outcome, _) = verified.from_string(&server_key, &rotated)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_ne!(verified.get_identifier(), &identifier);
+
+        let mut rejected = LiteSessionToken::default();
+        rejected
+            .from_string(&ServerKey::new([1_u8; 32]), &renewed)
+            .ok();
+        assert_eq!(
+            rejected.renew(&server_key, 600),
+            Err(LiteSessionError::TokenNotAuthentic)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn expired_grace() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).reissue_at(
+            tai64::TAI64N::now() - core::time::Duration::from_secs(3700),
+            3600,
+        );
+        let expired_token = token.build_secure(&server_key)?;
+
+        let mut verifier = LiteSessionToken::default();
+        assert_eq!(
+            verifier.get_expired_grace(),
+            &core::time::Duration::default()
+        );
+        let (outcome, _) = verifier.from_string(&server_key, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        verifier.expired_grace(core::time::Duration::from_secs(300));
+        let (outcome, verified) = verifier.from_string(&server_key, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::ExpiredWithinGrace);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Expired));
+        assert_eq!(verified.get_hmac_data().get_username(), "foo_user");
+
+        let result = verifier.from_string(&ServerKey::new([1_u8; 32]), &expired_token);
+        assert!(!matches!(result, Ok((TokenOutcome::ExpiredWithinGrace, _))));
+
+        verifier.expired_grace(core::time::Duration::from_secs(60));
+        let (outcome, _) = verifier.from_string(&server_key, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        Ok(())
+    }
+
+    #[test]
+    fn sliding() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).expiry(3600);
+        assert!(!token.should_refresh());
+        let fresh_token = token.build_secure(&server_key)?;
+
+        token.reissue_at(
+            tai64::TAI64N::now() - core::time::Duration::from_secs(3500),
+            3600,
+        );
+        assert!(token.should_refresh());
+        let stale_token = token.build_secure(&server_key)?;
+
+        let mut verifier = LiteSessionToken::default();
+        verifier.from_string(&server_key, &stale_token)?;
+        assert_eq!(verifier.get_refreshed(), None);
+
+        let mut policy = RefreshPolicy::default();
+        policy.threshold(10);
+        verifier.sliding(policy.clone());
+        assert_eq!(verifier.get_sliding(), &Some(policy));
+        let (outcome, _) = verifier.from_string(&server_key, &fresh_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(verifier.get_refreshed(), None);
+
+        let (outcome, _) = verifier.from_string(&server_key, &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let refreshed = verifier.get_refreshed().unwrap().to_owned();
+        assert!(!verifier.should_refresh());
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &refreshed)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_identifier(), token.get_identifier());
+        assert!(!destructured.should_refresh());
+
+        Ok(())
+    }
+
+    #[test]
+    fn sliding_max_age() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let issued = tai64::TAI64N::now() - core::time::Duration::from_secs(7200);
+        let at = |secs: u64| {
+            Arc::new(FixedClock::new(
+                issued + core::time::Duration::from_secs(secs),
+            ))
+        };
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data)
+            .confidential(false)
+            .clock(at(0))
+            .expiry(3600);
+        let session_token = token.build(&server_key)?;
+
+        let mut policy = RefreshPolicy::default();
+        policy.threshold(50);
+        let mut verifier = LiteSessionToken::default();
+        verifier
+            .sliding(policy)
+            .max_age(core::time::Duration::from_secs(7200))
+            .clock(at(3000));
+        verifier.from_string(&server_key, &session_token)?;
+        let refreshed = verifier.get_refreshed().unwrap().to_owned();
+
+        // The replacement keeps the confidentiality and the start of the session
+        verifier.clock(at(6000));
+        let (outcome, verified) = verifier.from_string(&server_key, &refreshed)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(verified.get_confidentiality(), &ConfidentialityMode::Low);
+        assert_eq!(verified.get_hmac_data().get_auth_time(), Some(&issued));
+        let refreshed = verifier.get_refreshed().unwrap().to_owned();
+
+        // The session ends at the maximum age however recently it was refreshed
+        verifier.clock(at(7300));
+        let (outcome, verified) = verifier.from_string(&server_key, &refreshed)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Expired));
+        assert_eq!(verified.get_refreshed(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_age() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let issued = tai64::TAI64N::now() - core::time::Duration::from_secs(7200);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data)
+            .clock(Arc::new(FixedClock::new(issued)))
+            .expiry(365 * 24 * 3600);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut verifier = LiteSessionToken::default();
+        assert_eq!(verifier.get_max_age(), &None);
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        verifier.max_age(core::time::Duration::from_secs(3600));
+        let (outcome, verified) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Expired));
+
+        verifier.max_age(core::time::Duration::from_secs(3 * 3600));
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn clock() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let issued = tai64::TAI64N::now() - core::time::Duration::from_secs(7200);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data)
+            .clock(Arc::new(FixedClock::new(issued)))
+            .expiry(3600);
+        assert_eq!(token.get_issued(), &issued);
+        assert_eq!(token.get_clock().now(), issued);
+        let session_token = token.build_secure(&server_key)?;
+
+        let (outcome, _) = LiteSessionToken::default().from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        let mut verifier = LiteSessionToken::default();
+        verifier.clock(Arc::new(FixedClock::new(
+            issued + core::time::Duration::from_secs(3599),
+        )));
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let mut verifier = LiteSessionToken::default();
+        verifier.clock(Arc::new(FixedClock::new(
+            issued + core::time::Duration::from_secs(3600),
+        )));
+        let (outcome, _) = verifier.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        Ok(())
+    }
+
+    #[test]
+    fn leeway() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).expiry(0);
+        let expired_token = token.build_secure(&server_key)?;
+
+        let mut verifier = LiteSessionToken::default();
+        assert_eq!(verifier.get_leeway(), &core::time::Duration::default());
+        let (outcome, _) = verifier.from_string(&server_key, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::SessionExpired);
+
+        let mut verifier = LiteSessionToken::default();
+        verifier.leeway(core::time::Duration::from_secs(60));
+        let (outcome, _) = verifier.from_string(&server_key, &expired_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        token.issued = tai64::TAI64N::now() + core::time::Duration::from_secs(30);
+        token.expiry(300);
+        let future_token = token.build_secure(&server_key)?;
+        let mut strict = LiteSessionToken::default();
+        assert_eq!(
+            strict
+                .from_string(&server_key, &future_token)
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::TimestampOutOfRange)
+        );
+        assert_eq!(
+            strict.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "issued" })
+        );
+        strict.parse_policy(ParsePolicy::Lenient);
+        let (outcome, _) = strict.from_string(&server_key, &future_token)?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        let (outcome, _) = verifier.from_string(&server_key, &future_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        token.expiry = token.issued - core::time::Duration::from_secs(60);
+        let inverted_token = token.build_secure(&server_key)?;
+        verifier.parse_policy(ParsePolicy::Lenient);
+        let (outcome, verified) = verifier.from_string(&server_key, &inverted_token)?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "issued" })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        assert!(!format!("{:?}", token).contains("foo_user"));
+        let session_token = token.build_secure(&server_key)?;
+        let other_token = token.build_secure(&server_key)?;
+
+        let fingerprint = LiteSessionToken::fingerprint(&session_token);
+        assert_eq!(fingerprint.len(), 64);
+        assert_eq!(fingerprint, LiteSessionToken::fingerprint(&session_token));
+        assert_ne!(fingerprint, LiteSessionToken::fingerprint(&other_token));
+        assert!(!fingerprint.contains(&session_token));
+
+        Ok(())
+    }
+
+    #[test]
+    fn empty_acl() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_hmac_data().get_acl().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn tls_exporter_binding() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data)
+            .mode(LiteSessionMode::tls_exporter(&[1_u8; 32]));
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.mode(LiteSessionMode::tls_exporter(&[1_u8; 32]));
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        destructured.mode(LiteSessionMode::tls_exporter(&[2_u8; 32]));
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn device_binding() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data)
+            .mode(LiteSessionMode::device_bound(b"foo-device"));
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) =
+            destructured.from_string_with_device(&server_key, b"foo-device", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data().get_username(), "foo_user");
+
+        let (outcome, _) =
+            destructured.from_string_with_device(&server_key, b"bar-device", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+
+        // Verifying with a device does not pin the mode of the verifier to that device
+        assert_eq!(destructured.get_mode(), &LiteSessionMode::Passive);
+        let unbound_token = token
+            .mode(LiteSessionMode::Passive)
+            .build_secure(&server_key)?;
+        let (outcome, _) = destructured.from_string(&server_key, &unbound_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        // A device bound token is never accepted as bound to a session ID of the same bytes,
+        // even with `Low Confidentiality` where no key is derived from the fingerprint
+        let fingerprint = *b"0123456789abcdef0123456789abcdef";
+        let device = || LiteSessionMode::DeviceBound(DeviceFingerprint::from_bytes(fingerprint));
+        let low_token = token
+            .confidential(false)
+            .mode(device())
+            .build(&server_key)?;
+        let (outcome, _) = destructured
+            .mode(device())
+            .from_string(&server_key, &low_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = destructured
+            .mode(LiteSessionMode::session_id(
+                "0123456789abcdef0123456789abcdef",
+            ))
+            .from_string(&server_key, &low_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn network_binding() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let client: IpAddr = "10.0.0.7".parse().unwrap();
+        let mut data = LiteSessionData::default();
+        data.username("foo_admin").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data.clone())
+            .network_binding(NetworkBinding::ip(client));
+        let session_token = token.build_secure(&server_key)?;
+        assert!(!session_token.contains("10.0.0.7"));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) =
+            destructured.from_string_with_client_ip(&server_key, client, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_network_binding(), None);
+        let (outcome, _) = destructured.from_string_with_client_ip(
+            &server_key,
+            "10.0.0.8".parse().unwrap(),
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
         assert_eq!(
-            token.expiry,
-            token.issued + core::time::Duration::from_secs(change_expiry)
+            destructured.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        let range = NetworkBinding::from_string("10.0.0.0/24")?;
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).network_binding(range.clone());
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.network_binding(range);
+        let (outcome, _) = destructured.from_string_with_client_ip(
+            &server_key,
+            "10.0.0.200".parse().unwrap(),
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = destructured.from_string_with_client_ip(
+            &server_key,
+            "10.0.1.7".parse().unwrap(),
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "network".into()
+            })
+        );
+
+        let mut legacy = LiteSessionToken::default();
+        legacy
+            .version(TokenVersion::V1)
+            .network_binding(NetworkBinding::ip(client));
+        assert_eq!(
+            legacy.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn certificate_binding() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let thumbprint = [7_u8; 32];
+        let mut data = LiteSessionData::default();
+        data.username("foo_service").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let unbound_token = token.build_secure(&server_key)?;
+        data.certificate_thumbprint(&thumbprint);
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) =
+            destructured.from_string_with_certificate(&server_key, &thumbprint, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let (outcome, _) =
+            destructured.from_string_with_certificate(&server_key, &[8_u8; 32], &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "cnf".into()
+            })
+        );
+
+        let (outcome, _) =
+            destructured.from_string_with_certificate(&server_key, &thumbprint, &unbound_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        // Without the certificate of the client a bound token is never accepted
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "cnf".into()
+            })
+        );
+        let (outcome, _) = destructured.from_string(&server_key, &unbound_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        // A token presented with another certificate is not refreshed
+        token.reissue_at(
+            tai64::TAI64N::now() - core::time::Duration::from_secs(3500),
+            3600,
+        );
+        let stale_token = token.build_secure(&server_key)?;
+        let mut policy = RefreshPolicy::default();
+        policy.threshold(10);
+        destructured.sliding(policy);
+        let (outcome, _) =
+            destructured.from_string_with_certificate(&server_key, &[8_u8; 32], &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_refreshed(), None);
+        let (outcome, _) =
+            destructured.from_string_with_certificate(&server_key, &thumbprint, &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_refreshed().is_some());
+        let (outcome, _) = destructured.from_string(&server_key, &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_refreshed(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn proof_of_possession() -> Result<(), LiteSessionError> {
+        // Stands in for a signature scheme, the public key doubles as the signing key
+        #[derive(Debug)]
+        struct KeyedHashVerifier;
+        impl ProofSignatureVerifier for KeyedHashVerifier {
+            fn verify(&self, public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+                public_key.len() == 32
+                    && blake3::keyed_hash(public_key.try_into().unwrap(), message).as_bytes()
+                        == signature
+            }
+        }
+        let sign = |proof: &mut ProofOfPossession, key: &[u8; 32], token: &str| {
+            let signature = blake3::keyed_hash(key, &proof.signing_input(token));
+            proof.signature(signature.as_bytes());
+            ProofOfPossession::to_string(proof)
+        };
+
+        let server_key = ServerKey::new([0_u8; 32]);
+        let client_key = [7_u8; 32];
+        let mut data = LiteSessionData::default();
+        data.username("foo_service")
+            .add_acl("Network-TCP")
+            .proof_key(&client_key);
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut proof = ProofOfPossession::new(&client_key, "POST", "/transfers");
+        let signed = sign(&mut proof, &client_key, &session_token);
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string_with_proof(
+            &server_key,
+            &KeyedHashVerifier,
+            &signed,
+            "post",
+            "/transfers",
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let dpop_failed = Some(RejectionReason::PolicyFailed {
+            which: "dpop".into(),
+        });
+        let (outcome, _) = destructured.from_string_with_proof(
+            &server_key,
+            &KeyedHashVerifier,
+            &signed,
+            "GET",
+            "/transfers",
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_rejection(), &dpop_failed);
+
+        let other_key = [8_u8; 32];
+        let mut proof = ProofOfPossession::new(&other_key, "POST", "/transfers");
+        let signed = sign(&mut proof, &other_key, &session_token);
+        let (outcome, _) = destructured.from_string_with_proof(
+            &server_key,
+            &KeyedHashVerifier,
+            &signed,
+            "POST",
+            "/transfers",
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        let mut proof = ProofOfPossession::new(&client_key, "POST", "/transfers");
+        proof.issued(tai64::TAI64N::now() - core::time::Duration::from_secs(120));
+        let signed = sign(&mut proof, &client_key, &session_token);
+        let (outcome, _) = destructured.from_string_with_proof(
+            &server_key,
+            &KeyedHashVerifier,
+            &signed,
+            "POST",
+            "/transfers",
+            &session_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_rejection(), &dpop_failed);
+
+        // A replayed bearer token without a valid proof is not refreshed
+        token.reissue_at(
+            tai64::TAI64N::now() - core::time::Duration::from_secs(3500),
+            3600,
+        );
+        let stale_token = token.build_secure(&server_key)?;
+        let mut policy = RefreshPolicy::default();
+        policy.threshold(10);
+        destructured.sliding(policy);
+        let (outcome, _) = destructured.from_string_with_proof(
+            &server_key,
+            &KeyedHashVerifier,
+            "",
+            "POST",
+            "/transfers",
+            &stale_token,
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_refreshed(), None);
+
+        // Without a proof the token is not a bearer token
+        let (outcome, _) = destructured.from_string(&server_key, &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_rejection(), &dpop_failed);
+        assert_eq!(destructured.get_refreshed(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn csrf_token() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure(&server_key)?;
+        let csrf_token = token.derive_csrf_token();
+        assert_eq!(csrf_token.len(), 64);
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(destructured.derive_csrf_token(), csrf_token);
+        assert!(destructured.verify_csrf(&csrf_token));
+        assert!(!destructured.verify_csrf(&csrf_token[..63]));
+        assert!(!destructured.verify_csrf(""));
+
+        let mut other = LiteSessionToken::default();
+        other.hmac_data(data);
+        other.build_secure(&ServerKey::new([1_u8; 32]))?;
+        assert!(!other.verify_csrf(&csrf_token));
+
+        Ok(())
+    }
+
+    #[test]
+    fn attenuation() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).mac_length(MacLength::Truncated);
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut attenuated = AttenuatedToken::from_string(&session_token)?;
+        attenuated.attenuate("path=/api/files")?;
+        let delegated = AttenuatedToken::to_string(&attenuated);
+        assert!(!delegated.contains(&session_token));
+
+        let mut destructured = LiteSessionToken::default();
+        destructured.mac_length(MacLength::Truncated);
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/files/foo.txt", &delegated)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/users", &delegated)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "caveat".into()
+            })
+        );
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/users", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        // Narrowing further keeps the chain valid while dropping a caveat breaks it
+        let mut narrower = AttenuatedToken::from_string(&delegated)?;
+        narrower.attenuate("expires<1")?;
+        let (outcome, _) = destructured.from_string_attenuated(
+            &server_key,
+            "/api/files",
+            &AttenuatedToken::to_string(&narrower),
+        )?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "caveat".into()
+            })
+        );
+        let stripped = AttenuatedToken::to_string(&narrower);
+        let stripped = &stripped[..stripped.rfind('.').unwrap()];
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/files", stripped)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+        let (outcome, _) = destructured.from_string(&server_key, attenuated.get_token())?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        assert_eq!(
+            destructured
+                .from_string_attenuated(&server_key, "/", "lsat1.%%")
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::InvalidAttenuation)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn attenuation_never_refreshes() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).reissue_at(
+            tai64::TAI64N::now() - core::time::Duration::from_secs(3500),
+            3600,
         );
+        let stale_token = token.build_secure(&server_key)?;
+
+        let mut attenuated = AttenuatedToken::from_string(&stale_token)?;
+        attenuated.attenuate("path=/api/files")?;
+        let delegated = AttenuatedToken::to_string(&attenuated);
+
+        let mut policy = RefreshPolicy::default();
+        policy.threshold(10);
+        let mut destructured = LiteSessionToken::default();
+        destructured.sliding(policy);
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/users", &delegated)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(destructured.get_refreshed(), None);
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/files", &delegated)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_refreshed(), None);
+
+        // A token that was never attenuated still slides
+        let (outcome, _) =
+            destructured.from_string_attenuated(&server_key, "/api/users", &stale_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert!(destructured.get_refreshed().is_some());
+
+        Ok(())
+    }
 
+    #[test]
+    fn max_data_size() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
         let mut data = LiteSessionData::default();
         data.username("foo_user");
-        data.role(Role::SuperUser);
-        data.tag("Foo-Tag");
-        data.add_acl("Network-TCP");
-        data.add_acl("Network-UDP");
+        (0..128).for_each(|index| {
+            data.add_acl(&format!("files:read:/home/foo_user/documents/{}", index));
+        });
+
+        let mut token = LiteSessionToken::default();
+        assert_eq!(token.get_max_data_size(), 4 * 1024);
         token.hmac_data(data.clone());
-        assert_eq!(token.hmac_data, data);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::DataSizeTooLarge)
+        );
 
-        token.confidential(false);
-        assert_eq!(token.confidentiality, ConfidentialityMode::Low);
-        token.confidential(true);
-        assert_eq!(token.confidentiality, ConfidentialityMode::High);
+        token.max_data_size(64 * 1024);
+        let session_token = token.build_secure(&server_key)?;
+        let mut destructured = LiteSessionToken::default();
+        assert_eq!(
+            destructured
+                .from_string(&server_key, &session_token)
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::DataSizeTooLarge)
+        );
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::Malformed {
+                field: "ciphertext"
+            })
+        );
+        destructured.max_data_size(64 * 1024);
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
 
-        token.mode(LiteSessionMode::SessionID("foobarbaz".into()));
-        assert_eq!(token.mode, LiteSessionMode::SessionID("foobarbaz".into()));
-        assert_ne!(token.mode, LiteSessionMode::SessionID("garbage".into()));
-        token.mode(LiteSessionMode::Passive);
-        assert_eq!(token.mode, LiteSessionMode::Passive);
+        let mut small = LiteSessionData::default();
+        small.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(small).max_data_size(8);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::DataSizeTooLarge)
+        );
 
-        {
-            let bad_key = [0_u8; 5];
-            assert_eq!(
-                token.build_secure(&bad_key),
-                Err(LiteSessionError::ServerKeyLengthError)
-            );
+        Ok(())
+    }
+
+    #[test]
+    fn session_binding() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data.clone())
+            .mode(LiteSessionMode::session_id("foo-session"));
+        let session_token = token.build_secure(&server_key)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) =
+            destructured.from_string_with_session(&server_key, "foo-session", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        // A token replayed over another transport session is not authentic
+        let (outcome, _) =
+            destructured.from_string_with_session(&server_key, "bar-session", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::MacMismatch)
+        );
+        let (outcome, _) = LiteSessionToken::default().from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        // A verifier pinned to a session rejects tokens that are not bound to it
+        let unbound_token = token
+            .mode(LiteSessionMode::Passive)
+            .build_secure(&server_key)?;
+        assert_ne!(unbound_token, session_token);
+        destructured.mode(LiteSessionMode::session_id("foo-session"));
+        let (outcome, _) = destructured.from_string(&server_key, &unbound_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        destructured.mode(LiteSessionMode::Passive);
+        let (outcome, _) = destructured.from_string(&server_key, &unbound_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        // Verifying with a session does not pin the mode of the verifier to that session
+        let (outcome, _) =
+            destructured.from_string_with_session(&server_key, "foo-session", &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_mode(), &LiteSessionMode::Passive);
+        let (outcome, _) = destructured.from_string(&server_key, &unbound_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        token
+            .mode(LiteSessionMode::session_id("foo-session"))
+            .version(TokenVersion::V1);
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn entropy_source() -> Result<(), LiteSessionError> {
+        #[derive(Debug)]
+        struct FixedEntropy(u8);
+        impl EntropySource for FixedEntropy {
+            fn fill(&self, dest: &mut [u8]) {
+                dest.iter_mut().for_each(|byte| *byte = self.0);
+            }
         }
 
-        {
-            let server_key = [0_u8; 32];
-            let session_token = token.build_secure(&server_key)?;
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
 
-            let mut destructured = LiteSessionToken::default();
-            let outcome = destructured.from_string(&server_key, &session_token)?;
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data.clone())
+            .entropy_source(Arc::new(FixedEntropy(7)));
+        assert_eq!(token.get_identifier(), &"h".repeat(32));
 
-            assert_eq!(outcome, (TokenOutcome::TokenAuthentic, token.immutable()));
+        // A deterministic source makes the token reproducible
+        let session_token = token.build_secure(&server_key)?;
+        assert_eq!(token.clone().build_secure(&server_key)?, session_token);
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert_eq!(parsed.get_nonce(), "07".repeat(12));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_ne!(
+            LiteSessionToken::default().get_identifier(),
+            LiteSessionToken::default().get_identifier()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn binary_nonces() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure(&server_key)?;
+        let parsed = TokenParser::default().parse(&session_token).unwrap();
+        assert_eq!(
+            hex::decode(parsed.get_nonce()).map(|nonce| nonce.len()),
+            Ok(12)
+        );
+
+        // Tokens with the alphanumeric nonces of older versions are still authentic
+        let legacy_nonce = "abcdefghijkl";
+        let mut ciphertext = data.build().into_bytes();
+        CipherText::default().decrypt_bytes(
+            &token.get_key(server_key.expose_secret()),
+            &mut ciphertext,
+            legacy_nonce.as_bytes(),
+            &TokenCipher::default(),
+        )?;
+        let ciphertext = hex::encode(ciphertext);
+        let hmac = token.compute_hmac(server_key.expose_secret(), &ciphertext, legacy_nonce);
+        let mut fields = session_token.split('⊕').collect::<Vec<&str>>();
+        let hmac_hex = hex::encode(&hmac.as_bytes()[..fields[7].len() / 2]);
+        fields[4] = &ciphertext;
+        fields[5] = legacy_nonce;
+        fields[7] = &hmac_hex;
+        let legacy_token = fields.join("⊕");
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &legacy_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+
+        let legacy_hex = token.hex_blob(&legacy_token)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &legacy_hex)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = destructured.from_hex(&server_key, &token.build_hex(&server_key)?)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn chacha_rounds() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token
+            .hmac_data(data.clone())
+            .cipher(TokenCipher::ChaCha(Rounds::R20));
+        let session_token = token.build_secure(&server_key)?;
+        assert!(session_token.starts_with("ls2-r20⊕"));
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string(&server_key, &session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+        assert_eq!(destructured.get_cipher(), &TokenCipher::ChaCha(Rounds::R20));
+
+        let hex_token = token.build_hex(&server_key)?;
+        let (outcome, _) = destructured.from_hex(&server_key, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_cipher(), &TokenCipher::ChaCha(Rounds::R20));
+
+        // The rounds are authenticated so the header cannot be downgraded
+        let downgraded = session_token.replacen("ls2-r20", "ls2-r12", 1);
+        assert_ne!(
+            destructured
+                .from_string(&server_key, &downgraded)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+
+        // The default rounds keep the plain `ls1` header
+        token.cipher(TokenCipher::ChaCha(Rounds::R8));
+        assert!(token.build_secure(&server_key)?.starts_with("ls2⊕"));
+
+        token
+            .version(TokenVersion::V0)
+            .cipher(TokenCipher::ChaCha(Rounds::R12));
+        assert_eq!(
+            token.build_secure(&server_key),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn tenant_tokens() -> Result<(), LiteSessionError> {
+        let master_key = ServerKey::new([0_u8; 32]);
+        let tenants = TenantKeyring::new(ServerKey::new([0_u8; 32]));
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        assert_eq!(
+            token.build_secure_with_tenant(&tenants),
+            Err(LiteSessionError::InvalidTenantId)
+        );
+        token.tenant_id(TenantId::new("foo")?);
+        let foo_token = token.build_secure_with_tenant(&tenants)?;
+        assert!(foo_token.starts_with("ls2-tfoo⊕"));
+        token.tenant_id(TenantId::new("bar")?);
+        let bar_token = token.build_secure_with_tenant(&tenants)?;
+
+        let mut destructured = LiteSessionToken::default();
+        let (outcome, _) = destructured.from_string_with_tenant(&tenants, &foo_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_hmac_data(), &data);
+        assert_eq!(destructured.get_tenant_id(), Some(&TenantId::new("foo")?));
+
+        let hex_token = token.build_hex(&tenants.tenant_key(&TenantId::new("bar")?))?;
+        destructured.encoding(TokenEncoding::Hex);
+        let (outcome, _) = destructured.from_string_with_tenant(&tenants, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(destructured.get_tenant_id(), Some(&TenantId::new("bar")?));
+        destructured.encoding(TokenEncoding::Plain);
+
+        // The subkey of one tenant cannot authenticate the tokens of another tenant
+        let foo_key = tenants.tenant_key(&TenantId::new("foo")?);
+        assert_ne!(
+            destructured
+                .from_string(&foo_key, &bar_token)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+        assert_ne!(
+            destructured
+                .from_string_with_tenant(&tenants, &bar_token.replacen("tbar", "tfoo", 1))
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+        assert_ne!(
+            destructured
+                .from_string(&master_key, &foo_token)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+
+        let plain_token = LiteSessionToken::default()
+            .hmac_data(data)
+            .build_secure(&master_key)?;
+        assert_eq!(
+            destructured
+                .from_string_with_tenant(&tenants, &plain_token)
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::UnknownKeyId)
+        );
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "version" })
+        );
+
+        token.version(TokenVersion::V0);
+        assert_eq!(
+            token.build_secure_with_tenant(&tenants),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn key_provider_tokens() -> Result<(), LiteSessionError> {
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut ring = KeyRing::default();
+        ring.add_key("old", ServerKey::new([0_u8; 32]))?;
+
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let old_token = token.build_secure_with_provider(&ring)?;
+        assert!(old_token.starts_with("ls2-kold⊕"));
+
+        // Rotating the active key keeps outstanding sessions valid
+        ring.add_key("new", ServerKey::new([1_u8; 32]))?
+            .activate("new")?;
+        let new_token = token.build_secure_with_provider(&ring)?;
+        assert!(new_token.starts_with("ls2-knew⊕"));
+
+        let mut destructured = LiteSessionToken::default();
+        for session_token in [&old_token, &new_token].iter() {
+            let (outcome, _) = destructured.from_string_with_provider(&ring, session_token)?;
+            assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+            assert_eq!(destructured.get_hmac_data(), &data);
         }
+        assert_eq!(destructured.get_key_id(), Some("new"));
 
-        {
-            let server_key = [0_u8; 32];
-            let session_token = token.build_secure(&server_key)?;
+        let hex_token = token.build_hex(&ServerKey::new([1_u8; 32]))?;
+        destructured.encoding(TokenEncoding::Hex);
+        let (outcome, _) = destructured.from_string_with_provider(&ring, &hex_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        destructured.encoding(TokenEncoding::Plain);
 
-            let mut destructured = LiteSessionToken::default();
-            let outcome = destructured.from_string(&[1_u8; 32], &session_token);
+        // The key ID is authenticated so it cannot point a token at another key
+        let forged_token = old_token.replacen("kold", "knew", 1);
+        assert_ne!(
+            destructured
+                .from_string_with_provider(&ring, &forged_token)
+                .map(|(outcome, _)| outcome),
+            Ok(TokenOutcome::TokenAuthentic)
+        );
+
+        ring.remove_key("old")?;
+        assert_eq!(
+            destructured
+                .from_string_with_provider(&ring, &old_token)
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::UnknownKeyId)
+        );
+        assert_eq!(
+            destructured.get_rejection(),
+            &Some(RejectionReason::Malformed { field: "version" })
+        );
 
-            assert_eq!(outcome, Err(LiteSessionError::FromUtf8TokenError));
+        // Errors of a remote provider are returned as-is instead of rejecting the token
+        struct OfflineProvider;
+        impl KeyProvider for OfflineProvider {
+            fn current_key(&self) -> Result<(String, ServerKey), LiteSessionError> {
+                Err(LiteSessionError::ServerKeyLengthError)
+            }
+            fn key_by_id(&self, _key_id: &str) -> Result<ServerKey, LiteSessionError> {
+                Err(LiteSessionError::ServerKeyLengthError)
+            }
         }
+        assert_eq!(
+            destructured
+                .from_string_with_provider(&OfflineProvider, &new_token)
+                .map(|(outcome, _)| outcome),
+            Err(LiteSessionError::ServerKeyLengthError)
+        );
+        assert_eq!(
+            token.build_secure_with_provider(&OfflineProvider),
+            Err(LiteSessionError::ServerKeyLengthError)
+        );
+
+        token.version(TokenVersion::V0);
+        assert_eq!(
+            token.build_secure_with_provider(&ring),
+            Err(LiteSessionError::UnsupportedTokenVersion)
+        );
 
         Ok(())
     }
+
+    #[test]
+    fn key_derivation() {
+        let server_key = [0_u8; 32];
+        let mut token = LiteSessionToken::default();
+        token.identifier("foo");
+
+        let derived_key = token.get_key(&server_key);
+        assert_eq!(derived_key, token.get_key(&server_key));
+        assert_ne!(derived_key, token.get_legacy_key(&server_key));
+
+        token.version(TokenVersion::V1);
+        assert_eq!(
+            token.get_key(&server_key),
+            token.get_legacy_key(&server_key)
+        );
+    }
 }
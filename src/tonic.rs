@@ -0,0 +1,117 @@
+//! A tonic interceptor verifying the session of a gRPC request, enabled by the `tonic` feature
+
+use crate::{GrpcRejection, LiteSessionVerifier};
+use ::tonic::{service::Interceptor, Code, Request, Status};
+use std::sync::Arc;
+
+/// A tonic `Interceptor` verifying the bearer token in the `authorization` metadata of a
+/// request with `LiteSessionVerifier::verify_metadata()`. The `LiteSessionData` of the verified
+/// token is inserted into the extensions of the request for the handler to read, rejected
+/// requests fail with the `Status` of their `GrpcRejection`
+///
+/// ```
+/// use lite_session::{tonic::SessionInterceptor, KeyRing, LiteSessionVerifier};
+/// use std::sync::Arc;
+///
+/// let verifier = Arc::new(LiteSessionVerifier::new(KeyRing::default()));
+/// let interceptor = SessionInterceptor::new(verifier);
+/// // `GreeterServer::with_interceptor(greeter, interceptor)`
+/// ```
+#[derive(Debug)]
+pub struct SessionInterceptor {
+    verifier: Arc<LiteSessionVerifier>,
+}
+
+impl core::clone::Clone for SessionInterceptor {
+    fn clone(&self) -> Self {
+        Self {
+            verifier: self.verifier.clone(),
+        }
+    }
+}
+
+impl SessionInterceptor {
+    /// Create an interceptor verifying requests with `verifier`
+    pub fn new(verifier: Arc<LiteSessionVerifier>) -> Self {
+        Self { verifier }
+    }
+    /// Get the verifier
+    pub fn get_verifier(&self) -> &Arc<LiteSessionVerifier> {
+        &self.verifier
+    }
+}
+
+impl Interceptor for SessionInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        // Plain tokens carry their `⊕` separators as UTF-8 rather than visible ASCII
+        let authorization = request
+            .metadata()
+            .get("authorization")
+            .and_then(|value| core::str::from_utf8(value.as_bytes()).ok());
+        let token = self.verifier.verify_metadata(authorization)?;
+        request
+            .extensions_mut()
+            .insert(token.get_hmac_data().clone());
+
+        Ok(request)
+    }
+}
+
+impl From<GrpcRejection> for Status {
+    fn from(rejection: GrpcRejection) -> Self {
+        Status::new(Code::from(rejection.get_code().code()), rejection.message())
+    }
+}
+
+#[cfg(test)]
+mod tonic_tests {
+    use super::SessionInterceptor;
+    use crate::{
+        KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken, LiteSessionVerifier,
+        ServerKey,
+    };
+    use ::tonic::{metadata::AsciiMetadataValue, service::Interceptor, Code, Request};
+    use core::convert::TryFrom;
+    use std::sync::Arc;
+
+    #[test]
+    fn interceptor() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let bearer = format!("Bearer {}", token.build_secure_with_provider(&ring)?);
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.require_acl("Network-TCP");
+        let mut interceptor = SessionInterceptor::new(Arc::new(verifier));
+        let request = |authorization: Option<&str>| {
+            let mut request = Request::new(());
+            if let Some(authorization) = authorization {
+                request.metadata_mut().insert(
+                    "authorization",
+                    AsciiMetadataValue::try_from(authorization.as_bytes()).unwrap(),
+                );
+            }
+            request
+        };
+
+        let intercepted = interceptor.call(request(Some(&bearer))).unwrap();
+        assert_eq!(
+            intercepted.extensions().get::<LiteSessionData>(),
+            Some(&data)
+        );
+
+        let status = interceptor.call(request(None)).unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        assert_eq!(status.message(), "missing session token");
+        let status = interceptor
+            .call(request(Some("Bearer garbage")))
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+
+        Ok(())
+    }
+}
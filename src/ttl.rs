@@ -0,0 +1,90 @@
+use crate::Role;
+
+/// Maps roles to the maximum lifetime of the tokens issued to them.
+/// `LiteSessionToken::build_secure` caps the expiry of tokens whose role has a maximum lifetime
+/// so that high privilege sessions cannot accidentally be issued for long periods
+///
+/// ```
+/// use lite_session::{Role, RoleTtls};
+/// use timelite::LiteDuration;
+///
+/// let mut ttls = RoleTtls::default();
+/// ttls.insert(Role::SuperUser, LiteDuration::hours(1));
+/// ttls.insert(Role::User, LiteDuration::hours(24));
+/// ttls.insert(Role::SlaveNode, LiteDuration::days(30));
+///
+/// assert_eq!(ttls.get(&Role::SuperUser), Some(LiteDuration::hours(1)));
+/// assert_eq!(ttls.get(&Role::Admin), None);
+/// ```
+#[derive(Debug, Default)]
+pub struct RoleTtls {
+    ttls: Vec<(Role, u64)>,
+}
+
+impl core::cmp::PartialEq for RoleTtls {
+    fn eq(&self, other: &Self) -> bool {
+        self.ttls == other.ttls
+    }
+}
+
+impl core::clone::Clone for RoleTtls {
+    fn clone(&self) -> Self {
+        Self {
+            ttls: self.ttls.clone(),
+        }
+    }
+}
+
+impl RoleTtls {
+    /// Set the maximum lifetime in seconds of tokens issued to `role`
+    pub fn insert(&mut self, role: Role, max_ttl_in_secs: u64) -> &mut Self {
+        match self.ttls.iter_mut().find(|(existing, _)| *existing == role) {
+            Some(entry) => entry.1 = max_ttl_in_secs,
+            None => self.ttls.push((role, max_ttl_in_secs)),
+        }
+
+        self
+    }
+    /// Remove the maximum lifetime of tokens issued to `role`
+    pub fn remove(&mut self, role: &Role) -> Option<u64> {
+        let index = self
+            .ttls
+            .iter()
+            .position(|(existing, _)| existing == role)?;
+
+        Some(self.ttls.remove(index).1)
+    }
+    /// Get the maximum lifetime in seconds of tokens issued to `role`
+    pub fn get(&self, role: &Role) -> Option<u64> {
+        self.ttls
+            .iter()
+            .find(|(existing, _)| existing == role)
+            .map(|(_, ttl)| *ttl)
+    }
+    /// Get all the roles and their maximum lifetimes
+    pub fn get_all(&self) -> &Vec<(Role, u64)> {
+        &self.ttls
+    }
+}
+
+#[cfg(test)]
+mod ttl_tests {
+    use super::RoleTtls;
+    use crate::Role;
+
+    #[test]
+    fn role_ttls() {
+        let mut ttls = RoleTtls::default();
+        ttls.insert(Role::SuperUser, 3600);
+        ttls.insert(Role::Custom("Auditor".into()), 60);
+        ttls.insert(Role::SuperUser, 1800);
+
+        assert_eq!(ttls.get(&Role::SuperUser), Some(1800));
+        assert_eq!(ttls.get(&Role::Custom("Auditor".into())), Some(60));
+        assert_eq!(ttls.get(&Role::User), None);
+        assert_eq!(ttls.get_all().len(), 2_usize);
+
+        assert_eq!(ttls.remove(&Role::SuperUser), Some(1800));
+        assert_eq!(ttls.get(&Role::SuperUser), None);
+    }
+}
@@ -0,0 +1,80 @@
+/// A misconfiguration of a token found by `LiteSessionToken::validate_config()`
+/// before it is issued
+#[derive(Debug)]
+pub enum ConfigIssue {
+    /// The expiry is not after the issued time so the token is dead on arrival
+    ZeroTtl,
+    /// The ACL is empty so the token grants no capabilities
+    EmptyAcl,
+    /// The token has no username
+    EmptyUsername,
+    /// The token carries a username, tag or claims that are readable by anyone
+    /// because the data section is not encrypted under `ConfidentialityMode::Low`
+    LowConfidentialityWithClaims,
+    /// A `SuperUser` token is not pinned to a session by `LiteSessionMode::SessionID`
+    /// so a stolen token can be replayed from anywhere
+    PassiveSuperUser,
+    /// The expiry exceeds the maximum lifetime of the role and will be shortened by `build_secure`
+    TtlExceedsRoleCap,
+    /// The field separator of the `TokenFormat` is not allowed
+    InvalidTokenFormat,
+}
+
+impl core::cmp::PartialEq for ConfigIssue {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (ConfigIssue::ZeroTtl, ConfigIssue::ZeroTtl)
+                | (ConfigIssue::EmptyAcl, ConfigIssue::EmptyAcl)
+                | (ConfigIssue::EmptyUsername, ConfigIssue::EmptyUsername)
+                | (
+                    ConfigIssue::LowConfidentialityWithClaims,
+                    ConfigIssue::LowConfidentialityWithClaims
+                )
+                | (ConfigIssue::PassiveSuperUser, ConfigIssue::PassiveSuperUser)
+                | (
+                    ConfigIssue::TtlExceedsRoleCap,
+                    ConfigIssue::TtlExceedsRoleCap
+                )
+                | (
+                    ConfigIssue::InvalidTokenFormat,
+                    ConfigIssue::InvalidTokenFormat
+                )
+        )
+    }
+}
+
+impl core::clone::Clone for ConfigIssue {
+    fn clone(&self) -> Self {
+        match self {
+            ConfigIssue::ZeroTtl => ConfigIssue::ZeroTtl,
+            ConfigIssue::EmptyAcl => ConfigIssue::EmptyAcl,
+            ConfigIssue::EmptyUsername => ConfigIssue::EmptyUsername,
+            ConfigIssue::LowConfidentialityWithClaims => ConfigIssue::LowConfidentialityWithClaims,
+            ConfigIssue::PassiveSuperUser => ConfigIssue::PassiveSuperUser,
+            ConfigIssue::TtlExceedsRoleCap => ConfigIssue::TtlExceedsRoleCap,
+            ConfigIssue::InvalidTokenFormat => ConfigIssue::InvalidTokenFormat,
+        }
+    }
+}
+
+impl ConfigIssue {
+    /// Check whether the issue prevents a usable token from being built.
+    /// Issues that are not errors are warnings about risky but working configurations
+    pub fn is_error(&self) -> bool {
+        matches!(self, ConfigIssue::ZeroTtl | ConfigIssue::InvalidTokenFormat)
+    }
+}
+
+#[cfg(test)]
+mod validate_tests {
+    use super::ConfigIssue;
+
+    #[test]
+    fn config_issue() {
+        assert!(ConfigIssue::ZeroTtl.is_error());
+        assert!(!ConfigIssue::EmptyAcl.is_error());
+        assert!(!ConfigIssue::PassiveSuperUser.is_error());
+        assert!(!ConfigIssue::LowConfidentialityWithClaims.is_error());
+    }
+}
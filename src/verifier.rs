@@ -0,0 +1,810 @@
+use crate::{
+    epoch::epoch_user, token::DEFAULT_MAX_DATA_SIZE, AuthLevel, KeyRing, LiteSessionError,
+    LiteSessionToken, ParsePolicy, QuotaStore, RejectionReason, ReplayGuard, RevocationBloom,
+    RevocationStore, SessionFamily, TokenOutcome, UserEpochProvider,
+};
+use core::time::Duration;
+use std::sync::Arc;
+
+/// Verifies tokens against a policy configured once, separating the verification settings
+/// from the `LiteSessionToken` used to build tokens. Every call to `verify()` destructures
+/// the token into a new `LiteSessionToken` so a verifier can be shared between requests
+///
+/// ```
+/// use lite_session::{
+///     KeyRing, LiteSessionData, LiteSessionToken, LiteSessionVerifier, ServerKey, TokenOutcome,
+/// };
+///
+/// let mut ring = KeyRing::default();
+/// ring.add_key("2021a", ServerKey::new([0_u8; 32])).unwrap();
+///
+/// let mut data = LiteSessionData::default();
+/// data.username("foo_user").add_acl("Network-TCP");
+/// let mut token = LiteSessionToken::default();
+/// token.hmac_data(data);
+/// let session_token = token.build_secure_with_provider(&ring).unwrap();
+///
+/// let mut verifier = LiteSessionVerifier::new(ring);
+/// verifier.require_acl("Network-TCP");
+/// let (outcome, verified) = verifier.verify(&session_token).unwrap();
+/// assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+/// assert_eq!(verified.get_hmac_data().get_username(), "foo_user");
+/// ```
+pub struct LiteSessionVerifier {
+    key_ring: KeyRing,
+    leeway: Duration,
+    max_data_size: usize,
+    required_audience: Option<String>,
+    required_acl: Vec<String>,
+    required_auth_level: Option<AuthLevel>,
+    parse_policy: ParsePolicy,
+    dummy_verification: bool,
+    replay_guard: Option<Arc<dyn ReplayGuard>>,
+    revocations: Option<Arc<dyn RevocationStore + Send + Sync>>,
+    revocation_bloom: Option<Arc<RevocationBloom>>,
+    user_epochs: Option<Arc<dyn UserEpochProvider>>,
+    quota: Option<Arc<dyn QuotaStore + Send + Sync>>,
+    cookie_name: String,
+}
+
+impl core::fmt::Debug for LiteSessionVerifier {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("LiteSessionVerifier")
+            .field("key_ring", &self.key_ring)
+            .field("leeway", &self.leeway)
+            .field("max_data_size", &self.max_data_size)
+            .field("required_audience", &self.required_audience)
+            .field("required_acl", &self.required_acl)
+            .field("required_auth_level", &self.required_auth_level)
+            .field("parse_policy", &self.parse_policy)
+            .field("dummy_verification", &self.dummy_verification)
+            .field("replay_guard", &self.replay_guard)
+            .field("revocations", &self.revocations.is_some())
+            .field("revocation_bloom", &self.revocation_bloom)
+            .field("user_epochs", &self.user_epochs)
+            .field("quota", &self.quota.is_some())
+            .field("cookie_name", &self.cookie_name)
+            .finish()
+    }
+}
+
+impl LiteSessionVerifier {
+    /// Create a verifier authenticating tokens with the keys of `key_ring`.
+    /// Tokens without a key ID are verified with the active key
+    pub fn new(key_ring: KeyRing) -> Self {
+        Self {
+            key_ring,
+            leeway: Duration::default(),
+            max_data_size: DEFAULT_MAX_DATA_SIZE,
+            required_audience: None,
+            required_acl: Vec::default(),
+            required_auth_level: None,
+            parse_policy: ParsePolicy::default(),
+            dummy_verification: false,
+            replay_guard: None,
+            revocations: None,
+            revocation_bloom: None,
+            user_epochs: None,
+            quota: None,
+            cookie_name: "lite_session".into(),
+        }
+    }
+    /// Tolerate clocks drifting between servers by `leeway`, see `LiteSessionToken::leeway()`
+    pub fn leeway(&mut self, leeway: Duration) -> &mut Self {
+        self.leeway = leeway;
+
+        self
+    }
+    /// Reject tokens whose data is larger than `max_data_size` bytes as malformed, failing with
+    /// `LiteSessionError::DataSizeTooLarge` or `TokenOutcome::BadToken` under `ParsePolicy::Lenient`
+    pub fn max_data_size(&mut self, max_data_size: usize) -> &mut Self {
+        self.max_data_size = max_data_size;
+
+        self
+    }
+    /// Reject tokens whose data does not name `audience` as the service they are intended for
+    pub fn require_audience(&mut self, audience: &str) -> &mut Self {
+        self.required_audience = Some(audience.into());
+
+        self
+    }
+    /// Reject tokens whose access control list does not grant `capability`.
+    /// Such tokens are `TokenOutcome::TokenRejected` with `RejectionReason::PolicyFailed`
+    pub fn require_acl(&mut self, capability: &str) -> &mut Self {
+        self.required_acl.push(capability.into());
+
+        self
+    }
+    /// Require the user to have authenticated at `auth_level` or stronger, see
+    /// `LiteSessionData::auth_level()`. Tokens of a weaker or unknown level are
+    /// `TokenOutcome::StepUpRequired` with `RejectionReason::PolicyFailed`
+    pub fn require_auth_level(&mut self, auth_level: AuthLevel) -> &mut Self {
+        self.required_auth_level = Some(auth_level);
+
+        self
+    }
+    /// Choose whether malformed tokens are errors or `TokenOutcome::BadToken`
+    pub fn parse_policy(&mut self, parse_policy: ParsePolicy) -> &mut Self {
+        self.parse_policy = parse_policy;
+
+        self
+    }
+    /// Run a dummy verification for tokens rejected before their MAC is checked,
+    /// see `LiteSessionToken::dummy_verification()`
+    pub fn dummy_verification(&mut self, enabled: bool) -> &mut Self {
+        self.dummy_verification = enabled;
+
+        self
+    }
+    /// Reject replays of tokens marked with `LiteSessionData::single_use()` using `guard`.
+    /// A single-use token is recorded once every other check but the quota has passed, so
+    /// that replays are not charged to the quota, and is
+    /// `TokenOutcome::TokenRejected` with `RejectionReason::PolicyFailed` when presented again.
+    /// Single-use tokens are not tracked without a guard and fail the verification with
+    /// the error of the guard when it cannot record them.
+    /// Every generation of a refresh token of a `SessionFamily` is single-use too, presenting
+    /// it again makes the token `TokenOutcome::TokenRevoked` and revokes the whole family
+    /// in the store set by `revocations()`
+    pub fn replay_guard(&mut self, guard: Arc<dyn ReplayGuard>) -> &mut Self {
+        self.replay_guard = Some(guard);
+
+        self
+    }
+    /// Consult `revocations` for every authentic token. Tokens whose identifier or
+    /// session family has been revoked are `TokenOutcome::TokenRevoked`
+    /// with `RejectionReason::Revoked`
+    pub fn revocations(
+        &mut self,
+        revocations: Arc<dyn RevocationStore + Send + Sync>,
+    ) -> &mut Self {
+        self.revocations = Some(revocations);
+
+        self
+    }
+    /// Consult the `RevocationBloom` pushed to this verifier for every authentic token,
+    /// like `revocations()` for edge verifiers without the full revocation list
+    pub fn revocation_bloom(&mut self, bloom: Arc<RevocationBloom>) -> &mut Self {
+        self.revocation_bloom = Some(bloom);
+
+        self
+    }
+    /// Reject tokens whose `LiteSessionData::epoch()` is older than the current epoch of
+    /// their user held by `epochs`. Such tokens are `TokenOutcome::TokenRevoked`
+    /// with `RejectionReason::Revoked`
+    pub fn user_epochs(&mut self, epochs: Arc<dyn UserEpochProvider>) -> &mut Self {
+        self.user_epochs = Some(epochs);
+
+        self
+    }
+    /// Enforce the `Quota` claim of every otherwise accepted token by counting its requests
+    /// in `store`, see `LiteSessionToken::enforce_quota()`. Tokens over their quota are
+    /// `TokenOutcome::QuotaExceeded` with `RejectionReason::PolicyFailed`
+    pub fn quota(&mut self, store: Arc<dyn QuotaStore + Send + Sync>) -> &mut Self {
+        self.quota = Some(store);
+
+        self
+    }
+    /// Set the name of the session cookie web framework extractors read the token from
+    /// when a request carries no bearer token, `lite_session` by default like `CookieProfile`
+    pub fn cookie_name(&mut self, cookie_name: &str) -> &mut Self {
+        self.cookie_name = cookie_name.into();
+
+        self
+    }
+    /// Get the `KeyRing`
+    pub fn get_key_ring(&self) -> &KeyRing {
+        &self.key_ring
+    }
+    /// Get the leeway
+    pub fn get_leeway(&self) -> &Duration {
+        &self.leeway
+    }
+    /// Get the maximum size of the data of a token in bytes
+    pub fn get_max_data_size(&self) -> usize {
+        self.max_data_size
+    }
+    /// Get the required audience
+    pub fn get_required_audience(&self) -> Option<&str> {
+        self.required_audience.as_deref()
+    }
+    /// Get the capabilities the access control list of a token must grant
+    pub fn get_required_acl(&self) -> &Vec<String> {
+        &self.required_acl
+    }
+    /// Get the `AuthLevel` the user must have authenticated at
+    pub fn get_required_auth_level(&self) -> Option<&AuthLevel> {
+        self.required_auth_level.as_ref()
+    }
+    /// Get the `ParsePolicy`
+    pub fn get_parse_policy(&self) -> &ParsePolicy {
+        &self.parse_policy
+    }
+    /// Check whether rejected tokens go through a dummy verification
+    pub fn get_dummy_verification(&self) -> bool {
+        self.dummy_verification
+    }
+    /// Get the name of the session cookie
+    pub fn get_cookie_name(&self) -> &str {
+        &self.cookie_name
+    }
+    /// Destructure and authenticate `token` into a new `LiteSessionToken` and check it
+    /// against the requirements of the verifier
+    pub fn verify(
+        &self,
+        token: &str,
+    ) -> Result<(TokenOutcome, LiteSessionToken), LiteSessionError> {
+        let mut verified = LiteSessionToken::default();
+        verified
+            .leeway(self.leeway)
+            .max_data_size(self.max_data_size)
+            .parse_policy(self.parse_policy.clone())
+            .dummy_verification(self.dummy_verification);
+        if let Some(audience) = &self.required_audience {
+            verified.require_audience(audience);
+        }
+
+        let (outcome, _) = verified.from_string_with_provider(&self.key_ring, token)?;
+        if outcome != TokenOutcome::TokenAuthentic {
+            return Ok((outcome, verified));
+        }
+
+        if self.is_revoked(&verified) {
+            verified.reject(RejectionReason::Revoked);
+            return Ok((TokenOutcome::TokenRevoked, verified));
+        }
+
+        let data = verified.get_hmac_data();
+        if self
+            .required_acl
+            .iter()
+            .any(|capability| !data.has_capability(capability))
+        {
+            verified.reject(RejectionReason::PolicyFailed {
+                which: "acl".into(),
+            });
+            return Ok((TokenOutcome::TokenRejected, verified));
+        }
+
+        if let Some(required) = &self.required_auth_level {
+            let stepped_up = match data.get_auth_level() {
+                Some(auth_level) => auth_level.satisfies(required),
+                None => false,
+            };
+            if !stepped_up {
+                verified.reject(RejectionReason::PolicyFailed {
+                    which: "auth_level".into(),
+                });
+                return Ok((TokenOutcome::StepUpRequired, verified));
+            }
+        }
+
+        if let Some(guard) = &self.replay_guard {
+            let reused_family = match data.get_family() {
+                Some(family) if family.is_refresh() => {
+                    if guard.seen(&SessionFamily::to_string(family), *verified.get_expiry())? {
+                        Some(family.get_id().clone())
+                    } else {
+                        None
+                    }
+                }
+                _ => None,
+            };
+            if let Some(family_id) = reused_family {
+                self.revoke_family(&verified, &family_id);
+                verified.reject(RejectionReason::Revoked);
+                return Ok((TokenOutcome::TokenRevoked, verified));
+            }
+
+            if data.is_single_use()
+                && guard.seen(verified.get_identifier(), *verified.get_expiry())?
+            {
+                verified.reject(RejectionReason::PolicyFailed {
+                    which: "replay".into(),
+                });
+                return Ok((TokenOutcome::TokenRejected, verified));
+            }
+        }
+
+        if let Some(quota) = &self.quota {
+            if verified.enforce_quota(&**quota) == TokenOutcome::QuotaExceeded {
+                verified.reject(RejectionReason::PolicyFailed {
+                    which: "quota".into(),
+                });
+                return Ok((TokenOutcome::QuotaExceeded, verified));
+            }
+        }
+
+        Ok((outcome, verified))
+    }
+
+    // Revoke the family of a reused refresh token for as long as a token it refreshed
+    // into could remain valid
+    fn revoke_family(&self, verified: &LiteSessionToken, family_id: &str) {
+        if let Some(revocations) = &self.revocations {
+            let lifetime = verified
+                .get_expiry()
+                .duration_since(verified.get_issued())
+                .unwrap_or_default();
+            revocations.revoke(family_id, verified.get_clock().now() + lifetime);
+        }
+    }
+
+    // Whether the identifier or the session family of the token has been revoked
+    // or the user has logged out everywhere since it was issued
+    fn is_revoked(&self, verified: &LiteSessionToken) -> bool {
+        let data = verified.get_hmac_data();
+        if let Some(epochs) = &self.user_epochs {
+            if data.get_epoch() < epochs.current_epoch(epoch_user(data)) {
+                return true;
+            }
+        }
+
+        let mut identifiers = vec![verified.get_identifier().as_str()];
+        if let Some(family) = data.get_family() {
+            identifiers.push(family.get_id().as_str());
+        }
+
+        identifiers.iter().any(|identifier| {
+            let revoked = match &self.revocations {
+                Some(revocations) => revocations.is_revoked(identifier),
+                None => false,
+            };
+
+            revoked
+                || match &self.revocation_bloom {
+                    Some(bloom) => bloom.is_revoked(identifier),
+                    None => false,
+                }
+        })
+    }
+}
+
+#[cfg(test)]
+mod verifier_tests {
+    use super::LiteSessionVerifier;
+    use crate::{
+        AuthLevel, KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken, MemoryQuotaStore,
+        MemoryReplayGuard, MemoryRevocationStore, MemoryUserEpochs, ParsePolicy, Quota, QuotaStore,
+        RefreshRotation, RejectionReason, RevocationBloom, RevocationStore, ServerKey,
+        TokenOutcome,
+    };
+    use std::sync::Arc;
+
+    #[test]
+    fn verifier() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .audience("api.example.com");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let unkeyed_token = token.build_secure(&ServerKey::new([0_u8; 32]))?;
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier
+            .require_audience("api.example.com")
+            .require_acl("Network-TCP");
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        assert_eq!(verified.get_key_id(), Some("2021a"));
+        assert_eq!(verified.get_rejection(), &None);
+        let (outcome, _) = verifier.verify(&unkeyed_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        verifier.require_acl("Network-UDP");
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "acl".into()
+            })
+        );
+
+        let mut verifier = LiteSessionVerifier::new(KeyRing::default());
+        verifier.require_audience("files.example.com");
+        assert_eq!(
+            verifier.verify(&session_token).map(|(outcome, _)| outcome),
+            Err(LiteSessionError::UnknownKeyId)
+        );
+
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.require_audience("files.example.com");
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        verifier.parse_policy(ParsePolicy::Lenient);
+        let (outcome, verified) = verifier.verify("foo")?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        assert!(!verified.get_dummy_verification());
+
+        verifier.dummy_verification(true);
+        assert!(verifier.get_dummy_verification());
+        let (outcome, verified) = verifier.verify("foo")?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        assert!(verified.get_dummy_verification());
+
+        Ok(())
+    }
+
+    #[test]
+    fn max_data_size() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        (0..50).for_each(|index| {
+            data.add_acl(&format!("Network-{}", index));
+        });
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        verifier.max_data_size(10);
+        assert_eq!(
+            verifier.verify(&session_token).map(|(outcome, _)| outcome),
+            Err(LiteSessionError::DataSizeTooLarge)
+        );
+        verifier.parse_policy(ParsePolicy::Lenient);
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::BadToken);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::Malformed {
+                field: "ciphertext"
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn certificate_binding() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_service")
+            .certificate_thumbprint(&[7_u8; 32]);
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        // The verifier never sees the client certificate the token is bound to
+        let verifier = LiteSessionVerifier::new(ring);
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "cnf".into()
+            })
+        );
+        assert_eq!(
+            verifier
+                .verify_request(Some(&format!("Bearer {}", session_token)), None, "")
+                .map(|_| ())
+                .unwrap_err()
+                .get_status(),
+            403
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn proof_binding() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").proof_key(&[7_u8; 32]);
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        // The verifier checks no proof of possession
+        let verifier = LiteSessionVerifier::new(ring);
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "dpop".into()
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn step_up() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.require_auth_level(AuthLevel::Mfa);
+        assert_eq!(verifier.get_required_auth_level(), Some(&AuthLevel::Mfa));
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let (outcome, verified) = verifier.verify(&token.build_secure(&server_key)?)?;
+        assert_eq!(outcome, TokenOutcome::StepUpRequired);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "auth_level".into()
+            })
+        );
+
+        data.auth_level(AuthLevel::Password);
+        token.hmac_data(data.clone());
+        let (outcome, _) = verifier.verify(&token.build_secure(&server_key)?)?;
+        assert_eq!(outcome, TokenOutcome::StepUpRequired);
+
+        data.auth_level(AuthLevel::HardwareKey);
+        token.hmac_data(data);
+        let (outcome, _) = verifier.verify(&token.build_secure(&server_key)?)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn replay_guard() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Password-Reset");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure_with_provider(&ring)?;
+        data.single_use(true);
+        token.hmac_data(data).identifier("resetlink");
+        let reset_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        let (outcome, _) = verifier.verify(&reset_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = verifier.verify(&reset_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        verifier.replay_guard(Arc::new(MemoryReplayGuard::new(16)));
+        let (outcome, _) = verifier.verify(&reset_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, verified) = verifier.verify(&reset_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "replay".into()
+            })
+        );
+
+        // Tokens that are not single-use can be presented any number of times
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        // A single-use token the guard cannot record is not accepted
+        verifier.replay_guard(Arc::new(MemoryReplayGuard::new(0)));
+        assert_eq!(
+            verifier.verify(&reset_token).map(|(outcome, _)| outcome),
+            Err(LiteSessionError::ReplayGuardFull)
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn quota() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .add_acl("Network-TCP")
+            .quota(Quota::new(1, 0));
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let store = Arc::new(MemoryQuotaStore::new());
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.quota(store.clone()).require_acl("Network-UDP");
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+
+        // Rejected tokens are not charged
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.quota(store);
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::QuotaExceeded);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "quota".into()
+            })
+        );
+
+        // Replays are rejected before they are charged
+        let mut data = LiteSessionData::default();
+        data.username("foo_user")
+            .single_use(true)
+            .quota(Quota::new(2, 0));
+        token.hmac_data(data).identifier("resetlink");
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let reset_token = token.build_secure_with_provider(&ring)?;
+        let store = Arc::new(MemoryQuotaStore::new());
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier
+            .quota(store.clone())
+            .replay_guard(Arc::new(MemoryReplayGuard::new(16)));
+        let (outcome, _) = verifier.verify(&reset_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, verified) = verifier.verify(&reset_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRejected);
+        assert_eq!(
+            verified.get_rejection(),
+            &Some(RejectionReason::PolicyFailed {
+                which: "replay".into()
+            })
+        );
+        assert_eq!(store.increment("resetlink", 0, u64::MAX), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn revocations() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let revocations = Arc::new(MemoryRevocationStore::new());
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.revocations(revocations.clone());
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        revocations.revoke(token.get_identifier(), *token.get_expiry());
+        let (outcome, verified) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Revoked));
+
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.revocation_bloom(Arc::new(RevocationBloom::from_store(&*revocations, 0.01)));
+        let (outcome, _) = verifier.verify(&session_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+
+        Ok(())
+    }
+
+    #[test]
+    fn user_epochs() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let epochs = Arc::new(MemoryUserEpochs::new());
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let laptop_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.user_epochs(epochs.clone());
+        let (outcome, _) = verifier.verify(&laptop_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        data.epoch(epochs.bump("foo_user"));
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data);
+        let phone_token = token.build_secure_with_provider(verifier.get_key_ring())?;
+
+        let (outcome, verified) = verifier.verify(&laptop_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Revoked));
+        let (outcome, _) = verifier.verify(&phone_token)?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_reuse() -> Result<(), LiteSessionError> {
+        let server_key = ServerKey::new([0_u8; 32]);
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let revocations = Arc::new(MemoryRevocationStore::new());
+        let rotation = RefreshRotation::new(&*revocations);
+
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let pair = rotation.issue(&server_key, data)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier
+            .replay_guard(Arc::new(MemoryReplayGuard::new(16)))
+            .revocations(revocations.clone());
+        let (outcome, _) = verifier.verify(pair.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+        let (outcome, _) = verifier.verify(pair.get_access())?;
+        assert_eq!(outcome, TokenOutcome::TokenAuthentic);
+
+        let (outcome, verified) = verifier.verify(pair.get_refresh())?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+        assert_eq!(verified.get_rejection(), &Some(RejectionReason::Revoked));
+        assert!(revocations.is_revoked(pair.get_family_id()));
+        let (outcome, _) = verifier.verify(pair.get_access())?;
+        assert_eq!(outcome, TokenOutcome::TokenRevoked);
+
+        Ok(())
+    }
+
+    #[test]
+    fn concurrent_replays() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").single_use(true);
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).identifier("resetlink");
+        let reset_token = token.build_secure_with_provider(&ring)?;
+
+        let server_key = ServerKey::new([0_u8; 32]);
+        let revocations = Arc::new(MemoryRevocationStore::new());
+        let pair =
+            RefreshRotation::new(&*revocations).issue(&server_key, LiteSessionData::default())?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier
+            .replay_guard(Arc::new(MemoryReplayGuard::new(16)))
+            .revocations(revocations);
+
+        // Only one of the verifications racing on the same single-use token is accepted
+        let accepted = |token: &str| {
+            std::thread::scope(|scope| {
+                let verifications = (0..8)
+                    .map(|_| scope.spawn(|| verifier.verify(token)))
+                    .collect::<Vec<_>>();
+                verifications
+                    .into_iter()
+                    .map(|verification| verification.join().unwrap())
+                    .filter(|verification| {
+                        matches!(verification, Ok((TokenOutcome::TokenAuthentic, _)))
+                    })
+                    .count()
+            })
+        };
+        assert_eq!(accepted(&reset_token), 1);
+        assert_eq!(accepted(pair.get_refresh()), 1);
+
+        Ok(())
+    }
+}
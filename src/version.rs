@@ -0,0 +1,148 @@
+use crate::TokenCipher;
+
+/// The version of the token layout and algorithms, emitted as a prefix field like `ls1`
+/// so that the format can evolve while tokens of older versions are still accepted
+#[derive(Debug, Default)]
+pub enum TokenVersion {
+    /// The original unversioned layout
+    V0,
+    /// The `ls1` layout which authenticates the version prefix along with the other fields
+    V1,
+    /// The `ls2` layout which derives the encryption key using Blake3 `derive_key` with a
+    /// context string and length-prefixed fields instead of concatenating the fields
+    #[default]
+    V2,
+}
+
+impl core::cmp::PartialEq for TokenVersion {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (TokenVersion::V0, TokenVersion::V0)
+                | (TokenVersion::V1, TokenVersion::V1)
+                | (TokenVersion::V2, TokenVersion::V2)
+        )
+    }
+}
+
+impl core::clone::Clone for TokenVersion {
+    fn clone(&self) -> Self {
+        match self {
+            TokenVersion::V0 => TokenVersion::V0,
+            TokenVersion::V1 => TokenVersion::V1,
+            TokenVersion::V2 => TokenVersion::V2,
+        }
+    }
+}
+
+impl TokenVersion {
+    /// Get the prefix field of the version. `TokenVersion::V0` tokens carry no prefix
+    pub fn prefix(&self) -> Option<&'static str> {
+        match self {
+            TokenVersion::V0 => None,
+            TokenVersion::V1 => Some("ls1"),
+            TokenVersion::V2 => Some("ls2"),
+        }
+    }
+    /// Get the version header field of a token, the prefix followed by the parameter of
+    /// `cipher` if it is not the default, the `KeyRing` key ID prefixed with `k`, the
+    /// `TenantId` prefixed with `t` and the hex key commitment prefixed with `c`,
+    /// for example `ls1-r20-k2021a-tacme`. `None` for `TokenVersion::V0` tokens
+    pub fn header(
+        &self,
+        cipher: &TokenCipher,
+        key_id: Option<&str>,
+        tenant_id: Option<&str>,
+        key_commitment: Option<&str>,
+    ) -> Option<String> {
+        let mut header = self.prefix()?.to_owned();
+        if let Some(parameter) = cipher.header() {
+            header.push('-');
+            header.push_str(parameter);
+        }
+        if let Some(key_id) = key_id {
+            header.push_str("-k");
+            header.push_str(key_id);
+        }
+        if let Some(tenant_id) = tenant_id {
+            header.push_str("-t");
+            header.push_str(tenant_id);
+        }
+        if let Some(key_commitment) = key_commitment {
+            header.push_str("-c");
+            header.push_str(key_commitment);
+        }
+
+        Some(header)
+    }
+    /// Check whether a field has the shape of a version header, `ls` followed by digits
+    /// and optionally parameters which are each a `-` followed by alphanumeric characters
+    pub fn is_prefix(field: &str) -> bool {
+        let mut parts = field.split('-');
+        let valid_prefix = match parts.next().and_then(|prefix| prefix.strip_prefix("ls")) {
+            Some(number) => !number.is_empty() && number.bytes().all(|byte| byte.is_ascii_digit()),
+            None => false,
+        };
+
+        valid_prefix
+            && parts.all(|parameter| {
+                !parameter.is_empty() && parameter.bytes().all(|byte| byte.is_ascii_alphanumeric())
+            })
+    }
+    /// Get the version of a prefix field. `None` if the version is not supported
+    pub fn from_prefix(prefix: &str) -> Option<Self> {
+        match prefix {
+            "ls1" => Some(TokenVersion::V1),
+            "ls2" => Some(TokenVersion::V2),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod version_tests {
+    use super::TokenVersion;
+    use crate::{Rounds, TokenCipher};
+
+    #[test]
+    fn token_version() {
+        assert_eq!(TokenVersion::default(), TokenVersion::V2);
+        assert_eq!(TokenVersion::V0.prefix(), None);
+        assert_eq!(
+            TokenVersion::V1
+                .prefix()
+                .and_then(TokenVersion::from_prefix),
+            Some(TokenVersion::V1)
+        );
+        assert!(TokenVersion::is_prefix("ls2"));
+        assert!(!TokenVersion::is_prefix("ls"));
+        assert!(!TokenVersion::is_prefix("lsfoo"));
+        assert!(TokenVersion::is_prefix("ls1-r20"));
+        assert!(!TokenVersion::is_prefix("ls1-"));
+        assert!(TokenVersion::is_prefix("ls1-r20-kfoo"));
+        assert!(!TokenVersion::is_prefix("ls1-r20-"));
+        assert_eq!(
+            TokenVersion::V2.header(&TokenCipher::ChaCha(Rounds::R20), None, None, None),
+            Some("ls2-r20".to_owned())
+        );
+        assert_eq!(
+            TokenVersion::V1.header(&TokenCipher::XChaCha20, Some("foo"), None, None),
+            Some("ls1-kfoo".to_owned())
+        );
+        assert_eq!(
+            TokenVersion::V2.header(
+                &TokenCipher::default(),
+                Some("foo"),
+                Some("bar"),
+                Some("00")
+            ),
+            Some("ls2-kfoo-tbar-c00".to_owned())
+        );
+        assert_eq!(
+            TokenVersion::V0.header(&TokenCipher::default(), None, None, None),
+            None
+        );
+        assert_eq!(TokenVersion::from_prefix("ls2"), Some(TokenVersion::V2));
+        assert_eq!(TokenVersion::from_prefix("ls3"), None);
+    }
+}
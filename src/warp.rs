@@ -0,0 +1,130 @@
+//! Warp filters yielding the verified session of a request, enabled by the `warp` feature
+
+use crate::{HttpRejection, LiteSessionData, LiteSessionVerifier};
+use ::warp::{
+    http::{
+        header::{AUTHORIZATION, COOKIE, WWW_AUTHENTICATE},
+        HeaderMap, StatusCode,
+    },
+    reject::{Reject, Rejection},
+    reply::{self, Reply, Response},
+    Filter,
+};
+use std::sync::Arc;
+
+impl Reject for HttpRejection {}
+
+/// Create a filter verifying the bearer token or the session cookie of a request with
+/// `verifier`, see `LiteSessionVerifier::cookie_name()`, and extracting the `LiteSessionData`
+/// of the verified token. Requests without an accepted token are rejected with their
+/// `HttpRejection`, turn it into a response with `handle_rejection()`
+///
+/// ```
+/// use lite_session::{KeyRing, LiteSessionData, LiteSessionVerifier};
+/// use std::sync::Arc;
+/// use warp::Filter;
+///
+/// let verifier = Arc::new(LiteSessionVerifier::new(KeyRing::default()));
+/// let route = warp::path("profile")
+///     .and(lite_session::warp::with_session(verifier))
+///     .map(|data: LiteSessionData| data.get_username().clone())
+///     .recover(lite_session::warp::handle_rejection);
+/// ```
+pub fn with_session(
+    verifier: Arc<LiteSessionVerifier>,
+) -> impl Filter<Extract = (LiteSessionData,), Error = Rejection> + Clone {
+    let cookie_name = verifier.get_cookie_name().to_owned();
+    let session = crate::with_session(verifier, &cookie_name);
+
+    ::warp::header::headers_cloned().and_then(move |headers: HeaderMap| {
+        // Plain tokens carry their `⊕` separators as UTF-8 rather than visible ASCII
+        let authorization = headers
+            .get(AUTHORIZATION)
+            .and_then(|value| core::str::from_utf8(value.as_bytes()).ok())
+            .map(Into::into);
+        // HTTP/2 clients may split the cookies over several headers
+        let cookie = headers
+            .get_all(COOKIE)
+            .iter()
+            .filter_map(|value| core::str::from_utf8(value.as_bytes()).ok())
+            .collect::<Vec<&str>>()
+            .join("; ");
+        let verified = session(
+            authorization,
+            Some(cookie).filter(|cookie| !cookie.is_empty()),
+        );
+
+        async move { verified.map_err(::warp::reject::custom) }
+    })
+}
+
+/// Turn an `HttpRejection` of `with_session()` into a response with its status code,
+/// passing any other rejection on. Use it with `Filter::recover()`
+pub async fn handle_rejection(rejection: Rejection) -> Result<Response, Rejection> {
+    let status = match rejection.find::<HttpRejection>() {
+        Some(rejection) => StatusCode::from_u16(rejection.get_status())
+            .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        None => return Err(rejection),
+    };
+
+    Ok(match status {
+        StatusCode::UNAUTHORIZED => reply::with_header(
+            reply::with_status(reply::reply(), status),
+            WWW_AUTHENTICATE,
+            "Bearer",
+        )
+        .into_response(),
+        _ => reply::with_status(reply::reply(), status).into_response(),
+    })
+}
+
+#[cfg(test)]
+mod warp_tests {
+    use super::{handle_rejection, with_session};
+    use crate::{
+        KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken, LiteSessionVerifier,
+        ServerKey,
+    };
+    use ::warp::Filter;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn session_filter() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user").add_acl("Network-TCP");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data.clone());
+        let session_token = token.build_secure_with_provider(&ring)?;
+
+        let mut verifier = LiteSessionVerifier::new(ring);
+        verifier.cookie_name("sid");
+        let filter = with_session(Arc::new(verifier));
+
+        let extracted = ::warp::test::request()
+            .header("authorization", format!("Bearer {}", session_token))
+            .filter(&filter)
+            .await;
+        assert_eq!(extracted.ok(), Some(data.clone()));
+        let extracted = ::warp::test::request()
+            .header("cookie", format!("theme=dark; sid={}", session_token))
+            .filter(&filter)
+            .await;
+        assert_eq!(extracted.ok(), Some(data));
+
+        let route = filter
+            .map(|data: LiteSessionData| data.get_username().clone())
+            .recover(handle_rejection);
+        let response = ::warp::test::request().reply(&route).await;
+        assert_eq!(response.status(), 401);
+        assert_eq!(response.headers()["www-authenticate"], "Bearer");
+        let response = ::warp::test::request()
+            .header("cookie", "sid=garbage")
+            .reply(&route)
+            .await;
+        assert_eq!(response.status(), 401);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+use crate::{
+    escape, Clock, HttpRejection, LiteSessionData, LiteSessionToken, LiteSessionVerifier,
+    SystemClock,
+};
+use core::time::Duration;
+use std::sync::Arc;
+use tai64::TAI64N;
+
+/// The prefix of the `Sec-WebSocket-Protocol` entry carrying the base64url encoded token.
+/// Browsers cannot set headers on a WebSocket upgrade so the token travels as a subprotocol
+pub const WEBSOCKET_PROTOCOL_PREFIX: &str = "base64url.lite-session.";
+
+/// What a long-lived WebSocket connection should do after `WebSocketSession::poll()`
+#[derive(Debug)]
+pub enum WebSocketEvent {
+    /// The session is still valid
+    Authenticated,
+    /// The token expired, the client must present a fresh token or the connection is closed
+    ReauthRequired,
+}
+
+impl core::cmp::PartialEq for WebSocketEvent {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (WebSocketEvent::Authenticated, WebSocketEvent::Authenticated)
+                | (
+                    WebSocketEvent::ReauthRequired,
+                    WebSocketEvent::ReauthRequired
+                )
+        )
+    }
+}
+
+/// The session of a WebSocket connection authenticated during the upgrade.
+/// The expiry of the token is checked again on `poll()` at most once every
+/// `revalidate_every()` so a connection cannot outlive its token
+///
+/// ```
+/// use core::time::Duration;
+/// use lite_session::{Clock, LiteSessionToken};
+/// use std::sync::Arc;
+/// use tai64::TAI64N;
+///
+/// pub struct WebSocketSession {
+///     token: LiteSessionToken,
+///     interval: Duration,
+///     next_check: TAI64N,
+///     clock: Arc<dyn Clock>,
+/// }
+/// ```
+#[derive(Debug)]
+pub struct WebSocketSession {
+    token: LiteSessionToken,
+    interval: Duration,
+    next_check: TAI64N,
+    clock: Arc<dyn Clock>,
+}
+
+impl core::clone::Clone for WebSocketSession {
+    fn clone(&self) -> Self {
+        Self {
+            token: self.token.clone(),
+            interval: self.interval,
+            next_check: self.next_check,
+            clock: self.clock.clone(),
+        }
+    }
+}
+
+impl WebSocketSession {
+    /// Authenticate a WebSocket upgrade with the token of the `Sec-WebSocket-Protocol`
+    /// header or else of the `access_token` parameter of the query string.
+    /// The expiry is re-validated every 60 seconds by default
+    pub fn from_upgrade(
+        verifier: &LiteSessionVerifier,
+        protocols: Option<&str>,
+        query: Option<&str>,
+    ) -> Result<Self, HttpRejection> {
+        WebSocketSession::from_upgrade_with_clock(verifier, protocols, query, Arc::new(SystemClock))
+    }
+    /// Authenticate a WebSocket upgrade like `from_upgrade()`, reading the current time
+    /// from `clock` instead of `SystemClock` from the first check of the expiry on
+    pub fn from_upgrade_with_clock(
+        verifier: &LiteSessionVerifier,
+        protocols: Option<&str>,
+        query: Option<&str>,
+        clock: Arc<dyn Clock>,
+    ) -> Result<Self, HttpRejection> {
+        let token = protocols
+            .and_then(protocol_token)
+            .or_else(|| query.and_then(query_token));
+        let authorization = token.map(|token| format!("Bearer {}", token));
+        let token = verifier.verify_request(authorization.as_deref(), None, "")?;
+
+        let interval = Duration::from_secs(60);
+
+        Ok(Self {
+            token,
+            interval,
+            next_check: clock.now() + interval,
+            clock,
+        })
+    }
+    /// Check the expiry of the token at most once every `interval` from now
+    pub fn revalidate_every(&mut self, interval: Duration) -> &mut Self {
+        self.next_check = self.clock.now() + interval;
+        self.interval = interval;
+
+        self
+    }
+    /// Read the current time from `clock` instead of `SystemClock`. The next check keeps its
+    /// time, authenticate the upgrade with `from_upgrade_with_clock()` to schedule the first
+    /// check on `clock`
+    pub fn clock(&mut self, clock: Arc<dyn Clock>) -> &mut Self {
+        self.clock = clock;
+
+        self
+    }
+    /// Re-validate the expiry of the token once the interval elapsed since the last check.
+    /// Once `WebSocketEvent::ReauthRequired` is yielded it is yielded on every poll
+    pub fn poll(&mut self) -> WebSocketEvent {
+        let now = self.clock.now();
+        if now < self.next_check {
+            return WebSocketEvent::Authenticated;
+        }
+
+        // An expired token never becomes valid again so the next check is not postponed
+        match *self.token.get_expiry() + *self.token.get_leeway() <= now {
+            true => WebSocketEvent::ReauthRequired,
+            false => {
+                self.next_check = now + self.interval;
+                WebSocketEvent::Authenticated
+            }
+        }
+    }
+    /// Get the data of the session
+    pub fn get_data(&self) -> &LiteSessionData {
+        self.token.get_hmac_data()
+    }
+    /// Get the verified token
+    pub fn get_token(&self) -> &LiteSessionToken {
+        &self.token
+    }
+    /// Get the interval between checks of the expiry
+    pub fn get_interval(&self) -> &Duration {
+        &self.interval
+    }
+}
+
+// The token of the `Sec-WebSocket-Protocol` entry starting with `WEBSOCKET_PROTOCOL_PREFIX`
+fn protocol_token(protocols: &str) -> Option<String> {
+    protocols
+        .split(',')
+        .find_map(|protocol| protocol.trim().strip_prefix(WEBSOCKET_PROTOCOL_PREFIX))
+        .and_then(|encoded| base64::decode_config(encoded, base64::URL_SAFE_NO_PAD).ok())
+        .and_then(|decoded| String::from_utf8(decoded).ok())
+}
+
+// The percent-decoded `access_token` parameter of a query string, where `+` encodes a space
+fn query_token(query: &str) -> Option<String> {
+    query
+        .trim_start_matches('?')
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .and_then(|encoded| escape::unescape(&encoded.replace('+', " ")).ok())
+        .filter(|token| !token.is_empty())
+}
+
+#[cfg(test)]
+mod websocket_tests {
+    use super::{
+        protocol_token, query_token, WebSocketEvent, WebSocketSession, WEBSOCKET_PROTOCOL_PREFIX,
+    };
+    use crate::{
+        FixedClock, KeyRing, LiteSessionData, LiteSessionError, LiteSessionToken,
+        LiteSessionVerifier, ServerKey,
+    };
+    use core::time::Duration;
+    use std::sync::Arc;
+
+    #[test]
+    fn upgrade_tokens() {
+        let encoded = base64::encode_config("foo⥂bar", base64::URL_SAFE_NO_PAD);
+        let protocols = format!("chat, {}{}", WEBSOCKET_PROTOCOL_PREFIX, encoded);
+        assert_eq!(protocol_token(&protocols), Some("foo⥂bar".to_owned()));
+        assert_eq!(protocol_token("chat"), None);
+
+        assert_eq!(
+            query_token("?room=1&access_token=foo%E2%A5%82bar"),
+            Some("foo⥂bar".to_owned())
+        );
+        assert_eq!(query_token("room=1&access_token="), None);
+        assert_eq!(
+            query_token("access_token=foo+bar%2B"),
+            Some("foo bar+".to_owned())
+        );
+        assert_eq!(query_token("access_token=foo%2"), None);
+        assert_eq!(query_token("access_token=foo%zz"), None);
+    }
+
+    #[test]
+    fn websocket_session() -> Result<(), LiteSessionError> {
+        let mut ring = KeyRing::default();
+        ring.add_key("2021a", ServerKey::new([0_u8; 32]))?;
+        let mut data = LiteSessionData::default();
+        data.username("foo_user");
+        let mut token = LiteSessionToken::default();
+        token.hmac_data(data).expiry(120);
+        let session_token = token.build_secure_with_provider(&ring)?;
+        let issued = *token.get_issued();
+
+        let verifier = LiteSessionVerifier::new(ring);
+        let protocols = format!(
+            "{}{}",
+            WEBSOCKET_PROTOCOL_PREFIX,
+            base64::encode_config(&session_token, base64::URL_SAFE_NO_PAD)
+        );
+        let mut session =
+            WebSocketSession::from_upgrade(&verifier, Some(&protocols), None).unwrap();
+        assert_eq!(session.get_data().get_username(), "foo_user");
+
+        let at = |secs: u64| Arc::new(FixedClock::new(issued + Duration::from_secs(secs)));
+        session
+            .clock(at(0))
+            .revalidate_every(Duration::from_secs(200));
+        session.clock(at(121));
+        assert_eq!(session.poll(), WebSocketEvent::Authenticated);
+
+        session
+            .clock(at(0))
+            .revalidate_every(Duration::from_secs(30));
+        assert_eq!(session.poll(), WebSocketEvent::Authenticated);
+        session.clock(at(100));
+        assert_eq!(session.poll(), WebSocketEvent::Authenticated);
+        session.clock(at(121));
+        assert_eq!(session.poll(), WebSocketEvent::Authenticated);
+        session.clock(at(131));
+        assert_eq!(session.poll(), WebSocketEvent::ReauthRequired);
+        assert_eq!(session.poll(), WebSocketEvent::ReauthRequired);
+
+        // The first check is scheduled on the clock of the upgrade
+        let mut session =
+            WebSocketSession::from_upgrade_with_clock(&verifier, Some(&protocols), None, at(100))
+                .unwrap();
+        session.clock(at(130));
+        assert_eq!(session.poll(), WebSocketEvent::Authenticated);
+        session.clock(at(160));
+        assert_eq!(session.poll(), WebSocketEvent::ReauthRequired);
+
+        let rejection = WebSocketSession::from_upgrade(&verifier, None, Some("room=1"))
+            .map(|_| ())
+            .unwrap_err();
+        assert_eq!(rejection.get_status(), 401);
+
+        Ok(())
+    }
+}